@@ -167,3 +167,32 @@ impl LocalApicEx for sys::acpi_madt_local_apic {
 		self
 	}
 }
+
+/// Extension trait for I/O APIC MADT entries.
+pub trait IoApicEx {
+	/// Returns the entry.
+	fn inner_ref(&self) -> &sys::acpi_madt_io_apic;
+
+	/// Returns the I/O APIC ID.
+	fn id(&self) -> u8 {
+		self.inner_ref().Id.read()
+	}
+
+	/// Returns the physical address of the I/O APIC's MMIO registers.
+	fn phys(&self) -> u64 {
+		u64::from(self.inner_ref().Address.read())
+	}
+
+	/// Returns the first global system interrupt (GSI) number
+	/// handled by this I/O APIC.
+	fn gsi_base(&self) -> u32 {
+		self.inner_ref().GlobalIrqBase.read()
+	}
+}
+
+impl IoApicEx for sys::acpi_madt_io_apic {
+	#[inline(always)]
+	fn inner_ref(&self) -> &sys::acpi_madt_io_apic {
+		self
+	}
+}
@@ -7,8 +7,7 @@
 #[cold]
 #[panic_handler]
 unsafe fn panic(info: &::core::panic::PanicInfo) -> ! {
-	oro_debug::dbg_err!("panic: {info:?}");
-	oro_arch_aarch64::asm::halt();
+	oro_arch_aarch64::panic(info);
 }
 
 /// Main entry point for the Oro kernel. Bootloaders jump
@@ -20,6 +20,33 @@ pub unsafe fn invalidate_tlb_el1<T>(virt: *const T) {
 	);
 }
 
+/// Invalidates the TLB entries for all pages overlapping the given
+/// virtual address range, on all cores in the inner shareable domain.
+///
+/// The invalidation is broadcast by the hardware, and has completed
+/// on all cores by the time this function returns.
+pub fn invalidate_tlb_range_el1_is(virt: usize, len: usize) {
+	let end = virt.saturating_add(len);
+
+	unsafe {
+		asm!("dsb ishst", options(nostack, preserves_flags));
+	}
+
+	for page in (virt & !0xFFF..end).step_by(4096) {
+		unsafe {
+			asm!(
+				"tlbi vaae1is, {0}",
+				in(reg) ((page >> 12) & 0xFFF_FFFF_FFFF) as u64,
+				options(nostack, preserves_flags),
+			);
+		}
+	}
+
+	unsafe {
+		asm!("dsb ish", "isb", options(nostack, preserves_flags));
+	}
+}
+
 /// Invalidates the entire TLB.
 pub fn invalid_tlb_el1_all() {
 	unsafe {
@@ -110,6 +137,23 @@ pub fn disable_interrupts() {
 	}
 }
 
+/// Returns the current core's interrupt mask bits (`DAIF`).
+pub fn fetch_daif() -> u64 {
+	let daif: u64;
+	unsafe {
+		asm!("mrs {0:x}, DAIF", out(reg) daif, options(nostack, nomem, preserves_flags));
+	}
+	daif
+}
+
+/// Restores the current core's interrupt mask bits (`DAIF`)
+/// to a value previously returned by [`fetch_daif()`].
+pub fn restore_daif(daif: u64) {
+	unsafe {
+		asm!("msr DAIF, {0:x}", in(reg) daif, options(nostack, preserves_flags));
+	}
+}
+
 /// Halts the processor forever.
 pub fn halt() -> ! {
 	loop {
@@ -131,3 +175,113 @@ pub fn strong_memory_barrier() {
 		asm!("dsb sy", options(nostack, preserves_flags),);
 	}
 }
+
+/// Reads the generic timer's virtual count register (`CNTVCT_EL0`).
+#[inline(always)]
+#[must_use]
+pub fn read_cntvct() -> u64 {
+	let cntvct: u64;
+	unsafe {
+		asm!(
+			"isb",
+			"mrs {0:x}, CNTVCT_EL0",
+			out(reg) cntvct,
+			options(nostack, nomem, preserves_flags)
+		);
+	}
+	cntvct
+}
+
+/// Reads the generic timer's frequency register (`CNTFRQ_EL0`), in Hz.
+#[inline(always)]
+#[must_use]
+pub fn read_cntfrq() -> u64 {
+	let cntfrq: u64;
+	unsafe {
+		asm!(
+			"mrs {0:x}, CNTFRQ_EL0",
+			out(reg) cntfrq,
+			options(nostack, nomem, preserves_flags)
+		);
+	}
+	cntfrq
+}
+
+/// Programs the generic timer's virtual timer to fire once the
+/// virtual count (see [`read_cntvct()`]) reaches the given compare
+/// value (`CNTV_CVAL_EL0`), enabling it (and unmasking its interrupt).
+///
+/// Fires immediately if the compare value has already passed.
+#[inline(always)]
+pub fn arm_virtual_timer(cval: u64) {
+	unsafe {
+		asm!(
+			"msr CNTV_CVAL_EL0, {0:x}",
+			"msr CNTV_CTL_EL0, {1:x}",
+			"isb",
+			in(reg) cval,
+			in(reg) 1_u64,
+			options(nostack, preserves_flags)
+		);
+	}
+}
+
+/// Disables the generic timer's virtual timer, deasserting
+/// its interrupt.
+#[inline(always)]
+pub fn disarm_virtual_timer() {
+	unsafe {
+		asm!(
+			"msr CNTV_CTL_EL0, xzr",
+			"isb",
+			options(nostack, preserves_flags)
+		);
+	}
+}
+
+/// Reads the current core's multiprocessor affinity register (`MPIDR_EL1`).
+#[inline(always)]
+#[must_use]
+pub fn read_mpidr() -> u64 {
+	let mpidr: u64;
+	unsafe {
+		asm!(
+			"mrs {0:x}, MPIDR_EL1",
+			out(reg) mpidr,
+			options(nostack, nomem, preserves_flags)
+		);
+	}
+	mpidr
+}
+
+/// Sets the exception vector base address register (`VBAR_EL1`).
+///
+/// # Safety
+/// The given address must point to a valid, 2KiB-aligned
+/// exception vector table.
+pub unsafe fn store_vbar(vbar: u64) {
+	asm!(
+		"msr VBAR_EL1, {0:x}",
+		"isb",
+		in(reg) vbar,
+		options(nostack, preserves_flags)
+	);
+}
+
+/// Briefly unmasks IRQs, allowing any pending IRQ to be taken,
+/// and masks them again.
+///
+/// Meant to be used after a [`halt_once_and_wait()`] with IRQs
+/// masked, which still wakes upon a pending IRQ; this avoids the
+/// race of an IRQ arriving between unmasking and halting.
+#[inline(always)]
+pub fn take_pending_irqs() {
+	unsafe {
+		asm!(
+			"msr daifclr, 0x2",
+			"isb",
+			"msr daifset, 0x2",
+			options(nostack, preserves_flags)
+		);
+	}
+}
@@ -0,0 +1,211 @@
+//! DeviceTree discovery of the platform's memory and
+//! interrupt controller during boot.
+
+use core::ffi::CStr;
+
+use oro_boot_protocol::device_tree::DeviceTreeDataV0;
+use oro_debug::{dbg, dbg_warn};
+use oro_dtb::{FdtHeader, FdtToken};
+use oro_mem::phys::{Phys, PhysAddr};
+use oro_type::Be;
+
+use crate::gic::GicVersion;
+
+/// The maximum number of `reg` entries recorded for a single node.
+const MAX_REGS: usize = 4;
+
+/// A Generic Interrupt Controller (GIC) discovered in the DeviceTree.
+#[derive(Debug, Clone, Copy)]
+pub struct GicInfo {
+	/// The version of the GIC.
+	pub version:     GicVersion,
+	/// The physical base address and size of the distributor (`GICD`).
+	pub distributor: (u64, u64),
+	/// The physical base address and size of the CPU interface (`GICC`)
+	/// on GICv2, or of the redistributor region (`GICR`) on GICv3.
+	pub cpu:         (u64, u64),
+}
+
+/// The platform information discovered from the DeviceTree.
+#[derive(Debug, Clone, Copy)]
+pub struct DeviceTreeInfo {
+	/// The interrupt controller, if one was found.
+	pub gic: Option<GicInfo>,
+}
+
+/// Returns the validated DeviceTree blob provided by the bootloader.
+///
+/// # Safety
+/// Must only be called after the linear map has been prepared.
+///
+/// # Panics
+/// Panics if the DeviceTree blob is not provided, or is invalid.
+pub unsafe fn device_tree() -> &'static FdtHeader {
+	let dtb = super::protocol::DTB_REQUEST
+		.response()
+		.expect("no DeviceTree blob response was provided")
+		.v0()
+		.expect("DeviceTree blob response was provided but was the wrong revision");
+
+	let DeviceTreeDataV0 { base, length } = dtb.assume_init_ref();
+
+	FdtHeader::from(
+		Phys::from_address_unchecked(*base).as_ptr().unwrap(),
+		Some(*length),
+	)
+	.expect("dtb is invalid")
+}
+
+/// Scans the top-level nodes of the DeviceTree for memory and
+/// the interrupt controller, logging what it finds.
+///
+/// Memory is only logged; the kernel takes the memory map from the
+/// bootloader (which derives it from the same DeviceTree), so the two
+/// are expected to agree.
+// TODO(qix-): Interrupt controllers nested in a bus node (e.g. `/soc`)
+// TODO(qix-): aren't found, as `ranges` translation isn't supported.
+pub fn scan(dtb: &FdtHeader) -> DeviceTreeInfo {
+	let mut info = DeviceTreeInfo { gic: None };
+
+	// NOTE(qix-): The spec mandates these be specified; these are the
+	// NOTE(qix-): (also mandated) defaults if they're not.
+	let mut address_cells = 2;
+	let mut size_cells = 1;
+
+	let mut depth = 0;
+	let mut is_memory = false;
+	let mut gic_version: Option<GicVersion> = None;
+	let mut regs = [(0, 0); MAX_REGS];
+	let mut num_regs = 0;
+
+	for tkn in dtb.iter() {
+		match tkn {
+			FdtToken::Node { name } => {
+				depth += 1;
+
+				if depth == 2 {
+					is_memory = name.to_bytes().starts_with(b"memory@");
+					gic_version = None;
+					num_regs = 0;
+				}
+			}
+			FdtToken::Property { name, value } if depth == 1 => {
+				if name == c"#address-cells" {
+					address_cells = read_cells(value, 1)
+						.and_then(|(v, _)| usize::try_from(v).ok())
+						.unwrap_or(address_cells);
+				} else if name == c"#size-cells" {
+					size_cells = read_cells(value, 1)
+						.and_then(|(v, _)| usize::try_from(v).ok())
+						.unwrap_or(size_cells);
+				}
+			}
+			FdtToken::Property { name, value } if depth == 2 => {
+				if name == c"device_type" {
+					is_memory |= CStr::from_bytes_with_nul(value).is_ok_and(|v| v == c"memory");
+				} else if name == c"compatible" {
+					gic_version = gic_version_of(value);
+				} else if name == c"reg" {
+					num_regs = parse_reg(value, address_cells, size_cells, &mut regs);
+				}
+			}
+			FdtToken::Property { .. } | FdtToken::Nop => {}
+			FdtToken::EndNode => {
+				if depth == 2 {
+					let regs = &regs[..num_regs];
+
+					if is_memory {
+						for &(base, size) in regs {
+							dbg!("dtb: memory {base:#016X} ({size} bytes)");
+						}
+					}
+
+					if let Some(version) = gic_version {
+						match (info.gic, regs) {
+							(Some(_), _) => {
+								dbg_warn!("dtb: ignoring additional interrupt controller");
+							}
+							(None, &[distributor, cpu, ..]) => {
+								info.gic = Some(GicInfo {
+									version,
+									distributor,
+									cpu,
+								});
+							}
+							(None, _) => {
+								dbg_warn!("dtb: interrupt controller is missing its registers");
+							}
+						}
+					}
+				}
+
+				depth -= 1;
+			}
+			FdtToken::End => break,
+		}
+	}
+
+	if info.gic.is_none() {
+		dbg_warn!("dtb: no supported interrupt controller was found");
+	}
+
+	info
+}
+
+/// Determines the GIC version from a `compatible` string list,
+/// or `None` if the node is not a (supported) GIC.
+fn gic_version_of(compatible: &[u8]) -> Option<GicVersion> {
+	compatible.split(|&c| c == 0).find_map(|compat| {
+		match compat {
+			b"arm,gic-v3" => Some(GicVersion::V3),
+			b"arm,gic-400" | b"arm,cortex-a15-gic" | b"arm,cortex-a9-gic" => Some(GicVersion::V2),
+			_ => None,
+		}
+	})
+}
+
+/// Reads a big-endian value of `cells` 32-bit cells from the start of
+/// `value`, returning it along with the remaining bytes.
+///
+/// Returns `None` if there aren't enough bytes, or `cells` is
+/// greater than 2.
+fn read_cells(value: &[u8], cells: usize) -> Option<(u64, &[u8])> {
+	if cells > 2 || value.len() < cells * 4 {
+		return None;
+	}
+
+	let (head, rest) = value.split_at(cells * 4);
+	let v = head.chunks_exact(4).fold(0u64, |acc, cell| {
+		// SAFETY(qix-): The chunk is exactly 4 bytes; the read is unaligned.
+		let cell = unsafe { cell.as_ptr().cast::<Be<u32>>().read_unaligned() };
+		(acc << 32) | u64::from(cell.read())
+	});
+
+	Some((v, rest))
+}
+
+/// Parses a `reg` property into `(address, size)` pairs, returning the
+/// number of pairs written to `out`. Excess pairs are ignored.
+fn parse_reg(
+	mut value: &[u8],
+	address_cells: usize,
+	size_cells: usize,
+	out: &mut [(u64, u64); MAX_REGS],
+) -> usize {
+	let mut count = 0;
+
+	while count < MAX_REGS {
+		let Some((base, rest)) = read_cells(value, address_cells) else {
+			break;
+		};
+		let Some((size, rest)) = read_cells(rest, size_cells) else {
+			break;
+		};
+
+		out[count] = (base, size);
+		count += 1;
+		value = rest;
+	}
+
+	count
+}
@@ -2,7 +2,7 @@
 
 use core::arch::asm;
 
-use oro_boot_protocol::{MemoryMapEntry, MemoryMapEntryType, memory_map::MemoryMapKind};
+use oro_boot_protocol::{MemoryMapEntry, MemoryMapEntryType};
 use oro_debug::{dbg, dbg_warn};
 use oro_macro::assert;
 use oro_mem::{
@@ -65,11 +65,22 @@ pub unsafe fn prepare_memory() {
 	let otf = OnTheFlyMapper::new();
 	let mmap_iter = MemoryMapIterator::new(&otf);
 	let mut pfa_iter = MemoryMapPfa::new(mmap_iter.clone());
+	let phys_limit = mmap_iter
+		.clone()
+		.map(|region| region.base + region.length)
+		.max()
+		.unwrap_or(0);
+	let phys_limit = mmap_iter
+		.clone()
+		.map(|region| region.base + region.length)
+		.max()
+		.unwrap_or(0);
 
 	let linear_offset = linear_map_regions(&otf, &mut pfa_iter, mmap_iter)
 		.expect("ran out of memory while linear mapping regions");
 
 	oro_mem::translate::set_global_map_offset(linear_offset);
+	oro_mem::translate::set_physical_address_limit(phys_limit);
 
 	// Consume the MMAP PFA and free all memory that isn't used by the
 	// linear map intermediate page table entries.
@@ -436,15 +447,14 @@ impl<'a> MemoryMapIterator<'a> {
 	fn new(otf: &'a OnTheFlyMapper) -> Self {
 		Self {
 			next: {
-				let MemoryMapKind::V0(res) = super::protocol::MMAP_REQUEST
+				let res = super::protocol::MMAP_REQUEST
 					.response()
 					.expect("bootloader didn't provide a memory map response")
-				else {
-					panic!(
+					.v0()
+					.expect(
 						"bootloader provided a memory map response, but it was of a different \
-						 revision"
+						 revision",
 					);
-				};
 
 				// SAFETY(qix-): We're assuming the bootloader provided a valid memory map.
 				// SAFETY(qix-): We've also used the appropriate methods from the bootloader protocol
@@ -4,14 +4,18 @@
 //! directly after being transferred to by the
 //! bootloader.
 
+mod dtb;
 mod memory;
 mod protocol;
 mod secondary;
 
 use oro_debug::dbg;
+use oro_mem::mapper::{AddressSegment as _, AddressSpace};
 #[cfg(debug_assertions)]
 use oro_mem::phys::{Phys, PhysAddr};
 
+use crate::mem::address_space::AddressSpaceLayout;
+
 /// The number of pages to allocate for the secondary core stacks.
 // TODO(qix-): Make this configurable.
 const SECONDARY_STACK_PAGES: usize = 16;
@@ -24,10 +28,14 @@ const SECONDARY_STACK_PAGES: usize = 16;
 /// the kernel or anything else magic like that.
 ///
 /// # Panics
-/// Panics if the DeviceTree blob is not provided.
+/// Panics if the DeviceTree blob is not provided, or
+/// doesn't describe a supported interrupt controller.
 pub unsafe fn boot_primary() -> ! {
 	crate::asm::disable_interrupts();
 
+	#[cfg(debug_assertions)]
+	oro_debug::init();
+
 	memory::prepare_memory();
 
 	// We now have a valid physical map; let's re-init
@@ -35,9 +43,48 @@ pub unsafe fn boot_primary() -> ! {
 	#[cfg(debug_assertions)]
 	oro_debug::init_with_offset(Phys::from_address_unchecked(0).virt());
 
+	protocol::check_populated();
+
+	// Discover the platform's devices.
+	let devices = dtb::scan(dtb::device_tree());
+
+	// Share the kernel heap segment between all cores; must happen
+	// before anything allocates, and before the secondaries (whose
+	// address spaces are copied from ours) are booted.
+	AddressSpaceLayout::kernel_heap()
+		.provision_as_shared(&AddressSpaceLayout::current_supervisor_space())
+		.expect("failed to provision the kernel heap segment");
+
+	// Likewise for the MMIO segment.
+	AddressSpaceLayout::mmio()
+		.provision_as_shared(&AddressSpaceLayout::current_supervisor_space())
+		.expect("failed to provision the kernel MMIO segment");
+
+	// Initialize the interrupt controller's distributor; each core
+	// initializes its own interface upon boot.
+	{
+		let gic = devices
+			.gic
+			.expect("no supported interrupt controller was found in the DeviceTree");
+
+		dbg!(
+			"GIC{:?}: distributor={:#016X} cpu={:#016X}",
+			gic.version,
+			gic.distributor.0,
+			gic.cpu.0
+		);
+
+		crate::gic::initialize(gic.version, gic.distributor, gic.cpu)
+			.expect("failed to initialize the GIC");
+	}
+
 	// Initialize the primary core.
 	crate::init::initialize_primary();
 
+	// Register the generic timer as the kernel's clock source.
+	// NOTE(qix-): Must happen before secondary cores are booted.
+	crate::timer::register_clock_source();
+
 	{
 		// Boot secondaries.
 		let num_cores = secondary::boot_secondaries(SECONDARY_STACK_PAGES);
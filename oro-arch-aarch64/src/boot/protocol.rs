@@ -1,6 +1,11 @@
 //! Kernel boot protocol requests for the AArch64 architecture.
 
-use oro_boot_protocol::{DeviceTreeRequest, MemoryMapRequest};
+use oro_boot_protocol::{
+	DeviceTreeRequest, MemoryMapRequest,
+	note::{FEATURE_DEVICE_TREE, KernelNote, KernelNoteSection},
+	util::Populated,
+};
+use oro_debug::dbg_err;
 
 /// The memory map request.
 ///
@@ -15,3 +20,26 @@ pub static MMAP_REQUEST: MemoryMapRequest = MemoryMapRequest::with_revision(0);
 #[used]
 #[link_section = ".oro_boot"]
 pub static DTB_REQUEST: DeviceTreeRequest = DeviceTreeRequest::with_revision(0);
+
+/// The kernel metadata note, checked by bootloaders prior to
+/// booting the kernel.
+#[used]
+#[link_section = ".note.oro"]
+pub static KERNEL_NOTE: KernelNoteSection =
+	KernelNoteSection::new(KernelNote::new(FEATURE_DEVICE_TREE));
+
+/// Strictly checks the `populated` flag of each request, logging an
+/// error for any that hold neither `0x00` nor `0xFF`, which indicates
+/// a buggy bootloader or memory corruption.
+///
+/// Such requests are treated as unpopulated regardless.
+pub fn check_populated() {
+	for (name, state) in [
+		("memory map", MMAP_REQUEST.populated_state()),
+		("DeviceTree", DTB_REQUEST.populated_state()),
+	] {
+		if let Populated::Invalid(raw) = state {
+			dbg_err!("{name} request has an invalid populated flag ({raw:#04X}); ignoring it");
+		}
+	}
+}
@@ -6,9 +6,8 @@ use core::{
 	sync::atomic::{AtomicBool, Ordering},
 };
 
-use oro_boot_protocol::device_tree::{DeviceTreeDataV0, DeviceTreeKind};
 use oro_debug::{dbg, dbg_err, dbg_warn};
-use oro_dtb::{FdtHeader, FdtPathFilter, FdtToken};
+use oro_dtb::{FdtPathFilter, FdtToken};
 use oro_macro::{asm_buffer, assert};
 use oro_mem::{
 	global_alloc::GlobalPfa,
@@ -35,22 +34,7 @@ use crate::{
 /// This function is inherently unsafe and must only be called
 /// once at kernel boot by the bootstrap processor (primary core).
 pub unsafe fn boot_secondaries(stack_pages: usize) -> usize {
-	// Get the devicetree blob.
-	let DeviceTreeKind::V0(dtb) = super::protocol::DTB_REQUEST
-		.response()
-		.expect("no DeviceTree blob response was provided")
-	else {
-		panic!("DeviceTree blob response was provided but was the wrong revision");
-	};
-
-	let DeviceTreeDataV0 { base, length } = dtb.assume_init_ref();
-	dbg!("got DeviceTree blob of {} bytes", length);
-
-	let dtb = FdtHeader::from(
-		Phys::from_address_unchecked(*base).as_ptr().unwrap(),
-		Some(*length),
-	)
-	.expect("dtb is invalid");
+	let dtb = super::dtb::device_tree();
 	let boot_cpuid = dtb.phys_id();
 	dbg!("dtb is valid; primary core id is {boot_cpuid}");
 
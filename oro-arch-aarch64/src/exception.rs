@@ -0,0 +1,168 @@
+//! Exception handling for the AArch64 architecture.
+//!
+//! Installs the exception vector table (see [`install()`]). Only IRQs
+//! taken from EL1 (using `SP_EL1`) are handled; these are acknowledged
+//! via the core's GIC and dispatched. Any other exception panics.
+//!
+//! IRQ handlers do not call into the scheduler themselves; they instead
+//! flag the event on the core's state, to be picked up by the core's
+//! scheduler loop (see [`crate::init::boot()`]) upon waking.
+
+use core::{arch::global_asm, sync::atomic::Ordering::Relaxed};
+
+use oro_debug::dbg_warn;
+use oro_kernel::interrupt::InterruptController;
+
+use crate::gic::{HALT_SGI, RESCHEDULE_SGI, VIRTUAL_TIMER_PPI};
+
+/// The names of the exception vector table entries, in order.
+const VECTOR_NAMES: [&str; 16] = [
+	"EL1t synchronous",
+	"EL1t IRQ",
+	"EL1t FIQ",
+	"EL1t SError",
+	"EL1h synchronous",
+	"EL1h IRQ",
+	"EL1h FIQ",
+	"EL1h SError",
+	"EL0 (AArch64) synchronous",
+	"EL0 (AArch64) IRQ",
+	"EL0 (AArch64) FIQ",
+	"EL0 (AArch64) SError",
+	"EL0 (AArch32) synchronous",
+	"EL0 (AArch32) IRQ",
+	"EL0 (AArch32) FIQ",
+	"EL0 (AArch32) SError",
+];
+
+// NOTE(qix-): Each entry is 128 bytes; the table itself must be
+// NOTE(qix-): aligned to 2KiB. The IRQ trampoline preserves all
+// NOTE(qix-): caller-saved registers, along with the exception
+// NOTE(qix-): link register and saved program status.
+global_asm!(
+	".pushsection .text.oro_aarch64_vectors, \"ax\"",
+	".balign 2048",
+	".global oro_aarch64_vectors",
+	"oro_aarch64_vectors:",
+	".set idx, 0",
+	".rept 16",
+	".balign 128",
+	".if idx == 5",
+	"b oro_aarch64_irq",
+	".else",
+	"mov x0, #idx",
+	"b oro_aarch64_unhandled_exception",
+	".endif",
+	".set idx, idx + 1",
+	".endr",
+	"",
+	"oro_aarch64_irq:",
+	"sub sp, sp, #192",
+	"stp x0, x1, [sp, #0]",
+	"stp x2, x3, [sp, #16]",
+	"stp x4, x5, [sp, #32]",
+	"stp x6, x7, [sp, #48]",
+	"stp x8, x9, [sp, #64]",
+	"stp x10, x11, [sp, #80]",
+	"stp x12, x13, [sp, #96]",
+	"stp x14, x15, [sp, #112]",
+	"stp x16, x17, [sp, #128]",
+	"stp x18, x29, [sp, #144]",
+	"mrs x0, ELR_EL1",
+	"mrs x1, SPSR_EL1",
+	"stp x30, x0, [sp, #160]",
+	"str x1, [sp, #176]",
+	"bl oro_aarch64_irq_rust",
+	"ldr x1, [sp, #176]",
+	"ldp x30, x0, [sp, #160]",
+	"msr SPSR_EL1, x1",
+	"msr ELR_EL1, x0",
+	"ldp x18, x29, [sp, #144]",
+	"ldp x16, x17, [sp, #128]",
+	"ldp x14, x15, [sp, #112]",
+	"ldp x12, x13, [sp, #96]",
+	"ldp x10, x11, [sp, #80]",
+	"ldp x8, x9, [sp, #64]",
+	"ldp x6, x7, [sp, #48]",
+	"ldp x4, x5, [sp, #32]",
+	"ldp x2, x3, [sp, #16]",
+	"ldp x0, x1, [sp, #0]",
+	"add sp, sp, #192",
+	"eret",
+	".popsection",
+);
+
+extern "C" {
+	/// The exception vector table.
+	static oro_aarch64_vectors: u8;
+}
+
+/// Installs the exception vector table on the current core.
+///
+/// # Safety
+/// Must be called once per core during boot, after the kernel has
+/// been initialized for the core, and prior to unmasking IRQs.
+pub unsafe fn install() {
+	crate::asm::store_vbar(core::ptr::addr_of!(oro_aarch64_vectors) as u64);
+}
+
+/// Handles an IRQ taken from EL1.
+///
+/// # Safety
+/// Must only be called by the exception vector table.
+#[no_mangle]
+unsafe extern "C" fn oro_aarch64_irq_rust() {
+	let kernel = crate::Kernel::get();
+	kernel.enter_interrupt();
+
+	let gic = &kernel.core().gic;
+
+	// NOTE(qix-): Spurious interrupts must not be acknowledged with an EOI.
+	if let Some(intid) = gic.acknowledge() {
+		match intid {
+			intid if intid == u32::from(VIRTUAL_TIMER_PPI) => {
+				// NOTE(qix-): The timer interrupt is level-triggered; it must be
+				// NOTE(qix-): deasserted prior to the EOI. The scheduler re-arms it.
+				crate::timer::cancel_tick();
+				kernel.core().timer_expired.store(true, Relaxed);
+			}
+			intid if intid == u32::from(RESCHEDULE_SGI) => {
+				kernel.core().reschedule_requested.store(true, Relaxed);
+			}
+			intid if intid == u32::from(HALT_SGI) => {
+				// Sent by a panicking core. IRQs are masked upon exception entry.
+				crate::asm::halt();
+			}
+			intid => {
+				dbg_warn!("unhandled interrupt: {intid}");
+			}
+		}
+
+		gic.eoi();
+	}
+
+	kernel.exit_interrupt();
+}
+
+/// Handles any exception other than an IRQ taken from EL1.
+///
+/// # Safety
+/// Must only be called by the exception vector table.
+#[no_mangle]
+unsafe extern "C" fn oro_aarch64_unhandled_exception(idx: usize) -> ! {
+	let (esr, elr, far): (u64, u64, u64);
+	core::arch::asm!(
+		"mrs {0:x}, ESR_EL1",
+		"mrs {1:x}, ELR_EL1",
+		"mrs {2:x}, FAR_EL1",
+		out(reg) esr,
+		out(reg) elr,
+		out(reg) far,
+		options(nostack, nomem, preserves_flags)
+	);
+
+	panic!(
+		"unhandled exception: {}: ESR={esr:#016X} ELR={elr:#016X} FAR={far:#016X}",
+		VECTOR_NAMES.get(idx).copied().unwrap_or("?")
+	);
+}
@@ -0,0 +1,625 @@
+//! Provides the Generic Interrupt Controller (GIC) implementation
+//! for the Oro kernel, supporting both GICv2 and GICv3.
+//!
+//! The GIC is discovered from the DeviceTree during boot, after which
+//! the primary core initializes the (shared) distributor via
+//! [`initialize()`]. Each core then initializes its own CPU interface
+//! (and, on GICv3, its redistributor) via [`Gic::new_for_core()`].
+//!
+//! All interrupts are delivered as IRQs (non-secure group 1 on GICv3),
+//! with the same priority; interrupts are thus never nested.
+//!
+//! Interrupt IDs (INTIDs) below 256 double as the kernel's interrupt
+//! "vectors" (see [`oro_kernel::interrupt::InterruptController`]).
+//!
+//! Documentation found in the ARM GIC Architecture Specification
+//! (IHI 0069) and the GIC-400 Technical Reference Manual (DDI 0471).
+
+use core::{
+	arch::asm,
+	ptr::null_mut,
+	sync::atomic::{
+		AtomicPtr, AtomicU8, AtomicU32, AtomicU64, AtomicUsize,
+		Ordering::{Acquire, Relaxed, Release},
+	},
+};
+
+use oro_kernel::{core_id::CoreId, interrupt::InterruptController};
+
+use crate::mem::mmio::{MmioError, map_mmio};
+
+/// The SGI (software-generated interrupt) used to ask a core to
+/// reschedule (see [`crate::Arch`]'s `send_reschedule()`).
+pub const RESCHEDULE_SGI: u8 = 1;
+/// The SGI (software-generated interrupt) used by a panicking core
+/// to halt all others (see [`crate::Arch`]'s `halt_other_cores()`).
+pub const HALT_SGI: u8 = 2;
+/// The PPI (private peripheral interrupt) of the generic timer's
+/// virtual timer (PPI 11).
+// NOTE(qix-): Fixed by the Server Base System Architecture; the
+// NOTE(qix-): DeviceTree's `/timer` node is thus not consulted.
+pub const VIRTUAL_TIMER_PPI: u8 = 27;
+
+/// The priority given to all interrupts.
+const DEFAULT_PRIORITY: u8 = 0xA0;
+/// The lowest priority mask, allowing all interrupts through.
+const PRIORITY_MASK_ALL: u32 = 0xFF;
+/// The first of the special INTIDs (1020..=1023); acknowledgements
+/// yielding them are spurious.
+const SPECIAL_INTID_START: u32 = 1020;
+
+/// Distributor control register.
+const GICD_CTLR: usize = 0x0000;
+/// Distributor type register.
+const GICD_TYPER: usize = 0x0004;
+/// Interrupt group registers (one bit per INTID).
+const GICD_IGROUPR: usize = 0x0080;
+/// Interrupt set-enable registers (one bit per INTID).
+const GICD_ISENABLER: usize = 0x0100;
+/// Interrupt clear-enable registers (one bit per INTID).
+const GICD_ICENABLER: usize = 0x0180;
+/// Interrupt priority registers (one byte per INTID).
+const GICD_IPRIORITYR: usize = 0x0400;
+/// Interrupt processor targets registers (one byte per INTID; GICv2 only).
+const GICD_ITARGETSR: usize = 0x0800;
+/// Software generated interrupt register (GICv2 only).
+const GICD_SGIR: usize = 0x0F00;
+/// Interrupt routing registers (8 bytes per INTID; GICv3 only).
+const GICD_IROUTER: usize = 0x6000;
+/// The register write pending bit in `GICD_CTLR` (GICv3 only).
+const GICD_CTLR_RWP: u32 = 1 << 31;
+/// The affinity routing enable bit in `GICD_CTLR` (GICv3 only).
+const GICD_CTLR_ARE: u32 = 1 << 4;
+/// The group 1 enable bit in `GICD_CTLR`.
+const GICD_CTLR_ENABLE_GRP1: u32 = 1 << 1;
+/// The group 0 enable bit in `GICD_CTLR`.
+const GICD_CTLR_ENABLE_GRP0: u32 = 1 << 0;
+
+/// CPU interface control register (GICv2 only).
+const GICC_CTLR: usize = 0x00;
+/// CPU interface priority mask register (GICv2 only).
+const GICC_PMR: usize = 0x04;
+/// CPU interface binary point register (GICv2 only).
+const GICC_BPR: usize = 0x08;
+/// CPU interface interrupt acknowledge register (GICv2 only).
+const GICC_IAR: usize = 0x0C;
+/// CPU interface end of interrupt register (GICv2 only).
+const GICC_EOIR: usize = 0x10;
+/// The size of the CPU interface's register block (GICv2 only).
+const GICC_SIZE: usize = 0x2000;
+
+/// Redistributor control register (GICv3 only).
+const GICR_CTLR: usize = 0x0000;
+/// Redistributor type register; 64 bits (GICv3 only).
+const GICR_TYPER: usize = 0x0008;
+/// Redistributor wake register (GICv3 only).
+const GICR_WAKER: usize = 0x0014;
+/// The offset of a redistributor's SGI and PPI frame from its
+/// control frame (GICv3 only).
+const GICR_SGI_FRAME: usize = 0x1_0000;
+/// The size of a redistributor without virtual LPI support (GICv3 only).
+const GICR_STRIDE: usize = 0x2_0000;
+/// The size of a redistributor with virtual LPI support (GICv4 only).
+const GICR_STRIDE_VLPI: usize = 0x4_0000;
+/// The register write pending bit in `GICR_CTLR`.
+const GICR_CTLR_RWP: u32 = 1 << 3;
+/// The virtual LPI support bit in `GICR_TYPER`.
+const GICR_TYPER_VLPIS: u64 = 1 << 1;
+/// The "last redistributor in the region" bit in `GICR_TYPER`.
+const GICR_TYPER_LAST: u64 = 1 << 4;
+/// The processor sleep bit in `GICR_WAKER`.
+const GICR_WAKER_PROCESSOR_SLEEP: u32 = 1 << 1;
+/// The children asleep bit in `GICR_WAKER`.
+const GICR_WAKER_CHILDREN_ASLEEP: u32 = 1 << 2;
+
+/// The version of a Generic Interrupt Controller (GIC).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GicVersion {
+	/// GICv2 (`arm,gic-400`, `arm,cortex-a15-gic`, etc.)
+	V2,
+	/// GICv3 (`arm,gic-v3`)
+	V3,
+}
+
+/// The version of the GIC set up by [`initialize()`]; `0` if
+/// not yet initialized.
+static VERSION: AtomicU8 = AtomicU8::new(0);
+/// The (virtual) base of the distributor's register block.
+static GICD: AtomicPtr<u8> = AtomicPtr::new(null_mut());
+/// The (virtual) base of the CPU interface's register block (GICv2),
+/// or of the redistributor region (GICv3).
+static CPU_REGION: AtomicPtr<u8> = AtomicPtr::new(null_mut());
+/// The size of the redistributor region, in bytes (GICv3 only).
+static CPU_REGION_LEN: AtomicUsize = AtomicUsize::new(0);
+/// The number of cores whose interfaces have been initialized.
+static ONLINE: AtomicUsize = AtomicUsize::new(0);
+/// The core ID (plus one) owning each of the (up to 8) GICv2 CPU
+/// interfaces, or `0` if unowned. Used to target SGIs.
+static V2_INTERFACES: [AtomicU64; 8] = [const { AtomicU64::new(0) }; 8];
+
+/// Initializes the distributor of the given GIC, mapping all of its
+/// register blocks into the kernel MMIO segment. All shared peripheral
+/// interrupts (SPIs) are left masked.
+///
+/// # Safety
+/// Must be called exactly once, by the primary core, prior to any
+/// call to [`Gic::new_for_core()`]. The given regions must be those
+/// of the system's GIC.
+pub unsafe fn initialize(
+	version: GicVersion,
+	distributor: (u64, u64),
+	cpu: (u64, u64),
+) -> Result<(), MmioError> {
+	let cpu_len = match version {
+		GicVersion::V2 => GICC_SIZE,
+		GicVersion::V3 => usize::try_from(cpu.1).map_err(|_| MmioError::OutOfVirtualSpace)?,
+	};
+
+	let gicd = map_mmio(
+		distributor.0,
+		usize::try_from(distributor.1).map_err(|_| MmioError::OutOfVirtualSpace)?,
+	)?;
+	let cpu_base = map_mmio(cpu.0, cpu_len)?;
+
+	write32(gicd, GICD_CTLR, 0);
+	wait_for_rwp(version, gicd);
+
+	let num_intids =
+		(32 * ((read32(gicd, GICD_TYPER) as usize & 0x1F) + 1)).min(SPECIAL_INTID_START as usize);
+
+	for intid in (32..num_intids).step_by(32) {
+		write32(gicd, GICD_ICENABLER + (intid >> 3), 0xFFFF_FFFF);
+		if version == GicVersion::V3 {
+			write32(gicd, GICD_IGROUPR + (intid >> 3), 0xFFFF_FFFF);
+		}
+	}
+
+	for intid in 32..num_intids {
+		gicd.add(GICD_IPRIORITYR + intid)
+			.write_volatile(DEFAULT_PRIORITY);
+	}
+
+	match version {
+		GicVersion::V2 => {
+			write32(
+				gicd,
+				GICD_CTLR,
+				GICD_CTLR_ENABLE_GRP0 | GICD_CTLR_ENABLE_GRP1,
+			);
+		}
+		GicVersion::V3 => {
+			// NOTE(qix-): Affinity routing must be enabled prior to the groups.
+			write32(gicd, GICD_CTLR, GICD_CTLR_ARE);
+			wait_for_rwp(version, gicd);
+			write32(gicd, GICD_CTLR, GICD_CTLR_ARE | GICD_CTLR_ENABLE_GRP1);
+			wait_for_rwp(version, gicd);
+		}
+	}
+
+	GICD.store(gicd, Relaxed);
+	CPU_REGION.store(cpu_base, Relaxed);
+	CPU_REGION_LEN.store(cpu_len, Relaxed);
+	VERSION.store(
+		match version {
+			GicVersion::V2 => 2,
+			GicVersion::V3 => 3,
+		},
+		Release,
+	);
+
+	Ok(())
+}
+
+/// Returns whether or not any core other than the current one
+/// has initialized its GIC interface.
+#[must_use]
+pub fn others_online() -> bool {
+	ONLINE.load(Acquire) > 1
+}
+
+/// Returns the kernel's [`CoreId`] for the current core; the
+/// affinity fields of its `MPIDR_EL1`, packed as
+/// `Aff3:Aff2:Aff1:Aff0`.
+#[must_use]
+pub fn current_core_id() -> CoreId {
+	let mpidr = crate::asm::read_mpidr();
+	CoreId::new((mpidr & 0xFF_FFFF) | ((mpidr >> 8) & 0xFF00_0000))
+}
+
+/// The destination of an SGI (software-generated interrupt) sent
+/// via [`Gic::send_sgi()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SgiDest {
+	/// The current core only.
+	Current,
+	/// All cores except the current one.
+	AllExcludingSelf,
+	/// The core with the given ID.
+	Core(CoreId),
+}
+
+/// A core's interface to the GIC.
+pub struct Gic {
+	/// The version of the GIC.
+	version: GicVersion,
+	/// The (virtual) base of the distributor's register block.
+	gicd:    *mut u8,
+	/// The (virtual) base of the CPU interface's register block (GICv2),
+	/// or of this core's redistributor (GICv3).
+	cpu:     *mut u8,
+	/// The core's ID.
+	id:      CoreId,
+	/// The raw acknowledgement value of the interrupt currently
+	/// being serviced, which must be handed back upon EOI.
+	active:  AtomicU32,
+}
+
+// SAFETY(qix-): The register blocks are mapped into all cores, at the
+// SAFETY(qix-): same location; the registers themselves are either banked
+// SAFETY(qix-): per-core, or are safe to access concurrently.
+unsafe impl Send for Gic {}
+// SAFETY(qix-): See above.
+unsafe impl Sync for Gic {}
+
+impl Gic {
+	/// Initializes the current core's CPU interface (and, on GICv3,
+	/// its redistributor), returning the core's handle to the GIC.
+	///
+	/// SGIs are unmasked; all PPIs are left masked.
+	///
+	/// # Safety
+	/// Must be called exactly once per core, on the core itself, after
+	/// [`initialize()`].
+	///
+	/// # Panics
+	/// Panics if the GIC hasn't been initialized, or if (on GICv3) the
+	/// core's redistributor cannot be found.
+	pub unsafe fn new_for_core() -> Self {
+		let version = match VERSION.load(Acquire) {
+			2 => GicVersion::V2,
+			3 => GicVersion::V3,
+			_ => panic!("GIC has not been initialized"),
+		};
+
+		let gicd = GICD.load(Relaxed);
+		let id = current_core_id();
+
+		let this = match version {
+			GicVersion::V2 => {
+				let gicc = CPU_REGION.load(Relaxed);
+
+				// NOTE(qix-): The first targets register is banked, and reads
+				// NOTE(qix-): back as the current core's own interface mask.
+				let mask = gicd.add(GICD_ITARGETSR).read_volatile();
+				if mask.is_power_of_two() {
+					V2_INTERFACES[mask.trailing_zeros() as usize].store(id.get() + 1, Relaxed);
+				}
+
+				Self::init_private(gicd);
+
+				write32(gicc, GICC_PMR, PRIORITY_MASK_ALL);
+				write32(gicc, GICC_BPR, 0);
+				write32(gicc, GICC_CTLR, 0b11);
+
+				Self {
+					version,
+					gicd,
+					cpu: gicc,
+					id,
+					active: AtomicU32::new(0),
+				}
+			}
+			GicVersion::V3 => {
+				let gicr =
+					find_redistributor(id).expect("failed to find the core's GIC redistributor");
+
+				// Wake up the redistributor.
+				write32(
+					gicr,
+					GICR_WAKER,
+					read32(gicr, GICR_WAKER) & !GICR_WAKER_PROCESSOR_SLEEP,
+				);
+				while read32(gicr, GICR_WAKER) & GICR_WAKER_CHILDREN_ASLEEP != 0 {
+					core::hint::spin_loop();
+				}
+
+				let sgi_frame = gicr.add(GICR_SGI_FRAME);
+				write32(sgi_frame, GICD_IGROUPR, 0xFFFF_FFFF);
+				Self::init_private(sgi_frame);
+				while read32(gicr, GICR_CTLR) & GICR_CTLR_RWP != 0 {
+					core::hint::spin_loop();
+				}
+
+				// Enable the system register interface.
+				let sre: u64;
+				asm!("mrs {0:x}, S3_0_C12_C12_5", out(reg) sre, options(nostack, nomem));
+				asm!(
+					"msr S3_0_C12_C12_5, {0:x}",
+					"isb",
+					in(reg) sre | 1,
+					options(nostack)
+				);
+
+				asm!(
+					"msr S3_0_C4_C6_0, {0:x}",
+					"msr S3_0_C12_C12_3, xzr",
+					"msr S3_0_C12_C12_7, {1:x}",
+					"isb",
+					in(reg) u64::from(PRIORITY_MASK_ALL),
+					in(reg) 1_u64,
+					options(nostack)
+				);
+
+				Self {
+					version,
+					gicd,
+					cpu: gicr,
+					id,
+					active: AtomicU32::new(0),
+				}
+			}
+		};
+
+		ONLINE.fetch_add(1, Release);
+
+		this
+	}
+
+	/// Sets up the core's private interrupts (SGIs and PPIs) within
+	/// the given (banked distributor, or redistributor SGI) frame;
+	/// SGIs are enabled and PPIs are masked.
+	///
+	/// # Safety
+	/// `frame` must be the core's banked private interrupt frame.
+	unsafe fn init_private(frame: *mut u8) {
+		write32(frame, GICD_ICENABLER, 0xFFFF_0000);
+		write32(frame, GICD_ISENABLER, 0x0000_FFFF);
+
+		for intid in 0..32 {
+			frame
+				.add(GICD_IPRIORITYR + intid)
+				.write_volatile(DEFAULT_PRIORITY);
+		}
+	}
+
+	/// Acknowledges the highest priority pending interrupt, returning
+	/// its INTID, or `None` if the interrupt was spurious.
+	///
+	/// Each acknowledged interrupt must be followed by exactly one
+	/// [`InterruptController::eoi()`] prior to acknowledging another.
+	pub fn acknowledge(&self) -> Option<u32> {
+		let iar = match self.version {
+			// SAFETY(qix-): The CPU interface is mapped for the lifetime of the kernel.
+			GicVersion::V2 => unsafe { read32(self.cpu, GICC_IAR) },
+			GicVersion::V3 => {
+				let iar: u64;
+				// SAFETY(qix-): Reads `ICC_IAR1_EL1`, enabled during core initialization.
+				unsafe {
+					asm!("mrs {0:x}, S3_0_C12_C12_0", out(reg) iar, options(nostack));
+				}
+				// NOTE(qix-): The upper 40 bits are reserved.
+				iar as u32
+			}
+		};
+
+		let intid = match self.version {
+			GicVersion::V2 => iar & 0x3FF,
+			GicVersion::V3 => iar & 0xFF_FFFF,
+		};
+
+		if (SPECIAL_INTID_START..SPECIAL_INTID_START + 4).contains(&intid) {
+			return None;
+		}
+
+		self.active.store(iar, Relaxed);
+
+		Some(intid)
+	}
+
+	/// Sends the given SGI (`0..=15`) to the given destination.
+	///
+	/// Must not allocate, log, or take any locks, as it's used
+	/// by the panic path.
+	pub fn send_sgi(&self, dest: SgiDest, sgi: u8) {
+		debug_assert!(sgi < 16, "SGI out of range");
+		let sgi = u32::from(sgi & 0xF);
+
+		match self.version {
+			GicVersion::V2 => {
+				let sgir = match dest {
+					SgiDest::Current => (0b10 << 24) | sgi,
+					SgiDest::AllExcludingSelf => (0b01 << 24) | sgi,
+					SgiDest::Core(core) => {
+						let Some(interface) = V2_INTERFACES
+							.iter()
+							.position(|owner| owner.load(Relaxed) == core.get() + 1)
+						else {
+							// NOTE(qix-): The core hasn't come online (yet); it'll
+							// NOTE(qix-): pick up whatever it was being told once it does.
+							return;
+						};
+
+						(1 << (16 + interface)) | sgi
+					}
+				};
+
+				// SAFETY(qix-): The distributor is mapped for the lifetime of the kernel.
+				unsafe {
+					write32(self.gicd, GICD_SGIR, sgir);
+				}
+			}
+			GicVersion::V3 => {
+				let sgi = u64::from(sgi) << 24;
+				let sgi1r = match dest {
+					SgiDest::Current => sgi | sgi1r_target(self.id),
+					SgiDest::AllExcludingSelf => sgi | (1 << 40),
+					SgiDest::Core(core) => sgi | sgi1r_target(core),
+				};
+
+				// SAFETY(qix-): Writes `ICC_SGI1R_EL1`, enabled during core initialization.
+				unsafe {
+					asm!(
+						"dsb ishst",
+						"msr S3_0_C12_C11_5, {0:x}",
+						"isb",
+						in(reg) sgi1r,
+						options(nostack)
+					);
+				}
+			}
+		}
+	}
+
+	/// Enables or disables the given INTID's delivery, routing it to
+	/// the current core if it's a shared peripheral interrupt (SPI).
+	fn set_enabled(&self, intid: usize, enabled: bool) {
+		let reg = if enabled {
+			GICD_ISENABLER
+		} else {
+			GICD_ICENABLER
+		};
+
+		// SAFETY(qix-): The register blocks are mapped for the lifetime of the kernel;
+		// SAFETY(qix-): the set/clear enable registers are write-1-to-modify.
+		unsafe {
+			if intid < 32 {
+				let frame = match self.version {
+					GicVersion::V2 => self.gicd,
+					GicVersion::V3 => self.cpu.add(GICR_SGI_FRAME),
+				};
+
+				write32(frame, reg, 1 << intid);
+				return;
+			}
+
+			if enabled {
+				match self.version {
+					GicVersion::V2 => {
+						let mask = self.gicd.add(GICD_ITARGETSR).read_volatile();
+						self.gicd.add(GICD_ITARGETSR + intid).write_volatile(mask);
+					}
+					GicVersion::V3 => {
+						let id = self.id.get();
+						let route = (id & 0xFF_FFFF) | ((id & 0xFF00_0000) << 8);
+						self.gicd
+							.add(GICD_IROUTER + intid * 8)
+							.cast::<u64>()
+							.write_volatile(route);
+					}
+				}
+			}
+
+			write32(self.gicd, reg + ((intid >> 5) << 2), 1 << (intid % 32));
+		}
+	}
+}
+
+impl InterruptController for Gic {
+	/// Signals the end of the interrupt last returned by
+	/// [`Gic::acknowledge()`].
+	fn eoi(&self) {
+		let iar = self.active.load(Relaxed);
+
+		match self.version {
+			// SAFETY(qix-): The CPU interface is mapped for the lifetime of the kernel.
+			GicVersion::V2 => unsafe { write32(self.cpu, GICC_EOIR, iar) },
+			// SAFETY(qix-): Writes `ICC_EOIR1_EL1`, enabled during core initialization.
+			GicVersion::V3 => unsafe {
+				asm!(
+					"msr S3_0_C12_C12_1, {0:x}",
+					"isb",
+					in(reg) u64::from(iar),
+					options(nostack)
+				);
+			},
+		}
+	}
+
+	/// Masks the given INTID.
+	///
+	/// SGIs and PPIs are masked only for the current core.
+	fn mask(&self, vector: u8) {
+		self.set_enabled(usize::from(vector), false);
+	}
+
+	/// Unmasks the given INTID.
+	///
+	/// SGIs and PPIs are unmasked only for the current core;
+	/// SPIs are additionally routed to the current core.
+	fn unmask(&self, vector: u8) {
+		self.set_enabled(usize::from(vector), true);
+	}
+}
+
+/// Returns the `Aff3`, `Aff2`, `Aff1`, range selector and target
+/// list fields of an `ICC_SGI1R_EL1` value targeting the given core.
+fn sgi1r_target(core: CoreId) -> u64 {
+	let id = core.get();
+	let aff0 = id & 0xFF;
+	let aff1 = (id >> 8) & 0xFF;
+	let aff2 = (id >> 16) & 0xFF;
+	let aff3 = (id >> 24) & 0xFF;
+
+	(aff3 << 48) | ((aff0 >> 4) << 44) | (aff2 << 32) | (aff1 << 16) | (1 << (aff0 % 16))
+}
+
+/// Finds the redistributor of the core with the given ID within
+/// the redistributor region (GICv3 only).
+///
+/// # Safety
+/// The GIC must have been initialized.
+unsafe fn find_redistributor(id: CoreId) -> Option<*mut u8> {
+	let region = CPU_REGION.load(Relaxed);
+	let region_len = CPU_REGION_LEN.load(Relaxed);
+
+	let mut offset = 0;
+	while offset + GICR_STRIDE <= region_len {
+		let gicr = region.add(offset);
+		let typer = gicr.add(GICR_TYPER).cast::<u64>().read_volatile();
+
+		if typer >> 32 == id.get() {
+			return Some(gicr);
+		}
+
+		if typer & GICR_TYPER_LAST != 0 {
+			break;
+		}
+
+		offset += if typer & GICR_TYPER_VLPIS == 0 {
+			GICR_STRIDE
+		} else {
+			GICR_STRIDE_VLPI
+		};
+	}
+
+	None
+}
+
+/// Waits for a pending distributor register write to complete
+/// (GICv3 only; a no-op on GICv2).
+///
+/// # Safety
+/// `gicd` must be the distributor's register block.
+unsafe fn wait_for_rwp(version: GicVersion, gicd: *mut u8) {
+	if version == GicVersion::V3 {
+		while read32(gicd, GICD_CTLR) & GICD_CTLR_RWP != 0 {
+			core::hint::spin_loop();
+		}
+	}
+}
+
+/// Reads a 32-bit register at the given offset from a register block.
+///
+/// # Safety
+/// `base + offset` must be a valid, mapped register.
+unsafe fn read32(base: *mut u8, offset: usize) -> u32 {
+	base.add(offset).cast::<u32>().read_volatile()
+}
+
+/// Writes a 32-bit register at the given offset from a register block.
+///
+/// # Safety
+/// `base + offset` must be a valid, mapped register.
+unsafe fn write32(base: *mut u8, offset: usize, value: u32) {
+	base.add(offset).cast::<u32>().write_volatile(value);
+}
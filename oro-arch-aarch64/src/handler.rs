@@ -0,0 +1,41 @@
+//! Implementation of [`oro_kernel::scheduler::Handler`] for the AArch64 architecture.
+
+use oro_kernel::time::Duration;
+
+/// AArch64 [`oro_kernel::scheduler::Handler`] implementation
+/// for the Oro kernel scheduler.
+///
+/// The scheduler timer is the generic timer's virtual timer
+/// (see [`crate::timer`]), whose ticks are those of the system counter.
+pub struct Handler;
+
+impl Handler {
+	/// Creates a new handler instance.
+	#[must_use]
+	pub fn new() -> Self {
+		Self
+	}
+}
+
+impl oro_kernel::scheduler::Handler<crate::Arch> for Handler {
+	fn schedule_timer(&self, ticks: u32) {
+		crate::timer::arm_tick_after(u64::from(ticks));
+	}
+
+	fn cancel_timer(&self) {
+		crate::timer::cancel_tick();
+	}
+
+	fn duration_to_ticks(&self, duration: Duration) -> u32 {
+		crate::timer::duration_to_ticks(duration)
+	}
+
+	fn migrate_thread(
+		_kernel: &oro_kernel::Kernel<crate::Arch>,
+		_thread: &mut oro_kernel::thread::Thread<crate::Arch>,
+	) {
+		// NOTE(qix-): Core-local kernel mappings live in each core's own
+		// NOTE(qix-): TTBR1 tables, whereas threads only own TTBR0 tables;
+		// NOTE(qix-): there's nothing to re-map.
+	}
+}
@@ -1,9 +1,15 @@
 //! Architecture / core initialization
 //! routines and global state definitions.
 
-use core::mem::MaybeUninit;
+use core::{
+	mem::MaybeUninit,
+	sync::atomic::{AtomicBool, Ordering::Relaxed},
+};
 
-use oro_kernel::KernelState;
+use oro_kernel::{KernelState, interrupt::InterruptController};
+use oro_sync::Lock;
+
+use crate::{gic::Gic, handler::Handler};
 
 /// The global kernel state. Initialized once during boot
 /// and re-used across all cores.
@@ -45,14 +51,61 @@ pub unsafe fn initialize_primary() {
 pub unsafe fn boot() -> ! {
 	// SAFETY(qix-): THIS MUST ABSOLUTELY BE FIRST.
 	#[expect(static_mut_refs)]
-	let _kernel = crate::Kernel::initialize_for_core(
-		0, // TODO(qix-): pass in the core ID
+	let kernel = crate::Kernel::initialize_for_core(
+		crate::gic::current_core_id(),
 		KERNEL_STATE.assume_init_ref(),
-		(),
+		crate::CoreState {
+			gic: Gic::new_for_core(),
+			timer_expired: AtomicBool::new(false),
+			reschedule_requested: AtomicBool::new(false),
+		},
 	)
 	.expect("failed to initialize kernel");
 
+	crate::exception::install();
+
+	// Note: the timer stays disarmed until the
+	// scheduler arms it via `Handler::schedule_timer`.
+	kernel.core().gic.unmask(crate::gic::VIRTUAL_TIMER_PPI);
+
 	oro_debug::dbg!("boot");
 
-	crate::asm::halt();
+	let handler = Handler::new();
+	loop {
+		let maybe_ctx = {
+			let mut lock = kernel.scheduler().lock();
+			let ctx = if kernel.core().timer_expired.swap(false, Relaxed) {
+				lock.event_timer_expired(&handler)
+			} else if kernel.core().reschedule_requested.swap(false, Relaxed) {
+				lock.event_reschedule(&handler)
+			} else {
+				lock.event_idle(&handler)
+			};
+			drop(lock);
+			ctx
+		};
+
+		// TODO(qix-): Switch to the thread once userspace is supported on aarch64.
+		assert!(
+			maybe_ctx.is_none(),
+			"switching to user threads is not yet supported on aarch64"
+		);
+
+		if kernel.take_reschedule_pending() {
+			// A thread was queued onto this core since the
+			// scheduler last ran; don't idle past it.
+			continue;
+		}
+
+		// Return any pages freed in the meantime to the
+		// page frame allocator while no locks are held.
+		#[cfg(not(test))]
+		crate::KERNEL_HEAP.reclaim();
+
+		// Nothing to do. Wait for an interrupt with IRQs masked
+		// (which still wakes the core), and only then take it,
+		// such that no event is missed in between.
+		crate::asm::halt_once_and_wait();
+		crate::asm::take_pending_irqs();
+	}
 }
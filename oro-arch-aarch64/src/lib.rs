@@ -33,14 +33,23 @@
 
 pub mod asm;
 pub mod boot;
+pub mod exception;
+pub mod gic;
+pub mod handler;
 pub mod mair;
 pub mod mem;
 pub mod psci;
 pub mod reg;
+pub mod timer;
 
 pub(crate) mod init;
 
+use core::sync::atomic::AtomicBool;
+
 use oro_elf::{ElfClass, ElfEndianness, ElfMachine};
+use oro_kernel::core_id::CoreId;
+
+use crate::gic::SgiDest;
 
 /// The ELF class for the AArch64 architecture.
 pub const ELF_CLASS: ElfClass = ElfClass::Class64;
@@ -57,15 +66,80 @@ pub(crate) struct Arch;
 
 impl oro_kernel::Arch for Arch {
 	type AddrSpace = crate::mem::address_space::AddressSpaceLayout;
+	type CoreState = CoreState;
+	type IntCtrl = gic::Gic;
+	type InterruptState = u64;
+
+	const ELF_CLASS: ElfClass = ELF_CLASS;
+	const ELF_ENDIANNESS: ElfEndianness = ELF_ENDIANNESS;
+	const ELF_MACHINE: ElfMachine = ELF_MACHINE;
+
+	fn interrupt_controller(core: &Self::CoreState) -> &Self::IntCtrl {
+		&core.gic
+	}
+
+	fn fetch_interrupts() -> Self::InterruptState {
+		crate::asm::fetch_daif()
+	}
+
+	fn disable_interrupts() {
+		crate::asm::disable_interrupts();
+	}
+
+	fn restore_interrupts(state: Self::InterruptState) {
+		crate::asm::restore_daif(state);
+	}
+
+	fn send_reschedule(core: CoreId) {
+		Kernel::get()
+			.core()
+			.gic
+			.send_sgi(SgiDest::Core(core), gic::RESCHEDULE_SGI);
+	}
+
+	fn halt_other_cores() {
+		if !gic::others_online() {
+			return;
+		}
+
+		Kernel::get()
+			.core()
+			.gic
+			.send_sgi(SgiDest::AllExcludingSelf, gic::HALT_SGI);
+	}
+
+	fn flush_tlb_range(virt: usize, len: usize) {
+		crate::asm::invalidate_tlb_range_el1_is(virt, len);
+	}
+
+	fn halt_once_and_wait() {
+		crate::asm::halt_once_and_wait();
+	}
+}
+
+/// The kernel's panic path (see [`oro_kernel::panic()`]).
+///
+/// Meant to be called only by the `#[panic_handler]`.
+pub fn panic(info: &core::panic::PanicInfo) -> ! {
+	oro_kernel::panic::<Arch>(info)
 }
 
 /// Type alias for the Oro kernel core-local instance type.
 pub(crate) type Kernel = oro_kernel::Kernel<Arch>;
 
+/// The kernel heap, backing the `alloc` crate.
+#[cfg(not(test))]
+#[global_allocator]
+static KERNEL_HEAP: oro_kernel::heap::LockedHeap<Arch> = oro_kernel::heap::LockedHeap::new();
+
 /// Architecture-specific core-local state.
-#[expect(dead_code)] // XXX DEBUG
 pub(crate) struct CoreState {
-	// XXX DEBUG
-	#[doc(hidden)]
-	pub unused: u64,
+	/// The core's interface to the GIC.
+	pub gic: gic::Gic,
+	/// Set by the timer interrupt; the scheduler loop is to
+	/// signal a timer expiry upon waking.
+	pub timer_expired: AtomicBool,
+	/// Set by the reschedule SGI; the scheduler loop is to
+	/// signal a reschedule upon waking.
+	pub reschedule_requested: AtomicBool,
 }
@@ -1,7 +1,7 @@
 //! Implements the Oro-specific address space layout for the Aarch64 architecture.
 
 use oro_mem::{
-	mapper::AddressSpace,
+	mapper::{AddressSpace, PageFlags},
 	pfa::Alloc,
 	phys::{Phys, PhysAddr},
 };
@@ -11,8 +11,8 @@ use crate::{
 	mem::{
 		paging::{
 			L0PageTableDescriptor, L1PageTableDescriptor, L2PageTableDescriptor,
-			L3PageTableBlockDescriptor, PageTable, PageTableEntryBlockAccessPerm,
-			PageTableEntryTableAccessPerm,
+			L3PageTableBlockDescriptor, PageTable, PageTableEntry, PageTableEntryBlockAccessPerm,
+			PageTableEntryTableAccessPerm, PageTableEntryType,
 		},
 		segment::Segment,
 	},
@@ -78,7 +78,15 @@ impl AddressSpaceLayout {
 	pub const STUBS_IDX: (usize, usize) = (0, 255);
 
 	/// The index for the module segments.
+	///
+	/// Shared by the user code, data and read-only data segments.
 	pub const MODULE_EXE_IDX: (usize, usize) = (5, 16);
+	/// The index for the module thread stack segment.
+	pub const MODULE_THREAD_STACK_IDX: usize = 17;
+	/// The index for the sysabi segment.
+	pub const SYSABI_IDX: usize = 20;
+	/// The index range for the user heap segment.
+	pub const USER_HEAP_IDX: (usize, usize) = (32, 127);
 
 	/// The recursive entry indices.
 	pub const RECURSIVE_ENTRY_IDX: (usize, usize) = (256, 259);
@@ -93,6 +101,10 @@ impl AddressSpaceLayout {
 	pub const BOOT_RESERVED_IDX: usize = 350;
 	/// The segment for the kernel core-local data.
 	pub const KERNEL_CORE_LOCAL_IDX: usize = 375;
+	/// The segment for the kernel heap.
+	pub const KERNEL_HEAP_IDX: usize = 400;
+	/// The segment for memory-mapped device registers.
+	pub const KERNEL_MMIO_IDX: usize = 425;
 
 	/// The kernel executable range, shared by the RX, RO, and RW segments.
 	///
@@ -211,6 +223,49 @@ impl AddressSpaceLayout {
 		&DESCRIPTOR
 	}
 
+	/// Returns the segment descriptor for memory-mapped device registers
+	/// (see [`crate::mem::mmio`]).
+	///
+	/// Mapped as `Device-nGnRnE` memory; never executable.
+	#[must_use]
+	pub fn mmio() -> <Self as AddressSpace>::SupervisorSegment {
+		#[expect(clippy::missing_docs_in_private_items)]
+		static DESCRIPTOR: Segment = unsafe {
+			Segment {
+				valid_range:       (
+					AddressSpaceLayout::KERNEL_MMIO_IDX,
+					AddressSpaceLayout::KERNEL_MMIO_IDX,
+				),
+				l0_template:       L0PageTableDescriptor::new()
+					.with_valid()
+					.with_table_access_permissions(PageTableEntryTableAccessPerm::KernelOnly)
+					.with_user_no_exec()
+					.with_kernel_no_exec(),
+				l1_table_template: L1PageTableDescriptor::new()
+					.with_valid()
+					.with_table_access_permissions(PageTableEntryTableAccessPerm::KernelOnly)
+					.with_user_no_exec()
+					.with_kernel_no_exec(),
+				l2_table_template: L2PageTableDescriptor::new()
+					.with_valid()
+					.with_table_access_permissions(PageTableEntryTableAccessPerm::KernelOnly)
+					.with_user_no_exec()
+					.with_kernel_no_exec(),
+				l3_template:       L3PageTableBlockDescriptor::new()
+					.with_valid()
+					.with_block_access_permissions(
+						PageTableEntryBlockAccessPerm::KernelRWUserNoAccess,
+					)
+					.with_user_no_exec()
+					.with_kernel_no_exec()
+					.with_not_secure()
+					.with_mair_index(MairEntry::DeviceMMIO.index() as u64),
+			}
+		};
+
+		&DESCRIPTOR
+	}
+
 	/// Creates a new supervisor (EL1) address space that addresses
 	/// the TT0 address range (i.e. for use with `TTBR0_EL1`). Uses
 	/// the global allocator.
@@ -282,6 +337,37 @@ const KERNEL_EXE_L2: L2PageTableDescriptor = unsafe {
 		.with_user_no_exec()
 };
 
+/// L0 intermediate PTE for all userspace segments.
+///
+/// Defined here as a constant since it's used within overlapping
+/// segments and any differences will cause indeterministic behavior.
+const USER_L0: L0PageTableDescriptor = unsafe {
+	L0PageTableDescriptor::new()
+		.with_valid()
+		.with_table_access_permissions(PageTableEntryTableAccessPerm::NoEffect)
+		.with_kernel_no_exec()
+};
+/// L1 intermediate PTE for all userspace segments.
+///
+/// Defined here as a constant since it's used within overlapping
+/// segments and any differences will cause indeterministic behavior.
+const USER_L1: L1PageTableDescriptor = unsafe {
+	L1PageTableDescriptor::new()
+		.with_valid()
+		.with_table_access_permissions(PageTableEntryTableAccessPerm::NoEffect)
+		.with_kernel_no_exec()
+};
+/// L2 intermediate PTE for all userspace segments.
+///
+/// Defined here as a constant since it's used within overlapping
+/// segments and any differences will cause indeterministic behavior.
+const USER_L2: L2PageTableDescriptor = unsafe {
+	L2PageTableDescriptor::new()
+		.with_valid()
+		.with_table_access_permissions(PageTableEntryTableAccessPerm::NoEffect)
+		.with_kernel_no_exec()
+};
+
 unsafe impl AddressSpace for AddressSpaceLayout {
 	type SupervisorHandle = Ttbr1Handle;
 	type SupervisorSegment = &'static Segment;
@@ -335,11 +421,21 @@ unsafe impl AddressSpace for AddressSpaceLayout {
 		Some(Ttbr0Handle { base_phys })
 	}
 
-	fn free_user_space_in<A>(_space: Self::UserHandle, _alloc: &mut A)
+	fn free_user_space_handle_in<A>(space: Self::UserHandle, alloc: &mut A)
 	where
 		A: Alloc,
 	{
-		todo!();
+		alloc.free(space.base_phys.address_u64());
+	}
+
+	fn free_user_space_deep_in<A>(space: Self::UserHandle, alloc: &mut A)
+	where
+		A: Alloc,
+	{
+		// SAFETY(qix-): The handle is consumed, and deep frees reclaim everything it maps.
+		unsafe {
+			free_table_deep(space.base_phys.address_u64(), 0, alloc);
+		}
 	}
 
 	fn duplicate_supervisor_space_shallow_in<A>(
@@ -382,6 +478,129 @@ unsafe impl AddressSpace for AddressSpaceLayout {
 		Some(Self::UserHandle { base_phys })
 	}
 
+	fn duplicate_user_space_deep_in<A>(
+		space: &Self::UserHandle,
+		alloc: &mut A,
+	) -> Option<Self::UserHandle>
+	where
+		A: Alloc,
+	{
+		// NOTE(qix-): Copy-on-write isn't implemented on AArch64 yet,
+		// NOTE(qix-): so every mapped page is copied eagerly. The entirety
+		// NOTE(qix-): of TTBR0 is userspace; there's no upper half to share.
+		// SAFETY(qix-): The handle is trusted to point to a valid page table hierarchy.
+		let base_phys = unsafe { clone_table_deep(space.base_phys.address_u64(), 0, alloc)? };
+
+		Some(Self::UserHandle {
+			// SAFETY(qix-): The address was just returned by the allocator.
+			base_phys: unsafe { Phys::from_address_unchecked(base_phys) },
+		})
+	}
+
+	fn translate(space: &Self::UserHandle, virt: usize) -> Option<(u64, PageFlags)> {
+		// NOTE(qix-): TTBR0 spans the lower 48 bits (`TCR_EL1.T0SZ=16`).
+		if virt >> 48 != 0 {
+			return None;
+		}
+
+		let mut flags = PageFlags {
+			writable:   true,
+			executable: true,
+			user:       true,
+		};
+
+		let mut table_phys = space.base_phys.address_u64();
+
+		for level in 0..=3 {
+			let shift = 39 - 9 * usize::from(level);
+
+			// SAFETY(qix-): The handle is trusted to point to a valid page table hierarchy.
+			let table =
+				unsafe { Phys::from_address_unchecked(table_phys).as_ref_unchecked::<PageTable>() };
+
+			// SAFETY(qix-): `level` is the level of `table`.
+			let (address, perm, user_no_exec) =
+				match unsafe { table[(virt >> shift) & 0x1FF].entry_type(level) } {
+					PageTableEntryType::Invalid(_) | PageTableEntryType::Malformed(_) => {
+						return None;
+					}
+					PageTableEntryType::L0Descriptor(desc) => {
+						restrict_table_flags(
+							&mut flags,
+							desc.table_access_permissions(),
+							desc.user_no_exec(),
+						);
+						table_phys = desc.address();
+						continue;
+					}
+					PageTableEntryType::L1Descriptor(desc) => {
+						restrict_table_flags(
+							&mut flags,
+							desc.table_access_permissions(),
+							desc.user_no_exec(),
+						);
+						table_phys = desc.address();
+						continue;
+					}
+					PageTableEntryType::L2Descriptor(desc) => {
+						restrict_table_flags(
+							&mut flags,
+							desc.table_access_permissions(),
+							desc.user_no_exec(),
+						);
+						table_phys = desc.address();
+						continue;
+					}
+					PageTableEntryType::L1Block(desc) => {
+						(
+							desc.address(),
+							desc.block_access_permissions(),
+							desc.user_no_exec(),
+						)
+					}
+					PageTableEntryType::L2Block(desc) => {
+						(
+							desc.address(),
+							desc.block_access_permissions(),
+							desc.user_no_exec(),
+						)
+					}
+					PageTableEntryType::L3Block(desc) => {
+						(
+							desc.address(),
+							desc.block_access_permissions(),
+							desc.user_no_exec(),
+						)
+					}
+				};
+
+			flags.executable &= !user_no_exec;
+
+			match perm {
+				PageTableEntryBlockAccessPerm::KernelRWUserRW => {}
+				PageTableEntryBlockAccessPerm::KernelRWUserNoAccess => flags.user = false,
+				PageTableEntryBlockAccessPerm::KernelROUserRO => flags.writable = false,
+				PageTableEntryBlockAccessPerm::KernelROUserNoAccess => {
+					flags.writable = false;
+					flags.user = false;
+				}
+			}
+
+			let page_mask = (1_u64 << shift) - 1;
+			return Some((address | (virt as u64 & page_mask), flags));
+		}
+
+		// NOTE(qix-): Level 3 entries are always either blocks or invalid.
+		None
+	}
+
+	unsafe fn resolve_write_fault(_space: &Self::UserHandle, _virt: usize) -> bool {
+		// NOTE(qix-): Nothing is ever mapped copy-on-write on AArch64 (see
+		// NOTE(qix-): `duplicate_user_space_deep_in`), so a write fault is
+		// NOTE(qix-): never resolvable.
+		false
+	}
+
 	fn kernel_code() -> Self::SupervisorSegment {
 		#[expect(clippy::missing_docs_in_private_items)]
 		static DESCRIPTOR: Segment = unsafe {
@@ -523,7 +742,285 @@ unsafe impl AddressSpace for AddressSpaceLayout {
 		&DESCRIPTOR
 	}
 
+	fn kernel_heap() -> Self::SupervisorSegment {
+		#[expect(clippy::missing_docs_in_private_items)]
+		static DESCRIPTOR: Segment = unsafe {
+			Segment {
+				valid_range:       (
+					AddressSpaceLayout::KERNEL_HEAP_IDX,
+					AddressSpaceLayout::KERNEL_HEAP_IDX,
+				),
+				l0_template:       L0PageTableDescriptor::new()
+					.with_valid()
+					.with_table_access_permissions(PageTableEntryTableAccessPerm::KernelOnly)
+					.with_user_no_exec()
+					.with_kernel_no_exec(),
+				l1_table_template: L1PageTableDescriptor::new()
+					.with_valid()
+					.with_table_access_permissions(PageTableEntryTableAccessPerm::KernelOnly)
+					.with_user_no_exec()
+					.with_kernel_no_exec(),
+				l2_table_template: L2PageTableDescriptor::new()
+					.with_valid()
+					.with_table_access_permissions(PageTableEntryTableAccessPerm::KernelOnly)
+					.with_user_no_exec()
+					.with_kernel_no_exec(),
+				l3_template:       L3PageTableBlockDescriptor::new()
+					.with_valid()
+					.with_block_access_permissions(
+						PageTableEntryBlockAccessPerm::KernelRWUserNoAccess,
+					)
+					.with_user_no_exec()
+					.with_kernel_no_exec()
+					.with_not_secure()
+					.with_mair_index(MairEntry::NormalMemory.index() as u64),
+			}
+		};
+
+		&DESCRIPTOR
+	}
+
+	fn sysabi() -> Self::UserSegment {
+		#[expect(clippy::missing_docs_in_private_items)]
+		static DESCRIPTOR: Segment = unsafe {
+			Segment {
+				valid_range:       (
+					AddressSpaceLayout::SYSABI_IDX,
+					AddressSpaceLayout::SYSABI_IDX,
+				),
+				l0_template:       USER_L0,
+				l1_table_template: USER_L1,
+				l2_table_template: USER_L2,
+				l3_template:       L3PageTableBlockDescriptor::new()
+					.with_valid()
+					.with_block_access_permissions(PageTableEntryBlockAccessPerm::KernelROUserRO)
+					.with_user_no_exec()
+					.with_kernel_no_exec()
+					.with_not_global()
+					.with_not_secure()
+					.with_mair_index(MairEntry::NormalMemory.index() as u64),
+			}
+		};
+
+		&DESCRIPTOR
+	}
+
+	fn user_code() -> Self::UserSegment {
+		#[expect(clippy::missing_docs_in_private_items)]
+		static DESCRIPTOR: Segment = unsafe {
+			Segment {
+				valid_range:       AddressSpaceLayout::MODULE_EXE_IDX,
+				l0_template:       USER_L0,
+				l1_table_template: USER_L1,
+				l2_table_template: USER_L2,
+				l3_template:       L3PageTableBlockDescriptor::new()
+					.with_valid()
+					.with_block_access_permissions(PageTableEntryBlockAccessPerm::KernelROUserRO)
+					.with_kernel_no_exec()
+					.with_not_global()
+					.with_not_secure()
+					.with_mair_index(MairEntry::NormalMemory.index() as u64),
+			}
+		};
+
+		&DESCRIPTOR
+	}
+
+	fn user_data() -> Self::UserSegment {
+		#[expect(clippy::missing_docs_in_private_items)]
+		static DESCRIPTOR: Segment = unsafe {
+			Segment {
+				valid_range:       AddressSpaceLayout::MODULE_EXE_IDX,
+				l0_template:       USER_L0,
+				l1_table_template: USER_L1,
+				l2_table_template: USER_L2,
+				l3_template:       L3PageTableBlockDescriptor::new()
+					.with_valid()
+					.with_block_access_permissions(PageTableEntryBlockAccessPerm::KernelRWUserRW)
+					.with_user_no_exec()
+					.with_kernel_no_exec()
+					.with_not_global()
+					.with_not_secure()
+					.with_mair_index(MairEntry::NormalMemory.index() as u64),
+			}
+		};
+
+		&DESCRIPTOR
+	}
+
+	fn user_rodata() -> Self::UserSegment {
+		#[expect(clippy::missing_docs_in_private_items)]
+		static DESCRIPTOR: Segment = unsafe {
+			Segment {
+				valid_range:       AddressSpaceLayout::MODULE_EXE_IDX,
+				l0_template:       USER_L0,
+				l1_table_template: USER_L1,
+				l2_table_template: USER_L2,
+				l3_template:       L3PageTableBlockDescriptor::new()
+					.with_valid()
+					.with_block_access_permissions(PageTableEntryBlockAccessPerm::KernelROUserRO)
+					.with_user_no_exec()
+					.with_kernel_no_exec()
+					.with_not_global()
+					.with_not_secure()
+					.with_mair_index(MairEntry::NormalMemory.index() as u64),
+			}
+		};
+
+		&DESCRIPTOR
+	}
+
+	fn user_heap() -> Self::UserSegment {
+		#[expect(clippy::missing_docs_in_private_items)]
+		static DESCRIPTOR: Segment = unsafe {
+			Segment {
+				valid_range:       AddressSpaceLayout::USER_HEAP_IDX,
+				l0_template:       USER_L0,
+				l1_table_template: USER_L1,
+				l2_table_template: USER_L2,
+				l3_template:       L3PageTableBlockDescriptor::new()
+					.with_valid()
+					.with_block_access_permissions(PageTableEntryBlockAccessPerm::KernelRWUserRW)
+					.with_user_no_exec()
+					.with_kernel_no_exec()
+					.with_not_global()
+					.with_not_secure()
+					.with_mair_index(MairEntry::NormalMemory.index() as u64),
+			}
+		};
+
+		&DESCRIPTOR
+	}
+
 	fn user_thread_stack() -> Self::UserSegment {
-		todo!();
+		#[expect(clippy::missing_docs_in_private_items)]
+		static DESCRIPTOR: Segment = unsafe {
+			Segment {
+				valid_range:       (
+					AddressSpaceLayout::MODULE_THREAD_STACK_IDX,
+					AddressSpaceLayout::MODULE_THREAD_STACK_IDX,
+				),
+				l0_template:       USER_L0,
+				l1_table_template: USER_L1,
+				l2_table_template: USER_L2,
+				l3_template:       L3PageTableBlockDescriptor::new()
+					.with_valid()
+					.with_block_access_permissions(PageTableEntryBlockAccessPerm::KernelRWUserRW)
+					.with_user_no_exec()
+					.with_kernel_no_exec()
+					.with_not_global()
+					.with_not_secure()
+					.with_mair_index(MairEntry::NormalMemory.index() as u64),
+			}
+		};
+
+		&DESCRIPTOR
 	}
 }
+
+/// Restricts the given effective permissions by those of a table descriptor,
+/// which apply to every subsequent level.
+fn restrict_table_flags(
+	flags: &mut PageFlags,
+	perm: PageTableEntryTableAccessPerm,
+	user_no_exec: bool,
+) {
+	flags.executable &= !user_no_exec;
+
+	match perm {
+		PageTableEntryTableAccessPerm::NoEffect => {}
+		PageTableEntryTableAccessPerm::KernelOnly => flags.user = false,
+		PageTableEntryTableAccessPerm::ReadOnly => flags.writable = false,
+		PageTableEntryTableAccessPerm::KernelReadOnly => {
+			flags.writable = false;
+			flags.user = false;
+		}
+	}
+}
+
+/// Deeply clones the TTBR0 page table at the given physical address and
+/// level (`3` being a leaf table), eagerly copying every mapped page, and
+/// returns the physical address of the new table.
+///
+/// Returns `None` if any allocation fails, in which case everything
+/// allocated by this call has been freed.
+///
+/// # Safety
+/// `src_phys` must point to a valid page table hierarchy that is not
+/// being modified elsewhere.
+///
+/// # Panics
+/// Panics if a malformed entry or an L1/L2 block mapping is encountered;
+/// neither is ever created by the userspace segments.
+unsafe fn clone_table_deep<A: Alloc>(src_phys: u64, level: u8, alloc: &mut A) -> Option<u64> {
+	let dst_phys = alloc.allocate()?;
+	let dst = Phys::from_address_unchecked(dst_phys).as_mut_unchecked::<PageTable>();
+	dst.reset();
+
+	let src = Phys::from_address_unchecked(src_phys).as_ref_unchecked::<PageTable>();
+
+	for idx in 0..512 {
+		let new_entry: Option<PageTableEntry> = match src[idx].entry_type(level) {
+			PageTableEntryType::Invalid(_) => continue,
+			PageTableEntryType::L0Descriptor(desc) => {
+				clone_table_deep(desc.address(), 1, alloc)
+					.map(|table| desc.with_address(table).into())
+			}
+			PageTableEntryType::L1Descriptor(desc) => {
+				clone_table_deep(desc.address(), 2, alloc)
+					.map(|table| desc.with_address(table).into())
+			}
+			PageTableEntryType::L2Descriptor(desc) => {
+				clone_table_deep(desc.address(), 3, alloc)
+					.map(|table| desc.with_address(table).into())
+			}
+			PageTableEntryType::L3Block(desc) => {
+				alloc.allocate().map(|frame| {
+					Phys::from_address_unchecked(frame)
+						.as_mut_ptr_unchecked::<u8>()
+						.copy_from_nonoverlapping(
+							Phys::from_address_unchecked(desc.address()).as_ptr_unchecked(),
+							4096,
+						);
+					desc.with_address(frame).into()
+				})
+			}
+			entry => panic!("unexpected user page table entry at level {level}: {entry:?}"),
+		};
+
+		let Some(new_entry) = new_entry else {
+			free_table_deep(dst_phys, level, alloc);
+			return None;
+		};
+
+		dst[idx] = new_entry;
+	}
+
+	Some(dst_phys)
+}
+
+/// Frees a TTBR0 page table hierarchy, including all of the pages it maps.
+///
+/// # Safety
+/// The table must not be in use, and none of the pages it maps may be
+/// mapped anywhere else.
+unsafe fn free_table_deep<A: Alloc>(table_phys: u64, level: u8, alloc: &mut A) {
+	let table = Phys::from_address_unchecked(table_phys).as_ref_unchecked::<PageTable>();
+
+	for idx in 0..512 {
+		let entry = &table[idx];
+		if !entry.valid() {
+			continue;
+		}
+
+		if let Some(address) = entry.address(level) {
+			if level == 3 {
+				alloc.free(address);
+			} else {
+				free_table_deep(address, level + 1, alloc);
+			}
+		}
+	}
+
+	alloc.free(table_phys);
+}
@@ -0,0 +1,103 @@
+//! Mapping of memory-mapped I/O (MMIO) regions.
+//!
+//! The linear map is mapped as normal (cacheable) memory, which is
+//! unsuitable for device registers. Drivers instead map their MMIO
+//! regions into the kernel MMIO segment (see [`AddressSpaceLayout::mmio()`])
+//! via [`map_mmio()`], which maps them as `Device-nGnRnE` memory.
+//!
+//! MMIO mappings are permanent; the segment's virtual address space is
+//! handed out linearly and never reclaimed.
+
+use oro_mem::mapper::{AddressSegment, AddressSpace as _, MapError};
+use oro_sync::{Lock, TicketMutex};
+
+use crate::mem::{
+	address_space::{AddressSpaceLayout, Ttbr1Handle},
+	segment::Segment,
+};
+
+/// The next unused virtual address in the MMIO segment, or `0`
+/// if nothing has been mapped yet.
+///
+/// Also serializes all modifications to the MMIO segment's page tables.
+static NEXT_VIRT: TicketMutex<usize> = TicketMutex::new(0);
+
+/// Errors returned by [`map_mmio()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MmioError {
+	/// The region is empty.
+	Empty,
+	/// The MMIO segment has no room left for the region.
+	OutOfVirtualSpace,
+	/// Mapping the region failed.
+	Map(MapError),
+}
+
+impl core::fmt::Display for MmioError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			Self::Empty => write!(f, "MMIO region is empty"),
+			Self::OutOfVirtualSpace => write!(f, "MMIO segment is out of virtual address space"),
+			Self::Map(err) => write!(f, "failed to map MMIO region: {err}"),
+		}
+	}
+}
+
+impl core::error::Error for MmioError {}
+
+/// Maps `len` bytes of MMIO starting at the given physical address into
+/// the kernel MMIO segment, returning a pointer to the (virtual) start
+/// of the region.
+///
+/// The physical address need not be page-aligned.
+///
+/// The mapping is visible to all cores, and is never unmapped. Must
+/// only be called after the MMIO segment has been provisioned as shared
+/// during boot.
+pub fn map_mmio(phys: u64, len: usize) -> Result<*mut u8, MmioError> {
+	let segment = AddressSpaceLayout::mmio();
+
+	if len == 0 {
+		return Err(MmioError::Empty);
+	}
+
+	let offset = (phys & 0xFFF) as usize;
+	let base_phys = phys & !0xFFF;
+	let pages = offset
+		.checked_add(len)
+		.ok_or(MmioError::OutOfVirtualSpace)?
+		.div_ceil(4096);
+
+	let (segment_start, segment_end) = <&Segment as AddressSegment<Ttbr1Handle>>::range(&segment);
+
+	let mut next_virt = NEXT_VIRT.lock();
+	if *next_virt == 0 {
+		*next_virt = segment_start;
+	}
+
+	let base_virt = *next_virt;
+	let end_virt = pages
+		.checked_mul(4096)
+		.and_then(|size| base_virt.checked_add(size))
+		.filter(|&end| end - 1 <= segment_end)
+		.ok_or(MmioError::OutOfVirtualSpace)?;
+
+	// SAFETY(qix-): Modifications to the MMIO segment are serialized by the lock.
+	let space = unsafe { AddressSpaceLayout::current_supervisor_space() };
+
+	for page in 0..pages {
+		let virt = base_virt + page * 4096;
+		if let Err(err) = segment.map(&space, virt, base_phys + (page * 4096) as u64) {
+			// NOTE(qix-): The frames are device memory; they're not to be freed.
+			for mapped in (base_virt..virt).step_by(4096) {
+				let _ = segment.unmap(&space, mapped);
+			}
+
+			return Err(MmioError::Map(err));
+		}
+	}
+
+	*next_virt = end_virt;
+
+	Ok((base_virt + offset) as *mut u8)
+}
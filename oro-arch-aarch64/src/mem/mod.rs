@@ -2,5 +2,6 @@
 //! for the Aarch64 architecture.
 
 pub mod address_space;
+pub mod mmio;
 pub mod paging;
 pub mod segment;
@@ -0,0 +1,103 @@
+//! Timekeeping and the scheduler tick, via the architectural generic timer.
+//!
+//! The system counter (`CNTVCT_EL0`) serves as the kernel's monotonic
+//! clock source, and the virtual timer (`CNTV_CVAL_EL0`) as the per-core
+//! preemption tick, delivered as [`crate::gic::VIRTUAL_TIMER_PPI`].
+//!
+//! The counter's frequency is reported by firmware in `CNTFRQ_EL0`, in Hz,
+//! and is the same across all cores. All conversions between counter ticks
+//! and nanoseconds use integer arithmetic, widened to 128 bits such that
+//! the intermediate products don't overflow.
+
+use oro_debug::{dbg, dbg_warn};
+use oro_kernel::time::{self, Duration, Instant};
+
+/// The number of nanoseconds in a second.
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// Returns the frequency of the system counter, in Hz, or
+/// `None` if firmware didn't set it.
+#[must_use]
+pub fn frequency_hz() -> Option<u64> {
+	match crate::asm::read_cntfrq() {
+		0 => None,
+		hz => Some(hz),
+	}
+}
+
+/// Converts a number of counter ticks to nanoseconds, rounding down
+/// and saturating.
+#[must_use]
+pub fn ticks_to_ns(ticks: u64, hz: u64) -> u64 {
+	#[expect(clippy::integer_division)]
+	let nanos = (u128::from(ticks) * NANOS_PER_SEC) / u128::from(hz.max(1));
+	u64::try_from(nanos).unwrap_or(u64::MAX)
+}
+
+/// Converts a number of nanoseconds to counter ticks, rounding up
+/// and saturating.
+#[must_use]
+pub fn ns_to_ticks(nanos: u64, hz: u64) -> u64 {
+	let ticks = (u128::from(nanos) * u128::from(hz)).div_ceil(NANOS_PER_SEC);
+	u64::try_from(ticks).unwrap_or(u64::MAX)
+}
+
+/// Returns the value of the system counter, in nanoseconds.
+///
+/// Note that this is **not** the kernel's notion of time since boot
+/// (see [`oro_kernel::time::now()`]), as the counter doesn't necessarily
+/// start at zero upon boot. Returns `0` if the counter frequency is unset.
+#[must_use]
+pub fn now_ns() -> u64 {
+	frequency_hz().map_or(0, |hz| ticks_to_ns(crate::asm::read_cntvct(), hz))
+}
+
+/// Programs the current core's tick to fire at the given deadline,
+/// replacing any previously armed tick.
+///
+/// Fires (nearly) immediately if the deadline has already passed.
+pub fn arm_tick(deadline: Instant) {
+	let hz = frequency_hz().unwrap_or(1);
+	let delta = ns_to_ticks(
+		deadline.saturating_duration_since(time::now()).as_nanos(),
+		hz,
+	);
+	crate::asm::arm_virtual_timer(crate::asm::read_cntvct().saturating_add(delta));
+}
+
+/// Programs the current core's tick to fire after the given number
+/// of counter ticks, replacing any previously armed tick.
+pub fn arm_tick_after(ticks: u64) {
+	crate::asm::arm_virtual_timer(crate::asm::read_cntvct().saturating_add(ticks));
+}
+
+/// Cancels the current core's tick, if armed, and deasserts its interrupt.
+pub fn cancel_tick() {
+	crate::asm::disarm_virtual_timer();
+}
+
+/// Converts the given duration to a number of counter ticks, rounding up,
+/// saturating at `u32::MAX`, and never returning zero.
+#[must_use]
+pub fn duration_to_ticks(duration: Duration) -> u32 {
+	let ticks = ns_to_ticks(duration.as_nanos(), frequency_hz().unwrap_or(1));
+	u32::try_from(ticks).unwrap_or(u32::MAX).max(1)
+}
+
+/// Registers the system counter as the kernel's clock source.
+///
+/// If firmware didn't set the counter frequency, a warning is logged
+/// and no clock source is registered.
+///
+/// # Safety
+/// Must be called exactly once, during boot, by the primary core and
+/// prior to booting any secondary cores.
+pub unsafe fn register_clock_source() {
+	let Some(hz) = frequency_hz() else {
+		dbg_warn!("generic timer frequency is not set; no monotonic clock source available");
+		return;
+	};
+
+	dbg!("generic timer: {hz} Hz");
+	time::register_clock_source(crate::asm::read_cntvct, hz);
+}
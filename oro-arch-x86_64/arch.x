@@ -8,6 +8,7 @@ PHDRS {
 	text     PT_LOAD    FLAGS((1 << 0) | (1 << 2) | (1 << 20)            ); /* rx + oro-kernel */
 	rodata   PT_LOAD    FLAGS((1 << 2)            | (1 << 20)            ); /* r  + oro-kernel */
 	data     PT_LOAD    FLAGS((1 << 1) | (1 << 2) | (1 << 20)            ); /* rw + oro-kernel */
+	note     PT_NOTE    FLAGS((1 << 2)                                   ); /* r */
 }
 
 SECTIONS {
@@ -31,6 +32,11 @@ SECTIONS {
 		*(.rodata .rodata.*)
 	} :rodata
 
+	/* MUST come before the discarded notes. */
+	.note.oro : {
+		KEEP(*(.note.oro))
+	} :rodata :note
+
 	. = ALIGN(4096);
 
 	.data : {
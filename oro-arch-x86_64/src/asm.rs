@@ -20,6 +20,9 @@ pub fn invlpg<T>(virtual_address: *const T) {
 ///
 /// This is *very* expensive and should be used sparingly.
 ///
+/// Once PCIDs are enabled, only the current PCID's (non-global)
+/// entries are flushed (see [`crate::pcid`]).
+///
 /// Assumes there's a stack.
 #[inline(always)]
 pub fn flush_tlb() {
@@ -110,6 +113,35 @@ pub fn outb(port: u16, value: u8) {
 	}
 }
 
+/// Reads a byte from the specified I/O port.
+#[inline(always)]
+#[must_use]
+pub fn inb(port: u16) -> u8 {
+	let value: u8;
+	unsafe {
+		asm!(
+			"in al, dx",
+			out("al") value,
+			in("dx") port,
+			options(nostack, preserves_flags)
+		);
+	}
+	value
+}
+
+/// Sends a word to the specified I/O port.
+#[inline(always)]
+pub fn outw(port: u16, value: u16) {
+	unsafe {
+		asm!(
+			"out dx, ax",
+			in("dx") port,
+			in("ax") value,
+			options(nostack, preserves_flags)
+		);
+	}
+}
+
 /// Reads a word from the specified I/O port.
 #[inline(always)]
 #[must_use]
@@ -147,6 +179,31 @@ pub fn hang() -> ! {
 	}
 }
 
+/// Resets the CPU by way of a triple fault; an empty IDT is
+/// loaded, after which an interrupt is raised.
+pub fn triple_fault() -> ! {
+	/// An empty IDT (Interrupt Descriptor Table) descriptor.
+	#[repr(C, packed)]
+	struct EmptyIdtr {
+		/// The limit of the IDT.
+		limit: u16,
+		/// The base address of the IDT.
+		base:  u64,
+	}
+
+	let idtr = EmptyIdtr { limit: 0, base: 0 };
+
+	unsafe {
+		asm!(
+			"cli",
+			"lidt [{}]",
+			"int3",
+			in(reg) core::ptr::addr_of!(idtr),
+			options(noreturn)
+		);
+	}
+}
+
 /// Halts the CPU once and waits for an interrupt.
 pub fn halt_once() {
 	unsafe {
@@ -162,25 +219,80 @@ pub fn strong_memory_barrier() {
 	}
 }
 
-/// Reads the value of an MSR
+/// Reads the processor's time-stamp counter (TSC).
 #[inline(always)]
 #[must_use]
-pub fn rdmsr(msr: u32) -> u64 {
+pub fn rdtsc() -> u64 {
 	let val_a: u32;
 	let val_d: u32;
 	unsafe {
 		asm!(
-			"rdmsr",
-			in("ecx") msr,
+			"rdtsc",
 			out("eax") val_a,
 			out("edx") val_d,
-			options(nostack, preserves_flags)
+			options(nostack, nomem, preserves_flags)
 		);
 	}
 
 	(u64::from(val_d) << 32) | u64::from(val_a)
 }
 
+/// Returns whether or not the `rdfsbase`/`wrfsbase` family of
+/// instructions has been enabled (`CR4.FSGSBASE`).
+///
+/// Boot code only sets the bit if CPUID reports support for it.
+#[inline(always)]
+#[must_use]
+fn fsgsbase_enabled() -> bool {
+	cr4() & (1 << 16) != 0
+}
+
+/// Sets the `FS` segment base for the current core.
+///
+/// Uses `wrfsbase` if enabled, otherwise writes `IA32_FS_BASE`.
+///
+/// # Safety
+/// The address must be canonical. Any code relying on
+/// the previous `FS` base will observe the new value.
+#[inline(always)]
+pub unsafe fn set_fs_base(base: u64) {
+	if fsgsbase_enabled() {
+		asm!("wrfsbase {}", in(reg) base, options(nostack, preserves_flags));
+	} else {
+		crate::msr::write(crate::msr::IA32_FS_BASE, base);
+	}
+}
+
+/// Sets the `GS` segment base for the current core.
+///
+/// Uses `wrgsbase` if enabled, otherwise writes `IA32_GS_BASE`.
+///
+/// # Safety
+/// The address must be canonical. Any code relying on
+/// the previous `GS` base will observe the new value.
+#[inline(always)]
+pub unsafe fn set_gs_base(base: u64) {
+	if fsgsbase_enabled() {
+		asm!("wrgsbase {}", in(reg) base, options(nostack, preserves_flags));
+	} else {
+		crate::msr::write(crate::msr::IA32_GS_BASE, base);
+	}
+}
+
+/// Sets the inactive `GS` segment base (`IA32_KERNEL_GS_BASE`)
+/// for the current core, which is swapped in by `swapgs`.
+///
+/// There is no instruction form for this base; it is always
+/// written via the MSR.
+///
+/// # Safety
+/// The address must be canonical. The syscall entry stub
+/// relies on this value pointing to the core's scratch space.
+#[inline(always)]
+pub unsafe fn set_kernel_gs_base(base: u64) {
+	crate::msr::write(crate::msr::IA32_KERNEL_GS_BASE, base);
+}
+
 /// Loads (sets) the given GDT offset as the TSS (Task State Segment) for the current core.
 #[inline(always)]
 pub fn load_tss(offset: u16) {
@@ -203,3 +315,134 @@ pub fn rflags() -> u64 {
 	}
 	rflags
 }
+
+/// Returns the current stack pointer (`rsp`).
+#[inline(always)]
+#[must_use]
+pub fn rsp() -> u64 {
+	let rsp: u64;
+	unsafe {
+		asm!("mov {}, rsp", out(reg) rsp, options(nostack, nomem, preserves_flags));
+	}
+	rsp
+}
+
+/// Returns the current frame pointer (`rbp`).
+///
+/// Only meaningful if the calling code was built with frame pointers.
+#[inline(always)]
+#[must_use]
+pub fn rbp() -> u64 {
+	let rbp: u64;
+	unsafe {
+		asm!("mov {}, rbp", out(reg) rbp, options(nostack, nomem, preserves_flags));
+	}
+	rbp
+}
+
+/// Clears the task-switched flag (`CR0.TS`), allowing x87/SSE/AVX
+/// instructions to execute without raising `#NM`.
+#[inline(always)]
+pub fn clts() {
+	unsafe {
+		asm!("clts", options(nostack, nomem, preserves_flags));
+	}
+}
+
+/// Sets the task-switched flag (`CR0.TS`), causing the next
+/// x87/SSE/AVX instruction to raise `#NM`.
+#[inline(always)]
+pub fn set_task_switched() {
+	unsafe {
+		asm!(
+			"mov {0}, cr0",
+			"or {0}, 8",
+			"mov cr0, {0}",
+			out(reg) _,
+			options(nostack, nomem, preserves_flags)
+		);
+	}
+}
+
+/// Returns whether or not the task-switched flag (`CR0.TS`) is set.
+#[inline(always)]
+#[must_use]
+pub fn task_switched() -> bool {
+	let cr0: u64;
+	unsafe {
+		asm!("mov {}, cr0", out(reg) cr0, options(nostack, nomem, preserves_flags));
+	}
+	cr0 & (1 << 3) != 0
+}
+
+/// Writes the given extended control register (e.g. `XCR0`).
+///
+/// # Safety
+/// `CR4.OSXSAVE` must be set, and `value` must be valid for the register;
+/// otherwise, a general protection fault is raised.
+#[inline(always)]
+pub unsafe fn xsetbv(xcr: u32, value: u64) {
+	asm!(
+		"xsetbv",
+		in("ecx") xcr,
+		in("eax") value as u32,
+		in("edx") (value >> 32) as u32,
+		options(nostack, nomem, preserves_flags)
+	);
+}
+
+/// Saves the x87/SSE state to the given 512-byte area via `fxsave64`.
+///
+/// # Safety
+/// `area` must be valid for writes of 512 bytes and aligned to 16 bytes.
+/// `CR0.TS` must be clear.
+#[inline(always)]
+pub unsafe fn fxsave(area: *mut u8) {
+	asm!("fxsave64 [{}]", in(reg) area, options(nostack, preserves_flags));
+}
+
+/// Restores the x87/SSE state from the given 512-byte area via `fxrstor64`.
+///
+/// # Safety
+/// `area` must be valid for reads of 512 bytes, aligned to 16 bytes, and
+/// hold a valid state image. `CR0.TS` must be clear.
+#[inline(always)]
+pub unsafe fn fxrstor(area: *const u8) {
+	asm!("fxrstor64 [{}]", in(reg) area, options(nostack, readonly, preserves_flags));
+}
+
+/// Saves all state components enabled in `XCR0` to the given area
+/// via `xsave64`.
+///
+/// # Safety
+/// `area` must be valid for writes of the size reported by
+/// [`crate::cpuid::xsave_area_size()`], and aligned to 64 bytes.
+/// `CR4.OSXSAVE` must be set, and `CR0.TS` must be clear.
+#[inline(always)]
+pub unsafe fn xsave(area: *mut u8) {
+	asm!(
+		"xsave64 [{}]",
+		in(reg) area,
+		in("eax") u32::MAX,
+		in("edx") u32::MAX,
+		options(nostack, preserves_flags)
+	);
+}
+
+/// Restores all state components enabled in `XCR0` from the given area
+/// via `xrstor64`.
+///
+/// # Safety
+/// `area` must be valid for reads of the size reported by
+/// [`crate::cpuid::xsave_area_size()`], aligned to 64 bytes, and hold a
+/// valid state image. `CR4.OSXSAVE` must be set, and `CR0.TS` must be clear.
+#[inline(always)]
+pub unsafe fn xrstor(area: *const u8) {
+	asm!(
+		"xrstor64 [{}]",
+		in(reg) area,
+		in("eax") u32::MAX,
+		in("edx") u32::MAX,
+		options(nostack, readonly, preserves_flags)
+	);
+}
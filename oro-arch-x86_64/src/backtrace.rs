@@ -0,0 +1,77 @@
+//! Frame pointer based backtraces.
+//!
+//! Relies on the kernel being built with frame pointers (see the
+//! `frame-pointer` key of the target specification), such that each
+//! frame begins with the caller's `rbp`, followed by the return address.
+
+use oro_mem::mapper::AddressSegment;
+
+use crate::mem::address_space::AddressSpaceLayout;
+
+/// The maximum number of frames walked.
+pub const MAX_FRAMES: usize = 16;
+
+/// Walks the frame pointer chain starting at the given frame pointer,
+/// calling `f` with the index and return address of each frame.
+///
+/// The walk is bounded to [`MAX_FRAMES`] frames. Further, each frame
+/// pointer is validated before being dereferenced; it must lie within the
+/// mapped portion of the stack the current core is running on (between
+/// the current stack pointer and the stack's high guard page), and above
+/// the previous frame. Corrupt or cyclic chains thus end the walk rather
+/// than faulting (or looping).
+///
+/// Must be called on the same stack as the frames being walked.
+pub fn walk<F: FnMut(usize, u64)>(rbp: u64, mut f: F) {
+	let Some((low, high)) = stack_bounds(crate::asm::rsp()) else {
+		return;
+	};
+
+	let mut fp = rbp;
+	for i in 0..MAX_FRAMES {
+		if fp & 7 != 0 || fp < low || fp + 16 > high {
+			break;
+		}
+
+		// SAFETY(qix-): Validated above to lie within the mapped portion of the stack.
+		let (next, ret) = unsafe { (*(fp as *const u64), *((fp + 8) as *const u64)) };
+		if ret == 0 {
+			break;
+		}
+
+		f(i, ret);
+
+		if next <= fp {
+			break;
+		}
+
+		fp = next;
+	}
+}
+
+/// Logs a backtrace of the caller via [`oro_debug::log_unlocked()`].
+///
+/// # Safety
+/// Meant only for the panic path; the same requirements as
+/// [`oro_debug::log_unlocked()`] apply.
+#[inline(always)]
+pub unsafe fn log_unlocked() {
+	walk(crate::asm::rbp(), |i, ret| {
+		oro_debug::log_unlocked(format_args!("backtrace: #{i:<2} 0x{ret:016X}"));
+	});
+}
+
+/// Returns the bounds of the mapped portion of the stack containing the
+/// given stack pointer, or `None` if it's not a known stack.
+///
+/// Stacks are mapped contiguously from their high guard page downward;
+/// everything between the stack pointer and the guard page is mapped.
+fn stack_bounds(rsp: u64) -> Option<(u64, u64)> {
+	[
+		AddressSpaceLayout::kernel_stack().range(),
+		AddressSpaceLayout::interrupt_stack().range(),
+	]
+	.into_iter()
+	.find(|&(start, end)| (start..=end).contains(&(rsp as usize)))
+	.map(|(_, end)| (rsp, (end & !0xFFF) as u64))
+}
@@ -4,7 +4,7 @@
 //! several memory facilities usable by the kernel (e.g. a page frame
 //! allocator, linear map translator, etc.).
 
-use oro_boot_protocol::{MemoryMapEntry, MemoryMapEntryType, memory_map::MemoryMapKind};
+use oro_boot_protocol::{MemoryMapEntry, MemoryMapEntryType};
 use oro_debug::{dbg, dbg_warn};
 use oro_macro::assert;
 use oro_mem::{
@@ -15,7 +15,7 @@ use oro_mem::{
 
 use crate::mem::{
 	address_space::AddressSpaceLayout,
-	paging::{PageTable, PageTableEntry},
+	paging::{PageTable, PageTableEntry, PageTableWalker, WalkEntry},
 	paging_level::PagingLevel,
 };
 
@@ -86,8 +86,11 @@ pub unsafe fn prepare_memory() -> PreparedMemory {
 	let mmap_iterator = MemoryMapIterator::new(&otf_mapper);
 	let mut has_cs8 = false;
 	let mut has_cs9 = false;
+	let mut phys_limit = 0;
 
 	for region in mmap_iterator.clone() {
+		phys_limit = phys_limit.max(region.base + region.length);
+
 		if region.base < MIB_1 {
 			let end = region.base + region.length;
 
@@ -118,6 +121,7 @@ pub unsafe fn prepare_memory() -> PreparedMemory {
 		.expect("system ran out of memory during linear map");
 
 	oro_mem::translate::set_global_map_offset(linear_offset);
+	oro_mem::translate::set_physical_address_limit(phys_limit);
 
 	// Consume the MMAP PFA and free all memory that isn't used by the
 	// linear map intermediate page table entries.
@@ -174,6 +178,8 @@ unsafe fn linear_map_regions<'a>(
 		};
 	}
 
+	let supports_1gib = crate::cpuid::Features::detect().page_1gib;
+
 	// Get the virtual address of the linear map base.
 	let linear_map_segment = AddressSpaceLayout::linear_map();
 	let (linear_map_base, linear_map_last_incl) = linear_map_segment.range();
@@ -259,6 +265,14 @@ unsafe fn linear_map_regions<'a>(
 
 		let mut total_mappings = 0;
 		while length > 0 {
+			// Prefer 1GiB pages where the region allows for it,
+			// falling back to 2MiB pages otherwise.
+			let use_1gib = supports_1gib
+				&& base_virt % (1 << 30) == 0
+				&& base_phys % (1 << 30) == 0
+				&& length >= (1 << 30);
+			let mut step = 1 << 21;
+
 			for level in (2..=paging_level as u64).rev() {
 				let mut page_table_virt = (base_virt >> (12 + 9 * level)) as usize;
 				for rec_level in 0..level {
@@ -273,6 +287,24 @@ unsafe fn linear_map_regions<'a>(
 				let entry_idx = base_virt >> (12 + 9 * (level - 1)) & 0x1FF;
 				let entry = &mut page_table[entry_idx as usize];
 
+				if level == 3 && entry.present() && entry.huge() {
+					// Already covered by a 1GiB page from an overlapping region.
+					break;
+				}
+
+				if level == 3 && use_1gib && !entry.present() {
+					*entry = PageTableEntry::new()
+						.with_writable()
+						.with_present()
+						.with_global()
+						.with_no_exec()
+						.with_huge()
+						.with_address(base_phys);
+					total_mappings += 1;
+					step = 1 << 30;
+					break;
+				}
+
 				if level == 2 {
 					// `entry.present() == true` occurs when two regions that
 					// have been 2MiB extended end up overlapping. In this
@@ -304,9 +336,9 @@ unsafe fn linear_map_regions<'a>(
 				}
 			}
 
-			base_virt += 1 << 21;
-			base_phys += 1 << 21;
-			length -= 1 << 21;
+			base_virt += step;
+			base_phys += step;
+			length -= step;
 		}
 
 		dbg!(
@@ -405,15 +437,14 @@ impl<'a> MemoryMapIterator<'a> {
 	fn new(otf: &'a OnTheFlyMapper) -> Self {
 		Self {
 			next: {
-				let MemoryMapKind::V0(res) = super::protocol::MMAP_REQUEST
+				let res = super::protocol::MMAP_REQUEST
 					.response()
 					.expect("bootloader didn't provide a memory map response")
-				else {
-					panic!(
+					.v0()
+					.expect(
 						"bootloader provided a memory map response, but it was of a different \
-						 revision"
+						 revision",
 					);
-				};
 
 				// SAFETY(qix-): We're assuming the bootloader provided a valid memory map.
 				// SAFETY(qix-): We've also used the appropriate methods from the bootloader protocol
@@ -601,3 +632,156 @@ impl OnTheFlyMapper {
 		}
 	}
 }
+
+/// The maximum number of memory map regions considered by
+/// [`reclaim_bootloader_memory`]; excess regions are ignored.
+const MAX_RECLAIM_REGIONS: usize = 128;
+
+/// The number of pages tracked by a single page-sized bitmap.
+const PAGES_PER_BITMAP: u64 = 4096 * 8;
+
+/// Frees all pages in [`MemoryMapEntryType::Reclaimable`] regions to
+/// the global page frame allocator, returning the number of pages
+/// that were reclaimed.
+///
+/// Pages that are still in use by the active (supervisor) page tables -
+/// either as page tables themselves, or as 4KiB frames mapped into the
+/// kernel's half of the address space (e.g. the kernel image and the
+/// primary core's stack, which the preboot stage allocates from memory
+/// it later marks as reclaimable) - are skipped, as are any pages
+/// overlapping a [`MemoryMapEntryType::Modules`] region. Pages below
+/// 1MiB are never reclaimed.
+///
+/// # Panics
+/// Panics if the root ring modules have not yet been loaded (see
+/// [`crate::init::MODULES_LOADED`]), as the module list resides in
+/// reclaimable memory.
+///
+/// # Safety
+/// Must be called exactly once, by the primary core, after the linear
+/// map has been established and after the kernel (including all
+/// secondary cores) has finished reading any boot protocol structures.
+pub unsafe fn reclaim_bootloader_memory() -> usize {
+	if !crate::init::MODULES_LOADED.load(core::sync::atomic::Ordering::Acquire) {
+		panic!("attempted to reclaim bootloader memory before modules were loaded");
+	}
+
+	// NOTE(qix-): The memory map itself lives in reclaimable memory, and
+	// NOTE(qix-): freeing a page writes to it; copy out what we need first.
+	let mut regions = oro_mem::alloc::vec::Vec::new();
+	let mut truncated = false;
+
+	let res = super::protocol::MMAP_REQUEST
+		.response()
+		.expect("bootloader didn't provide a memory map response")
+		.v0()
+		.expect("bootloader provided a memory map response, but it was of a different revision");
+
+	let mut next = core::ptr::read_volatile(&res.assume_init_ref().next);
+	while next != 0 {
+		let entry = Phys::from_address_unchecked(next)
+			.as_ptr_unchecked::<MemoryMapEntry>()
+			.read_unaligned();
+		next = entry.next;
+
+		if matches!(
+			entry.ty,
+			MemoryMapEntryType::Reclaimable | MemoryMapEntryType::Modules
+		) {
+			if regions.len() == MAX_RECLAIM_REGIONS {
+				truncated = true;
+				break;
+			}
+
+			regions.push(entry);
+		}
+	}
+
+	if truncated {
+		dbg_warn!(
+			"memory map has too many reclaimable/module regions; only considering the first \
+			 {MAX_RECLAIM_REGIONS}"
+		);
+	}
+
+	let overlaps_module = |page: u64| {
+		regions.iter().any(|region| {
+			region.ty == MemoryMapEntryType::Modules
+				&& page < region.base + region.length
+				&& region.base < page + 4096
+		})
+	};
+
+	let paging_level = PagingLevel::current_from_cpu();
+	let root = crate::asm::cr3();
+	let mut reclaimed = 0;
+
+	for region in regions
+		.iter()
+		.filter(|region| region.ty == MemoryMapEntryType::Reclaimable)
+	{
+		let start = ((region.base + 4095) & !4095).max(MIB_1);
+		let end = (region.base + region.length) & !4095;
+
+		let mut chunk_start = start;
+		while chunk_start < end {
+			let chunk_end = (chunk_start + PAGES_PER_BITMAP * 4096).min(end);
+
+			let Some(bitmap_phys) = GlobalPfa.allocate() else {
+				dbg_warn!("out of memory while reclaiming bootloader memory");
+				return reclaimed;
+			};
+
+			let bitmap = core::slice::from_raw_parts_mut(
+				Phys::from_address_unchecked(bitmap_phys).as_mut_ptr_unchecked::<u64>(),
+				4096 >> 3,
+			);
+			bitmap.fill(0);
+
+			visit_in_use_frames(root, paging_level, |frame| {
+				if (chunk_start..chunk_end).contains(&frame) {
+					let bit = (frame - chunk_start) >> 12;
+					bitmap[(bit >> 6) as usize] |= 1 << (bit & 63);
+				}
+			});
+
+			// Runs of free pages are exposed to the PFA in bulk, as they were
+			// never tracked by it to begin with.
+			let mut run_start = None;
+			for page in (chunk_start..chunk_end).step_by(4096) {
+				let bit = (page - chunk_start) >> 12;
+				if bitmap[(bit >> 6) as usize] & (1 << (bit & 63)) == 0 && !overlaps_module(page) {
+					run_start.get_or_insert(page);
+					reclaimed += 1;
+				} else if let Some(start) = run_start.take() {
+					GlobalPfa::expose_phys_range(start, page - start);
+				}
+			}
+
+			if let Some(start) = run_start {
+				GlobalPfa::expose_phys_range(start, chunk_end - start);
+			}
+
+			GlobalPfa.free(bitmap_phys);
+
+			chunk_start = chunk_end;
+		}
+	}
+
+	reclaimed
+}
+
+/// Calls `f` with the physical address of the given root table, every page
+/// table reachable from the upper (kernel) half of it, as well as every 4KiB
+/// frame mapped by them. Huge page mappings are not reported.
+unsafe fn visit_in_use_frames<F: FnMut(u64)>(root: u64, paging_level: PagingLevel, mut f: F) {
+	f(root);
+
+	PageTableWalker::with_root_range(root, paging_level, 256..512).visit_all_mut(|walked| {
+		match walked {
+			WalkEntry::Table(entry, _) => f(entry.address()),
+			WalkEntry::Leaf(_, entry, 1) => f(entry.address()),
+			WalkEntry::Leaf(..) => {}
+		}
+	});
+}
@@ -5,20 +5,25 @@ mod secondary;
 
 use oro_acpi::{
 	AcpiTable,
-	madt::{LocalApicEx as _, MadtEntry},
+	madt::{IoApicEx as _, LocalApicEx as _, MadtEntry},
 	sys as acpi_sys,
 };
-use oro_boot_protocol::acpi::AcpiKind;
 use oro_debug::{dbg, dbg_warn};
 use oro_mem::{
-	mapper::AddressSpace,
+	global_alloc::GlobalPfa,
+	mapper::{AddressSegment as _, AddressSpace},
+	pfa::Alloc,
 	phys::{Phys, PhysAddr},
 };
 
-use crate::mem::address_space::AddressSpaceLayout;
+use crate::mem::{
+	address_space::{AddressSpaceHandle, AddressSpaceLayout},
+	mmio::{MemoryType, map_mmio},
+	paging_level::PagingLevel,
+};
 
-/// Temporary value for the number of stack pages to allocate for secondary cores.
-// TODO(qix-): Discover the stack size of the primary core and use that instead.
+/// The number of stack pages to allocate for secondary cores
+/// if the primary core's stack size cannot be determined.
 const SECONDARY_STACK_PAGES: usize = 16;
 
 /// Boots the primary core (boostrap processor) of the system.
@@ -34,6 +39,11 @@ pub unsafe fn boot_primary() -> ! {
 	crate::gdt::GDT.install();
 	crate::asm::flush_tlb();
 
+	// NOTE(qix-): The paging level is chosen by the bootloader; `CR4.LA57`
+	// NOTE(qix-): cannot be changed from long mode, so we go with whatever
+	// NOTE(qix-): we were given. Secondary cores must match it.
+	let paging_level = PagingLevel::latch();
+
 	#[cfg(debug_assertions)]
 	oro_debug::init();
 
@@ -44,24 +54,57 @@ pub unsafe fn boot_primary() -> ! {
 	#[cfg(debug_assertions)]
 	oro_debug::init_with_offset(Phys::from_address_unchecked(0).virt());
 
+	// Share the kernel heap segment between all cores; must happen
+	// before anything allocates, and before the secondaries (whose
+	// address spaces are copied from ours) are booted.
+	AddressSpaceLayout::kernel_heap()
+		.provision_as_shared(&AddressSpaceLayout::current_supervisor_space())
+		.expect("failed to provision the kernel heap segment");
+
+	// Likewise for the MMIO segment (shared by all memory types).
+	AddressSpaceLayout::mmio()
+		.provision_as_shared(&AddressSpaceLayout::current_supervisor_space())
+		.expect("failed to provision the kernel MMIO segment");
+
+	// NOTE(qix-): Emulation must be off for SSE; FPU use is instead
+	// NOTE(qix-): trapped lazily via `CR0.TS` (see `crate::fpu`).
 	crate::reg::Cr0::new()
 		.with_monitor_coprocessor()
-		.with_emulation()
 		.with_alignment_mask()
 		.with_paging_enable()
 		.with_protected_mode_enable()
 		.inherit()
 		.load();
 
+	let features = crate::cpuid::Features::detect();
+
+	crate::reg::Cr4::new()
+		.with_global_pages()
+		.with_osfxsr()
+		.with_osxmmexcpt()
+		.with_osxsave()
+		.with_smep()
+		.with_fsgsbase()
+		.with_pcid()
+		.with_only_supported(&features)
+		.inherit()
+		.load();
+
 	dbg!("booting primary core");
 
+	protocol::check_populated();
+
+	dbg!("using {}-level paging", paging_level.as_usize());
+	if paging_level == PagingLevel::Level4 && features.la57 {
+		dbg!("CPU supports 5-level paging, but the bootloader did not enable it");
+	}
+
 	// Get the RSDP from the bootloader.
-	let AcpiKind::V0(rsdp_response) = protocol::ACPI_REQUEST
+	let rsdp_response = protocol::ACPI_REQUEST
 		.response()
 		.expect("ACPI request was not populated")
-	else {
-		panic!("ACPI request and response revision number differ");
-	};
+		.v0()
+		.expect("ACPI request and response revision number differ");
 
 	let rsdp_phys = core::ptr::read_volatile(&rsdp_response.assume_init_ref().rsdp);
 	dbg!("ACPI response OK: RSDP at {rsdp_phys:016?}");
@@ -103,6 +146,8 @@ pub unsafe fn boot_primary() -> ! {
 		dbg!("ACPI already enabled");
 	}
 
+	crate::power::initialize(fadt);
+
 	let madt = sdt
 		.find::<oro_acpi::Madt>()
 		.expect("MADT table not found in ACPI tables");
@@ -112,6 +157,28 @@ pub unsafe fn boot_primary() -> ! {
 		crate::asm::disable_8259();
 	}
 
+	for entry in madt.entries().flatten() {
+		if let MadtEntry::IoApic(ioapic) = entry {
+			dbg!(
+				"I/O APIC {}: base {:016X}, GSI base {}",
+				ioapic.id(),
+				ioapic.phys(),
+				ioapic.gsi_base()
+			);
+
+			// NOTE(qix-): The linear map is write-back; the registers
+			// NOTE(qix-): must be mapped uncacheable.
+			match map_mmio(
+				ioapic.phys(),
+				crate::ioapic::REGISTERS_LEN,
+				MemoryType::Uncacheable,
+			) {
+				Ok(base) => crate::ioapic::register(base, ioapic.id(), ioapic.gsi_base()),
+				Err(err) => dbg_warn!("I/O APIC {}: failed to map registers: {err}", ioapic.id()),
+			}
+		}
+	}
+
 	let lapic = crate::lapic::Lapic::new(
 		Phys::from_address_unchecked(madt.lapic_phys()).as_mut_ptr_unchecked::<u8>(),
 	);
@@ -119,6 +186,13 @@ pub unsafe fn boot_primary() -> ! {
 	let lapic_id = lapic.id();
 	dbg!("local APIC ID: {lapic_id}");
 
+	// NOTE(qix-): Must happen before secondary cores are booted, as
+	// NOTE(qix-): calibration uses the (shared) PIT.
+	let timer_ticks_per_ms = lapic.calibrate_timer(crate::interrupt::TIMER_DIVIDER);
+	dbg!("local APIC timer: {timer_ticks_per_ms} ticks/ms");
+
+	crate::tsc::register_clock_source();
+
 	crate::init::initialize_primary();
 
 	{
@@ -128,6 +202,16 @@ pub unsafe fn boot_primary() -> ! {
 			// Get the current supervisor address space.
 			let mapper = AddressSpaceLayout::current_supervisor_space();
 
+			// Give secondaries the same stack size as the primary core.
+			let stack_pages = primary_stack_pages(&mapper).unwrap_or_else(|| {
+				dbg_warn!(
+					"failed to determine primary core stack size; using {SECONDARY_STACK_PAGES} \
+					 page(s) for secondary cores"
+				);
+				SECONDARY_STACK_PAGES
+			});
+			dbg!("secondary core stack size: {stack_pages} page(s)");
+
 			// Boot the secondary cores.
 			let mut num_cores = 1; // start at one for the bsp
 			for entry in madt.entries().flatten() {
@@ -137,12 +221,8 @@ pub unsafe fn boot_primary() -> ! {
 							dbg!("cpu {}: not booting (primary core)", apic.id());
 						} else {
 							dbg!("cpu {}: booting...", apic.id());
-							match secondary::boot_secondary(
-								&mapper,
-								&lapic,
-								apic.id(),
-								SECONDARY_STACK_PAGES,
-							) {
+							match secondary::boot_secondary(&mapper, &lapic, apic.id(), stack_pages)
+							{
 								Ok(()) => {
 									num_cores += 1;
 								}
@@ -168,5 +248,45 @@ pub unsafe fn boot_primary() -> ! {
 		dbg!("proceeding with {} core(s)", num_cores);
 	}
 
+	// NOTE(qix-): The boot protocol structures are safe to reclaim only now;
+	// NOTE(qix-): the root ring modules have been loaded from the module list
+	// NOTE(qix-): (`initialize_primary()`), the secondaries have finished reading
+	// NOTE(qix-): the ACPI response, and nothing after this point (including
+	// NOTE(qix-): `init::boot()`) reads any bootloader response.
+	let reclaimed = memory::reclaim_bootloader_memory();
+	dbg!(
+		"reclaimed {reclaimed} page(s) ({} KiB) of bootloader memory",
+		reclaimed * 4
+	);
+	dbg!(
+		"physical memory: {} of {} page(s) free",
+		GlobalPfa.free_page_count(),
+		GlobalPfa.total_page_count()
+	);
+
 	crate::init::boot(lapic)
 }
+
+/// Determines the number of stack pages mapped for the primary core,
+/// counting down from the top guard page of the kernel stack segment
+/// until the first unmapped page.
+///
+/// Returns `None` if no stack pages are mapped.
+fn primary_stack_pages(mapper: &AddressSpaceHandle) -> Option<usize> {
+	let segment = AddressSpaceLayout::kernel_stack();
+	let (bottom, top) = segment.range();
+
+	let mut pages = 0;
+	let mut virt = top & !0xFFF;
+	while virt - 4096 >= bottom {
+		virt -= 4096;
+
+		if segment.translate(mapper, virt).is_none() {
+			break;
+		}
+
+		pages += 1;
+	}
+
+	(pages > 0).then_some(pages)
+}
@@ -1,6 +1,11 @@
 //! Defines the Oro kernel boot requests for the x86_64 architecture.
 
-use oro_boot_protocol::{AcpiRequest, MemoryMapRequest, ModulesRequest};
+use oro_boot_protocol::{
+	AcpiRequest, MemoryMapRequest, ModulesRequest,
+	note::{FEATURE_ACPI, KernelNote, KernelNoteSection},
+	util::Populated,
+};
+use oro_debug::dbg_err;
 
 /// The ACPI root table request.
 ///
@@ -23,3 +28,26 @@ pub static MMAP_REQUEST: MemoryMapRequest = MemoryMapRequest::with_revision(0);
 #[used]
 #[link_section = ".oro_boot"]
 pub static MODULES_REQUEST: ModulesRequest = ModulesRequest::with_revision(0);
+
+/// The kernel metadata note, checked by bootloaders prior to
+/// booting the kernel.
+#[used]
+#[link_section = ".note.oro"]
+pub static KERNEL_NOTE: KernelNoteSection = KernelNoteSection::new(KernelNote::new(FEATURE_ACPI));
+
+/// Strictly checks the `populated` flag of each request, logging an
+/// error for any that hold neither `0x00` nor `0xFF`, which indicates
+/// a buggy bootloader or memory corruption.
+///
+/// Such requests are treated as unpopulated regardless.
+pub fn check_populated() {
+	for (name, state) in [
+		("ACPI", ACPI_REQUEST.populated_state()),
+		("memory map", MMAP_REQUEST.populated_state()),
+		("modules", MODULES_REQUEST.populated_state()),
+	] {
+		if let Populated::Invalid(raw) = state {
+			dbg_err!("{name} request has an invalid populated flag ({raw:#04X}); ignoring it");
+		}
+	}
+}
@@ -3,12 +3,11 @@
 use core::{mem::MaybeUninit, sync::atomic::AtomicU64};
 
 use oro_acpi::{Madt, Rsdp};
-use oro_boot_protocol::acpi::AcpiKind;
 use oro_debug::{dbg, dbg_err};
 use oro_macro::{asm_buffer, assert};
 use oro_mem::{
 	global_alloc::GlobalPfa,
-	mapper::{AddressSegment, AddressSpace, MapError, UnmapError},
+	mapper::{AddressSegment, AddressSpace, GuardError, MapError},
 	pfa::Alloc,
 	phys::{Phys, PhysAddr},
 };
@@ -17,6 +16,7 @@ use crate::{
 	lapic::Lapic,
 	mem::{
 		address_space::{AddressSpaceHandle, AddressSpaceLayout},
+		paging_level::PagingLevel,
 		segment::MapperHandle,
 	},
 };
@@ -33,8 +33,8 @@ pub enum BootError {
 	OutOfMemory,
 	/// An error occurred while mapping memory.
 	MapError(MapError),
-	/// An error occurred while unmapping memory (probably the stack guard pages)
-	UnmapError(UnmapError),
+	/// A stack guard page check failed.
+	GuardError(GuardError),
 	/// The secondary errored out with the given value.
 	SecondaryError(u64),
 	/// Timed out waiting for the secondary to boot.
@@ -112,14 +112,11 @@ pub unsafe fn boot_secondary(
 	// before we remap them is sufficient enough.
 	kernel_stack_segment.unmap_without_reclaim(&mapper);
 
-	// make sure top guard page is unmapped
-	match kernel_stack_segment.unmap(&mapper, last_stack_page_virt) {
-		// NOTE(qix-): The Ok() case would never hit here since we explicitly unmapped the entire segment.
-		Ok(_) => unreachable!(),
-		Err(UnmapError::NotMapped) => {}
-		// NOTE(qix-): Should never happen.
-		Err(e) => return Err(BootError::UnmapError(e)),
-	}
+	// Make sure the top guard page is unmapped.
+	// NOTE(qix-): Should never fail, since we explicitly unmapped the entire segment.
+	kernel_stack_segment
+		.assert_guard_unmapped(&mapper, last_stack_page_virt)
+		.map_err(BootError::GuardError)?;
 
 	let mut bottom_stack_page_virt = last_stack_page_virt;
 	for stack_page_idx in 0..stack_pages {
@@ -146,14 +143,11 @@ pub unsafe fn boot_secondary(
 		}
 	}
 
-	// Make sure that the bottom guard page is unmapped
-	match kernel_stack_segment.unmap(&mapper, bottom_stack_page_virt - 4096) {
-		// NOTE(qix-): The Ok() case would never hit here isnce we explicitly unmapped the entire segment.
-		Ok(_) => unreachable!(),
-		Err(UnmapError::NotMapped) => {}
-		// NOTE(qix-): Should never happen.
-		Err(e) => return Err(BootError::UnmapError(e)),
-	}
+	// Make sure that the bottom guard page is unmapped.
+	// NOTE(qix-): Should never fail, since we explicitly unmapped the entire segment.
+	kernel_stack_segment
+		.assert_guard_unmapped(&mapper, bottom_stack_page_virt - 4096)
+		.map_err(BootError::GuardError)?;
 
 	// The 32-bit stack pointer is at 0x20000 + 4096 = 0x21000.
 	// This variable holds the long mode stack pointer that needs
@@ -445,13 +439,27 @@ unsafe extern "C" fn oro_kernel_x86_64_rust_secondary_core_entry() -> ! {
 	let primary_flag = &*(0x8FB0 as *const AtomicU64);
 	let secondary_flag = &*(0x8FB8 as *const AtomicU64);
 
+	// The primary core latched its paging level before booting us,
+	// and all cores must share it.
+	let paging_level = PagingLevel::current_from_cpu();
+	if Some(paging_level) != PagingLevel::latched() {
+		// Tell the primary we failed.
+		dbg_err!(
+			"paging level mismatch: primary is {:?}, but we're {paging_level:?}",
+			PagingLevel::latched()
+		);
+		secondary_flag.store(0xFFFF_FFFF_FFFF_FFFE, core::sync::atomic::Ordering::Release);
+		crate::asm::hang();
+	}
+
 	// Pull the RSDP from the boot protocol
 	// SAFETY(qix-): We can just unwrap these values as they're guaranteed to be OK
 	// SAFETY(qix-): since the primary core has already validated them to even boot
 	// SAFETY(qix-): the secondaries.
-	let AcpiKind::V0(acpi) = super::protocol::ACPI_REQUEST.response().unwrap() else {
-		unreachable!();
-	};
+	let acpi = super::protocol::ACPI_REQUEST
+		.response()
+		.and_then(|response| response.v0())
+		.unwrap();
 	let Some(sdt) = Rsdp::get(core::ptr::read_volatile(&acpi.assume_init_ref().rsdp))
 		.as_ref()
 		.and_then(Rsdp::sdt)
@@ -0,0 +1,157 @@
+//! CPUID-based CPU feature detection.
+//!
+//! Boot code should consult [`Features`] before enabling any
+//! optional processor functionality (e.g. `cr4` bits, x2APIC mode)
+//! so as to avoid general protection faults on older hardware.
+
+use core::arch::x86_64::{__cpuid, __cpuid_count, CpuidResult};
+
+/// The set of optional CPU features the kernel cares about,
+/// as reported by the `cpuid` instruction.
+///
+/// Detected once per core (see [`Features::detect`]) and cached
+/// in the core-local state.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[expect(clippy::struct_excessive_bools)]
+pub struct Features {
+	/// x2APIC mode is supported.
+	pub x2apic: bool,
+	/// Process-context identifiers (`CR4.PCIDE`) are supported.
+	pub pcid: bool,
+	/// The `invpcid` instruction is supported.
+	pub invpcid: bool,
+	/// The no-execute page bit (`EFER.NXE`) is supported.
+	pub nx: bool,
+	/// Supervisor mode access prevention (`CR4.SMAP`) is supported.
+	pub smap: bool,
+	/// Supervisor mode execution prevention (`CR4.SMEP`) is supported.
+	pub smep: bool,
+	/// 1GiB pages are supported.
+	pub page_1gib: bool,
+	/// Global pages (`CR4.PGE`) are supported.
+	pub pge: bool,
+	/// The page attribute table (`IA32_PAT`) is supported.
+	pub pat: bool,
+	/// 5-level paging (`CR4.LA57`) is supported.
+	pub la57: bool,
+	/// The `fxsave`/`fxrstor` instructions (`CR4.OSFXSR`) are supported.
+	pub fxsr: bool,
+	/// The `xsave` family of instructions (`CR4.OSXSAVE`) is supported.
+	pub xsave: bool,
+	/// The `rdfsbase`/`wrfsbase` family of instructions
+	/// (`CR4.FSGSBASE`) is supported.
+	pub fsgsbase: bool,
+	/// The `rdrand` instruction is supported.
+	pub rdrand: bool,
+	/// The TSC runs at a constant rate across all power states.
+	pub invariant_tsc: bool,
+	/// The LAPIC timer supports TSC-deadline mode.
+	pub tsc_deadline: bool,
+}
+
+impl Features {
+	/// Queries the current core's features via the `cpuid` instruction.
+	///
+	/// Prefer the cached [`crate::CoreState::features`] once the kernel
+	/// has been initialized for the core.
+	#[must_use]
+	pub fn detect() -> Self {
+		let max_leaf = cpuid(0).eax;
+		let max_ext_leaf = cpuid(0x8000_0000).eax;
+
+		let leaf1 = cpuid(1);
+		let leaf7 = if max_leaf >= 7 {
+			cpuid_count(7, 0)
+		} else {
+			CpuidResult {
+				eax: 0,
+				ebx: 0,
+				ecx: 0,
+				edx: 0,
+			}
+		};
+		let ext1 = if max_ext_leaf >= 0x8000_0001 {
+			cpuid(0x8000_0001)
+		} else {
+			CpuidResult {
+				eax: 0,
+				ebx: 0,
+				ecx: 0,
+				edx: 0,
+			}
+		};
+		let ext7_edx = if max_ext_leaf >= 0x8000_0007 {
+			cpuid(0x8000_0007).edx
+		} else {
+			0
+		};
+
+		Self {
+			x2apic: bit(leaf1.ecx, 21),
+			pcid: bit(leaf1.ecx, 17),
+			invpcid: bit(leaf7.ebx, 10),
+			nx: bit(ext1.edx, 20),
+			smap: bit(leaf7.ebx, 20),
+			smep: bit(leaf7.ebx, 7),
+			page_1gib: bit(ext1.edx, 26),
+			pge: bit(leaf1.edx, 13),
+			pat: bit(leaf1.edx, 16),
+			la57: bit(leaf7.ecx, 16),
+			fxsr: bit(leaf1.edx, 24),
+			xsave: bit(leaf1.ecx, 26),
+			fsgsbase: bit(leaf7.ebx, 0),
+			rdrand: bit(leaf1.ecx, 30),
+			invariant_tsc: bit(ext7_edx, 8),
+			tsc_deadline: bit(leaf1.ecx, 24),
+		}
+	}
+}
+
+/// Returns the mask of the `XCR0` state components supported by the
+/// processor (`CPUID.(EAX=0DH,ECX=0):EDX:EAX`).
+///
+/// Returns `0` if the processor does not support `xsave`.
+#[must_use]
+pub fn xsave_supported_components() -> u64 {
+	if cpuid(0).eax < 0xD || !Features::detect().xsave {
+		return 0;
+	}
+
+	let leaf = cpuid_count(0xD, 0);
+	(u64::from(leaf.edx) << 32) | u64::from(leaf.eax)
+}
+
+/// Returns the size, in bytes, of the `xsave` area required for the
+/// state components currently enabled in `XCR0`
+/// (`CPUID.(EAX=0DH,ECX=0):EBX`).
+///
+/// Only meaningful once `CR4.OSXSAVE` has been set and `XCR0` has
+/// been configured; returns `0` if the processor does not support `xsave`.
+#[must_use]
+pub fn xsave_area_size() -> usize {
+	if cpuid(0).eax < 0xD || !Features::detect().xsave {
+		return 0;
+	}
+
+	cpuid_count(0xD, 0).ebx as usize
+}
+
+/// Returns whether or not the given bit is set in `value`.
+#[inline]
+const fn bit(value: u32, bit: u32) -> bool {
+	(value & (1 << bit)) != 0
+}
+
+/// Executes `cpuid` for the given leaf.
+#[inline]
+fn cpuid(leaf: u32) -> CpuidResult {
+	// SAFETY(qix-): `cpuid` is available on all x86_64 processors.
+	unsafe { __cpuid(leaf) }
+}
+
+/// Executes `cpuid` for the given leaf and sub-leaf.
+#[inline]
+fn cpuid_count(leaf: u32, sub_leaf: u32) -> CpuidResult {
+	// SAFETY(qix-): `cpuid` is available on all x86_64 processors.
+	unsafe { __cpuid_count(leaf, sub_leaf) }
+}
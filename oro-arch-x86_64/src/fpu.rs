@@ -0,0 +1,276 @@
+//! Lazy x87/SSE/AVX (FPU) state management for user threads.
+//!
+//! Threads don't have an FPU save area until they first execute a
+//! floating-point or SIMD instruction. Whenever the kernel switches away
+//! from a user thread, `CR0.TS` is set, such that the next such instruction
+//! raises a device-not-available exception (`#NM`). The handler allocates
+//! the thread's save area upon first use (initialized to the default FPU
+//! state), restores it, and clears `CR0.TS` to let the thread continue.
+//!
+//! If `CR0.TS` is clear when switching away from a thread, the thread has
+//! used the FPU since it was last switched to, and its state is saved back
+//! to its save area (see [`save_and_disable()`]). Since the state is always
+//! saved upon switching away, threads may freely migrate between cores.
+//!
+//! `xsave`/`xrstor` are used if the processor supports them (saving all
+//! state components enabled in `XCR0`), otherwise `fxsave`/`fxrstor`.
+//!
+//! The kernel itself never uses the FPU.
+
+use core::{
+	arch::naked_asm,
+	sync::atomic::{
+		AtomicBool, AtomicUsize,
+		Ordering::{Acquire, Release},
+	},
+};
+
+use oro_debug::dbg_err;
+use oro_mem::{
+	global_alloc::GlobalPfa,
+	pfa::Alloc,
+	phys::{Phys, PhysAddr},
+};
+use oro_sync::Lock;
+
+/// The vector for the device-not-available (`#NM`) exception.
+pub const DEVICE_NOT_AVAILABLE_VECTOR: u8 = 7;
+
+/// The size of the legacy `fxsave` area.
+const FXSAVE_AREA_SIZE: usize = 512;
+
+/// The `XCR0` x87 state component bit.
+const XCR0_X87: u64 = 1 << 0;
+/// The `XCR0` SSE state component bit.
+const XCR0_SSE: u64 = 1 << 1;
+/// The `XCR0` AVX state component bit.
+const XCR0_AVX: u64 = 1 << 2;
+
+/// The default x87 control word (all exceptions masked, 64-bit precision,
+/// round-to-nearest), as set by `fninit`.
+const DEFAULT_FCW: u16 = 0x037F;
+/// The default `MXCSR` value (all exceptions masked, round-to-nearest).
+const DEFAULT_MXCSR: u32 = 0x1F80;
+
+/// Whether `xsave`/`xrstor` are used (rather than `fxsave`/`fxrstor`).
+static USE_XSAVE: AtomicBool = AtomicBool::new(false);
+/// The size of each thread's save area, in bytes.
+static AREA_SIZE: AtomicUsize = AtomicUsize::new(FXSAVE_AREA_SIZE);
+
+/// The method used to save and restore FPU state.
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub enum SaveMethod {
+	/// `fxsave`/`fxrstor` (x87 and SSE state only).
+	Fxsave,
+	/// `xsave`/`xrstor` (all state components enabled in `XCR0`).
+	Xsave,
+}
+
+/// Returns the method used to save and restore FPU state.
+#[must_use]
+pub fn save_method() -> SaveMethod {
+	if USE_XSAVE.load(Acquire) {
+		SaveMethod::Xsave
+	} else {
+		SaveMethod::Fxsave
+	}
+}
+
+/// Returns the size, in bytes, of each thread's FPU save area.
+#[must_use]
+pub fn area_size() -> usize {
+	AREA_SIZE.load(Acquire)
+}
+
+/// A thread's FPU save area.
+///
+/// Backed by (physically contiguous) page frames, which are freed
+/// when the area is dropped.
+pub(crate) struct FpuArea {
+	/// The physical address of the first page frame.
+	phys:  u64,
+	/// The number of page frames backing the area.
+	pages: usize,
+}
+
+impl FpuArea {
+	/// Allocates a new save area, initialized to the default FPU state.
+	///
+	/// Returns `None` if the system is out of memory.
+	fn new() -> Option<Self> {
+		let pages = area_size().div_ceil(4096);
+
+		let phys = if pages == 1 {
+			GlobalPfa.allocate_zeroed()?
+		} else {
+			let phys = GlobalPfa.allocate_contiguous(pages, 12)?;
+			// SAFETY(qix-): We just allocated the frames; they're ours.
+			unsafe {
+				Phys::from_address_unchecked(phys)
+					.as_mut_ptr_unchecked::<u8>()
+					.write_bytes(0, pages * 4096);
+			}
+			phys
+		};
+
+		let area = Self { phys, pages };
+
+		// NOTE(qix-): The legacy region layout is shared by `fxsave` and `xsave`.
+		// NOTE(qix-): With the `xsave` header zeroed, `xrstor` puts all other
+		// NOTE(qix-): components into their initial state, but always loads
+		// NOTE(qix-): MXCSR from the legacy region, so it must be set here.
+		// SAFETY(qix-): The area is page-aligned and at least 512 bytes in size.
+		unsafe {
+			let ptr = area.as_mut_ptr();
+			ptr.cast::<u16>().write(DEFAULT_FCW);
+			ptr.add(24).cast::<u32>().write(DEFAULT_MXCSR);
+		}
+
+		Some(area)
+	}
+
+	/// Returns a pointer to the save area.
+	fn as_mut_ptr(&self) -> *mut u8 {
+		// SAFETY(qix-): The frames are ours and are page (and thus 64-byte) aligned.
+		unsafe { Phys::from_address_unchecked(self.phys).as_mut_ptr_unchecked::<u8>() }
+	}
+
+	/// Saves the current core's FPU state into the area.
+	///
+	/// # Safety
+	/// `CR0.TS` must be clear.
+	unsafe fn save(&mut self) {
+		match save_method() {
+			SaveMethod::Xsave => crate::asm::xsave(self.as_mut_ptr()),
+			SaveMethod::Fxsave => crate::asm::fxsave(self.as_mut_ptr()),
+		}
+	}
+
+	/// Restores the current core's FPU state from the area.
+	///
+	/// # Safety
+	/// `CR0.TS` must be clear.
+	unsafe fn restore(&self) {
+		match save_method() {
+			SaveMethod::Xsave => crate::asm::xrstor(self.as_mut_ptr()),
+			SaveMethod::Fxsave => crate::asm::fxrstor(self.as_mut_ptr()),
+		}
+	}
+}
+
+impl Drop for FpuArea {
+	fn drop(&mut self) {
+		// SAFETY(qix-): The frames were allocated by us and are no longer in use.
+		unsafe {
+			GlobalPfa.free_contiguous(self.phys, self.pages);
+		}
+	}
+}
+
+/// Initializes lazy FPU handling on the current core.
+///
+/// Configures `XCR0` (if `xsave` is supported) to enable the x87, SSE,
+/// and (if supported) AVX state components, and sets `CR0.TS`.
+///
+/// # Safety
+/// Must be called exactly once per core during boot, after the core's
+/// kernel instance has been initialized and `CR4` has been configured,
+/// but prior to running any user threads.
+pub unsafe fn initialize() {
+	let supported = crate::cpuid::xsave_supported_components();
+
+	if crate::Kernel::get().core().features.xsave && supported != 0 {
+		crate::asm::xsetbv(0, supported & (XCR0_X87 | XCR0_SSE | XCR0_AVX));
+		AREA_SIZE.store(
+			crate::cpuid::xsave_area_size().max(FXSAVE_AREA_SIZE),
+			Release,
+		);
+		USE_XSAVE.store(true, Release);
+	}
+
+	crate::asm::set_task_switched();
+}
+
+/// Saves the FPU state of the thread being switched away from (if it
+/// has used the FPU since it was switched to), and sets `CR0.TS` such
+/// that the next thread's first FPU use restores its own state.
+///
+/// Must be called with interrupts disabled, whenever leaving a user
+/// thread's context.
+pub(crate) fn save_and_disable(thread_state: &mut crate::ThreadState) {
+	if !crate::asm::task_switched() {
+		if let Some(area) = thread_state.fpu.as_mut() {
+			// SAFETY(qix-): We've checked that `CR0.TS` is clear.
+			unsafe {
+				area.save();
+			}
+		}
+	}
+
+	crate::asm::set_task_switched();
+}
+
+/// The Rust side of the `#NM` handler.
+///
+/// Called by [`isr_device_not_available`] with interrupts disabled.
+#[no_mangle]
+unsafe extern "C" fn isr_device_not_available_rust() {
+	let kernel = crate::Kernel::get();
+	kernel.enter_interrupt();
+	let scheduler = kernel.scheduler().lock();
+
+	let Some(thread) = scheduler.current_thread() else {
+		panic!("kernel attempted to use the FPU");
+	};
+
+	let mut thread = thread.lock();
+	let state = thread.thread_state_mut();
+
+	if state.fpu.is_none() {
+		let Some(area) = FpuArea::new() else {
+			dbg_err!("out of memory allocating thread FPU save area");
+			crate::asm::hang();
+		};
+
+		state.fpu = Some(area);
+	}
+
+	crate::asm::clts();
+
+	if let Some(area) = state.fpu.as_ref() {
+		area.restore();
+	}
+
+	drop(thread);
+	drop(scheduler);
+	kernel.exit_interrupt();
+}
+
+/// The ISR (Interrupt Service Routine) trampoline stub for `#NM`.
+///
+/// Preserves all caller-saved registers prior to calling into the handler.
+#[naked]
+pub(crate) unsafe extern "C" fn isr_device_not_available() -> ! {
+	naked_asm!(
+		"push rax",
+		"push rcx",
+		"push rdx",
+		"push rsi",
+		"push rdi",
+		"push r8",
+		"push r9",
+		"push r10",
+		"push r11",
+		"call isr_device_not_available_rust",
+		"pop r11",
+		"pop r10",
+		"pop r9",
+		"pop r8",
+		"pop rdi",
+		"pop rsi",
+		"pop rdx",
+		"pop rcx",
+		"pop rax",
+		"iretq",
+	);
+}
@@ -254,10 +254,13 @@ pub struct Gdt<const COUNT: usize> {
 pub const KERNEL_CS: u16 = 0x08;
 /// The offset into the standard GDT of the kernel data segment.
 pub const KERNEL_DS: u16 = 0x10;
-/// The offset into the standard GDT of the user code segment.
-pub const USER_CS: u16 = 0x18;
+// NOTE(qix-): The user data segment MUST come before the user code segment;
+// NOTE(qix-): `sysret` derives both selectors from a single base in `IA32_STAR`
+// NOTE(qix-): (SS = base + 8, CS = base + 16). See the `syscall` module.
 /// The offset into the standard GDT of the user data segment.
-pub const USER_DS: u16 = 0x20;
+pub const USER_DS: u16 = 0x18;
+/// The offset into the standard GDT of the user code segment.
+pub const USER_CS: u16 = 0x20;
 
 impl<const COUNT: usize> Gdt<COUNT> {
 	/// Creates a new GDT with the standard entries (see the `*_CS` and `*_DS` constants).
@@ -268,8 +271,8 @@ impl<const COUNT: usize> Gdt<COUNT> {
 				GdtEntry::null_descriptor(),
 				GdtEntry::kernel_code_segment(),
 				GdtEntry::kernel_data_segment(),
-				GdtEntry::user_code_segment(),
 				GdtEntry::user_data_segment(),
+				GdtEntry::user_code_segment(),
 			],
 		}
 	}
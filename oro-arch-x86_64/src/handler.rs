@@ -1,5 +1,6 @@
 //! Implementation of [`oro_kernel::scheduler::Handler`] for the x86_64 architecture.
 
+use oro_kernel::time::Duration;
 use oro_mem::mapper::AddressSpace;
 
 use crate::mem::address_space::AddressSpaceLayout;
@@ -36,6 +37,19 @@ impl oro_kernel::scheduler::Handler<crate::Arch> for Handler {
 		self.kernel.core().lapic.cancel_timer();
 	}
 
+	fn duration_to_ticks(&self, duration: Duration) -> u32 {
+		/// The number of nanoseconds in a millisecond.
+		const NANOS_PER_MS: u128 = 1_000_000;
+
+		// NOTE(qix-): The timer is calibrated by the primary core prior to
+		// NOTE(qix-): any scheduling, so this fallback should never be hit.
+		let ticks_per_ms = self.kernel.core().lapic.timer_ticks_per_ms().unwrap_or(1);
+
+		let ticks =
+			(u128::from(duration.as_nanos()) * u128::from(ticks_per_ms)).div_ceil(NANOS_PER_MS);
+		u32::try_from(ticks).unwrap_or(u32::MAX).max(1)
+	}
+
 	fn migrate_thread(
 		kernel: &oro_kernel::Kernel<crate::Arch>,
 		thread: &mut oro_kernel::thread::Thread<crate::Arch>,
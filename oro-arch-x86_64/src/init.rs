@@ -1,14 +1,18 @@
 //! Architecture / core initialization
 //! routines and global state definitions.
 
-use core::{arch::asm, cell::UnsafeCell, mem::MaybeUninit};
+use core::{
+	arch::asm,
+	cell::UnsafeCell,
+	mem::MaybeUninit,
+	sync::atomic::{AtomicBool, Ordering},
+};
 
 use oro_debug::{dbg, dbg_err, dbg_warn};
-use oro_elf::{ElfSegment, ElfSegmentType};
-use oro_kernel::KernelState;
+use oro_kernel::{KernelState, core_id::CoreId, thread::Thread};
 use oro_mem::{
 	global_alloc::GlobalPfa,
-	mapper::AddressSegment,
+	mapper::{AddressSegment, AddressSpace},
 	pfa::Alloc,
 	phys::{Phys, PhysAddr},
 };
@@ -26,6 +30,13 @@ use crate::{
 /// and re-used across all cores.
 pub static mut KERNEL_STATE: MaybeUninit<KernelState<crate::Arch>> = MaybeUninit::uninit();
 
+/// Set once [`initialize_primary()`] has finished loading the root ring
+/// modules, after which the boot protocol's module list is no longer read.
+///
+/// The module list (and the modules response itself) reside in bootloader
+/// reclaimable memory; see [`crate::boot::memory::reclaim_bootloader_memory()`].
+pub(crate) static MODULES_LOADED: AtomicBool = AtomicBool::new(false);
+
 /// Initializes the global state of the architecture.
 ///
 /// # Panics
@@ -38,8 +49,6 @@ pub static mut KERNEL_STATE: MaybeUninit<KernelState<crate::Arch>> = MaybeUninit
 pub unsafe fn initialize_primary() {
 	#[cfg(debug_assertions)]
 	{
-		use core::sync::atomic::{AtomicBool, Ordering};
-
 		#[doc(hidden)]
 		static HAS_INITIALIZED: AtomicBool = AtomicBool::new(false);
 
@@ -58,17 +67,16 @@ pub unsafe fn initialize_primary() {
 	#[expect(static_mut_refs)]
 	let state = KERNEL_STATE.assume_init_ref();
 
-	// TODO(qix-): Not sure that I like that this is ELF-aware. This may get
-	// TODO(qix-): refactored at some point.
-	if let Some(oro_boot_protocol::modules::ModulesKind::V0(modules)) =
-		crate::boot::protocol::MODULES_REQUEST.response()
+	if let Some(modules) = crate::boot::protocol::MODULES_REQUEST
+		.response()
+		.and_then(|response| response.v0())
 	{
 		let modules = core::ptr::read_volatile(modules.assume_init_ref());
 		let mut next = modules.next;
 
 		let root_ring = state.root_ring();
 
-		'module: while next != 0 {
+		while next != 0 {
 			let Some(module) =
 				Phys::from_address_unchecked(next).as_ref::<oro_boot_protocol::Module>()
 			else {
@@ -102,114 +110,36 @@ pub unsafe fn initialize_primary() {
 				module.length
 			);
 
-			let module_handle = state
-				.create_module(id.clone())
-				.expect("failed to create root ring module");
-
-			let entry_point = {
-				let module_lock = module_handle.lock();
-
-				let mapper = module_lock.mapper();
-
-				let elf_base = Phys::from_address_unchecked(module.base).as_ptr_unchecked::<u8>();
-				let elf = oro_elf::Elf::parse(
-					elf_base,
-					usize::try_from(module.length).unwrap(),
-					crate::ELF_ENDIANNESS,
-					crate::ELF_CLASS,
-					crate::ELF_MACHINE,
-				)
-				.expect("failed to parse ELF");
-
-				for segment in elf.segments() {
-					let mapper_segment = match segment.ty() {
-						ElfSegmentType::Ignored => continue 'module,
-						ElfSegmentType::Invalid { flags, ptype } => {
-							dbg_err!(
-								"root ring module {id} has invalid segment; skipping: \
-								 ptype={ptype:?} flags={flags:?}",
-							);
-							continue 'module;
-						}
-						ElfSegmentType::ModuleCode => AddressSpaceLayout::module_code(),
-						ElfSegmentType::ModuleData => AddressSpaceLayout::module_data(),
-						ElfSegmentType::ModuleRoData => AddressSpaceLayout::module_rodata(),
-						ty => {
-							dbg_err!("root ring module {id} has invalid segment {ty:?}; skipping",);
-							continue 'module;
-						}
-					};
-
-					dbg!(
-						"{id}: loading {:?} segment: {:016X} {:016X} -> {:016X} ({})",
-						segment.ty(),
-						segment.load_address(),
-						segment.load_size(),
-						segment.target_address(),
-						segment.target_size()
-					);
-
-					// NOTE(qix-): This will almost definitely be improved in the future.
-					// NOTE(qix-): At the very least, hugepages will change this.
-					// NOTE(qix-): There will probably be some better machinery for
-					// NOTE(qix-): mapping ranges of memory in the future.
-					for page in 0..(segment.target_size().saturating_add(0xFFF) >> 12) {
-						let phys_addr = GlobalPfa
-							.allocate()
-							.expect("failed to map root ring module; out of memory");
-
-						let byte_offset = page << 12;
-						// Saturating sub here since the target size might exceed the file size,
-						// in which case we have to keep allocating those pages and zeroing them.
-						let load_size = segment.load_size().saturating_sub(byte_offset).min(4096);
-						let load_virt = segment.load_address() + byte_offset;
-						let target_virt = segment.target_address() + byte_offset;
-
-						let local_page_virt =
-							Phys::from_address_unchecked(phys_addr).as_mut_ptr_unchecked::<u8>();
-
-						// SAFETY(qix-): We can assume the kernel module is valid given that it's
-						// SAFETY(qix-): been loaded by the bootloader.
-						let (src, dest) = unsafe {
-							(
-								core::slice::from_raw_parts(load_virt as *const u8, load_size),
-								core::slice::from_raw_parts_mut(local_page_virt, 4096),
-							)
-						};
-
-						// copy data
-						if load_size > 0 {
-							dest[..load_size].copy_from_slice(&src[..load_size]);
-						}
-						// zero remaining
-						if load_size < 4096 {
-							dest[load_size..].fill(0);
-						}
-
-						mapper_segment
-							.map_nofree(mapper, target_virt, phys_addr)
-							.expect("failed to map segment");
-					}
-				}
+			// SAFETY(qix-): We can assume the module is valid given that it's
+			// SAFETY(qix-): been loaded by the bootloader.
+			let elf_bytes = core::slice::from_raw_parts(
+				Phys::from_address_unchecked(module.base).as_ptr_unchecked::<u8>(),
+				usize::try_from(module.length).unwrap(),
+			);
 
-				elf.entry_point()
+			let module_handle = match state.load_module(id.clone(), elf_bytes) {
+				Ok(module_handle) => module_handle,
+				Err(err) => {
+					dbg_err!("failed to load root ring module {id}; skipping: {err:?}");
+					continue;
+				}
 			};
 
 			let instance = state
-				.create_instance(module_handle, root_ring.clone())
+				.spawn_instance(&root_ring, &module_handle)
 				.expect("failed to create root ring instance");
 
-			// Create a thread for the entry point.
+			// Create a thread for each entry point.
 			// TODO(qix-): Allow stack size to be passed in via module command line.
-			let _thread = state
-				.create_thread(
-					instance,
-					16 * 1024,
-					crate::ThreadState::new(u64::try_from(entry_point).unwrap()),
-				)
-				.expect("failed to create root ring instance thread");
+			let entry_points = module_handle.lock().entry_points().to_vec();
+			for entry_point in entry_points {
+				Thread::new(&instance, entry_point, None)
+					.expect("failed to create root ring instance thread");
+			}
 		}
 	}
+
+	MODULES_LOADED.store(true, Ordering::Release);
 }
 
 /// Main boot sequence for all cores for each bringup
@@ -224,18 +154,51 @@ pub unsafe fn boot(lapic: Lapic) -> ! {
 	// SAFETY(qix-): THIS MUST ABSOLUTELY BE FIRST.
 	#[expect(static_mut_refs)]
 	let kernel = crate::Kernel::initialize_for_core(
-		lapic.id().into(),
+		CoreId::from(lapic.id()),
 		KERNEL_STATE.assume_init_ref(),
 		crate::CoreState {
 			lapic,
+			features: crate::cpuid::Features::detect(),
 			gdt: UnsafeCell::new(MaybeUninit::uninit()),
 			tss: UnsafeCell::new(Tss::default()),
 			kernel_stack: UnsafeCell::new(0),
 			kernel_irq_stack: UnsafeCell::new(0),
+			tlb_generation: UnsafeCell::new(0),
+			pcid: UnsafeCell::new(crate::pcid::PcidCache::new()),
+			syscall_scratch: UnsafeCell::new(crate::syscall::SyscallScratch::default()),
 		},
 	)
 	.expect("failed to initialize kernel");
 
+	// Point the active GS base at the core-local kernel instance.
+	// The inactive (kernel) GS base is later set up by the syscall
+	// initialization routine.
+	crate::asm::set_gs_base(core::ptr::from_ref(kernel) as u64);
+
+	// Map the core's dedicated double fault stack at the bottom of
+	// the (per-core) kernel stack segment, above an unmapped guard page.
+	{
+		let mapper = AddressSpaceLayout::current_supervisor_space();
+		let segment = AddressSpaceLayout::kernel_stack();
+		let stack_low_guard = segment.range().0 & !0xFFF;
+		let stack_top = stack_low_guard + 4096 + crate::DOUBLE_FAULT_STACK_PAGES * 4096;
+
+		if let Err(err) = segment.assert_guard_unmapped(&mapper, stack_low_guard) {
+			panic!("double fault stack guard check failed: {err}");
+		}
+
+		for virt in (stack_low_guard + 4096..stack_top).step_by(4096) {
+			let phys = GlobalPfa
+				.allocate()
+				.expect("failed to allocate double fault stack");
+			segment
+				.map(&mapper, virt, phys)
+				.expect("failed to map double fault stack");
+		}
+
+		(*kernel.core().tss.get()).ist1.write(stack_top as u64);
+	}
+
 	let (tss_offset, gdt) =
 		Gdt::<5>::new().with_sys_entry(SysEntry::for_tss(kernel.core().tss.get()));
 
@@ -250,7 +213,15 @@ pub unsafe fn boot(lapic: Lapic) -> ! {
 	}
 
 	crate::interrupt::install_idt();
+	kernel
+		.core()
+		.lapic
+		.enable(crate::interrupt::APIC_SVR_VECTOR);
 	crate::asm::load_tss(crate::TSS_GDT_OFFSET);
+	crate::tlb::mark_core_online();
+	crate::syscall::initialize();
+	crate::fpu::initialize();
+	crate::pat::initialize();
 
 	dbg!("boot");
 
@@ -264,9 +235,9 @@ pub unsafe fn boot(lapic: Lapic) -> ! {
 		};
 
 		if let Some(user_ctx) = maybe_ctx {
-			let (thread_cr3_phys, thread_rsp, kernel_rsp, kernel_irq_rsp) = unsafe {
+			let (thread_cr3, thread_rsp, kernel_rsp, kernel_irq_rsp) = unsafe {
 				let ctx_lock = user_ctx.lock();
-				let cr3 = ctx_lock.mapper().base_phys;
+				let cr3 = crate::pcid::cr3_for(ctx_lock.mapper().base_phys);
 				let rsp = ctx_lock.thread_state().irq_stack_ptr;
 				let kernel_rsp_ptr = kernel.core().kernel_stack.get() as u64;
 				let kernel_irq_rsp_ptr = kernel.core().kernel_irq_stack.get() as u64;
@@ -279,15 +250,27 @@ pub unsafe fn boot(lapic: Lapic) -> ! {
 
 			asm! {
 				"call oro_x86_64_kernel_to_user",
-				in("rax") thread_cr3_phys,
+				in("rax") thread_cr3,
 				in("rdx") thread_rsp,
 				in("r9") kernel_irq_rsp,
 				in("r10") kernel_rsp,
 			}
 		} else {
+			if kernel.take_reschedule_pending() {
+				// A thread was queued onto this core since the
+				// scheduler last ran; don't idle past it.
+				continue;
+			}
+
+			// Return any pages freed in the meantime to the
+			// page frame allocator while no locks are held.
+			#[cfg(not(test))]
+			crate::KERNEL_HEAP.reclaim();
+
 			// Nothing to do. Wait for an interrupt.
 			// Scheduler will have asked us to set a timer
-			// if it wants to be woken up.
+			// if it wants to be woken up. Other cores may
+			// also wake us via the reschedule IPI.
 			let kernel_rsp_ptr = kernel.core().kernel_stack.get() as u64;
 
 			asm! {
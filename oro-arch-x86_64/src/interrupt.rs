@@ -2,12 +2,14 @@
 
 use core::arch::{asm, naked_asm};
 
-use oro_mem::mapper::AddressSegment;
-use oro_sync::Lock;
+use oro_kernel::{interrupt::InterruptController, scheduler::Scheduler, thread::Thread};
+use oro_mem::{alloc::sync::Arc, mapper::AddressSegment};
+use oro_sync::{Lock, Mutex};
 
 use crate::{
+	handler::Handler,
 	isr_store_user_task_and_jmp,
-	lapic::{ApicSvr, ApicTimerConfig, ApicTimerMode},
+	lapic::{ApicTimerDivideBy, ApicTimerMode},
 	mem::address_space::AddressSpaceLayout,
 };
 
@@ -78,19 +80,36 @@ impl IdtEntry {
 		self.attributes = attributes;
 		self
 	}
+
+	/// Sets the IST (Interrupt Stack Table) index for the IDT entry.
+	///
+	/// An index of `0` means no IST is used; indices `1..=7` refer
+	/// to the `ist1..=ist7` fields of the core's TSS.
+	pub const fn with_ist(mut self, ist: u8) -> Self {
+		debug_assert!(ist <= 7, "IST index out of range");
+		self.ist = ist;
+		self
+	}
 }
 
 /// The IDT (Interrupt Descriptor Table) for the kernel.
 static mut IDT: Aligned16<[IdtEntry; 256]> = Aligned16([IdtEntry::new(); 256]);
 
-/// The ISR (Interrupt Service Routine) for the system timer.
-#[no_mangle]
-unsafe extern "C" fn isr_sys_timer_rust() -> ! {
-	// Must be first.
-	let irq_stack_ptr: u64;
-	asm!("", out("rcx") irq_stack_ptr, options(nostack, preserves_flags));
+/// A handle to a thread, as returned by the scheduler's events.
+type ThreadHandle = Arc<Mutex<Thread<crate::Arch>>>;
 
-	let handler = crate::handler::Handler::new();
+/// Common body of the ISRs (Interrupt Service Routines) that defer to
+/// the scheduler, given the IRQ stack pointer stored by
+/// [`isr_store_user_task_and_jmp!`].
+///
+/// Stores the interrupted context, acknowledges the interrupt, and then
+/// switches to whichever context the given scheduler `event` returns.
+unsafe fn isr_scheduler_event<F>(irq_stack_ptr: u64, event: F) -> !
+where
+	F: FnOnce(&mut Scheduler<crate::Arch>, &Handler) -> Option<ThreadHandle>,
+{
+	let handler = Handler::new();
+	handler.kernel().enter_interrupt();
 
 	let mut coming_from_user = false;
 	{
@@ -99,7 +118,11 @@ unsafe extern "C" fn isr_sys_timer_rust() -> ! {
 		// If this is `None`, then the kernel is currently running.
 		// Otherwise it's a userspace task that we just jumped from.
 		if let Some(user_task) = scheduler_lock.current_thread().as_ref() {
-			user_task.lock().thread_state_mut().irq_stack_ptr = irq_stack_ptr;
+			let mut user_task = user_task.lock();
+			let thread_state = user_task.thread_state_mut();
+			thread_state.irq_stack_ptr = irq_stack_ptr;
+			crate::fpu::save_and_disable(thread_state);
+			drop(user_task);
 
 			coming_from_user = true;
 		} else {
@@ -114,18 +137,17 @@ unsafe extern "C" fn isr_sys_timer_rust() -> ! {
 		drop(scheduler_lock);
 	}
 
-	handler.kernel().core().lapic.eoi();
+	handler.kernel().interrupt_controller().eoi();
+
+	let maybe_user_context = event(&mut handler.kernel().scheduler().lock(), &handler);
 
-	let maybe_user_context = handler
-		.kernel()
-		.scheduler()
-		.lock()
-		.event_timer_expired(&handler);
+	// Neither path below returns, so the interrupt is exited here.
+	handler.kernel().exit_interrupt();
 
 	if let Some(user_ctx) = maybe_user_context {
-		let (thread_cr3_phys, thread_rsp) = unsafe {
+		let (thread_cr3, thread_rsp) = unsafe {
 			let ctx_lock = user_ctx.lock();
-			let cr3 = ctx_lock.mapper().base_phys;
+			let cr3 = crate::pcid::cr3_for(ctx_lock.mapper().base_phys);
 			let rsp = ctx_lock.thread_state().irq_stack_ptr;
 			(*handler.kernel().core().tss.get())
 				.rsp0
@@ -136,7 +158,7 @@ unsafe extern "C" fn isr_sys_timer_rust() -> ! {
 
 		asm! {
 			"jmp oro_x86_64_user_to_user",
-			in("rax") thread_cr3_phys,
+			in("rax") thread_cr3,
 			in("rdx") thread_rsp,
 			options(noreturn),
 		};
@@ -144,7 +166,8 @@ unsafe extern "C" fn isr_sys_timer_rust() -> ! {
 		let kernel_irq_stack = handler.kernel().core().kernel_irq_stack.get().read();
 		let kernel_stack = handler.kernel().core().kernel_stack.get().read();
 		if coming_from_user {
-			let kernel_cr3 = handler.kernel().mapper().base_phys;
+			// SAFETY(qix-): Interrupts are disabled, and the value is loaded below.
+			let kernel_cr3 = unsafe { crate::pcid::cr3_for(handler.kernel().mapper().base_phys) };
 
 			asm! {
 				"mov cr3, rdx",
@@ -167,32 +190,110 @@ unsafe extern "C" fn isr_sys_timer_rust() -> ! {
 	}
 }
 
+/// The ISR (Interrupt Service Routine) for the system timer.
+#[no_mangle]
+unsafe extern "C" fn isr_sys_timer_rust() -> ! {
+	// Must be first.
+	let irq_stack_ptr: u64;
+	asm!("", out("rcx") irq_stack_ptr, options(nostack, preserves_flags));
+
+	isr_scheduler_event(irq_stack_ptr, |scheduler, handler| {
+		scheduler.event_timer_expired(handler)
+	})
+}
+
 /// The ISR (Interrupt Service Routine) trampoline stub for the system timer.
 #[naked]
 unsafe extern "C" fn isr_sys_timer() -> ! {
 	isr_store_user_task_and_jmp!(isr_sys_timer_rust);
 }
 
-/// The ISR (Interrupt Service Routine) for the APIC spurious interrupt.
+/// The ISR (Interrupt Service Routine) for the reschedule IPI.
+///
+/// Sent by other cores (see [`crate::Arch`]'s `send_reschedule()`)
+/// after placing a thread onto this core's run queue, such that an
+/// idle core picks it up without waiting for its next timer event.
+#[no_mangle]
+unsafe extern "C" fn isr_reschedule_rust() -> ! {
+	// Must be first.
+	let irq_stack_ptr: u64;
+	asm!("", out("rcx") irq_stack_ptr, options(nostack, preserves_flags));
+
+	isr_scheduler_event(irq_stack_ptr, |scheduler, handler| {
+		scheduler.event_reschedule(handler)
+	})
+}
+
+/// The ISR (Interrupt Service Routine) trampoline stub for the reschedule IPI.
+#[naked]
+unsafe extern "C" fn isr_reschedule() -> ! {
+	isr_store_user_task_and_jmp!(isr_reschedule_rust);
+}
+
+/// The ISR (Interrupt Service Routine) for the halt IPI.
+///
+/// Sent by a panicking core (see [`crate::Arch`]'s `halt_other_cores()`).
+/// Never returns, and never signals an EOI.
 #[no_mangle]
-unsafe extern "C" fn isr_apic_svr_rust() {
-	crate::Kernel::get().core().lapic.eoi();
+unsafe extern "C" fn isr_halt_rust() -> ! {
+	crate::asm::hang();
 }
 
-/// The ISR (Interrupt Service Routine) trampoline stub for the APIC spurious interrupt.
+/// The ISR (Interrupt Service Routine) trampoline stub for the halt IPI.
+#[naked]
+unsafe extern "C" fn isr_halt() -> ! {
+	naked_asm!("cli", "jmp isr_halt_rust");
+}
+
+/// The ISR (Interrupt Service Routine) for the APIC spurious interrupt.
+///
+/// **Spurious interrupts must NOT be acknowledged with an EOI.** The LAPIC
+/// does not set an in-service bit for the spurious vector, so an EOI here
+/// would instead retire whichever (unrelated) interrupt is currently in
+/// service, if any. Thus, the handler simply returns.
 #[naked]
 unsafe extern "C" fn isr_apic_svr() -> ! {
-	naked_asm!("cli", "jmp isr_apic_svr_rust");
+	naked_asm!("iretq");
+}
+
+/// The ISR (Interrupt Service Routine) for double faults.
+///
+/// Always runs on the core's dedicated double fault stack
+/// (see [`crate::DOUBLE_FAULT_IST`]), such that kernel stack
+/// overflows can be reported.
+#[no_mangle]
+unsafe extern "C" fn isr_double_fault_rust() -> ! {
+	panic!("double fault");
+}
+
+/// The ISR (Interrupt Service Routine) trampoline stub for double faults.
+#[naked]
+unsafe extern "C" fn isr_double_fault() -> ! {
+	naked_asm!("cli", "jmp isr_double_fault_rust");
 }
 
 /// Aligns a `T` value to 16 bytes.
 #[repr(C, align(16))]
 struct Aligned16<T: Sized>(pub T);
 
+/// The vector for the double fault exception.
+const DOUBLE_FAULT_VECTOR: u8 = 8;
 /// The vector for the main system timer interrupt.
 const TIMER_VECTOR: u8 = 32;
+/// The LAPIC timer divider used for the main system timer.
+///
+/// The LAPIC timer's calibration (see [`crate::lapic::Lapic::calibrate_timer`])
+/// is only valid for this divider.
+pub const TIMER_DIVIDER: ApicTimerDivideBy = ApicTimerDivideBy::Div128;
+/// The vector for the reschedule IPI (see [`isr_reschedule`]).
+pub const RESCHED_VECTOR: u8 = 0xF1;
+/// The vector for the halt IPI (see [`isr_halt`]).
+///
+/// In the highest priority class, such that it preempts any other
+/// interrupt being serviced by the receiving core.
+pub const HALT_VECTOR: u8 = 0xFE;
 /// The vector for the APIC spurious interrupt.
-const APIC_SVR_VECTOR: u8 = 255;
+pub const APIC_SVR_VECTOR: u8 = 255;
 
 /// Installs the IDT (Interrupt Descriptor Table) for the kernel
 /// and enables interrupts.
@@ -227,31 +328,66 @@ pub unsafe fn install_idt() {
 		options(nostack, preserves_flags)
 	);
 
+	// Set up the double fault handler on its own stack.
+	IDT.0[usize::from(DOUBLE_FAULT_VECTOR)] = IdtEntry::new()
+		.with_kernel_cs()
+		.with_attributes(0x8E)
+		.with_ist(crate::DOUBLE_FAULT_IST)
+		.with_isr(isr_double_fault);
+
+	// Set up the NMI handler, used to dump each core's state.
+	#[cfg(debug_assertions)]
+	{
+		IDT.0[usize::from(crate::nmi::NMI_VECTOR)] = IdtEntry::new()
+			.with_kernel_cs()
+			.with_attributes(0x8E)
+			.with_isr(crate::nmi::isr_nmi);
+	}
+
+	// Set up the lazy FPU handler.
+	IDT.0[usize::from(crate::fpu::DEVICE_NOT_AVAILABLE_VECTOR)] = IdtEntry::new()
+		.with_kernel_cs()
+		.with_attributes(0x8E)
+		.with_isr(crate::fpu::isr_device_not_available);
+
+	// Set up the page fault handler.
+	IDT.0[usize::from(crate::page_fault::PAGE_FAULT_VECTOR)] = IdtEntry::new()
+		.with_kernel_cs()
+		.with_attributes(0x8E)
+		.with_isr(crate::page_fault::isr_page_fault);
+
 	// Set up the main system timer.
 	IDT.0[usize::from(TIMER_VECTOR)] = IdtEntry::new()
 		.with_kernel_cs()
 		.with_attributes(0x8E)
 		.with_isr(isr_sys_timer);
 
-	lapic.set_timer_divider(crate::lapic::ApicTimerDivideBy::Div128);
+	// Note: this also enables the timer interrupts.
+	// The scheduler arms it via `Handler::schedule_timer`.
+	lapic.configure_timer(ApicTimerMode::OneShot, 0, TIMER_DIVIDER, TIMER_VECTOR);
 
-	// Note: this also enables the timer interrupts
-	lapic.configure_timer(
-		ApicTimerConfig::new()
-			.with_vector(TIMER_VECTOR)
-			.with_mode(ApicTimerMode::OneShot),
-	);
+	// Set up the TLB shootdown IPI handler.
+	IDT.0[usize::from(crate::tlb::TLB_SHOOTDOWN_VECTOR)] = IdtEntry::new()
+		.with_kernel_cs()
+		.with_attributes(0x8E)
+		.with_isr(crate::tlb::isr_tlb_shootdown);
+
+	// Set up the reschedule IPI handler.
+	IDT.0[usize::from(RESCHED_VECTOR)] = IdtEntry::new()
+		.with_kernel_cs()
+		.with_attributes(0x8E)
+		.with_isr(isr_reschedule);
+
+	// Set up the halt IPI handler.
+	IDT.0[usize::from(HALT_VECTOR)] = IdtEntry::new()
+		.with_kernel_cs()
+		.with_attributes(0x8E)
+		.with_isr(isr_halt);
 
 	// Set up the APIC spurious interrupt.
-	// This also enables the APIC if it isn't already.
+	// The LAPIC itself is enabled separately, via `Lapic::enable`.
 	IDT.0[usize::from(APIC_SVR_VECTOR)] = IdtEntry::new()
 		.with_kernel_cs()
 		.with_attributes(0x8E)
 		.with_isr(isr_apic_svr);
-
-	lapic.set_spurious_vector(
-		ApicSvr::new()
-			.with_vector(APIC_SVR_VECTOR)
-			.with_software_enable(),
-	);
 }
@@ -3,14 +3,30 @@
 //!
 //! Documentation found in Section 11 of the Intel SDM Volume 3A.
 
-use core::fmt;
+use core::{
+	fmt,
+	sync::atomic::{AtomicU32, Ordering::Relaxed},
+};
+
+use oro_kernel::{core_id::CoreId, critical::with_critical, interrupt::InterruptController};
 
 /// The LAPIC (Local Advanced Programmable Interrupt Controller (APIC))
 /// controller.
+///
+/// Transparently supports both xAPIC (MMIO) and x2APIC (MSR) modes;
+/// x2APIC mode is enabled for the current core upon construction if
+/// the processor supports it.
 pub struct Lapic {
 	/// The base address of the LAPIC.
-	/// Virtual and pre-translated.
-	base: *mut u8,
+	/// Virtual and pre-translated. Unused in x2APIC mode.
+	base:   *mut u8,
+	/// Whether or not the LAPIC is operating in x2APIC mode.
+	x2apic: bool,
+	/// The currently selected target APIC ID (x2APIC mode only).
+	///
+	/// In x2APIC mode, the ICR is a single 64-bit MSR and thus the
+	/// destination must be written alongside the command itself.
+	target: AtomicU32,
 }
 
 // SAFETY: The pointer is valid across all cores and is thus sendable.
@@ -18,29 +34,130 @@ pub struct Lapic {
 // SAFETY: cores and reside at the same location across each.
 unsafe impl Send for Lapic {}
 
+/// The base MSR of the x2APIC register block.
+const X2APIC_MSR_BASE: u32 = 0x800;
+/// The `EN` (global APIC enable) bit in `IA32_APIC_BASE`.
+const APIC_BASE_EN: u64 = 1 << 11;
+/// The `EXTD` (x2APIC mode enable) bit in `IA32_APIC_BASE`.
+const APIC_BASE_EXTD: u64 = 1 << 10;
+
+/// The calibrated number of LAPIC timer ticks per millisecond.
+/// Zero if not yet calibrated.
+static TIMER_TICKS_PER_MS: AtomicU32 = AtomicU32::new(0);
+
 impl Lapic {
 	/// Creates a new LAPIC controller.
 	///
+	/// If the current core supports x2APIC mode, it is enabled (if
+	/// the firmware hasn't done so already) and the MMIO base is
+	/// ignored. Otherwise, the LAPIC is accessed via the given MMIO
+	/// base (xAPIC mode).
+	///
 	/// # Panics
 	/// Panics if the LAPIC address is not 16-byte aligned.
 	///
 	/// # Safety
 	/// The caller must ensure that the LAPIC base address is valid and aligned.
+	///
+	/// Must be called on the core that owns the LAPIC.
 	pub unsafe fn new(base: *mut u8) -> Self {
 		assert_eq!(
 			base.align_offset(16),
 			0,
 			"LAPIC base is not 16-byte aligned"
 		);
-		Self { base }
+
+		let x2apic = crate::cpuid::Features::detect().x2apic;
+
+		if x2apic {
+			let apic_base = crate::msr::read(crate::msr::IA32_APIC_BASE);
+			if apic_base & APIC_BASE_EXTD == 0 {
+				// NOTE(qix-): Transitioning straight from disabled to x2APIC mode is
+				// NOTE(qix-): invalid; `EN` must be set alongside (or prior to) `EXTD`.
+				crate::msr::write(
+					crate::msr::IA32_APIC_BASE,
+					apic_base | APIC_BASE_EN | APIC_BASE_EXTD,
+				);
+			}
+		}
+
+		Self {
+			base,
+			x2apic,
+			target: AtomicU32::new(0),
+		}
+	}
+
+	/// Returns whether or not the LAPIC is operating in x2APIC mode.
+	#[must_use]
+	pub fn is_x2apic(&self) -> bool {
+		self.x2apic
+	}
+
+	/// Reads a 32-bit LAPIC register given its xAPIC MMIO offset.
+	///
+	/// In x2APIC mode, the offset is translated to its MSR equivalent.
+	#[inline]
+	fn read_reg(&self, offset: usize) -> u32 {
+		if self.x2apic {
+			// SAFETY(qix-): The register offsets used by this module all have
+			// SAFETY(qix-): valid x2APIC MSR counterparts.
+			unsafe { crate::msr::read(X2APIC_MSR_BASE + (offset >> 4) as u32) as u32 }
+		} else {
+			// SAFETY(qix-): The LAPIC base address is trusted to be valid and aligned.
+			#[expect(clippy::cast_ptr_alignment)]
+			unsafe {
+				self.base.add(offset).cast::<u32>().read_volatile()
+			}
+		}
+	}
+
+	/// Writes a 32-bit LAPIC register given its xAPIC MMIO offset.
+	///
+	/// In x2APIC mode, the offset is translated to its MSR equivalent.
+	#[inline]
+	fn write_reg(&self, offset: usize, value: u32) {
+		if self.x2apic {
+			// SAFETY(qix-): The register offsets used by this module all have
+			// SAFETY(qix-): valid x2APIC MSR counterparts.
+			unsafe {
+				crate::msr::write(X2APIC_MSR_BASE + (offset >> 4) as u32, u64::from(value));
+			}
+		} else {
+			// SAFETY(qix-): The LAPIC base address is trusted to be valid and aligned.
+			#[expect(clippy::cast_ptr_alignment)]
+			unsafe {
+				self.base.add(offset).cast::<u32>().write_volatile(value);
+			}
+		}
+	}
+
+	/// Issues an interrupt command given the low 32 bits of the ICR
+	/// (Interrupt Command Register), preserving the reserved bits
+	/// outside of `keep_mask`.
+	///
+	/// In x2APIC mode, the destination is taken from the target set
+	/// via [`Self::set_target_apic()`]; in xAPIC mode, it has already
+	/// been written to the high ICR register.
+	fn write_icr(&self, keep_mask: u32, command: u32) {
+		if self.x2apic {
+			// NOTE(qix-): In x2APIC mode, the ICR is a single 64-bit MSR and there are
+			// NOTE(qix-): no reserved bits to preserve in the low half.
+			let dest = u64::from(self.target.load(Relaxed));
+			// SAFETY(qix-): The ICR MSR always exists in x2APIC mode.
+			unsafe {
+				crate::msr::write(X2APIC_MSR_BASE + 0x30, (dest << 32) | u64::from(command));
+			}
+		} else {
+			let v = self.read_reg(0x300);
+			self.write_reg(0x300, (v & keep_mask) | command);
+		}
 	}
 
 	/// Returns the local APIC version.
 	#[must_use]
 	pub fn version(&self) -> LapicVersion {
-		// SAFETY(qix-): The LAPIC base address is trusted to be vali and aligned.
-		#[expect(clippy::cast_ptr_alignment)]
-		let version32 = unsafe { self.base.add(0x30).cast::<u32>().read_volatile() };
+		let version32 = self.read_reg(0x30);
 		LapicVersion {
 			supports_eoi_broadcast_suppression: (version32 & (1 << 24)) != 0,
 			max_lvt_entries: (version32 >> 16) as u8,
@@ -49,91 +166,166 @@ impl Lapic {
 	}
 
 	/// Returns the local APIC ID.
+	///
+	/// In x2APIC mode, the full ID is 32 bits wide; it is truncated to
+	/// the lower 8 bits, which matches the xAPIC IDs reported by the MADT
+	/// on systems with fewer than 255 cores.
 	#[must_use]
 	pub fn id(&self) -> u8 {
-		// SAFETY(qix-): The LAPIC base address is trusted to be valid and aligned.
-		#[expect(clippy::cast_ptr_alignment)]
-		let id32 = unsafe { self.base.add(0x20).cast::<u32>().read_volatile() };
-		(id32 >> 24) as u8
+		// TODO(qix-): Support APIC IDs larger than 255 (via the MADT's x2APIC entries).
+		let id32 = self.read_reg(0x20);
+		if self.x2apic {
+			id32 as u8
+		} else {
+			(id32 >> 24) as u8
+		}
 	}
 
 	/// Sets the local APIC ID.
+	///
+	/// The x2APIC ID is read-only; in x2APIC mode, this is a no-op.
 	pub fn set_id(&self, id: u8) {
-		// SAFETY(qix-): The LAPIC base address is trusted to be valid and aligned.
-		#[expect(clippy::cast_ptr_alignment)]
-		unsafe {
-			let v = self.base.add(0x20).cast::<u32>().read_volatile();
-			let v = (v & 0x00FF_FFFF) | (u32::from(id) << 24);
-			self.base.add(0x20).cast::<u32>().write_volatile(v);
+		if self.x2apic {
+			return;
 		}
+
+		let v = self.read_reg(0x20);
+		let v = (v & 0x00FF_FFFF) | (u32::from(id) << 24);
+		self.write_reg(0x20, v);
 	}
 
 	/// Clears the errors in the local APIC.
 	pub fn clear_errors(&self) {
-		// SAFETY(qix-): The LAPIC base address is trusted to be valid and aligned.
-		#[expect(clippy::cast_ptr_alignment)]
-		unsafe {
-			self.base.add(0x280).cast::<u32>().write_volatile(0);
-		}
+		self.write_reg(0x280, 0);
 	}
 
 	/// Selects the secondary processor we want to interact with.
 	pub fn set_target_apic(&self, apic_id: u8) {
-		// SAFETY(qix-): The LAPIC base address is trusted to be valid and aligned.
-		#[expect(clippy::cast_ptr_alignment)]
-		unsafe {
-			let v = self.base.add(0x310).cast::<u32>().read_volatile();
+		if self.x2apic {
+			self.target.store(u32::from(apic_id), Relaxed);
+		} else {
+			let v = self.read_reg(0x310);
 			let v = (v & 0x00FF_FFFF) | (u32::from(apic_id) << 24);
-			self.base.add(0x310).cast::<u32>().write_volatile(v);
+			self.write_reg(0x310, v);
 		}
 	}
 
 	/// Triggers an INIT IPI to the currently selected target secondary processor
 	/// (selected via [`Self::set_target_apic()`]).
 	pub fn send_init_ipi(&self) {
-		// SAFETY(qix-): The LAPIC base address is trusted to be valid and aligned.
-		#[expect(clippy::cast_ptr_alignment)]
-		unsafe {
-			let v = self.base.add(0x300).cast::<u32>().read_volatile();
-			let v = (v & 0xFFF0_0000) | 0x00_C500;
-			// let v = 0x00004500;
-			self.base.add(0x300).cast::<u32>().write_volatile(v);
-		}
+		self.write_icr(0xFFF0_0000, 0x00_C500);
 	}
 
 	/// Waits for the IPI to be acknowledged by the target processor.
+	///
+	/// x2APIC mode has no delivery status bit; this returns immediately.
 	pub fn wait_for_ipi_ack(&self) {
-		// SAFETY(qix-): The LAPIC base address is trusted to be valid and aligned.
-		#[expect(clippy::cast_ptr_alignment)]
-		unsafe {
-			while self.base.add(0x300).cast::<u32>().read_volatile() & 0x1000 != 0 {
-				core::hint::spin_loop();
-			}
+		if self.x2apic {
+			return;
+		}
+
+		while self.read_reg(0x300) & 0x1000 != 0 {
+			core::hint::spin_loop();
 		}
 	}
 
 	/// Deasserts the INIT IPI.
 	pub fn deassert_init_ipi(&self) {
-		// SAFETY(qix-): The LAPIC base address is trusted to be valid and aligned.
-		#[expect(clippy::cast_ptr_alignment)]
-		unsafe {
-			let v = self.base.add(0x300).cast::<u32>().read_volatile();
-			let v = (v & 0xFFF0_0000) | 0x00_8500;
-			self.base.add(0x300).cast::<u32>().write_volatile(v);
-		}
+		self.write_icr(0xFFF0_0000, 0x00_8500);
 	}
 
 	/// Sends a startup IPI to the currently selected target secondary processor
 	/// (selected via [`Self::set_target_apic()`]).
 	pub fn send_startup_ipi(&self, cs_page: u8) {
-		// SAFETY(qix-): The LAPIC base address is trusted to be valid and aligned.
-		#[expect(clippy::cast_ptr_alignment)]
-		unsafe {
-			let v = self.base.add(0x300).cast::<u32>().read_volatile();
-			let v = (v & 0xFFF0_F800) | 0x00_0600 | u32::from(cs_page);
-			// let v = 0x00004600 | cs_page as u32;
-			self.base.add(0x300).cast::<u32>().write_volatile(v);
-		}
+		self.write_icr(0xFFF0_F800, 0x00_0600 | u32::from(cs_page));
+	}
+
+	/// Sends a fixed, edge-triggered IPI with the given vector to the
+	/// given destination.
+	///
+	/// In xAPIC mode, waits for any previously issued IPI to be
+	/// accepted before sending, and for this one to be accepted
+	/// before returning (see [`Self::wait_for_ipi_ack()`]).
+	///
+	/// Interrupts are disabled while the ICR is being written, such
+	/// that an interrupt handler sending its own IPI cannot clobber
+	/// the destination.
+	///
+	/// # Panics
+	/// Panics in debug mode if a [`IpiDest::Core`] ID cannot be
+	/// represented by the LAPIC's current mode.
+	pub fn send_ipi(&self, dest: IpiDest, vector: u8) {
+		// Fixed delivery.
+		self.send_icr(dest, u32::from(vector));
+	}
+
+	/// Sends a non-maskable interrupt (NMI) to the given destination.
+	///
+	/// NMIs are delivered regardless of the receiving cores' interrupt
+	/// flags, and are always handled by vector 2.
+	///
+	/// The same notes as for [`Self::send_ipi()`] apply.
+	///
+	/// # Panics
+	/// Panics in debug mode if a [`IpiDest::Core`] ID cannot be
+	/// represented by the LAPIC's current mode.
+	pub fn send_nmi(&self, dest: IpiDest) {
+		// NMI delivery; the vector is ignored.
+		self.send_icr(dest, 0b100 << 8);
+	}
+
+	/// Writes the ICR with the given delivery mode and vector bits
+	/// (bits `0..=10`) for the given destination.
+	fn send_icr(&self, dest: IpiDest, mode_and_vector: u32) {
+		let (target, shorthand) = match dest {
+			IpiDest::Core(id) => {
+				let id = u64::from(id);
+				if self.x2apic {
+					debug_assert!(u32::try_from(id).is_ok(), "x2APIC ID out of range");
+				} else {
+					debug_assert!(u8::try_from(id).is_ok(), "xAPIC ID out of range");
+				}
+
+				(id as u32, 0b00)
+			}
+			IpiDest::Current => (0, 0b01),
+			IpiDest::All => (0, 0b10),
+			IpiDest::AllExcludingSelf => (0, 0b11),
+		};
+
+		// Physical destination, asserted, edge triggered.
+		let command = (shorthand << 18) | (1 << 14) | mode_and_vector;
+
+		with_critical::<crate::Arch, _, _>(|| {
+			self.wait_for_ipi_ack();
+
+			if self.x2apic {
+				self.target.store(target, Relaxed);
+			} else {
+				let v = self.read_reg(0x310);
+				self.write_reg(0x310, (v & 0x00FF_FFFF) | (target << 24));
+			}
+
+			self.write_icr(0xFFF0_0000, command);
+			self.wait_for_ipi_ack();
+		});
+	}
+
+	/// Software-enables the LAPIC, setting the spurious interrupt vector.
+	///
+	/// The LAPIC will not reliably deliver interrupts until this is called.
+	///
+	/// Note that the handler for the spurious vector **must not** send
+	/// an EOI; spurious interrupts do not set an in-service bit, and an
+	/// EOI would instead acknowledge an unrelated in-service interrupt.
+	///
+	/// Must be called on every core.
+	pub fn enable(&self, spurious_vector: u8) {
+		self.set_spurious_vector(
+			self.spurious_vector()
+				.with_vector(spurious_vector)
+				.with_software_enable(),
+		);
 	}
 
 	/// Boots a secondary core given its LAPIC ID.
@@ -171,66 +363,145 @@ impl Lapic {
 		}
 	}
 
-	/// Sends an End Of Interrupt (EOI) signal to the LAPIC.
-	pub fn eoi(&self) {
-		// SAFETY(qix-): The LAPIC base address is trusted to be valid and aligned.
-		#[expect(clippy::cast_ptr_alignment)]
-		unsafe {
-			self.base.add(0xB0).cast::<u32>().write_volatile(0);
+	/// Configures and starts the LAPIC timer.
+	///
+	/// In [`ApicTimerMode::TscDeadline`] mode, `initial_count` and
+	/// `divide` are ignored by the hardware; the timer is instead
+	/// armed via [`Self::set_tsc_deadline()`]. TSC-deadline mode
+	/// must only be used if [`crate::cpuid::Features::tsc_deadline`]
+	/// is set.
+	///
+	/// # Panics
+	/// Panics in debug mode if TSC-deadline mode is requested but
+	/// not supported by the core.
+	pub fn configure_timer(
+		&self,
+		mode: ApicTimerMode,
+		initial_count: u32,
+		divide: ApicTimerDivideBy,
+		vector: u8,
+	) {
+		let config = ApicTimerConfig::new().with_vector(vector).with_mode(mode);
+
+		if mode == ApicTimerMode::TscDeadline {
+			debug_assert!(
+				crate::cpuid::Features::detect().tsc_deadline,
+				"TSC-deadline mode is not supported"
+			);
+
+			self.set_timer_config(config);
+			// NOTE(qix-): The SDM requires the LVT write to be serialized
+			// NOTE(qix-): prior to any subsequent `IA32_TSC_DEADLINE` writes.
+			crate::asm::strong_memory_barrier();
+		} else {
+			self.set_timer_divider(divide);
+			self.set_timer_config(config);
+			self.set_timer_initial_count(initial_count);
 		}
 	}
 
-	/// Configures the LAPIC timer.
-	pub fn configure_timer(&self, config: ApicTimerConfig) {
-		// SAFETY(qix-): The LAPIC base address is trusted to be valid and aligned.
-		#[expect(clippy::cast_ptr_alignment)]
+	/// Stops the LAPIC timer, masking its interrupt.
+	///
+	/// Unlike [`Self::cancel_timer()`], the timer must be re-configured
+	/// via [`Self::configure_timer()`] before it will fire again.
+	pub fn stop_timer(&self) {
+		let config = self.timer_config();
+		self.set_timer_config(config.with_masked());
+
+		if config.mode() == Some(ApicTimerMode::TscDeadline) {
+			self.set_tsc_deadline(0);
+		} else {
+			self.set_timer_initial_count(0);
+		}
+	}
+
+	/// Arms the LAPIC timer to fire when the TSC reaches the given value.
+	/// A value of `0` disarms the timer.
+	///
+	/// Only has an effect in [`ApicTimerMode::TscDeadline`] mode.
+	pub fn set_tsc_deadline(&self, deadline: u64) {
+		// SAFETY(qix-): Only effective in TSC-deadline mode, which is only
+		// SAFETY(qix-): configured if the MSR is supported.
 		unsafe {
-			self.base.add(0x320).cast::<u32>().write_volatile(config.0);
+			crate::msr::write(crate::msr::IA32_TSC_DEADLINE, deadline);
+		}
+	}
+
+	/// Calibrates the LAPIC timer against the PIT, returning the number
+	/// of timer ticks per millisecond at the given divider.
+	///
+	/// The result is stored and is thereafter available via
+	/// [`Self::timer_ticks_per_ms()`]. LAPIC timers are driven by the
+	/// same clock across all cores, so this need only be performed
+	/// once, by the primary core, before secondary cores are booted.
+	///
+	/// Leaves the timer masked and stopped. Interrupts should be
+	/// disabled while calibrating.
+	pub fn calibrate_timer(&self, divide: ApicTimerDivideBy) -> u32 {
+		/// The length of the calibration window, in milliseconds.
+		const CALIBRATION_MS: u16 = 10;
+
+		self.set_timer_divider(divide);
+		self.set_timer_config(
+			ApicTimerConfig::new()
+				.with_masked()
+				.with_mode(ApicTimerMode::OneShot),
+		);
+		self.set_timer_initial_count(u32::MAX);
+
+		crate::pit::sleep_ms(CALIBRATION_MS);
+
+		let elapsed = u32::MAX - self.timer_current_count();
+		self.set_timer_initial_count(0);
+
+		// NOTE(qix-): Sub-tick precision is irrelevant here.
+		#[expect(clippy::integer_division)]
+		let ticks_per_ms = (elapsed / u32::from(CALIBRATION_MS)).max(1);
+		TIMER_TICKS_PER_MS.store(ticks_per_ms, Relaxed);
+		ticks_per_ms
+	}
+
+	/// Returns the number of LAPIC timer ticks per millisecond, as
+	/// determined by [`Self::calibrate_timer()`], or `None` if the
+	/// timer has not yet been calibrated.
+	///
+	/// Only valid for the divider that was used for calibration.
+	#[must_use]
+	pub fn timer_ticks_per_ms(&self) -> Option<u32> {
+		match TIMER_TICKS_PER_MS.load(Relaxed) {
+			0 => None,
+			ticks => Some(ticks),
 		}
 	}
 
+	/// Sets the LAPIC timer's local vector table (LVT) entry
+	/// without otherwise modifying the timer.
+	pub fn set_timer_config(&self, config: ApicTimerConfig) {
+		self.write_reg(0x320, config.0);
+	}
+
 	/// Sets the LAPIC timer divider value.
 	pub fn set_timer_divider(&self, divide_by: ApicTimerDivideBy) {
-		// SAFETY(qix-): The LAPIC base address is trusted to be valid and aligned.
-		#[expect(clippy::cast_ptr_alignment)]
-		unsafe {
-			self.base
-				.add(0x3E0)
-				.cast::<u32>()
-				.write_volatile(divide_by as u32);
-		}
+		self.write_reg(0x3E0, divide_by as u32);
 	}
 
 	/// Reads the LAPIC timer's configuration.
 	#[must_use]
 	pub fn timer_config(&self) -> ApicTimerConfig {
-		// SAFETY(qix-): The LAPIC base address is trusted to be valid and aligned.
-		#[expect(clippy::cast_ptr_alignment)]
-		unsafe {
-			ApicTimerConfig(self.base.add(0x320).cast::<u32>().read_volatile())
-		}
+		ApicTimerConfig(self.read_reg(0x320))
 	}
 
 	/// Reads the LAPIC timer's divide-by value.
 	#[must_use]
 	pub fn timer_divide_by(&self) -> ApicTimerDivideBy {
-		// SAFETY(qix-): The LAPIC base address is trusted to be valid and aligned,
-		// SAFETY(qix-): and the transmuted bits are always valid.
-		#[expect(clippy::cast_ptr_alignment)]
-		unsafe {
-			let v = self.base.add(0x3E0).cast::<u32>().read_volatile();
-			let v = v & 0b1011;
-			core::mem::transmute(v)
-		}
+		let v = self.read_reg(0x3E0) & 0b1011;
+		// SAFETY(qix-): The transmuted bits are always valid.
+		unsafe { core::mem::transmute(v) }
 	}
 
 	/// Sets the LAPIC timer's initial count.
 	pub fn set_timer_initial_count(&self, count: u32) {
-		// SAFETY(qix-): The LAPIC base address is trusted to be valid and aligned.
-		#[expect(clippy::cast_ptr_alignment)]
-		unsafe {
-			self.base.add(0x380).cast::<u32>().write_volatile(count);
-		}
+		self.write_reg(0x380, count);
 	}
 
 	/// Cancels the timer.
@@ -246,30 +517,18 @@ impl Lapic {
 	/// Reads the LAPIC timer's current count.
 	#[must_use]
 	pub fn timer_current_count(&self) -> u32 {
-		// SAFETY(qix-): The LAPIC base address is trusted to be valid and aligned.
-		#[expect(clippy::cast_ptr_alignment)]
-		unsafe {
-			self.base.add(0x390).cast::<u32>().read_volatile()
-		}
+		self.read_reg(0x390)
 	}
 
 	/// Reads the LAPIC's spurrious interrupt vector (SVR) value.
 	#[must_use]
 	pub fn spurious_vector(&self) -> ApicSvr {
-		// SAFETY(qix-): The LAPIC base address is trusted to be valid and aligned.
-		#[expect(clippy::cast_ptr_alignment)]
-		unsafe {
-			ApicSvr(self.base.add(0xF0).cast::<u32>().read_volatile())
-		}
+		ApicSvr(self.read_reg(0xF0))
 	}
 
 	/// Sets the LAPIC's spurrious interrupt vector (SVR) value.
 	pub fn set_spurious_vector(&self, svr: ApicSvr) {
-		// SAFETY(qix-): The LAPIC base address is trusted to be valid and aligned.
-		#[expect(clippy::cast_ptr_alignment)]
-		unsafe {
-			self.base.add(0xF0).cast::<u32>().write_volatile(svr.0);
-		}
+		self.write_reg(0xF0, svr.0);
 	}
 }
 
@@ -284,6 +543,53 @@ pub struct LapicVersion {
 	pub version: u8,
 }
 
+impl InterruptController for Lapic {
+	/// Sends an End Of Interrupt (EOI) signal to the LAPIC.
+	fn eoi(&self) {
+		self.write_reg(0xB0, 0);
+	}
+
+	/// Masks the given vector.
+	///
+	/// The LAPIC timer is masked if it delivers the vector; external
+	/// interrupts are masked at whichever I/O APIC redirection entries
+	/// deliver it (see [`crate::ioapic::mask_vector()`]).
+	fn mask(&self, vector: u8) {
+		let timer = self.timer_config();
+		if timer.vector() == vector {
+			self.set_timer_config(timer.with_masked());
+		}
+
+		crate::ioapic::mask_vector(vector);
+	}
+
+	/// Unmasks the given vector.
+	///
+	/// See [`Self::mask()`].
+	fn unmask(&self, vector: u8) {
+		let timer = self.timer_config();
+		if timer.vector() == vector {
+			self.set_timer_config(timer.with_unmasked());
+		}
+
+		crate::ioapic::unmask_vector(vector);
+	}
+}
+
+/// The destination of an IPI (inter-processor interrupt) sent
+/// via [`Lapic::send_ipi()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpiDest {
+	/// The current core only.
+	Current,
+	/// All cores, including the current one.
+	All,
+	/// All cores except the current one.
+	AllExcludingSelf,
+	/// The core with the given ID (its LAPIC ID).
+	Core(CoreId),
+}
+
 /// The configuration for the LAPIC timer.
 #[derive(Clone, Copy)]
 #[repr(transparent)]
@@ -327,6 +633,13 @@ impl ApicTimerConfig {
 		self
 	}
 
+	/// Clears the timer interrupt's mask bit.
+	#[must_use]
+	pub const fn with_unmasked(mut self) -> Self {
+		self.0 &= !(1 << 16);
+		self
+	}
+
 	/// Sets the timer mode.
 	#[must_use]
 	pub const fn with_mode(mut self, mode: ApicTimerMode) -> Self {
@@ -343,12 +656,11 @@ impl ApicTimerConfig {
 	/// Gets the timer mode. Returns `None` if the mode bits are invalid.
 	#[must_use]
 	pub const fn mode(self) -> Option<ApicTimerMode> {
-		let bits = (self.0 >> 17) & 0b11;
-		if bits == 0b11 {
-			None
-		} else {
-			// SAFETY(qix-): The mode bits are always valid.
-			Some(unsafe { core::mem::transmute::<u32, ApicTimerMode>(bits) })
+		match (self.0 >> 17) & 0b11 {
+			0b00 => Some(ApicTimerMode::OneShot),
+			0b01 => Some(ApicTimerMode::Periodic),
+			0b10 => Some(ApicTimerMode::TscDeadline),
+			_ => None,
 		}
 	}
 
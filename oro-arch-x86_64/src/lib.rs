@@ -63,22 +63,39 @@
 #![feature(generic_const_exprs)]
 
 pub mod asm;
+pub mod backtrace;
 pub mod boot;
+pub mod cpuid;
+pub mod fpu;
 pub mod gdt;
 pub mod handler;
 pub mod interrupt;
+pub mod ioapic;
 pub mod lapic;
 pub mod mem;
+pub mod msr;
+#[cfg(debug_assertions)]
+pub mod nmi;
+pub mod page_fault;
+pub mod pat;
+pub mod pcid;
+pub mod pit;
+pub mod power;
 pub mod reg;
+pub mod syscall;
 pub mod task;
+pub mod tlb;
+pub mod tsc;
 pub mod tss;
 
 pub(crate) mod init;
 
 use core::{cell::UnsafeCell, mem::MaybeUninit};
 
+use lapic::IpiDest;
 use mem::address_space::AddressSpaceLayout;
 use oro_elf::{ElfClass, ElfEndianness, ElfMachine};
+use oro_kernel::core_id::CoreId;
 use oro_mem::{
 	global_alloc::GlobalPfa,
 	mapper::{AddressSegment, MapError, UnmapError},
@@ -100,8 +117,75 @@ pub(crate) struct Arch;
 impl oro_kernel::Arch for Arch {
 	type AddrSpace = crate::mem::address_space::AddressSpaceLayout;
 	type CoreState = CoreState;
+	type IntCtrl = lapic::Lapic;
+	type InterruptState = bool;
 	type ThreadState = ThreadState;
 
+	const ELF_CLASS: ElfClass = ELF_CLASS;
+	const ELF_ENDIANNESS: ElfEndianness = ELF_ENDIANNESS;
+	const ELF_MACHINE: ElfMachine = ELF_MACHINE;
+
+	fn interrupt_controller(core: &Self::CoreState) -> &Self::IntCtrl {
+		&core.lapic
+	}
+
+	fn fetch_interrupts() -> Self::InterruptState {
+		crate::asm::rflags() & (1 << 9) != 0
+	}
+
+	fn disable_interrupts() {
+		crate::asm::disable_interrupts();
+	}
+
+	fn restore_interrupts(state: Self::InterruptState) {
+		if state {
+			crate::asm::enable_interrupts();
+		} else {
+			crate::asm::disable_interrupts();
+		}
+	}
+
+	fn send_reschedule(core: CoreId) {
+		Kernel::get()
+			.core()
+			.lapic
+			.send_ipi(IpiDest::Core(core), crate::interrupt::RESCHED_VECTOR);
+	}
+
+	fn halt_other_cores() {
+		if !crate::tlb::others_online() {
+			return;
+		}
+
+		Kernel::get()
+			.core()
+			.lapic
+			.send_ipi(IpiDest::AllExcludingSelf, crate::interrupt::HALT_VECTOR);
+	}
+
+	fn flush_tlb_range(virt: usize, len: usize) {
+		crate::tlb::flush_range(virt, len);
+	}
+
+	fn halt_once_and_wait() {
+		crate::asm::halt_once();
+	}
+
+	fn reboot() -> ! {
+		crate::power::reboot();
+	}
+
+	fn shutdown() -> ! {
+		crate::power::shutdown();
+	}
+
+	fn log_backtrace() {
+		// SAFETY(qix-): Only ever called by the panic path.
+		unsafe {
+			crate::backtrace::log_unlocked();
+		}
+	}
+
 	fn initialize_thread_mappings(
 		thread: &<Self::AddrSpace as oro_mem::mapper::AddressSpace>::UserHandle,
 		thread_state: &mut Self::ThreadState,
@@ -121,20 +205,12 @@ impl oro_kernel::Arch for Arch {
 		// with a bug-free implementation.
 		#[cfg(debug_assertions)]
 		{
-			match irq_stack_segment.unmap(thread, stack_high_guard) {
-				Ok(phys) => panic!("interrupt stack high guard was already mapped at {phys:016X}"),
-				Err(UnmapError::NotMapped) => {}
-				Err(err) => {
-					panic!("interrupt stack high guard encountered error when unmapping: {err:?}")
-				}
+			if let Err(err) = irq_stack_segment.assert_guard_unmapped(thread, stack_high_guard) {
+				panic!("interrupt stack high guard check failed: {err}");
 			}
 
-			match irq_stack_segment.unmap(thread, stack_low_guard) {
-				Ok(phys) => panic!("interrupt stack low guard was already mapped at {phys:016X}"),
-				Err(UnmapError::NotMapped) => {}
-				Err(err) => {
-					panic!("interrupt stack low guard encountered error when unmapping: {err:?}")
-				}
+			if let Err(err) = irq_stack_segment.assert_guard_unmapped(thread, stack_low_guard) {
+				panic!("interrupt stack low guard check failed: {err}");
 			}
 		}
 
@@ -166,20 +242,47 @@ impl oro_kernel::Arch for Arch {
 	}
 }
 
+/// The kernel's panic path (see [`oro_kernel::panic()`]).
+///
+/// Meant to be called only by the `#[panic_handler]`.
+pub fn panic(info: &core::panic::PanicInfo) -> ! {
+	oro_kernel::panic::<Arch>(info)
+}
+
 /// Type alias for the Oro kernel core-local instance type.
 pub(crate) type Kernel = oro_kernel::Kernel<Arch>;
 
+/// The kernel heap, backing the `alloc` crate.
+#[cfg(not(test))]
+#[global_allocator]
+static KERNEL_HEAP: oro_kernel::heap::LockedHeap<Arch> = oro_kernel::heap::LockedHeap::new();
+
 /// The guaranteed offset of the task state segment (TSS) in the GDT.
 ///
 /// Verified at boot time, such that this index can be used without having
 /// to perform a lookup.
 pub const TSS_GDT_OFFSET: u16 = 0x28;
 
+/// The IST (Interrupt Stack Table) index used by the double fault handler.
+///
+/// Each core's TSS points this entry at a dedicated stack
+/// so that double faults caused by kernel stack overflows
+/// can still be handled.
+pub const DOUBLE_FAULT_IST: u8 = 1;
+
+/// The number of pages in each core's double fault stack.
+///
+/// The stack resides at the bottom of the kernel stack segment,
+/// above an unmapped guard page.
+pub const DOUBLE_FAULT_STACK_PAGES: usize = 4;
+
 /// Architecture-specific core-local state.
 pub(crate) struct CoreState {
 	/// The LAPIC (Local Advanced Programmable Interrupt Controller)
 	/// for the core.
 	pub lapic: lapic::Lapic,
+	/// The CPU features supported by the core.
+	pub features: cpuid::Features,
 	/// The core's local GDT
 	///
 	/// Only valid after the Kernel has been initialized
@@ -191,6 +294,12 @@ pub(crate) struct CoreState {
 	pub kernel_stack: UnsafeCell<u64>,
 	/// The IRQ head of the kernel stack (with GP registers)
 	pub kernel_irq_stack: UnsafeCell<u64>,
+	/// The generation of the last TLB shootdown request serviced by the core.
+	pub tlb_generation: UnsafeCell<u64>,
+	/// The core's PCID assignments.
+	pub pcid: UnsafeCell<pcid::PcidCache>,
+	/// Scratch space for the syscall entry stub.
+	pub syscall_scratch: UnsafeCell<syscall::SyscallScratch>,
 }
 
 // XXX(qix-): This is temporary. The core state is not currently used
@@ -206,6 +315,9 @@ pub(crate) struct ThreadState {
 	pub irq_stack_ptr: u64,
 	/// The thread's entry point.
 	pub entry_point:   u64,
+	/// The thread's FPU save area, allocated upon its first
+	/// use of the FPU (see [`crate::fpu`]).
+	pub fpu:           Option<fpu::FpuArea>,
 }
 
 impl ThreadState {
@@ -218,6 +330,7 @@ impl ThreadState {
 		Self {
 			irq_stack_ptr: stack_high_guard as u64,
 			entry_point:   entry,
+			fpu:           None,
 		}
 	}
 }
@@ -2,8 +2,10 @@
 //!
 //! This code describes the overall address space layout used by the kernel and userspace processes.
 
+use core::ops::Range;
+
 use oro_mem::{
-	mapper::AddressSpace,
+	mapper::{AddressSpace, PageFlags},
 	pfa::Alloc,
 	phys::{Phys, PhysAddr},
 };
@@ -11,7 +13,10 @@ use oro_mem::{
 use super::{paging::PageTable, paging_level::PagingLevel, segment::MapperHandle};
 use crate::{
 	asm::cr3,
-	mem::{paging::PageTableEntry, segment::AddressSegment},
+	mem::{
+		paging::{PageTableEntry, PageTableWalker, WalkEntry},
+		segment::{AddressSegment, cow_acquire, cow_release},
+	},
 };
 
 /// A handle to an address space for the x86_64 architecture.
@@ -41,6 +46,12 @@ impl MapperHandle for AddressSpaceHandle {
 /// This struct describes not only the page table indices for each
 /// logical kernel / userspace memory segment, but also the flags
 /// used for each segment.
+///
+/// All indices are indices into the root page table, which is the
+/// L4 table under 4-level paging and the L5 table under 5-level paging.
+/// Each index thus spans 512GiB or 256TiB of the address space,
+/// respectively; the virtual address ranges of the segments are computed
+/// for the active paging level (see [`AddressSegment::range_for()`]).
 pub struct AddressSpaceLayout;
 
 // NOTE(qix-): Please keep this sorted.
@@ -52,11 +63,28 @@ impl AddressSpaceLayout {
 	pub const KERNEL_SECONDARY_BOOT_IDX: usize = 0;
 
 	/// The index for the module segments.
+	///
+	/// Shared by the user code, data and read-only data segments.
+	/// Spans `0x0000_0280_0000_0000..=0x0000_087F_FFFF_FFFF` under
+	/// 4-level paging.
 	pub const MODULE_EXE_IDX: (usize, usize) = (5, 16);
 	/// The index for the module thread stack segment.
+	///
+	/// Spans `0x0000_0880_0000_0000..=0x0000_08FF_FFFF_FFFF` under
+	/// 4-level paging.
 	pub const MODULE_THREAD_STACK_IDX: usize = 17;
 	/// The index for the module thread interrupt stack.
 	pub const MODULE_INTERRUPT_STACK_IDX: usize = 18;
+	/// The index for the sysabi segment.
+	///
+	/// Spans `0x0000_0A00_0000_0000..=0x0000_0A7F_FFFF_FFFF` under
+	/// 4-level paging.
+	pub const SYSABI_IDX: usize = 20;
+	/// The index range for the user heap segment.
+	///
+	/// Spans `0x0000_1000_0000_0000..=0x0000_3FFF_FFFF_FFFF` under
+	/// 4-level paging.
+	pub const USER_HEAP_IDX: (usize, usize) = (32, 127);
 
 	/// The recursive index for the page table.
 	pub const RECURSIVE_IDX: usize = 256;
@@ -66,6 +94,10 @@ impl AddressSpaceLayout {
 	pub const LINEAR_MAP_IDX: (usize, usize) = (259, 320);
 	/// The index for the kernel core-local segment.
 	pub const KERNEL_CORE_LOCAL_IDX: usize = 350;
+	/// The index for the kernel MMIO segment.
+	pub const KERNEL_MMIO_IDX: usize = 360;
+	/// The index for the kernel heap segment.
+	pub const KERNEL_HEAP_IDX: usize = 384;
 
 	/// The kernel executable range, shared by the RX, RO, and RW segments.
 	///
@@ -73,6 +105,94 @@ impl AddressSpaceLayout {
 	pub const KERNEL_EXE_IDX: usize = 511;
 }
 
+/// The root index ranges of all segments (and other fixed root entries)
+/// that must not collide with one another, sorted by index.
+///
+/// Segments that deliberately share their indices (e.g. the module
+/// code, data and read-only data segments) are listed once.
+const DISJOINT_IDX: &[(usize, usize)] = &[
+	(
+		AddressSpaceLayout::KERNEL_SECONDARY_BOOT_IDX,
+		AddressSpaceLayout::KERNEL_SECONDARY_BOOT_IDX,
+	),
+	AddressSpaceLayout::MODULE_EXE_IDX,
+	(
+		AddressSpaceLayout::MODULE_THREAD_STACK_IDX,
+		AddressSpaceLayout::MODULE_THREAD_STACK_IDX,
+	),
+	(
+		AddressSpaceLayout::MODULE_INTERRUPT_STACK_IDX,
+		AddressSpaceLayout::MODULE_INTERRUPT_STACK_IDX,
+	),
+	(
+		AddressSpaceLayout::SYSABI_IDX,
+		AddressSpaceLayout::SYSABI_IDX,
+	),
+	AddressSpaceLayout::USER_HEAP_IDX,
+	(
+		AddressSpaceLayout::RECURSIVE_IDX,
+		AddressSpaceLayout::RECURSIVE_IDX,
+	),
+	(
+		AddressSpaceLayout::KERNEL_STACK_IDX,
+		AddressSpaceLayout::KERNEL_STACK_IDX,
+	),
+	AddressSpaceLayout::LINEAR_MAP_IDX,
+	(
+		AddressSpaceLayout::KERNEL_CORE_LOCAL_IDX,
+		AddressSpaceLayout::KERNEL_CORE_LOCAL_IDX,
+	),
+	(
+		AddressSpaceLayout::KERNEL_MMIO_IDX,
+		AddressSpaceLayout::KERNEL_MMIO_IDX,
+	),
+	(
+		AddressSpaceLayout::KERNEL_HEAP_IDX,
+		AddressSpaceLayout::KERNEL_HEAP_IDX,
+	),
+	(
+		AddressSpaceLayout::KERNEL_EXE_IDX,
+		AddressSpaceLayout::KERNEL_EXE_IDX,
+	),
+];
+
+const _: () = {
+	assert_disjoint(PagingLevel::Level4);
+	assert_disjoint(PagingLevel::Level5);
+};
+
+/// Asserts (at compile time) that the virtual address ranges of the
+/// [`DISJOINT_IDX`] entries don't collide under the given paging level,
+/// and that none of them straddle the lower and upper halves.
+const fn assert_disjoint(level: PagingLevel) {
+	let mut i = 0;
+	while i < DISJOINT_IDX.len() {
+		let (lo, hi) = DISJOINT_IDX[i];
+		assert!(lo <= hi && hi < 512, "segment index range is invalid");
+		assert!(
+			hi < 256 || lo >= 256,
+			"segment index range straddles the lower and upper halves"
+		);
+
+		if i > 0 {
+			let (_, prev_end) = AddressSegment::range_of(DISJOINT_IDX[i - 1], level);
+			let (start, _) = AddressSegment::range_of(DISJOINT_IDX[i], level);
+			assert!(prev_end < start, "segment ranges collide");
+		}
+
+		i += 1;
+	}
+}
+
+/// Intermediate page table entry template for the kernel MMIO segments.
+///
+/// Defined here so that the overlapping MMIO segments (one per memory type)
+/// can share the same intermediate entry.
+const KERNEL_MMIO_INTERMEDIATE_ENTRY: PageTableEntry = PageTableEntry::new()
+	.with_present()
+	.with_no_exec()
+	.with_writable();
+
 /// Intermediate page table entry template for the module code/data segments.
 ///
 /// Defined here so that the overlapping module segments can share the same
@@ -120,6 +240,61 @@ impl AddressSpaceLayout {
 		&DESCRIPTOR
 	}
 
+	/// Returns the kernel MMIO segment, mapping pages as uncacheable (`UC`).
+	///
+	/// The segment is shared between all cores. Prefer
+	/// [`crate::mem::mmio::map_mmio()`] over mapping into it directly.
+	#[must_use]
+	pub fn mmio() -> &'static AddressSegment {
+		#[expect(clippy::missing_docs_in_private_items)]
+		const DESCRIPTOR: AddressSegment = AddressSegment {
+			valid_range: (
+				AddressSpaceLayout::KERNEL_MMIO_IDX,
+				AddressSpaceLayout::KERNEL_MMIO_IDX,
+			),
+			// NOTE(qix-): `PCD` and `PWT` select PAT index 3 (see `crate::pat`).
+			entry_template: PageTableEntry::new()
+				.with_global()
+				.with_present()
+				.with_no_exec()
+				.with_writable()
+				.with_write_through()
+				.with_cache_disable(),
+			intermediate_entry_template: KERNEL_MMIO_INTERMEDIATE_ENTRY,
+		};
+
+		&DESCRIPTOR
+	}
+
+	/// Returns the kernel MMIO segment, mapping pages as write-combining (`WC`).
+	///
+	/// Overlaps [`Self::mmio()`]. Only valid if the PAT is supported
+	/// (see [`crate::pat`]). Huge pages must not be mapped into it, as
+	/// the `PAT` flag occupies a different bit in huge page entries.
+	#[must_use]
+	pub fn mmio_write_combining() -> &'static AddressSegment {
+		#[expect(clippy::missing_docs_in_private_items)]
+		const DESCRIPTOR: AddressSegment = AddressSegment {
+			valid_range: (
+				AddressSpaceLayout::KERNEL_MMIO_IDX,
+				AddressSpaceLayout::KERNEL_MMIO_IDX,
+			),
+			// SAFETY(qix-): The entry template is only ever used for L1 entries.
+			// NOTE(qix-): `PAT` selects PAT index 4 (see `crate::pat`).
+			entry_template: unsafe {
+				PageTableEntry::new()
+					.with_global()
+					.with_present()
+					.with_no_exec()
+					.with_writable()
+					.with_pat()
+			},
+			intermediate_entry_template: KERNEL_MMIO_INTERMEDIATE_ENTRY,
+		};
+
+		&DESCRIPTOR
+	}
+
 	/// Shallow-duplicates the current address space into a new one
 	/// at the given physical address.
 	pub fn copy_shallow_into(handle: &AddressSpaceHandle, into_phys: u64) {
@@ -188,6 +363,7 @@ impl AddressSpaceLayout {
 		const DESCRIPTOR: AddressSegment = AddressSegment {
 			valid_range: AddressSpaceLayout::MODULE_EXE_IDX,
 			entry_template: PageTableEntry::new()
+				.with_user()
 				.with_present()
 				.with_no_exec()
 				.with_writable(),
@@ -203,7 +379,10 @@ impl AddressSpaceLayout {
 		#[expect(clippy::missing_docs_in_private_items)]
 		const DESCRIPTOR: AddressSegment = AddressSegment {
 			valid_range: AddressSpaceLayout::MODULE_EXE_IDX,
-			entry_template: PageTableEntry::new().with_present().with_no_exec(),
+			entry_template: PageTableEntry::new()
+				.with_user()
+				.with_present()
+				.with_no_exec(),
 			intermediate_entry_template: MODULE_EXE_INTERMEDIATE_ENTRY,
 		};
 
@@ -256,7 +435,8 @@ unsafe impl AddressSpace for AddressSpaceLayout {
 
 	unsafe fn current_supervisor_space() -> Self::SupervisorHandle {
 		Self::SupervisorHandle {
-			base_phys:    cr3(),
+			// NOTE(qix-): The low 12 bits hold the PCID, if enabled (see `crate::pcid`).
+			base_phys:    cr3() & !0xFFF,
 			paging_level: PagingLevel::current_from_cpu(),
 		}
 	}
@@ -349,6 +529,173 @@ unsafe impl AddressSpace for AddressSpaceLayout {
 		Self::duplicate_supervisor_space_shallow_in(space, alloc)
 	}
 
+	/// Deeply clones the lower half of the address space. Supervisor-only
+	/// leaf pages and read-only leaf pages are copied eagerly, whereas writable
+	/// user pages are shared copy-on-write (both the original and the new
+	/// mapping are marked as such). Huge page mappings in the lower half are
+	/// not supported and cause the duplication to fail.
+	fn duplicate_user_space_deep_in<A>(
+		space: &Self::UserHandle,
+		alloc: &mut A,
+	) -> Option<Self::UserHandle>
+	where
+		A: Alloc,
+	{
+		let levels = space.paging_level.as_usize();
+
+		// SAFETY(qix-): The handle is trusted to point to a valid page table hierarchy.
+		let base_phys = unsafe { clone_table_deep(space.base_phys, levels, 0, 0..256, alloc)? };
+
+		// SAFETY(qix-): Both tables are valid; the upper half is shared by reference.
+		unsafe {
+			let src = Phys::from_address_unchecked(space.base_phys).as_ref_unchecked::<PageTable>();
+			let dst = Phys::from_address_unchecked(base_phys).as_mut_unchecked::<PageTable>();
+			for idx in 256..512 {
+				dst[idx] = src[idx];
+			}
+		}
+
+		Some(Self::UserHandle {
+			base_phys,
+			paging_level: space.paging_level,
+		})
+	}
+
+	fn free_user_space_handle_in<A>(space: Self::UserHandle, alloc: &mut A)
+	where
+		A: Alloc,
+	{
+		// NOTE(qix-): The root table may be reused for another address space, which
+		// NOTE(qix-): would otherwise inherit its PCID assignments (and stale entries).
+		// NOTE(qix-): This must happen before it's freed, lest it be reused first.
+		crate::pcid::invalidate_all();
+
+		// SAFETY(qix-): The handle is consumed, and its root table is no longer in use.
+		unsafe {
+			alloc.free(space.base_phys);
+		}
+	}
+
+	/// Frees the lower half of the address space, along with the root table.
+	/// The upper half is shared with the kernel and left untouched. As with
+	/// [`Self::duplicate_user_space_deep_in`], huge page mappings in the lower
+	/// half are not supported.
+	fn free_user_space_deep_in<A>(space: Self::UserHandle, alloc: &mut A)
+	where
+		A: Alloc,
+	{
+		let levels = space.paging_level.as_usize();
+
+		// SAFETY(qix-): The handle is consumed, and deep frees reclaim everything
+		// SAFETY(qix-): it maps in the lower half.
+		unsafe {
+			let root =
+				Phys::from_address_unchecked(space.base_phys).as_ref_unchecked::<PageTable>();
+			for entry in root.iter().take(256) {
+				if entry.present() {
+					free_table_deep(entry.address(), levels - 1, alloc);
+				}
+			}
+		}
+
+		Self::free_user_space_handle_in(space, alloc);
+	}
+
+	fn translate(space: &Self::UserHandle, virt: usize) -> Option<(u64, PageFlags)> {
+		let mut flags = PageFlags {
+			writable:   true,
+			executable: true,
+			user:       true,
+		};
+
+		let mut phys = None;
+
+		// SAFETY(qix-): The handle is trusted to point to a valid page table hierarchy.
+		unsafe { PageTableWalker::at(space.base_phys, space.paging_level, virt) }.visit_all_mut(
+			|walked| {
+				let entry = match &walked {
+					WalkEntry::Table(entry, _) => entry,
+					WalkEntry::Leaf(_, entry, _) => &**entry,
+				};
+
+				flags.writable &= entry.writable();
+				flags.executable &= !entry.no_exec();
+				flags.user &= entry.user();
+
+				if let WalkEntry::Leaf(_, entry, level) = walked {
+					let page_mask = (1_u64 << (12 + 9 * (level - 1))) - 1;
+					// NOTE(qix-): For huge pages, bit 12 is the PAT bit, not part of the address.
+					phys = Some((entry.address() & !page_mask) | (virt as u64 & page_mask));
+				}
+			},
+		);
+
+		phys.map(|phys| (phys, flags))
+	}
+
+	unsafe fn resolve_write_fault(space: &Self::UserHandle, virt: usize) -> bool {
+		// NOTE(qix-): The user data segment overlaps the user code and read-only
+		// NOTE(qix-): data segments, so checking it covers all three.
+		[
+			Self::user_data(),
+			Self::user_heap(),
+			Self::user_thread_stack(),
+		]
+		.into_iter()
+		.any(|segment| segment.resolve_cow_fault(space, virt))
+	}
+
+	fn sysabi() -> Self::UserSegment {
+		#[expect(clippy::missing_docs_in_private_items)]
+		const DESCRIPTOR: AddressSegment = AddressSegment {
+			valid_range: (
+				AddressSpaceLayout::SYSABI_IDX,
+				AddressSpaceLayout::SYSABI_IDX,
+			),
+			entry_template: PageTableEntry::new()
+				.with_user()
+				.with_present()
+				.with_no_exec(),
+			intermediate_entry_template: PageTableEntry::new()
+				.with_user()
+				.with_present()
+				.with_no_exec(),
+		};
+
+		&DESCRIPTOR
+	}
+
+	fn user_code() -> Self::UserSegment {
+		Self::module_code()
+	}
+
+	fn user_data() -> Self::UserSegment {
+		Self::module_data()
+	}
+
+	fn user_rodata() -> Self::UserSegment {
+		Self::module_rodata()
+	}
+
+	fn user_heap() -> Self::UserSegment {
+		#[expect(clippy::missing_docs_in_private_items)]
+		const DESCRIPTOR: AddressSegment = AddressSegment {
+			valid_range: AddressSpaceLayout::USER_HEAP_IDX,
+			entry_template: PageTableEntry::new()
+				.with_user()
+				.with_writable()
+				.with_no_exec()
+				.with_present(),
+			intermediate_entry_template: PageTableEntry::new()
+				.with_user()
+				.with_present()
+				.with_writable()
+				.with_no_exec(),
+		};
+
+		&DESCRIPTOR
+	}
+
 	fn user_thread_stack() -> Self::UserSegment {
 		#[expect(clippy::missing_docs_in_private_items)]
 		const DESCRIPTOR: AddressSegment = AddressSegment {
@@ -459,4 +806,127 @@ unsafe impl AddressSpace for AddressSpaceLayout {
 
 		&DESCRIPTOR
 	}
+
+	fn kernel_heap() -> Self::SupervisorSegment {
+		#[expect(clippy::missing_docs_in_private_items)]
+		const DESCRIPTOR: AddressSegment = AddressSegment {
+			valid_range: (
+				AddressSpaceLayout::KERNEL_HEAP_IDX,
+				AddressSpaceLayout::KERNEL_HEAP_IDX,
+			),
+			entry_template: PageTableEntry::new()
+				.with_global()
+				.with_present()
+				.with_no_exec()
+				.with_writable(),
+			intermediate_entry_template: PageTableEntry::new()
+				.with_present()
+				.with_no_exec()
+				.with_writable(),
+		};
+
+		&DESCRIPTOR
+	}
+}
+
+/// Deeply clones the page table at the given physical address and level
+/// (`1` being a leaf table), visiting only the given range of indices, and
+/// returns the physical address of the new table.
+///
+/// `virt` is the virtual address mapped by the table's first entry.
+///
+/// Returns `None` if any allocation fails or a huge page is encountered, in
+/// which case everything allocated by this call has been freed.
+///
+/// # Safety
+/// `src_phys` must point to a valid page table hierarchy covering only the
+/// lower half of an address space.
+unsafe fn clone_table_deep<A: Alloc>(
+	src_phys: u64,
+	level: usize,
+	virt: usize,
+	range: Range<usize>,
+	alloc: &mut A,
+) -> Option<u64> {
+	let dst_phys = alloc.allocate()?;
+	let dst = Phys::from_address_unchecked(dst_phys).as_mut_unchecked::<PageTable>();
+	dst.reset();
+
+	let src = Phys::from_address_unchecked(src_phys).as_mut_unchecked::<PageTable>();
+
+	for idx in range {
+		let entry = &mut src[idx];
+		if !entry.present() {
+			continue;
+		}
+
+		let entry_virt = virt | (idx << (12 + 9 * (level - 1)));
+
+		let new_entry = if level == 1 {
+			if entry.user() && (entry.writable() || entry.cow()) {
+				if !entry.cow() {
+					// The original mapping holds its own reference.
+					cow_acquire(entry.address());
+					entry.clear_writable();
+					entry.set_cow();
+					// TODO(qix-): Batch these; one shootdown per page is slow.
+					crate::tlb::flush_global(entry_virt);
+				}
+
+				cow_acquire(entry.address());
+				Some(*entry)
+			} else {
+				alloc.allocate().map(|frame| {
+					Phys::from_address_unchecked(frame)
+						.as_mut_ptr_unchecked::<u8>()
+						.copy_from_nonoverlapping(
+							Phys::from_address_unchecked(entry.address()).as_ptr_unchecked(),
+							4096,
+						);
+					entry.with_address(frame)
+				})
+			}
+		} else if level <= 3 && entry.huge() {
+			None
+		} else {
+			clone_table_deep(entry.address(), level - 1, entry_virt, 0..512, alloc)
+				.map(|table| entry.with_address(table))
+		};
+
+		let Some(new_entry) = new_entry else {
+			free_table_deep(dst_phys, level, alloc);
+			return None;
+		};
+
+		dst[idx] = new_entry;
+	}
+
+	Some(dst_phys)
+}
+
+/// Frees a page table hierarchy created by [`clone_table_deep`], including
+/// all eagerly copied leaf frames, and releases all copy-on-write references
+/// it holds (freeing any frame for which it held the last one).
+///
+/// # Safety
+/// The table must have been created by [`clone_table_deep`] and must not
+/// be in use.
+unsafe fn free_table_deep<A: Alloc>(table_phys: u64, level: usize, alloc: &mut A) {
+	let table = Phys::from_address_unchecked(table_phys).as_ref_unchecked::<PageTable>();
+
+	for entry in table.iter() {
+		if !entry.present() {
+			continue;
+		}
+
+		if level == 1 {
+			if !entry.cow() || cow_release(entry.address()) {
+				alloc.free(entry.address());
+			}
+		} else {
+			free_table_deep(entry.address(), level - 1, alloc);
+		}
+	}
+
+	alloc.free(table_phys);
 }
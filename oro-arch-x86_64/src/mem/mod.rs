@@ -1,6 +1,7 @@
 //! Memory management structures and implementations for the x86_64 architecture.
 
 pub mod address_space;
+pub mod mmio;
 pub mod paging;
 pub mod paging_level;
 pub mod segment;
@@ -5,10 +5,16 @@
 
 use core::{
 	fmt,
-	ops::{Index, IndexMut},
+	ops::{Index, IndexMut, Range},
 };
 
 use oro_macro::assert;
+use oro_mem::phys::{Phys, PhysAddr};
+
+use crate::mem::{paging_level::PagingLevel, segment::sign_extend};
+
+/// The available (software) bit index used to mark copy-on-write entries.
+const COW_AVAILABLE_BIT: usize = 0;
 
 /// A page table for the x86_64 architecture.
 #[derive(Debug, Clone)]
@@ -388,6 +394,32 @@ impl PageTableEntry {
 		)
 	}
 
+	/// Checks if the page table entry is marked as copy-on-write.
+	///
+	/// Copy-on-write entries are mapped read-only, and are resolved
+	/// upon the first write fault (see [`crate::mem::segment::AddressSegment::resolve_cow_fault`]).
+	#[inline]
+	#[must_use]
+	pub fn cow(mut self) -> bool {
+		self.available().get(COW_AVAILABLE_BIT)
+	}
+
+	/// Marks the page table entry as copy-on-write.
+	///
+	/// Does not modify the writable flag.
+	#[inline]
+	pub fn set_cow(&mut self) {
+		self.available().set(COW_AVAILABLE_BIT);
+	}
+
+	/// Clears the copy-on-write marker of the page table entry.
+	///
+	/// Does not modify the writable flag.
+	#[inline]
+	pub fn clear_cow(&mut self) {
+		self.available().clear(COW_AVAILABLE_BIT);
+	}
+
 	/// Checks if the page is a huge page.
 	///
 	/// # Safety
@@ -426,6 +458,47 @@ impl PageTableEntry {
 		Self(self.0 | (1 << 7))
 	}
 
+	/// Checks if the page table entry has the PAT flag set.
+	///
+	/// Together with the write-through and cache-disable flags, selects
+	/// the page's memory type from the PAT (see [`crate::pat`]).
+	///
+	/// # Safety
+	/// Must only be called on a PT (L1) entry.
+	#[inline]
+	#[must_use]
+	pub unsafe fn pat(self) -> bool {
+		(self.0 & (1 << 7)) != 0
+	}
+
+	/// Sets the PAT flag of the page table entry.
+	///
+	/// # Safety
+	/// Must only be called on a PT (L1) entry.
+	#[inline]
+	pub unsafe fn set_pat(&mut self) {
+		self.0 |= 1 << 7;
+	}
+
+	/// Clears the PAT flag of the page table entry.
+	///
+	/// # Safety
+	/// Must only be called on a PT (L1) entry.
+	#[inline]
+	pub unsafe fn clear_pat(&mut self) {
+		self.0 &= !(1 << 7);
+	}
+
+	/// Replaces the PAT flag, returning a new `PageTableEntry`.
+	///
+	/// # Safety
+	/// Must only be called on a PT (L1) entry.
+	#[inline]
+	#[must_use]
+	pub const unsafe fn with_pat(self) -> Self {
+		Self(self.0 | (1 << 7))
+	}
+
 	/// Sets the physical address of the page table entry.
 	#[inline]
 	pub fn set_address(&mut self, address: u64) {
@@ -547,3 +620,285 @@ impl AvailableFields<'_> {
 		((*self.0) & (1 << bit)) != 0
 	}
 }
+
+/// Walks a page table hierarchy, yielding every present leaf mapping.
+///
+/// Each item is a `(virt, entry, level)` tuple, where `virt` is the
+/// (sign-extended) virtual address of the mapping, `entry` is the leaf
+/// page table entry, and `level` is the level of the table holding it
+/// (`1` for a 4KiB page, `2` for a 2MiB page, `3` for a 1GiB page).
+///
+/// Descent stops at huge page entries (those with the `PS` bit set at
+/// levels 2 and 3). The root table is at level 4 or 5, depending on the
+/// [`PagingLevel`] the walker was created with.
+///
+/// Walks may cover the entire hierarchy, a range of root table indices,
+/// or a single page (see [`PageTableWalker::at()`]); only the tables along
+/// the way are visited.
+///
+/// Use [`PageTableWalker::visit_mut()`] to modify the leaf entries
+/// in-place; callers are responsible for any TLB invalidation.
+pub struct PageTableWalker {
+	/// The walk state for each level, indexed by `level - 1`.
+	stack:        [WalkFrame; 5],
+	/// The level currently being walked, or `0` if the walk is complete.
+	level:        usize,
+	/// The first (non-sign-extended) virtual address to walk.
+	first:        usize,
+	/// The last (non-sign-extended) virtual address to walk, inclusive.
+	last:         usize,
+	/// The paging level of the hierarchy.
+	paging_level: PagingLevel,
+}
+
+/// The walk state of a single page table in a [`PageTableWalker`].
+#[derive(Debug, Clone, Copy, Default)]
+struct WalkFrame {
+	/// The physical address of the page table.
+	table: u64,
+	/// The next index to visit.
+	index: usize,
+	/// The exclusive upper bound of the indices to visit.
+	end:   usize,
+	/// The (non-sign-extended) virtual address mapped by the table's first entry.
+	virt:  usize,
+}
+
+/// An entry visited by [`PageTableWalker::visit_all_mut()`].
+pub enum WalkEntry<'a> {
+	/// A present intermediate entry, visited just before the walk descends
+	/// into the table it references. Holds the entry and the level of the
+	/// table holding it.
+	Table(PageTableEntry, usize),
+	/// A present leaf mapping, with the same fields as the walker's items.
+	Leaf(usize, &'a mut PageTableEntry, usize),
+}
+
+/// A raw step of a [`PageTableWalker`] walk.
+enum WalkStep {
+	/// See [`WalkEntry::Table`].
+	Table(PageTableEntry, usize),
+	/// See [`WalkEntry::Leaf`].
+	Leaf(usize, *mut PageTableEntry, usize),
+}
+
+impl PageTableWalker {
+	/// Creates a new walker over the entire page table hierarchy rooted at
+	/// the given physical address.
+	///
+	/// # Safety
+	/// `root` must be the physical address of a valid root page table for the
+	/// given paging level, accessible via the global physical address translator.
+	/// The hierarchy's intermediate tables must not be modified (other than via
+	/// [`PageTableWalker::visit_mut()`]) for the duration of the walk.
+	#[must_use]
+	pub unsafe fn new(root: u64, paging_level: PagingLevel) -> Self {
+		Self::with_root_range(root, paging_level, 0..512)
+	}
+
+	/// Creates a new walker over the page table hierarchy rooted at the given
+	/// physical address, visiting only the given range of root table indices
+	/// (e.g. `0..256` for the lower half).
+	///
+	/// # Safety
+	/// The same requirements as [`PageTableWalker::new()`] apply.
+	#[must_use]
+	pub unsafe fn with_root_range(
+		root: u64,
+		paging_level: PagingLevel,
+		range: Range<usize>,
+	) -> Self {
+		debug_assert!(range.end <= 512, "root range out of bounds: {range:?}");
+
+		if range.is_empty() {
+			return Self {
+				stack: [WalkFrame::default(); 5],
+				level: 0,
+				first: 0,
+				last: 0,
+				paging_level,
+			};
+		}
+
+		let shift = paging_level.root_shift();
+		Self::with_bounds(
+			root,
+			paging_level,
+			range.start << shift,
+			((range.end << shift) - 1) | ((1 << shift) - 1),
+		)
+	}
+
+	/// Creates a new walker over only the page containing the given virtual
+	/// address, yielding at most one leaf mapping (which may be a huge page
+	/// covering the address).
+	///
+	/// # Safety
+	/// The same requirements as [`PageTableWalker::new()`] apply.
+	#[must_use]
+	pub unsafe fn at(root: u64, paging_level: PagingLevel, virt: usize) -> Self {
+		let mask = (1 << (12 + 9 * paging_level.as_usize())) - 1;
+		let virt = virt & mask;
+		Self::with_bounds(root, paging_level, virt & !0xFFF, virt | 0xFFF)
+	}
+
+	/// Returns the present leaf entry mapping the given virtual address, along
+	/// with the level of the table holding it.
+	///
+	/// # Safety
+	/// The same requirements as [`PageTableWalker::new()`] apply, for as long
+	/// as the returned reference is held. The caller must ensure that no other
+	/// references to the entry exist.
+	#[must_use]
+	pub unsafe fn lookup(
+		root: u64,
+		paging_level: PagingLevel,
+		virt: usize,
+	) -> Option<(&'static mut PageTableEntry, usize)> {
+		Self::at(root, paging_level, virt)
+			.next_leaf()
+			.map(|(_, entry, level)| (&mut *entry, level))
+	}
+
+	/// Creates a new walker over the given inclusive range of (non-sign-extended)
+	/// virtual addresses.
+	///
+	/// # Safety
+	/// The same requirements as [`PageTableWalker::new()`] apply.
+	unsafe fn with_bounds(root: u64, paging_level: PagingLevel, first: usize, last: usize) -> Self {
+		debug_assert!(first <= last);
+
+		let level = paging_level.as_usize();
+		let shift = paging_level.root_shift();
+		let mut stack = [WalkFrame::default(); 5];
+		stack[level - 1] = WalkFrame {
+			table: root,
+			index: first >> shift,
+			end:   (last >> shift) + 1,
+			virt:  0,
+		};
+
+		Self {
+			stack,
+			level,
+			first,
+			last,
+			paging_level,
+		}
+	}
+
+	/// Calls `f` with each remaining leaf mapping, allowing the entry to be
+	/// modified in-place.
+	///
+	/// The arguments are the same as the iterator's items. Modifying an entry
+	/// does not affect which entries are visited afterward.
+	///
+	/// Callers are responsible for invalidating the TLB for any modified entries.
+	pub fn visit_mut<F: FnMut(usize, &mut PageTableEntry, usize)>(mut self, mut f: F) {
+		while let Some((virt, entry, level)) = self.next_leaf() {
+			// SAFETY(qix-): The walker's safety requirements guarantee the entry is valid,
+			// SAFETY(qix-): and we hold no other references to it.
+			f(virt, unsafe { &mut *entry }, level);
+		}
+	}
+
+	/// Calls `f` with each remaining intermediate entry and leaf mapping, in
+	/// walk order, allowing the leaf entries to be modified in-place.
+	///
+	/// The root table itself is not reported.
+	///
+	/// Callers are responsible for invalidating the TLB for any modified entries.
+	pub fn visit_all_mut<F: FnMut(WalkEntry<'_>)>(mut self, mut f: F) {
+		while let Some(step) = self.next_step() {
+			f(match step {
+				WalkStep::Table(entry, level) => WalkEntry::Table(entry, level),
+				// SAFETY(qix-): The walker's safety requirements guarantee the entry is valid,
+				// SAFETY(qix-): and we hold no other references to it.
+				WalkStep::Leaf(virt, entry, level) => {
+					WalkEntry::Leaf(virt, unsafe { &mut *entry }, level)
+				}
+			});
+		}
+	}
+
+	/// Advances the walk to the next present leaf mapping, returning its
+	/// virtual address, a pointer to its entry, and its level.
+	fn next_leaf(&mut self) -> Option<(usize, *mut PageTableEntry, usize)> {
+		loop {
+			if let WalkStep::Leaf(virt, entry, level) = self.next_step()? {
+				return Some((virt, entry, level));
+			}
+		}
+	}
+
+	/// Advances the walk to the next present entry, descending into
+	/// intermediate tables after they've been returned.
+	fn next_step(&mut self) -> Option<WalkStep> {
+		let root_level = self.paging_level.as_usize();
+
+		loop {
+			if self.level == 0 {
+				return None;
+			}
+
+			let level = self.level;
+			let frame = &mut self.stack[level - 1];
+
+			if frame.index >= frame.end {
+				if level == root_level {
+					self.level = 0;
+				} else {
+					self.level += 1;
+				}
+				continue;
+			}
+
+			let idx = frame.index;
+			frame.index += 1;
+
+			// SAFETY(qix-): The walker's safety requirements guarantee the table is valid.
+			let entry = unsafe {
+				&mut Phys::from_address_unchecked(frame.table).as_mut_unchecked::<PageTable>()[idx]
+			};
+
+			if !entry.present() {
+				continue;
+			}
+
+			let shift = 12 + 9 * (level - 1);
+			let virt = frame.virt | (idx << shift);
+
+			// SAFETY(qix-): We only check the huge bit for PDPT and PD entries.
+			if level == 1 || (level <= 3 && unsafe { entry.huge() }) {
+				let virt = match self.paging_level {
+					PagingLevel::Level4 => sign_extend!(L4, virt),
+					PagingLevel::Level5 => sign_extend!(L5, virt),
+				};
+
+				return Some(WalkStep::Leaf(virt, core::ptr::from_mut(entry), level));
+			}
+
+			// Only visit the child entries that overlap the walked range.
+			let child_shift = shift - 9;
+			let child_last = virt | ((1 << shift) - 1);
+			self.level -= 1;
+			self.stack[level - 2] = WalkFrame {
+				table: entry.address(),
+				index: self.first.saturating_sub(virt) >> child_shift,
+				end: ((self.last.min(child_last) - virt) >> child_shift) + 1,
+				virt,
+			};
+
+			return Some(WalkStep::Table(*entry, level));
+		}
+	}
+}
+impl Iterator for PageTableWalker {
+	type Item = (usize, PageTableEntry, usize);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.next_leaf()
+			// SAFETY(qix-): The walker's safety requirements guarantee the entry is valid.
+			.map(|(virt, entry, level)| (virt, unsafe { *entry }, level))
+	}
+}
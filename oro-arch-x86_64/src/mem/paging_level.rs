@@ -1,8 +1,19 @@
 //! The Oro kernel supports x86_64's 4-level and 5-level paging modes,
 //! which are determined by the CPU flags and conveyed to certain algorithms
 //! with the [`PagingLevel`] enum.
+//!
+//! The paging level is chosen by the bootloader; `CR4.LA57` cannot be
+//! changed while in long mode. The primary core latches whichever level
+//! it was booted with (see [`PagingLevel::latch()`]), and all secondary
+//! cores must then run with that same level.
 #![expect(clippy::inline_always)]
 
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// The paging level latched by the primary core, or `0`
+/// if it hasn't been latched yet.
+static LATCHED: AtomicUsize = AtomicUsize::new(0);
+
 /// The number of levels in the page table hierarchy,
 /// as determined by the CPU flags.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -22,6 +33,48 @@ impl PagingLevel {
 		self as usize
 	}
 
+	/// Returns the shift of the virtual address bits that
+	/// index into the root page table.
+	#[inline(always)]
+	#[must_use]
+	pub const fn root_shift(self) -> usize {
+		match self {
+			Self::Level4 => 39,
+			Self::Level5 => 48,
+		}
+	}
+
+	/// Returns the index into the root page table for
+	/// the given virtual address.
+	#[inline(always)]
+	#[must_use]
+	pub const fn root_index(self, virt: usize) -> usize {
+		(virt >> self.root_shift()) & 0x1FF
+	}
+
+	/// Latches the current core's paging level as that of the
+	/// entire system, returning it.
+	///
+	/// Must be called exactly once, by the primary core during boot,
+	/// before any secondary cores are booted.
+	#[cold]
+	pub fn latch() -> Self {
+		let level = Self::current_from_cpu();
+		LATCHED.store(level as usize, Ordering::Release);
+		level
+	}
+
+	/// Returns the paging level latched by the primary core,
+	/// or `None` if it hasn't been latched yet.
+	#[must_use]
+	pub fn latched() -> Option<Self> {
+		match LATCHED.load(Ordering::Acquire) {
+			4 => Some(Self::Level4),
+			5 => Some(Self::Level5),
+			_ => None,
+		}
+	}
+
 	/// Returns the current paging level based on CPU register flags.
 	#[inline]
 	#[cold]
@@ -2,15 +2,22 @@
 //! requests that a physical address be mapped into a specific range of virtual
 //! addresses.
 
+use oro_kernel::oom;
 use oro_macro::unlikely;
 use oro_mem::{
+	alloc::collections::BTreeMap,
+	global_alloc::GlobalPfa,
 	mapper::{AddressSegment as Segment, MapError, UnmapError},
 	pfa::Alloc,
 	phys::{Phys, PhysAddr},
 };
+use oro_sync::{Lock, TicketMutex};
 
 use super::{address_space::AddressSpaceHandle, paging::PageTable};
-use crate::mem::{paging::PageTableEntry, paging_level::PagingLevel};
+use crate::mem::{
+	paging::{PageTableEntry, PageTableWalker},
+	paging_level::PagingLevel,
+};
 
 /// Sign-extends a value to the appropriate size for the current paging level.
 macro_rules! sign_extend {
@@ -32,6 +39,79 @@ macro_rules! sign_extend {
 
 pub(crate) use sign_extend;
 
+/// Reference counts of frames shared via copy-on-write mappings, keyed by
+/// physical address.
+///
+/// Frames with a single copy-on-write mapping are still tracked, such that
+/// the last writer can reuse the frame rather than copying it.
+///
+/// # Lock Ordering
+/// The lock also serializes every transition of a copy-on-write page table
+/// entry (creation, resolution and removal), such that looking up an entry's
+/// frame and adjusting its reference count happen atomically with respect
+/// to other cores. Entries must be re-read after the lock is taken.
+///
+/// TLB shootdowns must not be issued while holding the lock, as other cores
+/// may be spinning on it (e.g. when resolving a write fault) with interrupts
+/// disabled.
+static COW_REFS: TicketMutex<BTreeMap<u64, usize>> = TicketMutex::new(BTreeMap::new());
+
+/// Adds a copy-on-write reference to the given frame.
+pub(crate) fn cow_acquire(phys: u64) {
+	cow_acquire_locked(&mut COW_REFS.lock(), phys);
+}
+
+/// Drops a copy-on-write reference to the given frame, returning
+/// `true` if it was the last reference (or the frame was untracked).
+pub(crate) fn cow_release(phys: u64) -> bool {
+	cow_release_locked(&mut COW_REFS.lock(), phys)
+}
+
+/// Adds a copy-on-write reference to the given frame, with [`COW_REFS`] held.
+fn cow_acquire_locked(refs: &mut BTreeMap<u64, usize>, phys: u64) {
+	*refs.entry(phys).or_insert(0) += 1;
+}
+
+/// Drops a copy-on-write reference to the given frame, with [`COW_REFS`] held.
+///
+/// See [`cow_release()`].
+fn cow_release_locked(refs: &mut BTreeMap<u64, usize>, phys: u64) -> bool {
+	match refs.get_mut(&phys) {
+		Some(count) if *count > 1 => {
+			*count -= 1;
+			false
+		}
+		_ => {
+			refs.remove(&phys);
+			true
+		}
+	}
+}
+
+/// Clears the given present 4KiB leaf entry, releasing its copy-on-write
+/// reference (if any). Returns the previously mapped frame, or `None` if it
+/// is still mapped elsewhere as a copy-on-write page and thus must not be
+/// freed.
+///
+/// The caller is responsible for invalidating the TLB.
+fn clear_leaf(entry: &mut PageTableEntry) -> Option<u64> {
+	if !entry.cow() {
+		let phys = entry.address();
+		entry.reset();
+		return Some(phys);
+	}
+
+	let mut refs = COW_REFS.lock();
+
+	// NOTE(qix-): Another core may have resolved a write fault
+	// NOTE(qix-): on the entry (replacing the frame) in the meantime.
+	let phys = entry.address();
+	let last = !entry.cow() || cow_release_locked(&mut refs, phys);
+	entry.reset();
+
+	last.then_some(phys)
+}
+
 /// A utility trait for extracting information about a mapper handle.
 pub trait MapperHandle {
 	/// Returns the base physical address of the page table.
@@ -41,6 +121,17 @@ pub trait MapperHandle {
 	fn paging_level(&self) -> PagingLevel;
 }
 
+/// The permissions applied to a mapping by [`AddressSegment::protect`].
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Default)]
+pub struct PageProtection {
+	/// Whether the page is writable.
+	pub writable:   bool,
+	/// Whether the page is executable.
+	pub executable: bool,
+	/// Whether the page is accessible from userspace.
+	pub user:       bool,
+}
+
 /// A segment of the address space. This is constructed as a
 /// constant value in the [`super::address_space::AddressSpaceLayout`] struct and returned
 /// as a static reference.
@@ -61,7 +152,71 @@ pub struct AddressSegment {
 	pub intermediate_entry_template: PageTableEntry,
 }
 
+/// The size of a huge page mapping.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HugePageSize {
+	/// A 2MiB page, mapped by a page directory (L2) entry.
+	Size2MiB,
+	/// A 1GiB page, mapped by a page directory pointer table (L3) entry.
+	///
+	/// Requires CPU support (`CPUID.80000001H:EDX.Page1GB`).
+	Size1GiB,
+}
+
+impl HugePageSize {
+	/// Returns the size of the page, in bytes.
+	#[must_use]
+	pub const fn bytes(self) -> usize {
+		match self {
+			Self::Size2MiB => 1 << 21,
+			Self::Size1GiB => 1 << 30,
+		}
+	}
+
+	/// Returns the number of page table levels that are skipped
+	/// by the leaf entry (i.e. `1` for 2MiB pages, `2` for 1GiB pages).
+	const fn depth(self) -> usize {
+		match self {
+			Self::Size2MiB => 1,
+			Self::Size1GiB => 2,
+		}
+	}
+}
+
 impl AddressSegment {
+	/// Returns the (inclusive, sign-extended) range of virtual addresses
+	/// covered by the segment under the given paging level.
+	///
+	/// [`Segment::range()`] returns this for the current paging level.
+	#[must_use]
+	pub const fn range_for(&self, level: PagingLevel) -> (usize, usize) {
+		Self::range_of(self.valid_range, level)
+	}
+
+	/// Returns the (inclusive, sign-extended) range of virtual addresses
+	/// covered by the given (inclusive) range of root indices under the
+	/// given paging level.
+	#[must_use]
+	pub const fn range_of(valid_range: (usize, usize), level: PagingLevel) -> (usize, usize) {
+		let shift = level.root_shift();
+		let span = (1 << shift) - 1;
+
+		match level {
+			PagingLevel::Level4 => {
+				(
+					sign_extend!(L4, valid_range.0 << shift),
+					sign_extend!(L4, (valid_range.1 << shift) | span),
+				)
+			}
+			PagingLevel::Level5 => {
+				(
+					sign_extend!(L5, valid_range.0 << shift),
+					sign_extend!(L5, (valid_range.1 << shift) | span),
+				)
+			}
+		}
+	}
+
 	/// Returns the page table entry for the given virtual address,
 	/// allocating intermediate page tables as necessary.
 	unsafe fn entry<'a, A, Handle: MapperHandle>(
@@ -73,15 +228,31 @@ impl AddressSegment {
 	where
 		A: Alloc,
 	{
-		if unlikely!(virt & 0xFFF != 0) {
+		self.entry_at(space, alloc, virt, 0)
+	}
+
+	/// Returns the page table entry for the given virtual address
+	/// at the given depth (`0` for 4KiB leaf entries, or [`HugePageSize::depth`]
+	/// for huge page entries), allocating intermediate page tables as necessary.
+	///
+	/// Fails with [`MapError::Exists`] if an intermediate entry along the way
+	/// is already mapped as a huge page.
+	unsafe fn entry_at<'a, A, Handle: MapperHandle>(
+		&'a self,
+		space: &'a Handle,
+		alloc: &'a mut A,
+		virt: usize,
+		depth: usize,
+	) -> Result<&'a mut PageTableEntry, MapError>
+	where
+		A: Alloc,
+	{
+		if unlikely!(virt & ((1 << (12 + depth * 9)) - 1) != 0) {
 			return Err(MapError::VirtNotAligned);
 		}
 
 		{
-			let root_index = match space.paging_level() {
-				PagingLevel::Level4 => (virt >> 39) & 0x1FF,
-				PagingLevel::Level5 => (virt >> 48) & 0x1FF,
-			};
+			let root_index = space.paging_level().root_index(virt);
 			if unlikely!(root_index < self.valid_range.0 || root_index > self.valid_range.1) {
 				return Err(MapError::VirtOutOfRange);
 			}
@@ -89,14 +260,19 @@ impl AddressSegment {
 
 		let mut current_page_table = space.base_phys().as_mut_ptr_unchecked::<PageTable>();
 
-		for level in (1..space.paging_level().as_usize()).rev() {
+		for level in (depth + 1..space.paging_level().as_usize()).rev() {
 			let index = (virt >> (12 + level * 9)) & 0x1FF;
 			let entry = &mut (&mut *current_page_table)[index];
 
+			// SAFETY(qix-): Only PDPT (level 2) and PD (level 1) entries may be huge.
+			if level <= 2 && entry.present() && entry.huge() {
+				return Err(MapError::Exists);
+			}
+
 			current_page_table = if entry.present() {
 				Phys::from_address_unchecked(entry.address()).as_mut_ptr_unchecked()
 			} else {
-				let frame_phys_addr = alloc.allocate().ok_or(MapError::OutOfMemory)?;
+				let frame_phys_addr = oom::allocate_in(alloc).ok_or(MapError::OutOfMemory)?;
 
 				// We zero it before placing it into the page table
 				// so as to not thrash the TLB.
@@ -123,23 +299,493 @@ impl AddressSegment {
 			};
 		}
 
-		let entry = &mut (*current_page_table)[(virt >> 12) & 0x1FF];
+		let entry = &mut (*current_page_table)[(virt >> (12 + depth * 9)) & 0x1FF];
 
 		Ok(entry)
 	}
 
+	/// Translates the given virtual address to the physical address
+	/// of the 4KiB frame it maps to, without modifying any page tables.
+	///
+	/// Returns `None` if the address is out of the segment's range,
+	/// not mapped, or mapped as part of a huge page.
+	#[must_use]
+	pub fn translate<Handle: MapperHandle>(&self, space: &Handle, virt: usize) -> Option<u64> {
+		// SAFETY(qix-): The page tables are only read, and the handle is trusted
+		// SAFETY(qix-): to point to a valid page table hierarchy.
+		unsafe { self.leaf_entry(space, virt) }.map(|entry| entry.address())
+	}
+
+	/// Clears the accessed bit of every leaf mapping in the segment, calling
+	/// `touched` with the virtual address and size (in bytes) of each page that
+	/// had been accessed since the last sweep (or since it was mapped).
+	///
+	/// Returns the number of touched pages. This is the primitive upon which
+	/// page replacement policies (e.g. clock/second-chance) are built.
+	///
+	/// The TLB is flushed for all touched pages, on all cores, so that the next
+	/// access to them sets the accessed bit again (see
+	/// [`crate::tlb::flush_range_accessed()`]).
+	///
+	/// # Safety
+	/// The handle must point to a valid page table hierarchy, whose intermediate
+	/// tables are not modified for the duration of the sweep.
+	pub unsafe fn sweep_accessed<Handle: MapperHandle>(
+		&self,
+		space: &Handle,
+		mut touched: impl FnMut(usize, usize),
+	) -> usize {
+		let mut count = 0;
+		// The current run of contiguous touched pages, to be flushed together.
+		let mut run: Option<(usize, usize)> = None;
+
+		PageTableWalker::with_root_range(
+			space.base_phys().address_u64(),
+			space.paging_level(),
+			self.valid_range.0..self.valid_range.1 + 1,
+		)
+		.visit_mut(|virt, entry, level| {
+			if !entry.accessed() {
+				return;
+			}
+
+			entry.clear_accessed();
+			count += 1;
+
+			let size = 1_usize << (12 + 9 * (level - 1));
+			touched(virt, size);
+
+			run = match run {
+				Some((start, end)) if end == virt => Some((start, virt + size)),
+				Some((start, end)) => {
+					crate::tlb::flush_range_accessed(start, end - start);
+					Some((virt, virt + size))
+				}
+				None => Some((virt, virt + size)),
+			};
+		});
+
+		if let Some((start, end)) = run {
+			crate::tlb::flush_range_accessed(start, end - start);
+		}
+
+		count
+	}
+
+	/// Returns the leaf (4KiB) page table entry for the given virtual address,
+	/// without allocating intermediate page tables.
+	///
+	/// Returns `None` if the address is out of the segment's range, if an
+	/// intermediate table is not present, or if the address is mapped as part
+	/// of a huge page.
+	unsafe fn leaf_entry<Handle: MapperHandle>(
+		&self,
+		space: &Handle,
+		virt: usize,
+	) -> Option<&'static mut PageTableEntry> {
+		let root_index = space.paging_level().root_index(virt);
+		if root_index < self.valid_range.0 || root_index > self.valid_range.1 {
+			return None;
+		}
+
+		PageTableWalker::lookup(space.base_phys().address_u64(), space.paging_level(), virt)
+			.and_then(|(entry, level)| (level == 1).then_some(entry))
+	}
+
+	/// Rewrites the permission bits of an existing 4KiB mapping in place,
+	/// leaving the mapped frame (and all other bits) untouched, and flushes
+	/// the mapping from the TLB on all cores.
+	///
+	/// Copy-on-write pages remain read-only regardless of
+	/// [`PageProtection::writable`]; writes are still resolved by copying
+	/// upon the first write fault.
+	///
+	/// Fails with [`UnmapError::NotMapped`] if the address is not mapped,
+	/// or is mapped as part of a huge page.
+	pub fn protect<Handle: MapperHandle>(
+		&self,
+		space: &Handle,
+		virt: usize,
+		protection: PageProtection,
+	) -> Result<(), UnmapError> {
+		if unlikely!(virt & 0xFFF != 0) {
+			return Err(UnmapError::VirtNotAligned);
+		}
+
+		// SAFETY(qix-): The handle is trusted to point to a valid page table hierarchy.
+		let entry = unsafe { self.leaf_entry(space, virt) }.ok_or(UnmapError::NotMapped)?;
+		if !entry.present() {
+			return Err(UnmapError::NotMapped);
+		}
+
+		if protection.writable && !entry.cow() {
+			entry.set_writable();
+		} else {
+			entry.clear_writable();
+		}
+
+		if protection.executable {
+			entry.clear_no_exec();
+		} else {
+			entry.set_no_exec();
+		}
+
+		if protection.user {
+			entry.set_user();
+		} else {
+			entry.clear_user();
+		}
+
+		crate::tlb::flush_global(virt);
+
+		Ok(())
+	}
+
+	/// Maps the given frame at the given virtual address as a read-only,
+	/// copy-on-write page. Uses the global allocator.
+	///
+	/// See [`AddressSegment::map_cow_in`] for details.
+	pub fn map_cow(
+		&self,
+		space: &AddressSpaceHandle,
+		virt: usize,
+		phys: u64,
+	) -> Result<(), MapError> {
+		self.map_cow_in(space, &mut GlobalPfa, virt, phys)
+	}
+
+	/// Maps the given frame at the given virtual address as a read-only,
+	/// copy-on-write page, using the given allocator for any intermediate
+	/// page tables.
+	///
+	/// Each copy-on-write mapping of a frame holds a reference to it; the
+	/// first write to the page (see [`AddressSegment::resolve_cow_fault`])
+	/// gives the faulting address space its own writable copy, unless it
+	/// holds the last reference, in which case the frame is reused as-is.
+	///
+	/// To share a frame that is already mapped writable elsewhere, the
+	/// existing mapping must also be converted via [`AddressSegment::mark_cow`].
+	pub fn map_cow_in<A>(
+		&self,
+		space: &AddressSpaceHandle,
+		alloc: &mut A,
+		virt: usize,
+		phys: u64,
+	) -> Result<(), MapError>
+	where
+		A: Alloc,
+	{
+		let entry = unsafe { self.entry(space, alloc, virt)? };
+		if entry.present() {
+			return Err(MapError::Exists);
+		}
+
+		let mut new_entry = self.entry_template.with_address(phys);
+		new_entry.clear_writable();
+		new_entry.set_cow();
+
+		{
+			let mut refs = COW_REFS.lock();
+			cow_acquire_locked(&mut refs, phys);
+			*entry = new_entry;
+		}
+
+		crate::asm::invlpg(virt as *const ());
+
+		Ok(())
+	}
+
+	/// Converts an existing 4KiB mapping into a read-only, copy-on-write
+	/// mapping, returning the physical address of the mapped frame such that
+	/// it can be shared via [`AddressSegment::map_cow`].
+	///
+	/// Converting a mapping that is already copy-on-write is a no-op.
+	///
+	/// Fails with [`UnmapError::NotMapped`] if the address is not mapped,
+	/// or is mapped as part of a huge page.
+	pub fn mark_cow(&self, space: &AddressSpaceHandle, virt: usize) -> Result<u64, UnmapError> {
+		if unlikely!(virt & 0xFFF != 0) {
+			return Err(UnmapError::VirtNotAligned);
+		}
+
+		// SAFETY(qix-): The handle is trusted to point to a valid page table hierarchy.
+		let entry = unsafe { self.leaf_entry(space, virt) }.ok_or(UnmapError::NotMapped)?;
+		if !entry.present() {
+			return Err(UnmapError::NotMapped);
+		}
+
+		let (phys, converted) = {
+			let mut refs = COW_REFS.lock();
+			let phys = entry.address();
+			let converted = !entry.cow();
+
+			if converted {
+				cow_acquire_locked(&mut refs, phys);
+				entry.clear_writable();
+				entry.set_cow();
+			}
+
+			(phys, converted)
+		};
+
+		if converted {
+			// The mapping may be cached as writable on other cores.
+			crate::tlb::flush_global(virt);
+		}
+
+		Ok(phys)
+	}
+
+	/// Resolves a write fault on a copy-on-write page at the given
+	/// virtual address, for the given address space only.
+	///
+	/// If other copy-on-write mappings of the frame remain, a fresh frame is
+	/// allocated, the contents are copied into it, and it is mapped writable
+	/// in place of the shared frame. Otherwise, the frame is remapped writable
+	/// as-is.
+	///
+	/// Returns `true` if the fault was resolved (including if the page was
+	/// already writable, e.g. due to a stale TLB entry), or `false` if the
+	/// address is not a copy-on-write page or the system is out of memory.
+	///
+	/// # Safety
+	/// Must only be called in response to a write fault for the given
+	/// address space, with interrupts disabled.
+	pub unsafe fn resolve_cow_fault<Handle: MapperHandle>(
+		&self,
+		space: &Handle,
+		virt: usize,
+	) -> bool {
+		let virt = virt & !0xFFF;
+
+		let Some(entry) = self.leaf_entry(space, virt) else {
+			return false;
+		};
+
+		{
+			// NOTE(qix-): The entry is (re-)read and updated with the lock held, such
+			// NOTE(qix-): that two cores faulting on the same page can't both copy the
+			// NOTE(qix-): frame and both drop its reference.
+			let mut refs = COW_REFS.lock();
+
+			if !entry.present() {
+				return false;
+			}
+
+			if !entry.cow() {
+				// Another core may have already resolved the fault.
+				if entry.writable() {
+					crate::asm::invlpg(virt as *const ());
+					return true;
+				}
+
+				return false;
+			}
+
+			let phys = entry.address();
+			let mut new_entry = *entry;
+
+			if !cow_release_locked(&mut refs, phys) {
+				let Some(new_phys) = GlobalPfa.allocate() else {
+					// Put the reference back; the fault is fatal anyway.
+					cow_acquire_locked(&mut refs, phys);
+					return false;
+				};
+
+				Phys::from_address_unchecked(new_phys)
+					.as_mut_ptr_unchecked::<u8>()
+					.copy_from_nonoverlapping(
+						Phys::from_address_unchecked(phys).as_ptr_unchecked(),
+						4096,
+					);
+
+				new_entry = new_entry.with_address(new_phys);
+			}
+
+			new_entry.set_writable();
+			new_entry.clear_cow();
+			*entry = new_entry;
+		}
+
+		// Other cores may be running the same address space.
+		crate::tlb::flush_global(virt);
+
+		true
+	}
+
+	/// Maps a huge page of the given size at the given virtual address.
+	/// Fails if the virtual address is already mapped. Uses the global allocator.
+	///
+	/// Both `virt` and `phys` must be aligned to the page size.
+	pub fn map_huge(
+		&self,
+		space: &AddressSpaceHandle,
+		virt: usize,
+		phys: u64,
+		size: HugePageSize,
+	) -> Result<(), MapError> {
+		self.map_huge_in(space, &mut GlobalPfa, virt, phys, size)
+	}
+
+	/// Maps a huge page of the given size at the given virtual address.
+	/// Fails if the virtual address is already mapped. Uses the given allocator.
+	///
+	/// Both `virt` and `phys` must be aligned to the page size.
+	pub fn map_huge_in<A>(
+		&self,
+		space: &AddressSpaceHandle,
+		alloc: &mut A,
+		virt: usize,
+		phys: u64,
+		size: HugePageSize,
+	) -> Result<(), MapError>
+	where
+		A: Alloc,
+	{
+		if unlikely!(phys & (size.bytes() as u64 - 1) != 0) {
+			return Err(MapError::PhysNotAligned);
+		}
+
+		let entry = unsafe { self.entry_at(space, alloc, virt, size.depth())? };
+		if entry.present() {
+			return Err(MapError::Exists);
+		}
+
+		// SAFETY(qix-): The entry is guaranteed to be a PDPT or PD entry.
+		*entry = unsafe { self.entry_template.with_huge() }.with_address(phys);
+		crate::asm::invlpg(virt as *const ());
+
+		Ok(())
+	}
+
+	/// Unmaps a huge page of the given size from the given virtual address,
+	/// returning the physical address that was previously mapped. Uses the
+	/// global allocator.
+	///
+	/// Fails with [`UnmapError::PageSizeMismatch`] if the address is not
+	/// mapped by a huge page of the given size.
+	pub fn unmap_huge(
+		&self,
+		space: &AddressSpaceHandle,
+		virt: usize,
+		size: HugePageSize,
+	) -> Result<u64, UnmapError> {
+		self.unmap_huge_in(space, &mut GlobalPfa, virt, size)
+	}
+
+	/// Unmaps a huge page of the given size from the given virtual address,
+	/// returning the physical address that was previously mapped. Uses the
+	/// given allocator to reclaim any intermediate page tables that become empty.
+	///
+	/// Fails with [`UnmapError::PageSizeMismatch`] if the address is not
+	/// mapped by a huge page of the given size.
+	pub fn unmap_huge_in<A>(
+		&self,
+		space: &AddressSpaceHandle,
+		alloc: &mut A,
+		virt: usize,
+		size: HugePageSize,
+	) -> Result<u64, UnmapError>
+	where
+		A: Alloc,
+	{
+		if unlikely!(virt & (size.bytes() - 1) != 0) {
+			return Err(UnmapError::VirtNotAligned);
+		}
+
+		let levels = space.paging_level().as_usize();
+
+		{
+			let root_index = (virt >> (12 + (levels - 1) * 9)) & 0x1FF;
+			if unlikely!(root_index < self.valid_range.0 || root_index > self.valid_range.1) {
+				return Err(UnmapError::VirtOutOfRange);
+			}
+		}
+
+		let depth = size.depth();
+
+		// The physical addresses of the page tables that were traversed,
+		// from the root down to (and including) the leaf table.
+		let mut tables = [0_u64; 5];
+		let mut count = 0;
+		let mut table_phys = space.base_phys().address_u64();
+
+		for level in (depth..levels).rev() {
+			tables[count] = table_phys;
+			count += 1;
+
+			// SAFETY(qix-): All page tables are guaranteed to be in the linear map.
+			let table =
+				unsafe { Phys::from_address_unchecked(table_phys).as_mut_unchecked::<PageTable>() };
+			let entry = &mut table[(virt >> (12 + level * 9)) & 0x1FF];
+
+			if !entry.present() {
+				return Err(UnmapError::NotMapped);
+			}
+
+			// SAFETY(qix-): Only PDPT (level 2) and PD (level 1) entries may be huge.
+			let huge = level <= 2 && unsafe { entry.huge() };
+
+			if level == depth {
+				if !huge {
+					return Err(UnmapError::PageSizeMismatch);
+				}
+
+				let phys = entry.address();
+				entry.reset();
+				crate::tlb::invalidate_local(virt);
+
+				// Reclaim any intermediate tables that are now empty
+				// (never the root table).
+				for i in (1..count).rev() {
+					// SAFETY(qix-): All page tables are guaranteed to be in the linear map.
+					let (table, parent) = unsafe {
+						(
+							Phys::from_address_unchecked(tables[i]).as_mut_unchecked::<PageTable>(),
+							Phys::from_address_unchecked(tables[i - 1])
+								.as_mut_unchecked::<PageTable>(),
+						)
+					};
+
+					if !table.empty() {
+						break;
+					}
+
+					parent[(virt >> (12 + (levels - i) * 9)) & 0x1FF].reset();
+					// SAFETY(qix-): The table is empty and no longer referenced.
+					unsafe {
+						alloc.free(tables[i]);
+					}
+				}
+
+				return Ok(phys);
+			}
+
+			if huge {
+				// A larger huge page covers this address.
+				return Err(UnmapError::PageSizeMismatch);
+			}
+
+			table_phys = entry.address();
+		}
+
+		unreachable!();
+	}
+
 	/// Attempts to unmap a virtual address from the segment, returning the
 	/// physical address that was previously mapped. Assumes that the CPU
 	/// is in a 4-level paging mode.
 	///
-	/// If no physical address was previously mapped, returns `None`.
+	/// If no physical address was previously mapped, returns `None`. If the
+	/// page was a copy-on-write page whose frame remains mapped elsewhere,
+	/// returns `Some(None)`.
 	// TODO(qix-): consolodate the l4 and l4 unmap functions.
 	unsafe fn try_unmap_l4<A, Handle: MapperHandle>(
 		&self,
 		space: &Handle,
 		alloc: &mut A,
 		virt: usize,
-	) -> Result<Option<u64>, UnmapError>
+	) -> Result<Option<Option<u64>>, UnmapError>
 	where
 		A: Alloc,
 	{
@@ -165,12 +811,20 @@ impl AddressSegment {
 			let l3_index = (virt >> 30) & 0x1FF;
 			let l3_entry = &mut l3[l3_index];
 
+			if l3_entry.present() && l3_entry.huge() {
+				return Err(UnmapError::PageSizeMismatch);
+			}
+
 			let r = if l3_entry.present() {
 				let l2_phys = l3_entry.address();
 				let l2 = Phys::from_address_unchecked(l2_phys).as_mut_unchecked::<PageTable>();
 				let l2_index = (virt >> 21) & 0x1FF;
 				let l2_entry = &mut l2[l2_index];
 
+				if l2_entry.present() && l2_entry.huge() {
+					return Err(UnmapError::PageSizeMismatch);
+				}
+
 				let r = if l2_entry.present() {
 					let l1_phys = l2_entry.address();
 					let l1 = Phys::from_address_unchecked(l1_phys).as_mut_unchecked::<PageTable>();
@@ -181,9 +835,8 @@ impl AddressSegment {
 						// NOTE: We DO NOT free the physical frame here.
 						// NOTE: We let the caller do that. This is an UNMAP,
 						// NOTE: not a FREE.
-						let phys = l1_entry.address();
-						l1_entry.reset();
-						crate::asm::invlpg(virt as *const ());
+						let phys = clear_leaf(l1_entry);
+						crate::tlb::invalidate_local(virt);
 						Some(phys)
 					} else {
 						None
@@ -224,14 +877,16 @@ impl AddressSegment {
 	/// physical address that was previously mapped. Assumes that the CPU
 	/// is in a 5-level paging mode.
 	///
-	/// If no physical address was previously mapped, returns `None`.
+	/// If no physical address was previously mapped, returns `None`. If the
+	/// page was a copy-on-write page whose frame remains mapped elsewhere,
+	/// returns `Some(None)`.
 	// TODO(qix-): consolodate the l4 and l4 unmap functions.
 	unsafe fn try_unmap_l5<A, Handle: MapperHandle>(
 		&self,
 		space: &Handle,
 		alloc: &mut A,
 		virt: usize,
-	) -> Result<Option<u64>, UnmapError>
+	) -> Result<Option<Option<u64>>, UnmapError>
 	where
 		A: Alloc,
 	{
@@ -263,12 +918,20 @@ impl AddressSegment {
 				let l3_index = (virt >> 30) & 0x1FF;
 				let l3_entry = &mut l3[l3_index];
 
+				if l3_entry.present() && l3_entry.huge() {
+					return Err(UnmapError::PageSizeMismatch);
+				}
+
 				let r = if l3_entry.present() {
 					let l2_phys = l3_entry.address();
 					let l2 = Phys::from_address_unchecked(l2_phys).as_mut_unchecked::<PageTable>();
 					let l2_index = (virt >> 21) & 0x1FF;
 					let l2_entry = &mut l2[l2_index];
 
+					if l2_entry.present() && l2_entry.huge() {
+						return Err(UnmapError::PageSizeMismatch);
+					}
+
 					let r = if l2_entry.present() {
 						let l1_phys = l2_entry.address();
 						let l1 =
@@ -280,9 +943,8 @@ impl AddressSegment {
 							// NOTE: We DO NOT free the physical frame here.
 							// NOTE: We let the caller do that. This is an UNMAP,
 							// NOTE: not a FREE.
-							let phys = l1_entry.address();
-							l1_entry.reset();
-							crate::asm::invlpg(virt as *const ());
+							let phys = clear_leaf(l1_entry);
+							crate::tlb::invalidate_local(virt);
 							Some(phys)
 						} else {
 							None
@@ -367,6 +1029,16 @@ impl AddressSegment {
 	{
 		if entry.present() {
 			let phys = entry.address();
+
+			// SAFETY(qix-): Only PDPT (level 2) and PD (level 1) entries may be huge.
+			if level <= 2 && entry.huge() {
+				let size = 1_u64 << (12 + level * 9);
+				for offset in (0..size).step_by(4096) {
+					alloc.free(phys + offset);
+				}
+				return;
+			}
+
 			// SAFETY(qix-): We know that the physical address is valid.
 			let pt = unsafe { Phys::from_address_unchecked(phys).as_mut_unchecked::<PageTable>() };
 
@@ -374,7 +1046,9 @@ impl AddressSegment {
 				for idx in 0..512 {
 					let entry = &mut pt[idx];
 					if entry.present() {
-						alloc.free(entry.address());
+						if let Some(frame) = clear_leaf(entry) {
+							alloc.free(frame);
+						}
 					}
 				}
 			} else {
@@ -411,21 +1085,7 @@ impl AddressSegment {
 unsafe impl Segment<AddressSpaceHandle> for &'static AddressSegment {
 	// TODO(qix-): Once const trait methods are stabilitized, make this const.
 	fn range(&self) -> (usize, usize) {
-		// Get the current paging level.
-		match PagingLevel::current_from_cpu() {
-			PagingLevel::Level4 => {
-				(
-					sign_extend!(L4, self.valid_range.0 << 39),
-					sign_extend!(L4, (self.valid_range.1 << 39) | 0x0000_007F_FFFF_FFFF),
-				)
-			}
-			PagingLevel::Level5 => {
-				(
-					sign_extend!(L5, self.valid_range.0 << 48),
-					sign_extend!(L5, (self.valid_range.1 << 48) | 0x0000_FFFF_FFFF_FFFF),
-				)
-			}
-		}
+		self.range_for(PagingLevel::current_from_cpu())
 	}
 
 	unsafe fn unmap_all_and_reclaim_in<A>(
@@ -466,7 +1126,7 @@ unsafe impl Segment<AddressSpaceHandle> for &'static AddressSegment {
 				return Err(MapError::Exists);
 			}
 
-			let frame_phys_addr = alloc.allocate().ok_or(MapError::OutOfMemory)?;
+			let frame_phys_addr = oom::allocate_in(alloc).ok_or(MapError::OutOfMemory)?;
 			unsafe {
 				Phys::from_address_unchecked(frame_phys_addr)
 					.as_mut_unchecked::<PageTable>()
@@ -529,7 +1189,7 @@ unsafe impl Segment<AddressSpaceHandle> for &'static AddressSegment {
 				PagingLevel::Level5 => self.try_unmap_l5(space, alloc, virt)?,
 			}
 		};
-		phys.ok_or(UnmapError::NotMapped)
+		phys.ok_or(UnmapError::NotMapped)?.ok_or(UnmapError::Shared)
 	}
 
 	fn remap_in<A>(
@@ -543,14 +1203,16 @@ unsafe impl Segment<AddressSpaceHandle> for &'static AddressSegment {
 		A: Alloc,
 	{
 		let entry = unsafe { self.entry(space, alloc, virt)? };
-		let old_phys = if entry.present() {
-			Some(entry.address())
-		} else {
-			None
-		};
+		let replaced = entry.present();
+		let old_phys = if replaced { clear_leaf(entry) } else { None };
 
 		*entry = self.entry_template.with_address(phys);
-		crate::asm::invlpg(virt as *const ());
+
+		if replaced {
+			crate::tlb::invalidate_local(virt);
+		} else {
+			crate::asm::invlpg(virt as *const ());
+		}
 
 		Ok(old_phys)
 	}
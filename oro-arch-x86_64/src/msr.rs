@@ -0,0 +1,68 @@
+//! Model-specific register (MSR) access.
+//!
+//! Centralizes the MSR addresses used by the kernel, along
+//! with typed `rdmsr`/`wrmsr` wrappers.
+
+use core::arch::asm;
+
+/// The `IA32_APIC_BASE` MSR.
+pub const IA32_APIC_BASE: u32 = 0x1B;
+/// The `IA32_PAT` (page attribute table) MSR.
+pub const IA32_PAT: u32 = 0x277;
+/// The `IA32_TSC_DEADLINE` MSR.
+pub const IA32_TSC_DEADLINE: u32 = 0x6E0;
+/// The `IA32_EFER` (extended feature enable) MSR.
+pub const IA32_EFER: u32 = 0xC000_0080;
+/// The `IA32_STAR` (syscall target segments) MSR.
+pub const IA32_STAR: u32 = 0xC000_0081;
+/// The `IA32_LSTAR` (64-bit syscall entry point) MSR.
+pub const IA32_LSTAR: u32 = 0xC000_0082;
+/// The `IA32_FMASK` (syscall RFLAGS mask) MSR.
+pub const IA32_FMASK: u32 = 0xC000_0084;
+/// The `IA32_FS_BASE` MSR.
+pub const IA32_FS_BASE: u32 = 0xC000_0100;
+/// The `IA32_GS_BASE` MSR.
+pub const IA32_GS_BASE: u32 = 0xC000_0101;
+/// The `IA32_KERNEL_GS_BASE` MSR (swapped with `IA32_GS_BASE` by `swapgs`).
+pub const IA32_KERNEL_GS_BASE: u32 = 0xC000_0102;
+
+/// Reads the value of an MSR.
+///
+/// # Safety
+/// The MSR must exist on the current processor; reading an
+/// unsupported MSR raises a #GP.
+#[inline(always)]
+#[must_use]
+pub unsafe fn read(msr: u32) -> u64 {
+	let val_a: u32;
+	let val_d: u32;
+	asm!(
+		"rdmsr",
+		in("ecx") msr,
+		out("eax") val_a,
+		out("edx") val_d,
+		options(nostack, preserves_flags)
+	);
+
+	(u64::from(val_d) << 32) | u64::from(val_a)
+}
+
+/// Writes the value of an MSR.
+///
+/// # Safety
+/// The MSR must exist on the current processor, and the value
+/// must not set any reserved bits; doing either raises a #GP.
+///
+/// Writing to MSRs can further have arbitrary side effects on the
+/// processor's operation; the caller must ensure the write is valid
+/// for the given MSR.
+#[inline(always)]
+pub unsafe fn write(msr: u32, value: u64) {
+	asm!(
+		"wrmsr",
+		in("ecx") msr,
+		in("eax") value as u32,
+		in("edx") (value >> 32) as u32,
+		options(nostack, preserves_flags)
+	);
+}
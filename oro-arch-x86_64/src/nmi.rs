@@ -0,0 +1,155 @@
+//! Non-maskable interrupt (NMI) handling, used to dump each core's
+//! state for hang diagnosis. Debug builds only.
+//!
+//! NMIs can be raised externally (e.g. via QEMU's `nmi` monitor command,
+//! or from GDB), or by one core on behalf of all of them via
+//! [`dump_all_cores()`]. Since they're delivered regardless of the
+//! interrupt flag, they reach cores that are spinning with interrupts
+//! disabled, too.
+//!
+//! Each core prints the interrupted instruction and stack pointers, its
+//! current thread and a short frame pointer backtrace. Held locks are
+//! tracked by `oro-dbgutil` and can be inspected from GDB.
+
+use core::{arch::naked_asm, fmt};
+
+use oro_sync::Lock;
+
+use crate::lapic::IpiDest;
+
+/// The vector on which NMIs are always delivered.
+pub const NMI_VECTOR: u8 = 2;
+
+/// The number of times to try to take the debug logger's lock for
+/// each line before dropping it.
+const LOG_ATTEMPTS: usize = 100_000;
+
+/// The frame pushed by the CPU upon delivering an interrupt.
+#[repr(C)]
+struct InterruptFrame {
+	/// The interrupted instruction pointer.
+	rip:    u64,
+	/// The interrupted code segment.
+	cs:     u64,
+	/// The interrupted flags register.
+	rflags: u64,
+	/// The interrupted stack pointer.
+	rsp:    u64,
+	/// The interrupted stack segment.
+	_ss:    u64,
+}
+
+/// Sends an NMI to every core (including the current one), causing
+/// each of them to dump its state.
+pub fn dump_all_cores() {
+	crate::Kernel::get().core().lapic.send_nmi(IpiDest::All);
+}
+
+/// Logs a line without ever blocking on the debug logger's lock, which
+/// the interrupted code may very well be holding.
+fn log(message: fmt::Arguments) {
+	for _ in 0..LOG_ATTEMPTS {
+		if oro_debug::try_log(message) {
+			return;
+		}
+
+		core::hint::spin_loop();
+	}
+}
+
+/// Logs the current core's current thread, if its scheduler (and
+/// the thread itself) aren't locked.
+///
+/// # Safety
+/// Must only be called with interrupts disabled.
+unsafe fn log_current_thread(kernel: &crate::Kernel) {
+	let id = kernel.id();
+
+	let Some(scheduler) = kernel.scheduler().try_lock() else {
+		log(format_args!("nmi: core {id}: thread=? (scheduler locked)"));
+		return;
+	};
+
+	let Some(thread) = scheduler.current_thread() else {
+		log(format_args!("nmi: core {id}: thread=none"));
+		return;
+	};
+
+	let Some(thread) = thread.try_lock() else {
+		log(format_args!("nmi: core {id}: thread=? (thread locked)"));
+		return;
+	};
+
+	log(format_args!(
+		"nmi: core {id}: thread={}:{}",
+		thread.id(),
+		thread.thread_name()
+	));
+}
+
+/// The NMI handler; dumps the current core's state.
+///
+/// # Safety
+/// Must only be called by [`isr_nmi`].
+#[no_mangle]
+unsafe extern "C" fn isr_nmi_rust(frame: &InterruptFrame, rbp: u64) {
+	let kernel = crate::Kernel::get();
+	let id = kernel.id();
+	let from_user = frame.cs & 3 == 3;
+
+	log(format_args!(
+		"nmi: core {id}: rip={:016X} rsp={:016X} rbp={rbp:016X} rflags={:016X}{}",
+		frame.rip,
+		frame.rsp,
+		frame.rflags,
+		if from_user { " (user)" } else { "" }
+	));
+
+	log_current_thread(kernel);
+
+	// Userspace frame pointers are not to be trusted.
+	if from_user {
+		return;
+	}
+
+	crate::backtrace::walk(rbp, |i, ret| {
+		log(format_args!("nmi: core {id}:   #{i:<2} 0x{ret:016X}"));
+	});
+}
+
+/// The ISR (Interrupt Service Routine) trampoline stub for NMIs.
+///
+/// Preserves all caller-saved registers prior to calling into the handler,
+/// passing it the interrupt frame and the interrupted frame pointer.
+///
+/// NMIs are not acknowledged with an EOI.
+// NOTE(qix-): The NMI runs on whichever stack was active when it arrived,
+// NOTE(qix-): including, in the narrow window just after a `syscall`, the
+// NOTE(qix-): user stack. Acceptable for a debug-only facility.
+#[naked]
+pub(crate) unsafe extern "C" fn isr_nmi() -> ! {
+	naked_asm!(
+		"push rax",
+		"push rcx",
+		"push rdx",
+		"push rsi",
+		"push rdi",
+		"push r8",
+		"push r9",
+		"push r10",
+		"push r11",
+		"lea rdi, [rsp + 72]",
+		"mov rsi, rbp",
+		"call isr_nmi_rust",
+		"pop r11",
+		"pop r10",
+		"pop r9",
+		"pop r8",
+		"pop rdi",
+		"pop rsi",
+		"pop rdx",
+		"pop rcx",
+		"pop rax",
+		"iretq",
+	);
+}
@@ -0,0 +1,267 @@
+//! Page fault (`#PF`) handling for the x86_64 architecture.
+//!
+//! The page fault handler reads the faulting address from `cr2`,
+//! decodes the error code pushed by the CPU, and attempts to resolve
+//! the fault - first via the architecture's own mechanisms (e.g.
+//! copy-on-write pages), and then via a kernel-provided resolver
+//! (see [`set_resolver`]). Faults that cannot be resolved are fatal;
+//! they are reported and the core is halted.
+
+use core::arch::naked_asm;
+
+use oro_debug::dbg_err;
+use oro_mem::mapper::AddressSpace;
+use oro_sync::AtomicFnPtr;
+
+use crate::mem::address_space::AddressSpaceLayout;
+
+/// The vector for the page fault exception.
+pub const PAGE_FAULT_VECTOR: u8 = 14;
+
+/// The kernel-provided page fault resolver, if one has been set.
+static RESOLVER: AtomicFnPtr<Resolver> = AtomicFnPtr::new();
+
+/// The outcome of attempting to resolve a page fault.
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub enum FaultResolution {
+	/// The fault was resolved; the faulting instruction is retried.
+	Handled,
+	/// The fault could not be resolved.
+	Fatal,
+}
+
+/// A page fault resolver, called for any page fault that the architecture
+/// itself could not resolve.
+///
+/// Called with interrupts disabled, on the faulting core.
+pub type Resolver = fn(&PageFault<'_>) -> FaultResolution;
+
+/// Sets the kernel's page fault resolver, replacing any previous one.
+pub fn set_resolver(resolver: Resolver) {
+	RESOLVER.store(resolver);
+}
+
+/// The page fault error code, as pushed by the CPU.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct PageFaultErrorCode(u64);
+
+impl PageFaultErrorCode {
+	/// Returns the raw error code.
+	#[inline]
+	#[must_use]
+	pub fn bits(self) -> u64 {
+		self.0
+	}
+
+	/// The fault was caused by a protection violation on a present page
+	/// (as opposed to a non-present page).
+	#[inline]
+	#[must_use]
+	pub fn present(self) -> bool {
+		self.0 & (1 << 0) != 0
+	}
+
+	/// The fault was caused by a write (as opposed to a read).
+	#[inline]
+	#[must_use]
+	pub fn write(self) -> bool {
+		self.0 & (1 << 1) != 0
+	}
+
+	/// The fault occurred in user mode (CPL 3).
+	#[inline]
+	#[must_use]
+	pub fn user(self) -> bool {
+		self.0 & (1 << 2) != 0
+	}
+
+	/// The fault was caused by a reserved bit being set in a page table entry.
+	#[inline]
+	#[must_use]
+	pub fn reserved(self) -> bool {
+		self.0 & (1 << 3) != 0
+	}
+
+	/// The fault was caused by an instruction fetch.
+	#[inline]
+	#[must_use]
+	pub fn instruction_fetch(self) -> bool {
+		self.0 & (1 << 4) != 0
+	}
+}
+
+impl core::fmt::Debug for PageFaultErrorCode {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_struct("PageFaultErrorCode")
+			.field("present", &self.present())
+			.field("write", &self.write())
+			.field("user", &self.user())
+			.field("reserved", &self.reserved())
+			.field("instruction_fetch", &self.instruction_fetch())
+			.finish()
+	}
+}
+
+/// The register frame pushed by the page fault entry stub (and the CPU).
+///
+/// **Field order is relied upon by the entry stub; do not re-order them.**
+#[derive(Debug)]
+#[repr(C)]
+pub struct PageFaultFrame {
+	/// The saved `r15` register.
+	pub r15:        u64,
+	/// The saved `r14` register.
+	pub r14:        u64,
+	/// The saved `r13` register.
+	pub r13:        u64,
+	/// The saved `r12` register.
+	pub r12:        u64,
+	/// The saved `r11` register.
+	pub r11:        u64,
+	/// The saved `r10` register.
+	pub r10:        u64,
+	/// The saved `r9` register.
+	pub r9:         u64,
+	/// The saved `r8` register.
+	pub r8:         u64,
+	/// The saved `rbp` register.
+	pub rbp:        u64,
+	/// The saved `rdi` register.
+	pub rdi:        u64,
+	/// The saved `rsi` register.
+	pub rsi:        u64,
+	/// The saved `rdx` register.
+	pub rdx:        u64,
+	/// The saved `rcx` register.
+	pub rcx:        u64,
+	/// The saved `rbx` register.
+	pub rbx:        u64,
+	/// The saved `rax` register.
+	pub rax:        u64,
+	/// The error code pushed by the CPU.
+	pub error_code: PageFaultErrorCode,
+	/// The faulting instruction pointer.
+	pub rip:        u64,
+	/// The code segment selector at the time of the fault.
+	pub cs:         u64,
+	/// The flags register at the time of the fault.
+	pub rflags:     u64,
+	/// The stack pointer at the time of the fault.
+	pub rsp:        u64,
+	/// The stack segment selector at the time of the fault.
+	pub ss:         u64,
+}
+
+/// A page fault, as passed to the kernel's [`Resolver`].
+#[derive(Debug)]
+pub struct PageFault<'a> {
+	/// The faulting virtual address (from `cr2`).
+	pub address: usize,
+	/// The decoded error code.
+	pub error:   PageFaultErrorCode,
+	/// The register state at the time of the fault.
+	pub frame:   &'a PageFaultFrame,
+}
+
+/// Attempts to resolve the given page fault.
+fn resolve(fault: &PageFault<'_>) -> FaultResolution {
+	// Writes to present, read-only pages may be copy-on-write pages.
+	if fault.error.present() && fault.error.write() && !fault.error.reserved() {
+		// SAFETY(qix-): The current address space is always valid, and we're
+		// SAFETY(qix-): responding to a write fault with interrupts disabled.
+		let resolved = unsafe {
+			let space = AddressSpaceLayout::current_supervisor_space();
+			AddressSpaceLayout::resolve_write_fault(&space, fault.address)
+		};
+
+		if resolved {
+			return FaultResolution::Handled;
+		}
+	}
+
+	match RESOLVER.load() {
+		None => FaultResolution::Fatal,
+		Some(resolver) => resolver(fault),
+	}
+}
+
+/// The Rust side of the page fault handler.
+///
+/// Called by [`isr_page_fault`] with interrupts disabled, with a pointer
+/// to the saved register frame.
+#[no_mangle]
+unsafe extern "C" fn isr_page_fault_rust(frame: &PageFaultFrame) {
+	let fault = PageFault {
+		address: crate::reg::read_cr2() as usize,
+		error: frame.error_code,
+		frame,
+	};
+
+	let kernel = crate::Kernel::get();
+	kernel.enter_interrupt();
+
+	if resolve(&fault) == FaultResolution::Handled {
+		kernel.exit_interrupt();
+		return;
+	}
+
+	dbg_err!(
+		"fatal page fault at {:016X} (rip={:016X}): {:?}",
+		fault.address,
+		frame.rip,
+		fault.error
+	);
+	dbg_err!("{frame:#016X?}");
+
+	crate::asm::hang();
+}
+
+/// The ISR (Interrupt Service Routine) trampoline stub for page faults.
+///
+/// Saves all general purpose registers into a [`PageFaultFrame`] prior
+/// to calling into the handler, and pops the error code before returning.
+#[naked]
+pub(crate) unsafe extern "C" fn isr_page_fault() -> ! {
+	naked_asm!(
+		"push rax",
+		"push rbx",
+		"push rcx",
+		"push rdx",
+		"push rsi",
+		"push rdi",
+		"push rbp",
+		"push r8",
+		"push r9",
+		"push r10",
+		"push r11",
+		"push r12",
+		"push r13",
+		"push r14",
+		"push r15",
+		"mov rdi, rsp",
+		// Re-align the stack to 16 bytes for the call; the CPU-pushed
+		// frame (with the error code) plus 15 registers leaves it off by 8.
+		"sub rsp, 8",
+		"call isr_page_fault_rust",
+		"add rsp, 8",
+		"pop r15",
+		"pop r14",
+		"pop r13",
+		"pop r12",
+		"pop r11",
+		"pop r10",
+		"pop r9",
+		"pop r8",
+		"pop rbp",
+		"pop rdi",
+		"pop rsi",
+		"pop rdx",
+		"pop rcx",
+		"pop rbx",
+		"pop rax",
+		// Pop the error code.
+		"add rsp, 8",
+		"iretq",
+	);
+}
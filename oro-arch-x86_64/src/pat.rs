@@ -0,0 +1,70 @@
+//! Page attribute table (PAT) configuration.
+//!
+//! The memory type of a 4KiB page is selected by the PAT entry at the
+//! index formed by its page table entry's `PAT`, `PCD` (cache-disable)
+//! and `PWT` (write-through) flags, in that order (most significant first).
+//!
+//! Each core programs the PAT with the following layout. The first four
+//! entries match the power-on defaults, such that mappings made without
+//! the `PAT` flag (including those made by the bootloader) keep their
+//! meaning; only entry 4 differs from the defaults.
+//!
+//! | Index | `PAT` | `PCD` | `PWT` | Memory Type          |
+//! |-------|-------|-------|-------|----------------------|
+//! | 0     | 0     | 0     | 0     | Write-back           |
+//! | 1     | 0     | 0     | 1     | Write-through        |
+//! | 2     | 0     | 1     | 0     | Uncached (`UC-`)     |
+//! | 3     | 0     | 1     | 1     | Uncacheable (`UC`)   |
+//! | 4     | 1     | 0     | 0     | **Write-combining**  |
+//! | 5     | 1     | 0     | 1     | Write-through        |
+//! | 6     | 1     | 1     | 0     | Uncached (`UC-`)     |
+//! | 7     | 1     | 1     | 1     | Uncacheable (`UC`)   |
+//!
+//! On hardware without PAT support, the `PAT` flag is ignored; only
+//! the first four memory types are then available.
+
+/// The PAT encoding of the uncacheable (`UC`) memory type.
+const UC: u64 = 0x00;
+/// The PAT encoding of the write-combining (`WC`) memory type.
+const WC: u64 = 0x01;
+/// The PAT encoding of the write-through (`WT`) memory type.
+const WT: u64 = 0x04;
+/// The PAT encoding of the write-back (`WB`) memory type.
+const WB: u64 = 0x06;
+/// The PAT encoding of the uncached (`UC-`) memory type.
+const UC_MINUS: u64 = 0x07;
+
+/// The value programmed into the `IA32_PAT` MSR by each core.
+pub const PAT_VALUE: u64 = WB
+	| (WT << 8)
+	| (UC_MINUS << 16)
+	| (UC << 24)
+	| (WC << 32)
+	| (WT << 40)
+	| (UC_MINUS << 48)
+	| (UC << 56);
+
+/// The PAT index of the uncacheable memory type used for MMIO
+/// (`PCD` and `PWT` set).
+pub const UNCACHEABLE_INDEX: u8 = 3;
+/// The PAT index of the write-combining memory type (`PAT` set).
+pub const WRITE_COMBINING_INDEX: u8 = 4;
+
+/// Programs the current core's PAT with the layout described
+/// in the [module documentation](self).
+///
+/// Does nothing if the core doesn't support the PAT.
+///
+/// # Safety
+/// Must be called exactly once per core during boot, after the core's
+/// kernel instance has been initialized, and prior to any mapping being
+/// made with the `PAT` flag set.
+pub unsafe fn initialize() {
+	if !crate::Kernel::get().core().features.pat {
+		return;
+	}
+
+	// NOTE(qix-): Entries 0-3 are left as-is, and entry 4 is not yet in
+	// NOTE(qix-): use, so no cache or TLB flush is required here.
+	crate::msr::write(crate::msr::IA32_PAT, PAT_VALUE);
+}
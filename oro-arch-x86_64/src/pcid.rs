@@ -0,0 +1,115 @@
+//! Process-context identifier (PCID) management.
+//!
+//! With `CR4.PCIDE` set, the TLB tags its (non-global) entries with the
+//! PCID held in the low 12 bits of `CR3`, such that switching address
+//! spaces no longer requires flushing the TLB. Each core assigns a small
+//! pool of PCIDs to the address spaces it switches to, keyed by the physical
+//! address of their root page table, and evicts them round-robin.
+//!
+//! Loading `CR3` with bit 63 clear flushes all entries tagged with the new
+//! PCID; this is done whenever a PCID is (re)assigned. Otherwise, bit 63 is
+//! set and the TLB is left intact.
+//!
+//! # Generations
+//! `invlpg` only affects the current PCID (and global pages), so removing
+//! or downgrading a mapping may leave stale entries behind under any other
+//! PCID, on any core. Such modifications (as well as freeing a root page
+//! table, which may later be reused for a new address space and would
+//! otherwise inherit the old one's assignments) bump a global generation
+//! (see [`invalidate_all()`]). Each core drops all of its assignments upon
+//! noticing a new generation, thus flushing each PCID once more upon its
+//! next use.
+//!
+//! New mappings, and new address spaces, leave no stale entries behind
+//! and don't bump the generation.
+//!
+//! PCID 0 is never assigned; it's used by the boot code and remains in use
+//! (along with plain, flushing `CR3` reloads) on hardware without PCID support.
+
+use core::sync::atomic::{
+	AtomicU64,
+	Ordering::{Acquire, Release},
+};
+
+/// The number of PCIDs assigned by each core.
+pub const PCID_COUNT: usize = 16;
+
+/// Set in a `CR3` value to preserve the TLB entries tagged
+/// with the new PCID upon loading it.
+const CR3_NO_FLUSH: u64 = 1 << 63;
+
+/// The current PCID generation.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// A core's PCID assignments.
+pub(crate) struct PcidCache {
+	/// The generation in which the assignments were made.
+	generation: u64,
+	/// The root page table physical address assigned to each
+	/// PCID (the PCID being the index plus one), or `0` if unassigned.
+	owners:     [u64; PCID_COUNT],
+	/// The index of the next PCID to evict.
+	next:       usize,
+}
+
+impl PcidCache {
+	/// Creates a new, empty PCID cache.
+	pub const fn new() -> Self {
+		Self {
+			generation: 0,
+			owners:     [0; PCID_COUNT],
+			next:       0,
+		}
+	}
+
+	/// Returns the `CR3` value with which to switch to the address
+	/// space with the given root page table, assigning it a PCID
+	/// if it doesn't already have one.
+	fn cr3_for(&mut self, base_phys: u64) -> u64 {
+		let generation = GENERATION.load(Acquire);
+		if self.generation != generation {
+			self.owners = [0; PCID_COUNT];
+			self.generation = generation;
+		}
+
+		if let Some(idx) = self.owners.iter().position(|&owner| owner == base_phys) {
+			return base_phys | (idx as u64 + 1) | CR3_NO_FLUSH;
+		}
+
+		let idx = self.next;
+		self.next = (idx + 1) % PCID_COUNT;
+		self.owners[idx] = base_phys;
+
+		base_phys | (idx as u64 + 1)
+	}
+}
+
+/// Returns the `CR3` value with which the current core is to switch
+/// to the address space with the given root page table.
+///
+/// Without PCID support, this is simply the root page table's
+/// physical address.
+///
+/// # Safety
+/// Must be called with interrupts disabled, after the kernel has
+/// been initialized for the core. The returned value must be loaded
+/// into `CR3` before this function is called again on the same core.
+pub(crate) unsafe fn cr3_for(base_phys: u64) -> u64 {
+	let core = crate::Kernel::get().core();
+
+	if !core.features.pcid {
+		return base_phys;
+	}
+
+	(*core.pcid.get()).cr3_for(base_phys)
+}
+
+/// Invalidates all PCID assignments on all cores, causing each PCID
+/// to be flushed upon its next use.
+///
+/// Must be called whenever a page table entry is removed or downgraded
+/// (e.g. made read-only, or pointed at another frame), and whenever a
+/// root page table is freed.
+pub fn invalidate_all() {
+	GENERATION.fetch_add(1, Release);
+}
@@ -0,0 +1,44 @@
+//! Minimal support for the legacy 8254 PIT (Programmable Interval Timer).
+//!
+//! Used solely as a known-frequency reference for calibrating other
+//! timers (e.g. the LAPIC timer) during boot. Channel 2 is used, as
+//! its output can be polled via port `0x61` without an interrupt.
+
+/// The PIT's input clock frequency, in Hz.
+pub const PIT_FREQUENCY: u32 = 1_193_182;
+
+/// The PIT channel 2 data port.
+const CHANNEL2_DATA: u16 = 0x42;
+/// The PIT mode/command port.
+const COMMAND: u16 = 0x43;
+/// The PC speaker / channel 2 gate control port.
+const GATE_CONTROL: u16 = 0x61;
+
+/// Busy-waits for (approximately) the given number of milliseconds.
+///
+/// The PIT's 16-bit counter limits a single wait to ~54ms; larger
+/// values are clamped.
+///
+/// Must not be called concurrently from multiple cores.
+pub fn sleep_ms(ms: u16) {
+	#[expect(clippy::integer_division)]
+	let count = (u64::from(PIT_FREQUENCY) * u64::from(ms) / 1000).min(0xFFFF) as u16;
+
+	// Enable the channel 2 gate (bit 0) and disable the speaker (bit 1).
+	let gate = (crate::asm::inb(GATE_CONTROL) & !0x02) | 0x01;
+	crate::asm::outb(GATE_CONTROL, gate);
+
+	// Channel 2, lobyte/hibyte access, mode 0 (interrupt on terminal count), binary.
+	crate::asm::outb(COMMAND, 0b1011_0000);
+	crate::asm::outb(CHANNEL2_DATA, count as u8);
+	crate::asm::outb(CHANNEL2_DATA, (count >> 8) as u8);
+
+	// Restart the count by pulsing the gate.
+	crate::asm::outb(GATE_CONTROL, gate & !0x01);
+	crate::asm::outb(GATE_CONTROL, gate);
+
+	// Bit 5 reflects channel 2's output, which goes high on terminal count.
+	while crate::asm::inb(GATE_CONTROL) & 0x20 == 0 {
+		core::hint::spin_loop();
+	}
+}
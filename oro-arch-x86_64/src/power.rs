@@ -0,0 +1,287 @@
+//! System reset and power-off.
+//!
+//! The relevant ACPI registers are recorded from the FADT during boot
+//! (see [`initialize()`]). Resets use the FADT's reset register, if it
+//! has one, falling back to the legacy keyboard controller and finally
+//! a triple fault. Power-off enters the S5 (soft-off) sleep state via
+//! the PM1 control registers, using the `SLP_TYP` values found in the
+//! DSDT's `\_S5` object.
+
+use oro_acpi::{AcpiTable, Fadt, sys as acpi_sys};
+use oro_debug::{dbg, dbg_warn, dbg_warn_try};
+use oro_mem::phys::{Phys, PhysAddr};
+use oro_sync::SeqLock;
+
+/// The `SLP_EN` bit of the PM1 control registers.
+const SLP_EN: u16 = 1 << 13;
+/// The bit offset of the `SLP_TYP` field of the PM1 control registers.
+const SLP_TYP_SHIFT: u16 = 10;
+/// The generic address space ID for system memory.
+const ADR_SPACE_SYSTEM_MEMORY: u8 = 0;
+/// The generic address space ID for system I/O ports.
+const ADR_SPACE_SYSTEM_IO: u8 = 1;
+/// The legacy keyboard controller's command port.
+const KBC_COMMAND_PORT: u16 = 0x64;
+/// The keyboard controller command that pulses the CPU reset line.
+const KBC_PULSE_RESET: u8 = 0xFE;
+/// The number of spins to wait for a reset or power-off to take effect
+/// before trying the next method.
+const SETTLE_SPINS: usize = 10_000_000;
+
+/// The power management registers, as discovered during boot.
+static POWER: SeqLock<PowerInfo> = SeqLock::new(PowerInfo {
+	reset:        None,
+	pm1a_control: 0,
+	pm1b_control: 0,
+	s5:           None,
+});
+
+/// The ACPI power management registers.
+#[derive(Clone, Copy)]
+struct PowerInfo {
+	/// The ACPI reset register, if supported.
+	reset:        Option<ResetRegister>,
+	/// The PM1a control register port, or `0` if there is none.
+	pm1a_control: u16,
+	/// The PM1b control register port, or `0` if there is none.
+	pm1b_control: u16,
+	/// The `SLP_TYPa` and `SLP_TYPb` values for the S5 sleep state.
+	s5:           Option<(u8, u8)>,
+}
+
+/// The ACPI reset register.
+#[derive(Clone, Copy)]
+struct ResetRegister {
+	/// Whether the register is an I/O port (rather than memory-mapped).
+	io:      bool,
+	/// The port number or physical address of the register.
+	address: u64,
+	/// The value to write to the register.
+	value:   u8,
+}
+
+/// Records the power management registers from the given FADT.
+///
+/// # Safety
+/// Must be called once, during boot, by the primary core, after ACPI
+/// has been enabled. The DSDT referenced by the FADT must be readable.
+pub unsafe fn initialize(fadt: &<Fadt as AcpiTable>::SysTable) {
+	let flags = fadt.Flags.read();
+
+	let reset = if flags & acpi_sys::ACPI_FADT_RESET_REGISTER != 0 {
+		let space = fadt.ResetRegister.SpaceId.read();
+		match space {
+			ADR_SPACE_SYSTEM_IO | ADR_SPACE_SYSTEM_MEMORY => {
+				Some(ResetRegister {
+					io:      space == ADR_SPACE_SYSTEM_IO,
+					address: fadt.ResetRegister.Address.read(),
+					value:   fadt.ResetValue.read(),
+				})
+			}
+			space => {
+				dbg_warn!("ACPI reset register uses unsupported address space {space}");
+				None
+			}
+		}
+	} else {
+		None
+	};
+
+	// NOTE(qix-): Hardware-reduced ACPI has no PM1 blocks.
+	let (pm1a_control, pm1b_control) = if flags & acpi_sys::ACPI_FADT_HW_REDUCED == 0 {
+		(
+			u16::try_from(fadt.Pm1aControlBlock.read()).unwrap_or(0),
+			u16::try_from(fadt.Pm1bControlBlock.read()).unwrap_or(0),
+		)
+	} else {
+		(0, 0)
+	};
+
+	let dsdt_phys = match fadt.XDsdt.read() {
+		0 => u64::from(fadt.Dsdt.read()),
+		phys => phys,
+	};
+
+	let s5 = dsdt(dsdt_phys).and_then(find_s5);
+
+	dbg!(
+		"power: reset register {}, S5 {}",
+		if reset.is_some() { "present" } else { "absent" },
+		if s5.is_some() && pm1a_control != 0 {
+			"supported"
+		} else {
+			"unsupported"
+		}
+	);
+
+	POWER.write(|power| {
+		*power = PowerInfo {
+			reset,
+			pm1a_control,
+			pm1b_control,
+			s5,
+		};
+	});
+}
+
+/// Resets the system.
+///
+/// Interrupts must be disabled, and all other cores halted. Since any of
+/// them may have been halted while logging, warnings are dropped rather
+/// than waiting on the debug logger.
+pub fn reboot() -> ! {
+	let power = POWER.read();
+
+	if let Some(reset) = power.reset {
+		if reset.io {
+			if let Ok(port) = u16::try_from(reset.address) {
+				crate::asm::outb(port, reset.value);
+			}
+		} else {
+			// SAFETY(qix-): The register's address was provided by firmware,
+			// SAFETY(qix-): and is accessed via the linear map.
+			unsafe {
+				Phys::from_address_unchecked(reset.address)
+					.as_mut_ptr_unchecked::<u8>()
+					.write_volatile(reset.value);
+			}
+		}
+
+		settle();
+		dbg_warn_try!("ACPI reset did not take effect; falling back to legacy reset");
+	} else {
+		dbg_warn_try!("ACPI reset is unavailable; falling back to legacy reset");
+	}
+
+	// Wait for the keyboard controller's input buffer to drain.
+	for _ in 0..SETTLE_SPINS {
+		if crate::asm::inb(KBC_COMMAND_PORT) & 0b10 == 0 {
+			break;
+		}
+		core::hint::spin_loop();
+	}
+
+	crate::asm::outb(KBC_COMMAND_PORT, KBC_PULSE_RESET);
+	settle();
+
+	dbg_warn_try!("keyboard controller reset did not take effect; triple faulting");
+	crate::asm::triple_fault();
+}
+
+/// Powers off the system.
+///
+/// If the system can't be powered off, the current core is halted
+/// instead. Interrupts must be disabled, and all other cores halted; as
+/// with [`reboot()`], warnings are dropped if the debug logger is in use.
+pub fn shutdown() -> ! {
+	let power = POWER.read();
+
+	if let Some((slp_typa, slp_typb)) = power.s5.filter(|_| power.pm1a_control != 0) {
+		let enter = |port: u16, slp_typ: u8| {
+			let value = crate::asm::inw(port) & !(0b111 << SLP_TYP_SHIFT);
+			crate::asm::outw(
+				port,
+				value | (u16::from(slp_typ & 0b111) << SLP_TYP_SHIFT) | SLP_EN,
+			);
+		};
+
+		enter(power.pm1a_control, slp_typa);
+		if power.pm1b_control != 0 {
+			enter(power.pm1b_control, slp_typb);
+		}
+
+		settle();
+		dbg_warn_try!("ACPI S5 did not take effect; halting");
+	} else {
+		dbg_warn_try!("ACPI S5 is unavailable; halting");
+	}
+
+	crate::asm::hang();
+}
+
+/// Spins for a while, giving a reset or power-off time to take effect.
+fn settle() {
+	for _ in 0..SETTLE_SPINS {
+		core::hint::spin_loop();
+	}
+}
+
+/// Returns the AML of the DSDT at the given physical address, or `None`
+/// if there isn't a valid one.
+///
+/// # Safety
+/// The physical address must be zero, or readable.
+unsafe fn dsdt(phys: u64) -> Option<&'static [u8]> {
+	if phys == 0 {
+		return None;
+	}
+
+	let header = Phys::from_address_unchecked(phys).as_ref::<acpi_sys::acpi_table_header>()?;
+	if header.Signature.map(|c| c.to_ne_bytes()[0]) != *b"DSDT" {
+		return None;
+	}
+
+	let len = usize::try_from(header.Length.read()).ok()?;
+	let aml_len = len.checked_sub(core::mem::size_of::<acpi_sys::acpi_table_header>())?;
+
+	Some(core::slice::from_raw_parts(
+		core::ptr::from_ref(header).add(1).cast::<u8>(),
+		aml_len,
+	))
+}
+
+/// Finds the `SLP_TYPa` and `SLP_TYPb` values of the `\_S5` object
+/// in the given AML, without interpreting it.
+///
+/// Only the common encoding is recognized; a `NameOp` (optionally
+/// rooted) naming `_S5_` followed by a package of integer constants.
+fn find_s5(aml: &[u8]) -> Option<(u8, u8)> {
+	/// The AML `NameOp` opcode.
+	const NAME_OP: u8 = 0x08;
+	/// The AML root prefix (`\`).
+	const ROOT_PREFIX: u8 = b'\\';
+	/// The AML `PackageOp` opcode.
+	const PACKAGE_OP: u8 = 0x12;
+	/// The AML `BytePrefix`.
+	const BYTE_PREFIX: u8 = 0x0A;
+
+	let start = aml.windows(4).position(|window| window == b"_S5_")?;
+
+	let is_name = match start {
+		0 => false,
+		1 => aml[0] == NAME_OP,
+		_ => {
+			aml[start - 1] == NAME_OP
+				|| (aml[start - 1] == ROOT_PREFIX && aml[start - 2] == NAME_OP)
+		}
+	};
+
+	if !is_name {
+		return None;
+	}
+
+	let mut rest = aml.get(start + 4..)?;
+	if *rest.first()? != PACKAGE_OP {
+		return None;
+	}
+
+	// Skip the package op, its length (whose encoded size is given by
+	// the top two bits of its lead byte) and the element count.
+	let pkg_length_bytes = usize::from(rest.get(1)? >> 6) + 1;
+	rest = rest.get(1 + pkg_length_bytes + 1..)?;
+
+	let mut next_integer = || {
+		let (value, len) = match *rest.first()? {
+			BYTE_PREFIX => (*rest.get(1)?, 2),
+			// `ZeroOp`, `OneOp`, or a bare byte.
+			value => (value, 1),
+		};
+		rest = rest.get(len..)?;
+		Some(value)
+	};
+
+	let slp_typa = next_integer()?;
+	let slp_typb = next_integer()?;
+
+	Some((slp_typa, slp_typb))
+}
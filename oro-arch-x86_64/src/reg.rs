@@ -153,3 +153,207 @@ impl From<u64> for Cr0 {
 		Cr0(val)
 	}
 }
+
+/// The CR4 register.
+///
+/// Bits that must not be changed while in long mode (namely
+/// `PAE`, `PSE` and `LA57`) are intentionally omitted and are
+/// always inherited from the current register value.
+#[repr(transparent)]
+pub struct Cr4(u64);
+
+impl Cr4 {
+	field!(
+		with_time_stamp_disable,
+		2,
+		"Restricts the RDTSC instruction to CPL0 when set."
+	);
+
+	field!(
+		with_debugging_extensions,
+		3,
+		"Enables debugging extensions; references to DR4 and DR5 raise #UD when set."
+	);
+
+	field!(
+		with_global_pages,
+		7,
+		"Enables global pages. Requires `Features::pge`."
+	);
+
+	field!(
+		with_performance_counter,
+		8,
+		"Allows the RDPMC instruction to be executed at any privilege level."
+	);
+
+	field!(
+		with_osfxsr,
+		9,
+		"Indicates OS support for the FXSAVE/FXRSTOR instructions. Requires `Features::fxsr`."
+	);
+
+	field!(
+		with_osxmmexcpt,
+		10,
+		"Indicates OS support for unmasked SIMD floating-point exceptions. Requires \
+		 `Features::fxsr`."
+	);
+
+	field!(
+		with_fsgsbase,
+		16,
+		"Enables the RDFSBASE/RDGSBASE/WRFSBASE/WRGSBASE instructions. Requires \
+		 `Features::fsgsbase`."
+	);
+
+	field!(
+		with_pcid,
+		17,
+		"Enables process-context identifiers. Requires `Features::pcid`."
+	);
+
+	field!(
+		with_osxsave,
+		18,
+		"Enables the XSAVE family of instructions and XCR0. Requires `Features::xsave`."
+	);
+
+	field!(
+		with_smep,
+		20,
+		"Enables supervisor-mode execution prevention. Requires `Features::smep`."
+	);
+
+	field!(
+		with_smap,
+		21,
+		"Enables supervisor-mode access prevention. Requires `Features::smap`."
+	);
+
+	/// Creates a new CR4 register with all bits cleared.
+	#[expect(clippy::new_without_default)]
+	#[must_use]
+	pub const fn new() -> Self {
+		Self(0)
+	}
+
+	/// Returns a mask of all supported bits.
+	/// ANDing this with an existing CR4 value will retain
+	/// all unsupported bits and zero the supported bits
+	/// such that the value can be OR'd with new bits.
+	#[must_use]
+	pub const fn mask() -> u64 {
+		!Self::new()
+			.with_time_stamp_disable()
+			.with_debugging_extensions()
+			.with_global_pages()
+			.with_performance_counter()
+			.with_osfxsr()
+			.with_osxmmexcpt()
+			.with_fsgsbase()
+			.with_pcid()
+			.with_osxsave()
+			.with_smep()
+			.with_smap()
+			.0
+	}
+
+	/// Clears any bits that the given CPU features report as unsupported.
+	///
+	/// Should be called after setting any new bits, so as to avoid
+	/// a #GP when loading the register on older hardware.
+	#[must_use]
+	pub const fn with_only_supported(self, features: &crate::cpuid::Features) -> Self {
+		/// Clears `bit` in `value` if the feature is not `supported`.
+		const fn gate(value: u64, bit: u64, supported: bool) -> u64 {
+			if supported {
+				value
+			} else {
+				value & !(1 << bit)
+			}
+		}
+
+		let mut value = self.0;
+		value = gate(value, 7, features.pge);
+		value = gate(value, 9, features.fxsr);
+		value = gate(value, 10, features.fxsr);
+		value = gate(value, 16, features.fsgsbase);
+		value = gate(value, 17, features.pcid);
+		value = gate(value, 18, features.xsave);
+		value = gate(value, 20, features.smep);
+		value = gate(value, 21, features.smap);
+		Self(value)
+	}
+
+	/// Returns the raw bits of the CR4 register.
+	// TODO(qix-): When const traits are stabilized, remove this
+	// TODO(qix-): in lieu of a const `From` trait impl.
+	#[must_use]
+	pub const fn bits(self) -> u64 {
+		self.0
+	}
+
+	/// Inherits any unused bits from the current CR4 register.
+	///
+	/// Should be called after setting any new bits.
+	///
+	/// # Safety
+	/// Interrupts should be disabled before calling this
+	/// function if the value is to be immediately loaded,
+	/// in order to make sure no race conditions occur.
+	#[must_use]
+	pub unsafe fn inherit(mut self) -> Self {
+		let mut current = 0;
+		asm!("mov {}, cr4", inout(reg) current);
+		self.0 |= current & Self::mask();
+		self
+	}
+
+	/// Loads the CR4 register.
+	pub fn load(self) {
+		// SAFETY(qix-): This is safe because the CR4 register is a
+		// SAFETY(qix-): well-defined register in the x86_64 architecture.
+		// SAFETY(qix-): Unsupported bits are expected to have been
+		// SAFETY(qix-): filtered via `with_only_supported()`.
+		unsafe {
+			asm!("mov cr4, {}", in(reg) self.0);
+		}
+	}
+
+	/// Gets the current CR4 register.
+	#[must_use]
+	pub fn read() -> Self {
+		// SAFETY(qix-): This is always safe.
+		let cr4: u64;
+		unsafe {
+			asm!("mov {}, cr4", out(reg) cr4);
+		}
+		Self(cr4)
+	}
+}
+
+impl From<Cr4> for u64 {
+	fn from(cr4: Cr4) -> u64 {
+		cr4.0
+	}
+}
+
+impl From<u64> for Cr4 {
+	fn from(val: u64) -> Cr4 {
+		Cr4(val)
+	}
+}
+
+/// Reads the CR2 register, which holds the faulting linear address
+/// of the most recent page fault.
+#[inline]
+#[must_use]
+pub fn read_cr2() -> u64 {
+	let cr2: u64;
+	// SAFETY(qix-): This is always safe.
+	unsafe {
+		asm!("mov {}, cr2", out(reg) cr2, options(nostack, nomem, preserves_flags));
+	}
+	cr2
+}
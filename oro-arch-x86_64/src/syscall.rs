@@ -0,0 +1,192 @@
+//! Implements the `syscall`/`sysret` fast-path for user/kernel transitions.
+//!
+//! # Calling Convention
+//! User code invokes a system call via the `syscall` instruction with
+//! the following register layout:
+//!
+//! | Register | Purpose                     |
+//! |----------|-----------------------------|
+//! | `rax`    | System call number          |
+//! | `rdi`    | Argument 0                  |
+//! | `rsi`    | Argument 1                  |
+//! | `rdx`    | Argument 2                  |
+//! | `r10`    | Argument 3                  |
+//! | `r8`     | Argument 4                  |
+//! | `r9`     | Argument 5                  |
+//!
+//! `rcx` and `r11` are clobbered by the `syscall` instruction itself
+//! (holding the user `rip` and `rflags`, respectively) and are thus
+//! not available for arguments.
+//!
+//! Upon return, `rax` holds the status (`0` on success, otherwise a
+//! [`SyscallError`] code) and `rdx` holds the return value. `rsi` holds
+//! the calling thread's pending signals as a bitset of signal numbers,
+//! which are thereby cleared (see [`oro_kernel::thread::Thread::signal()`]).
+//! All other registers, except for `rcx` and `r11`, are preserved.
+
+use core::arch::naked_asm;
+
+use oro_mem::mapper::AddressSegment;
+use oro_sync::Lock;
+
+use crate::{
+	mem::address_space::AddressSpaceLayout,
+	msr::{IA32_EFER, IA32_FMASK, IA32_LSTAR, IA32_STAR},
+};
+
+/// The `SCE` (System Call Extensions) bit in `IA32_EFER`.
+const EFER_SCE: u64 = 1 << 0;
+
+/// The RFLAGS bits cleared upon entering the kernel via `syscall`
+/// (`TF`, `IF`, `DF` and `AC`).
+const SYSCALL_FMASK: u64 = (1 << 8) | (1 << 9) | (1 << 10) | (1 << 18);
+
+/// Per-core scratch space used by the syscall entry stub.
+///
+/// Pointed to by `IA32_KERNEL_GS_BASE`, and thus accessible via
+/// the `gs` segment after a `swapgs`. **Field offsets are relied
+/// upon by the entry stub; do not re-order them.**
+#[derive(Debug, Default)]
+#[repr(C)]
+pub struct SyscallScratch {
+	/// The kernel stack pointer to switch to upon entry.
+	pub kernel_rsp: u64,
+	/// The user stack pointer, stored upon entry.
+	pub user_rsp:   u64,
+}
+
+/// The register frame pushed by the syscall entry stub.
+///
+/// **Field order is relied upon by the entry stub; do not re-order them.**
+#[derive(Debug)]
+#[repr(C)]
+pub struct SyscallFrame {
+	/// Argument 5.
+	pub r9:     u64,
+	/// Argument 4.
+	pub r8:     u64,
+	/// Argument 3.
+	pub r10:    u64,
+	/// Argument 2; holds the return value upon return.
+	pub rdx:    u64,
+	/// Argument 1; holds the pending signals upon return.
+	pub rsi:    u64,
+	/// Argument 0.
+	pub rdi:    u64,
+	/// The system call number; holds the status upon return.
+	pub rax:    u64,
+	/// The user instruction pointer (from `rcx`).
+	pub rip:    u64,
+	/// The user flags register (from `r11`).
+	pub rflags: u64,
+	/// The user stack pointer.
+	pub rsp:    u64,
+}
+
+/// Errors returned to userspace from system calls.
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+#[repr(u64)]
+pub enum SyscallError {
+	/// The system call number is not known to the kernel.
+	UnknownSyscall = 1,
+}
+
+/// Configures the `syscall`/`sysret` MSRs for the current core.
+///
+/// # Safety
+/// Must be called exactly once per core, after the kernel has been
+/// initialized for the core and the core's GDT has been installed.
+pub unsafe fn initialize() {
+	let scratch = crate::Kernel::get().core().syscall_scratch.get();
+	(*scratch).kernel_rsp = AddressSpaceLayout::interrupt_stack().range().1 as u64 & !0xFFF;
+
+	// SYSCALL loads CS from `STAR[47:32]` and SS from `STAR[47:32] + 8`.
+	// SYSRET (64-bit) loads CS from `STAR[63:48] + 16` and SS from `STAR[63:48] + 8`.
+	let star = (u64::from(crate::gdt::KERNEL_DS) << 48) | (u64::from(crate::gdt::KERNEL_CS) << 32);
+	debug_assert_eq!(crate::gdt::KERNEL_DS + 8, crate::gdt::USER_DS);
+	debug_assert_eq!(crate::gdt::KERNEL_DS + 16, crate::gdt::USER_CS);
+
+	crate::msr::write(IA32_STAR, star);
+	crate::msr::write(IA32_LSTAR, syscall_entry as usize as u64);
+	crate::msr::write(IA32_FMASK, SYSCALL_FMASK);
+	crate::asm::set_kernel_gs_base(scratch as u64);
+	crate::msr::write(IA32_EFER, crate::msr::read(IA32_EFER) | EFER_SCE);
+}
+
+/// The Rust side of the system call handler.
+///
+/// Called by [`syscall_entry`] with interrupts disabled, on the
+/// core's kernel stack, with a pointer to the saved register frame.
+#[no_mangle]
+unsafe extern "C" fn oro_x86_64_syscall_rust(frame: &mut SyscallFrame) {
+	let args = [
+		frame.rdi, frame.rsi, frame.rdx, frame.r10, frame.r8, frame.r9,
+	];
+
+	// NOTE(qix-): Taken before dispatching, as the call may block
+	// NOTE(qix-): the thread and leave another one current.
+	let thread = crate::Kernel::get().current_thread();
+
+	match dispatch(frame.rax, args) {
+		Ok(value) => {
+			frame.rax = 0;
+			frame.rdx = value;
+		}
+		Err(err) => {
+			frame.rax = err as u64;
+			frame.rdx = 0;
+		}
+	}
+
+	frame.rsi = thread.map_or(0, |thread| thread.lock().take_pending());
+}
+
+/// Dispatches a system call given its number and arguments.
+#[expect(clippy::unnecessary_wraps)]
+fn dispatch(number: u64, args: [u64; 6]) -> Result<u64, SyscallError> {
+	// TODO(qix-): There are no system calls yet.
+	let _ = (number, args);
+	Err(SyscallError::UnknownSyscall)
+}
+
+/// The `syscall` entry point (`IA32_LSTAR`).
+///
+/// Swaps to the kernel GS base, switches to the core's kernel stack,
+/// saves the user's registers into a [`SyscallFrame`], and calls into
+/// [`oro_x86_64_syscall_rust`] before restoring them and returning
+/// via `sysretq`.
+#[naked]
+unsafe extern "C" fn syscall_entry() -> ! {
+	naked_asm!(
+		"swapgs",
+		"mov gs:[8], rsp",
+		"mov rsp, gs:[0]",
+		"push qword ptr gs:[8]",
+		"push r11",
+		"push rcx",
+		"push rax",
+		"push rdi",
+		"push rsi",
+		"push rdx",
+		"push r10",
+		"push r8",
+		"push r9",
+		"mov rdi, rsp",
+		"call oro_x86_64_syscall_rust",
+		"pop r9",
+		"pop r8",
+		"pop r10",
+		"pop rdx",
+		"pop rsi",
+		"pop rdi",
+		"pop rax",
+		"pop rcx",
+		"pop r11",
+		"pop rsp",
+		"swapgs",
+		// NOTE(qix-): `rcx` is restored from the frame, which the kernel never
+		// NOTE(qix-): modifies; if that changes, it must be validated as canonical
+		// NOTE(qix-): prior to `sysretq` to avoid a #GP in ring 0.
+		"sysretq",
+	);
+}
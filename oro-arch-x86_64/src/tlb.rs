@@ -0,0 +1,246 @@
+//! TLB (Translation Lookaside Buffer) shootdown facilities.
+//!
+//! Invalidating a page via `invlpg` only affects the current core's
+//! TLB. When a mapping that is shared between cores (e.g. a kernel
+//! mapping) is changed, all other online cores must invalidate it as
+//! well. This module broadcasts an IPI (inter-processor interrupt)
+//! to all other cores carrying the range to invalidate, and blocks
+//! until every core has acknowledged it.
+
+use core::{
+	arch::naked_asm,
+	sync::atomic::{
+		AtomicBool, AtomicU64, AtomicUsize,
+		Ordering::{Acquire, Relaxed, Release},
+	},
+};
+
+use oro_kernel::{critical::with_critical, interrupt::InterruptController};
+
+use crate::lapic::IpiDest;
+
+/// The interrupt vector used for TLB shootdown IPIs.
+pub const TLB_SHOOTDOWN_VECTOR: u8 = 0xF0;
+
+/// Set while a shootdown request is in flight. Only one request
+/// may be in flight at a time.
+static IN_FLIGHT: AtomicBool = AtomicBool::new(false);
+/// The generation of the most recent shootdown request.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+/// The page-aligned base virtual address of the in-flight request.
+static REQUEST_VIRT: AtomicUsize = AtomicUsize::new(0);
+/// The number of pages covered by the in-flight request.
+static REQUEST_PAGES: AtomicUsize = AtomicUsize::new(0);
+/// The number of cores that have yet to acknowledge the in-flight request.
+static PENDING_ACKS: AtomicUsize = AtomicUsize::new(0);
+/// The number of cores that are online and able to service shootdowns.
+static ONLINE_CORES: AtomicUsize = AtomicUsize::new(0);
+
+/// Invalidates the page containing the given virtual address on all cores.
+///
+/// Blocks until all cores have acknowledged the invalidation.
+pub fn flush_global(virt: usize) {
+	flush_range(virt, 1);
+}
+
+/// Invalidates all pages overlapping the given virtual address range
+/// on all cores, after their mappings were removed or downgraded.
+///
+/// Entries tagged with other PCIDs (on any core) are invalidated
+/// lazily, upon their PCID's next use (see [`crate::pcid`]).
+///
+/// Blocks until all cores have acknowledged the invalidation. If no
+/// other cores are online, only the local TLB is invalidated.
+pub fn flush_range(virt: usize, len: usize) {
+	crate::pcid::invalidate_all();
+	shootdown(virt, len);
+}
+
+/// Invalidates all pages overlapping the given virtual address range
+/// on all cores, after their accessed (or dirty) bits were cleared.
+///
+/// Unlike [`flush_range()`], entries tagged with other PCIDs are left
+/// alone; they may keep the bits from being set again until they're
+/// flushed for some other reason, but can't be used to access anything
+/// that the current mappings don't allow.
+///
+/// Blocks until all cores have acknowledged the invalidation.
+pub fn flush_range_accessed(virt: usize, len: usize) {
+	shootdown(virt, len);
+}
+
+/// Invalidates all pages overlapping the given virtual address range
+/// under the current PCID of every core.
+///
+/// Blocks until all cores have acknowledged the invalidation. If no
+/// other cores are online, only the local TLB is invalidated.
+fn shootdown(virt: usize, len: usize) {
+	let start = virt & !0xFFF;
+	// NOTE(qix-): Counted in pages (rather than computing the end address) so
+	// NOTE(qix-): that ranges reaching the top of the address space don't overflow.
+	let pages = (virt & 0xFFF)
+		.saturating_add(len)
+		.div_ceil(4096)
+		.min(((usize::MAX - start) >> 12) + 1);
+
+	flush_local(start, pages);
+
+	// Fast path; no other cores to notify.
+	if ONLINE_CORES.load(Acquire) <= 1 {
+		return;
+	}
+
+	acquire();
+
+	let others = ONLINE_CORES.load(Acquire) - 1;
+	if others > 0 {
+		REQUEST_VIRT.store(start, Relaxed);
+		REQUEST_PAGES.store(pages, Relaxed);
+		PENDING_ACKS.store(others, Relaxed);
+		let generation = GENERATION.fetch_add(1, Release) + 1;
+
+		// SAFETY(qix-): Only ever touched by the current core.
+		unsafe {
+			crate::Kernel::get()
+				.core()
+				.tlb_generation
+				.get()
+				.write(generation);
+		}
+
+		crate::Kernel::get()
+			.core()
+			.lapic
+			.send_ipi(IpiDest::AllExcludingSelf, TLB_SHOOTDOWN_VECTOR);
+
+		while PENDING_ACKS.load(Acquire) != 0 {
+			core::hint::spin_loop();
+		}
+	}
+
+	IN_FLIGHT.store(false, Release);
+}
+
+/// Invalidates the page containing the given virtual address on the
+/// current core only, after its mapping was removed or downgraded.
+///
+/// Mappings that weren't previously present need no more than a
+/// plain `invlpg` (see [`crate::asm::invlpg()`]).
+///
+/// Entries tagged with other PCIDs (on any core) are invalidated
+/// lazily, upon their PCID's next use (see [`crate::pcid`]).
+pub fn invalidate_local(virt: usize) {
+	crate::pcid::invalidate_all();
+	crate::asm::invlpg(virt as *const ());
+}
+
+/// Marks the current core as online and able to service TLB shootdowns.
+///
+/// # Safety
+/// Must be called exactly once per core, after the kernel has been
+/// initialized for the core and the IDT has been installed, but
+/// before interrupts are enabled.
+pub(crate) unsafe fn mark_core_online() {
+	// NOTE(qix-): The in-flight flag is taken _without_ servicing requests.
+	// NOTE(qix-): This core isn't counted by any in-flight request yet, and
+	// NOTE(qix-): its generation isn't current; servicing one would ack on
+	// NOTE(qix-): behalf of a core that the initiator is still waiting on.
+	while IN_FLIGHT
+		.compare_exchange_weak(false, true, Acquire, Relaxed)
+		.is_err()
+	{
+		core::hint::spin_loop();
+	}
+
+	crate::Kernel::get()
+		.core()
+		.tlb_generation
+		.get()
+		.write(GENERATION.load(Acquire));
+	ONLINE_CORES.fetch_add(1, Release);
+
+	IN_FLIGHT.store(false, Release);
+}
+
+/// Returns whether or not any core other than the current one is online.
+pub(crate) fn others_online() -> bool {
+	ONLINE_CORES.load(Acquire) > 1
+}
+
+/// Invalidates `pages` pages starting at the given page-aligned
+/// address on the current core only.
+fn flush_local(start: usize, pages: usize) {
+	for page in 0..pages {
+		crate::asm::invlpg((start + (page << 12)) as *const ());
+	}
+}
+
+/// Acquires the in-flight flag, servicing any other core's pending
+/// request while waiting so as to avoid deadlocking against it.
+fn acquire() {
+	while IN_FLIGHT
+		.compare_exchange_weak(false, true, Acquire, Relaxed)
+		.is_err()
+	{
+		with_critical::<crate::Arch, _, _>(service);
+
+		core::hint::spin_loop();
+	}
+}
+
+/// Services the in-flight request, if the current core has not
+/// already done so.
+///
+/// Must be called with interrupts disabled.
+fn service() {
+	let generation = GENERATION.load(Acquire);
+	let last = crate::Kernel::get().core().tlb_generation.get();
+
+	// SAFETY(qix-): Only ever touched by the current core, with interrupts disabled.
+	unsafe {
+		if *last < generation {
+			flush_local(REQUEST_VIRT.load(Relaxed), REQUEST_PAGES.load(Relaxed));
+			last.write(generation);
+			PENDING_ACKS.fetch_sub(1, Release);
+		}
+	}
+}
+
+/// The ISR (Interrupt Service Routine) for TLB shootdown IPIs.
+#[no_mangle]
+unsafe extern "C" fn isr_tlb_shootdown_rust() {
+	let kernel = crate::Kernel::get();
+	kernel.enter_interrupt();
+	service();
+	kernel.interrupt_controller().eoi();
+	kernel.exit_interrupt();
+}
+
+/// The ISR (Interrupt Service Routine) trampoline stub for TLB shootdown IPIs.
+///
+/// Preserves all caller-saved registers prior to calling into the handler.
+#[naked]
+pub(crate) unsafe extern "C" fn isr_tlb_shootdown() -> ! {
+	naked_asm!(
+		"push rax",
+		"push rcx",
+		"push rdx",
+		"push rsi",
+		"push rdi",
+		"push r8",
+		"push r9",
+		"push r10",
+		"push r11",
+		"call isr_tlb_shootdown_rust",
+		"pop r11",
+		"pop r10",
+		"pop r9",
+		"pop r8",
+		"pop rdi",
+		"pop rsi",
+		"pop rdx",
+		"pop rcx",
+		"pop rax",
+		"iretq",
+	);
+}
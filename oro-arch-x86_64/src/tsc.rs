@@ -0,0 +1,45 @@
+//! Time-stamp counter (TSC) support.
+//!
+//! When the processor reports an invariant TSC (one that ticks at a
+//! constant rate across all power states, and is synchronized across
+//! cores), it is calibrated against the PIT and registered as the
+//! kernel's monotonic clock source (see [`oro_kernel::time`]).
+
+use oro_debug::{dbg, dbg_warn};
+
+/// Measures the TSC frequency, in Hz, against the PIT.
+///
+/// Interrupts should be disabled while calibrating, and this must
+/// not be called concurrently from multiple cores (the PIT is shared).
+#[must_use]
+// NOTE(qix-): Sub-tick precision is irrelevant here.
+#[expect(clippy::integer_division)]
+pub fn calibrate() -> u64 {
+	/// The length of the calibration window, in milliseconds.
+	const CALIBRATION_MS: u16 = 10;
+
+	let start = crate::asm::rdtsc();
+	crate::pit::sleep_ms(CALIBRATION_MS);
+	let end = crate::asm::rdtsc();
+
+	(end.saturating_sub(start) * 1000 / u64::from(CALIBRATION_MS)).max(1)
+}
+
+/// Calibrates the TSC and registers it as the kernel's clock source,
+/// if the TSC is invariant. Otherwise, the kernel's best-effort
+/// monotonic counter remains in use.
+///
+/// # Safety
+/// Must be called exactly once, by the primary core during boot,
+/// prior to booting any secondary cores, with interrupts disabled.
+pub unsafe fn register_clock_source() {
+	if !crate::cpuid::Features::detect().invariant_tsc {
+		dbg_warn!("TSC is not invariant; no monotonic clock source available");
+		return;
+	}
+
+	let hz = calibrate();
+	dbg!("TSC: {hz} Hz");
+
+	oro_kernel::time::register_clock_source(crate::asm::rdtsc, hz);
+}
@@ -25,10 +25,10 @@
 //! in the kernel address space. The bootloader is expected to scan
 //! for these requests and populate them with the necessary data.
 //!
-//! Note that tags are architecture-endian, meaning the kernel
-//! compiled for a little-endian system will have its tag bytes
-//! reversed when compared to a kernel compiled for a big-endian
-//! system.
+//! Note that tags are defined by their byte sequence (e.g. `ORO_MMAP`),
+//! which appears as-is in memory regardless of the kernel's endianness;
+//! read as an architecture-endian integer, however, a tag has a different
+//! value on a little-endian system than on a big-endian one.
 //!
 //! All discovered tags are expected to be populated, except for
 //! those that are explicitly marked as optional.
@@ -62,6 +62,27 @@
 //! The data directly after the request header is the data structure
 //! to populate.
 //!
+//! With the `utils` feature enabled, [`util::find_requests()`] serves
+//! as a reference implementation of the above.
+//!
+//! # Endianness
+//! All multi-byte integer fields in the protocol are architecture-endian;
+//! that is, they take on the byte order of the architecture the kernel was
+//! compiled for, which may differ from that of the bootloader. This covers:
+//!
+//! - the [`RequestHeader`] (`magic` and `revision`; see the note on tags
+//!   above),
+//! - every request's response data (e.g. [`memory_map::MemoryMapDataV0`]),
+//! - every structure referenced by physical address from a response
+//!   (e.g. [`MemoryMapEntry`], [`Module`]), including enum-typed fields
+//!   such as [`MemoryMapEntryType`],
+//! - the kernel metadata note (see [`note`]).
+//!
+//! Single-byte fields (e.g. a request's `populated` flag) and reserved
+//! byte arrays are not endian-sensitive. Bootloaders running on a host
+//! whose endianness differs from the kernel's must convert accordingly;
+//! with the `utils` feature, see [`util::Endian`] and [`util::KernelEndian`].
+//!
 //! # Populating Requests
 //! The bootloader is expected to populate the request with the
 //! appropriate data. The kernel will then use this data to
@@ -76,6 +97,17 @@
 //! `0x00` before populating the request, as a sanity check that
 //! some bug or corruption did not occur.
 //!
+//! The kernel treats any value other than `0xFF` as unpopulated;
+//! values other than `0x00` and `0xFF` are additionally reported
+//! as errors in debug builds.
+//!
+//! # Kernel Metadata
+//! In addition to the requests, the kernel ELF carries a note with
+//! the kernel's version, the boot protocol revision it implements and
+//! the features it requires of the bootloader. Bootloaders should check
+//! it prior to mapping the kernel; see the [`note`] module for its format
+//! and how it's discovered.
+//!
 //! # C Header Generation
 //! This crate supports generating a C header file that can be used
 //! to boot into the Oro kernel from C or other languages.
@@ -106,6 +138,7 @@
 compile_error!("The `utils` feature cannot be enabled when building the boot protocol C header.");
 
 mod macros;
+pub mod note;
 #[cfg(feature = "utils")]
 pub mod util;
 
@@ -131,6 +131,60 @@ macro_rules! oro_boot_protocol {
 						)*
 					}
 
+					#[cfg(feature = "utils")]
+					impl<'a> $ReqName %% Kind<'a> {
+						$(
+							#[doc = concat!("Returns the response data if it's of version ", stringify!($revision), ", or `None` otherwise.")]
+							#[must_use]
+							pub fn v %% $revision(&self) -> Option<&'a ::core::mem::MaybeUninit<$ReqName %% DataV %% $revision>> {
+								match self {
+									$ReqName %% Kind::V %% $revision(data) => Some(*data),
+									#[allow(unreachable_patterns)]
+									_ => None,
+								}
+							}
+						)*
+					}
+
+					#[cfg(feature = "utils")]
+					impl<'a> $ReqName %% KindMut<'a> {
+						$(
+							#[doc = concat!("Returns the response data if it's of version ", stringify!($revision), ", or `None` otherwise.")]
+							#[must_use]
+							pub fn v %% $revision(self) -> Option<&'a mut ::core::mem::MaybeUninit<$ReqName %% DataV %% $revision>> {
+								self.try_into().ok()
+							}
+						)*
+					}
+
+					$(
+						#[cfg(feature = "utils")]
+						impl<'a> TryFrom<$ReqName %% Kind<'a>> for &'a ::core::mem::MaybeUninit<$ReqName %% DataV %% $revision> {
+							type Error = $ReqName %% Kind<'a>;
+
+							fn try_from(kind: $ReqName %% Kind<'a>) -> Result<Self, Self::Error> {
+								match kind {
+									$ReqName %% Kind::V %% $revision(data) => Ok(data),
+									#[allow(unreachable_patterns)]
+									other => Err(other),
+								}
+							}
+						}
+
+						#[cfg(feature = "utils")]
+						impl<'a> TryFrom<$ReqName %% KindMut<'a>> for &'a mut ::core::mem::MaybeUninit<$ReqName %% DataV %% $revision> {
+							type Error = $ReqName %% KindMut<'a>;
+
+							fn try_from(kind: $ReqName %% KindMut<'a>) -> Result<Self, Self::Error> {
+								match kind {
+									$ReqName %% KindMut::V %% $revision(data) => Ok(data),
+									#[allow(unreachable_patterns)]
+									other => Err(other),
+								}
+							}
+						}
+					)*
+
 					#[cfg(feature = "utils")]
 					impl<'a> From<$ReqName %% KindMut<'a>> for $ReqName %% Kind<'a> {
 						fn from(kind: $ReqName %% KindMut<'a>) -> Self {
@@ -217,14 +271,33 @@ macro_rules! oro_boot_protocol {
 						}
 					}
 
+					/// Returns the state of the request's `populated` flag.
+					#[must_use]
+					#[cfg(feature = "utils")]
+					pub fn populated_state(&self) -> crate::util::Populated {
+						crate::util::Populated::from_raw(unsafe { core::ptr::read_volatile(&self.populated) })
+					}
+
+					/// Returns whether or not the bootloader populated the request
+					/// (i.e. its `populated` flag is `0xFF`).
+					///
+					/// Any other value, including an invalid one (see
+					/// [`Self::populated_state()`]), is treated as unpopulated.
+					#[must_use]
+					#[cfg(feature = "utils")]
+					pub fn is_populated(&self) -> bool {
+						self.populated_state() == crate::util::Populated::Yes
+					}
+
 					/// Returns the response data for the request
 					/// or `None` if the response was not populated
-					/// or if the revision number is not recognized.
+					/// (see [`Self::is_populated()`]) or if the revision
+					/// number is not recognized.
 					#[must_use]
 					#[cfg(feature = "utils")]
 					#[expect(clippy::needless_lifetimes)]
 					pub fn response<'a>(&'a self) -> Option<%<snake_case:$ReqName>%::$ReqName %% Kind<'a>> {
-						if unsafe { core::ptr::read_volatile(&self.populated) } == 0 {
+						if !self.is_populated() {
 							return None;
 						}
 
@@ -283,6 +356,10 @@ macro_rules! oro_boot_protocol {
 				)*
 			}
 
+			/// The tags of all known requests.
+			#[cfg(feature = "utils")]
+			pub const KNOWN_TAGS: &[crate::Tag] = &[$($ReqName %% Request::TAG),*];
+
 			/// Attempts to look up a request by a pointer to a tag value.
 			///
 			/// Returns `None` if the tag is not recognized.
@@ -0,0 +1,162 @@
+//! The kernel metadata note.
+//!
+//! The kernel ELF carries a single, machine-readable note describing
+//! the kernel, such that bootloaders (and tooling) can reject an
+//! incompatible kernel before ever mapping or jumping to it.
+//!
+//! # Note Format
+//! The note is a standard ELF note, placed in the `.note.oro` section
+//! and covered by a `PT_NOTE` program header. Like the requests, all
+//! fields are architecture-endian.
+//!
+//! | Offset | Size | Field                                        |
+//! |--------|------|----------------------------------------------|
+//! | `0`    | `4`  | Name length; always `4`                      |
+//! | `4`    | `4`  | Payload length, in bytes                     |
+//! | `8`    | `4`  | Type; always [`KERNEL_NOTE_TYPE`]            |
+//! | `12`   | `4`  | Name; always `"Oro\0"` (see [`NOTE_NAME`])   |
+//! | `16`   | *n*  | Payload; a [`KernelNote`]                    |
+//!
+//! The payload length is at least the size of a [`KernelNote`]; fields
+//! may be appended to the payload in the future, and must be ignored
+//! by bootloaders that don't know about them.
+//!
+//! # Discovering the Note
+//! Bootloaders are to look for a `PT_NOTE` program header (type `4`)
+//! and walk the notes within it (each of which has its name and payload
+//! padded to a multiple of 4 bytes), looking for a note with the above
+//! name and type. With the `utils` feature enabled, this is done by
+//! [`crate::util::find_kernel_note()`].
+//!
+//! # Rejecting a Kernel
+//! Bootloaders should refuse to boot a kernel whose note is missing,
+//! whose [`KernelNote::protocol_revision`] doesn't match the revision
+//! they implement (see [`PROTOCOL_REVISION`]), or whose
+//! [`KernelNote::required_features`] contain any bit they don't know
+//! about, or any feature they cannot provide.
+
+/// The revision of the boot protocol as a whole.
+///
+/// Bumped whenever a change is made to the protocol that isn't
+/// covered by the revisioning of the individual requests.
+pub const PROTOCOL_REVISION: u64 = 0;
+
+/// The name (owner) of the kernel metadata note.
+pub const NOTE_NAME: [u8; 4] = *b"Oro\0";
+
+/// The type of the kernel metadata note.
+pub const KERNEL_NOTE_TYPE: u32 = 0x4F52_4F4B;
+
+/// The kernel requires an ACPI RSDP (see [`crate::AcpiRequest`]).
+pub const FEATURE_ACPI: u64 = 1 << 0;
+/// The kernel requires a device tree blob (see [`crate::DeviceTreeRequest`]).
+pub const FEATURE_DEVICE_TREE: u64 = 1 << 1;
+/// All currently defined feature bits.
+pub const KNOWN_FEATURES: u64 = FEATURE_ACPI | FEATURE_DEVICE_TREE;
+
+/// The payload of the kernel metadata note.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KernelNote {
+	/// The major version of the kernel.
+	pub version_major:     u16,
+	/// The minor version of the kernel.
+	pub version_minor:     u16,
+	/// The patch version of the kernel.
+	pub version_patch:     u16,
+	/// Reserved for future use. Must be zero.
+	pub reserved:          u16,
+	/// The boot protocol revision the kernel implements.
+	///
+	/// See [`PROTOCOL_REVISION`].
+	pub protocol_revision: u64,
+	/// A bitmask of `FEATURE_*` values that the kernel requires
+	/// of the bootloader.
+	pub required_features: u64,
+}
+
+impl KernelNote {
+	/// Creates a new kernel note for the current protocol revision
+	/// with the given `FEATURE_*` bits.
+	///
+	/// The kernel version is taken to be that of this crate, which is
+	/// versioned alongside the kernel.
+	#[must_use]
+	pub const fn new(required_features: u64) -> Self {
+		Self {
+			version_major: parse_version(env!("CARGO_PKG_VERSION_MAJOR")),
+			version_minor: parse_version(env!("CARGO_PKG_VERSION_MINOR")),
+			version_patch: parse_version(env!("CARGO_PKG_VERSION_PATCH")),
+			reserved: 0,
+			protocol_revision: PROTOCOL_REVISION,
+			required_features,
+		}
+	}
+}
+
+/// The header of an ELF note.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct NoteHeader {
+	/// The length of the name, including the NUL terminator.
+	pub name_length: u32,
+	/// The length of the payload.
+	pub payload_length: u32,
+	/// The type of the note.
+	pub ty: u32,
+}
+
+/// The complete kernel metadata note, as placed in the kernel's
+/// `.note.oro` section.
+///
+/// The kernel declares exactly one of these, as a `#[used]` static
+/// with `#[link_section = ".note.oro"]`.
+#[repr(C, align(8))]
+#[derive(Debug, Clone, Copy)]
+pub struct KernelNoteSection {
+	/// The note header.
+	pub header:  NoteHeader,
+	/// The note name; always [`NOTE_NAME`].
+	pub name:    [u8; 4],
+	/// The note payload.
+	pub payload: KernelNote,
+}
+
+impl KernelNoteSection {
+	/// Builds the kernel metadata note around the given payload.
+	#[must_use]
+	pub const fn new(payload: KernelNote) -> Self {
+		Self {
+			header: NoteHeader {
+				name_length: NOTE_NAME.len() as u32,
+				payload_length: ::core::mem::size_of::<KernelNote>() as u32,
+				ty: KERNEL_NOTE_TYPE,
+			},
+			name: NOTE_NAME,
+			payload,
+		}
+	}
+}
+
+const _: () = {
+	::oro_macro::assert::size_of::<NoteHeader, 12>();
+	::oro_macro::assert::size_of::<KernelNote, 24>();
+	::oro_macro::assert_offset_of!(KernelNoteSection, name, 12);
+	::oro_macro::assert_offset_of!(KernelNoteSection, payload, 16);
+};
+
+/// Parses a version component at compile time.
+///
+/// Invalid digits (which Cargo doesn't allow) are ignored.
+const fn parse_version(s: &str) -> u16 {
+	let bytes = s.as_bytes();
+	let mut value: u16 = 0;
+	let mut i = 0;
+	while i < bytes.len() {
+		if bytes[i].is_ascii_digit() {
+			value = value * 10 + (bytes[i] - b'0') as u16;
+		}
+		i += 1;
+	}
+	value
+}
@@ -4,8 +4,13 @@
 //! See the crate documentation for information on how to populate
 //! the kernel requests without using this module.
 use oro_macro::assert;
+/// CRC-32 checksums, shared with the kernel (see [`oro_type::crc32`]).
+pub use oro_type::crc32::{Crc32, crc32};
 
-use crate::{Request, RequestHeader, RequestTag, Tag};
+use crate::{
+	KNOWN_TAGS, Request, RequestHeader, RequestTag, Tag,
+	note::{KERNEL_NOTE_TYPE, KernelNote, NOTE_NAME, NoteHeader},
+};
 
 /// A scanner for scanning for the kernel's requests.
 ///
@@ -164,6 +169,30 @@ pub enum TrySendError {
 	},
 }
 
+/// The state of a request's `populated` flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Populated {
+	/// The request was not populated (`0x00`).
+	No,
+	/// The request was populated (`0xFF`).
+	Yes,
+	/// The flag holds neither `0x00` nor `0xFF`, indicating a buggy
+	/// bootloader or memory corruption. The response must not be trusted.
+	Invalid(u8),
+}
+
+impl Populated {
+	/// Interprets a raw `populated` value.
+	#[must_use]
+	pub const fn from_raw(raw: u8) -> Self {
+		match raw {
+			0x00 => Self::No,
+			0xFF => Self::Yes,
+			raw => Self::Invalid(raw),
+		}
+	}
+}
+
 /// An iterator over the requests in a request segment.
 pub struct RequestScannerIter<'a> {
 	/// The next pointer we'll attempt to read.
@@ -227,3 +256,578 @@ pub trait SetNext: crate::macros::Sealed + 'static {
 	/// Sets the next pointer to the given physical address.
 	fn set_next(&mut self, next: u64);
 }
+
+/// The byte order of the architecture a kernel was compiled for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+	/// Little-endian.
+	Little,
+	/// Big-endian.
+	Big,
+}
+
+impl Endian {
+	/// The byte order of the architecture this crate was compiled for.
+	pub const NATIVE: Self = if cfg!(target_endian = "big") {
+		Self::Big
+	} else {
+		Self::Little
+	};
+}
+
+/// Conversions between native integers and the byte order of the
+/// kernel being booted, for bootloaders whose endianness may differ
+/// from the kernel's.
+///
+/// Implemented for every integer type used by the protocol, including
+/// [`Tag`]. See the crate documentation for which fields are endian-sensitive.
+///
+/// > **Note**: `TAG` constants (e.g. [`crate::MemoryMapRequest::TAG`]) are
+/// > derived from their byte sequence, and thus already match the bytes
+/// > found in the kernel's memory when read natively; they must not be
+/// > converted.
+pub trait KernelEndian: Sized + Copy {
+	/// Converts a native value to the kernel's byte order.
+	#[must_use]
+	fn to_kernel_endian(self, target: Endian) -> Self;
+
+	/// Converts a value in the kernel's byte order to a native value.
+	#[must_use]
+	fn to_native_endian(self, target: Endian) -> Self;
+}
+
+/// Implements [`KernelEndian`] for the given integer types.
+macro_rules! impl_kernel_endian {
+	($($ty:ty),*) => {
+		$(
+			impl KernelEndian for $ty {
+				#[inline]
+				fn to_kernel_endian(self, target: Endian) -> Self {
+					match target {
+						Endian::Little => self.to_le(),
+						Endian::Big => self.to_be(),
+					}
+				}
+
+				#[inline]
+				fn to_native_endian(self, target: Endian) -> Self {
+					match target {
+						Endian::Little => Self::from_le(self),
+						Endian::Big => Self::from_be(self),
+					}
+				}
+			}
+		)*
+	};
+}
+
+impl_kernel_endian!(u16, u32, u64);
+
+// NOTE(qix-): The `magic` field deliberately has no endian-aware accessors;
+// NOTE(qix-): tags are compared as raw bytes, which are the same in either
+// NOTE(qix-): byte order.
+impl RequestHeader {
+	/// Reads the request's revision from a kernel with the given byte order.
+	#[must_use]
+	pub fn revision_for(&self, target: Endian) -> u64 {
+		// SAFETY(qix-): The reference is valid and aligned.
+		unsafe { ::core::ptr::read_volatile(::core::ptr::from_ref(&self.revision)) }
+			.to_native_endian(target)
+	}
+
+	/// Sets the request's revision for a kernel with the given byte order.
+	pub fn set_revision_for(&mut self, revision: u64, target: Endian) {
+		// SAFETY(qix-): The reference is valid and aligned.
+		unsafe {
+			::core::ptr::write_volatile(
+				::core::ptr::from_mut(&mut self.revision),
+				revision.to_kernel_endian(target),
+			);
+		}
+	}
+}
+
+/// The location of a request within a kernel ELF image,
+/// as found by [`find_requests()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestLocation {
+	/// The offset of the request's [`RequestHeader`] within the ELF image.
+	pub offset: usize,
+	/// The request's tag (see e.g. [`crate::MemoryMapRequest::TAG`]).
+	pub tag:    Tag,
+}
+
+/// Finds all requests in the given kernel ELF image, as described
+/// in the crate documentation.
+///
+/// Only the read-only `PT_LOAD` segment(s) with both the Oro kernel
+/// bit `(1 << 20)` and the boot protocol bit `(1 << 21)` raised are
+/// scanned; segments with the boot protocol bit but without the kernel
+/// bit (or that are writable or executable) are skipped. Tags are looked
+/// for on 16-byte boundaries (relative to the segment's virtual address)
+/// and only known tags are yielded; their revision is not checked.
+///
+/// Only 64-bit, architecture-endian ELF images are supported; for any
+/// other image (or a malformed one), nothing is yielded.
+pub fn find_requests(elf: &[u8]) -> impl Iterator<Item = RequestLocation> + '_ {
+	program_headers(elf)
+		.into_iter()
+		.flatten()
+		.filter(|phdr| {
+			phdr.ty == PT_LOAD
+				&& phdr.flags & (PF_X | PF_W | PF_R) == PF_R
+				&& phdr.flags & ORO_BOOT_PROTOCOL_FLAG != 0
+				&& phdr.flags & ORO_KERNEL_FLAG != 0
+		})
+		.flat_map(move |phdr| {
+			let align = ::core::mem::align_of::<RequestHeader>();
+			let first = phdr.vaddr.wrapping_neg() & (align as u64 - 1);
+			let first = usize::try_from(first).unwrap_or(usize::MAX);
+
+			let offset = usize::try_from(phdr.offset).unwrap_or(usize::MAX);
+			let size = usize::try_from(phdr.filesz).unwrap_or(0);
+			let end = offset.saturating_add(size).min(elf.len());
+
+			(offset.saturating_add(first)..end)
+				.step_by(align)
+				.filter_map(move |offset| {
+					let tag = read_u64(elf, offset)?;
+					KNOWN_TAGS
+						.contains(&tag)
+						.then_some(RequestLocation { offset, tag })
+				})
+		})
+}
+
+/// Finds and parses the kernel metadata note (see [`crate::note`])
+/// from the given kernel ELF image.
+///
+/// Only 64-bit, architecture-endian ELF images are supported; any
+/// other image (or a malformed one) is treated as having no note.
+///
+/// Returns `None` if the note could not be found.
+#[must_use]
+pub fn find_kernel_note(elf: &[u8]) -> Option<KernelNote> {
+	for phdr in program_headers(elf)? {
+		if phdr.ty != PT_NOTE {
+			continue;
+		}
+
+		let offset = usize::try_from(phdr.offset).ok()?;
+		let size = usize::try_from(phdr.filesz).ok()?;
+		let notes = elf.get(offset..offset.checked_add(size)?)?;
+
+		if let Some(note) = find_note_in(notes) {
+			return Some(note);
+		}
+	}
+
+	None
+}
+
+/// The ELF program header type for loadable segments.
+const PT_LOAD: u32 = 1;
+/// The ELF program header type for notes.
+const PT_NOTE: u32 = 4;
+/// The ELF program header flag for executable segments.
+const PF_X: u32 = 1 << 0;
+/// The ELF program header flag for writable segments.
+const PF_W: u32 = 1 << 1;
+/// The ELF program header flag for readable segments.
+const PF_R: u32 = 1 << 2;
+/// The OS-specific program header flag raised on all kernel segments.
+const ORO_KERNEL_FLAG: u32 = 1 << 20;
+/// The OS-specific program header flag raised on the requests segment.
+const ORO_BOOT_PROTOCOL_FLAG: u32 = 1 << 21;
+
+/// The fields of a 64-bit ELF program header used by the utilities.
+struct ProgramHeader {
+	/// The segment type.
+	ty:     u32,
+	/// The segment flags.
+	flags:  u32,
+	/// The offset of the segment within the ELF image.
+	offset: u64,
+	/// The virtual address at which the segment is loaded.
+	vaddr:  u64,
+	/// The size of the segment within the ELF image.
+	filesz: u64,
+}
+
+/// Returns an iterator over the program headers of the given ELF image,
+/// or `None` if it's not a 64-bit, architecture-endian ELF image.
+///
+/// Iteration stops early at the first truncated program header.
+fn program_headers(elf: &[u8]) -> Option<impl Iterator<Item = ProgramHeader> + '_> {
+	/// The size of a 64-bit ELF header.
+	const EHDR_SIZE: usize = 64;
+	/// The size of a 64-bit ELF program header.
+	const PHDR_SIZE: usize = 56;
+
+	#[cfg(target_endian = "little")]
+	const ELFDATA: u8 = 1;
+	#[cfg(target_endian = "big")]
+	const ELFDATA: u8 = 2;
+
+	if elf.len() < EHDR_SIZE || elf[..4] != *b"\x7FELF" || elf[4] != 2 || elf[5] != ELFDATA {
+		return None;
+	}
+
+	let phoff = usize::try_from(read_u64(elf, 0x20)?).ok()?;
+	let phentsize = usize::from(read_u16(elf, 0x36)?);
+	let phnum = usize::from(read_u16(elf, 0x38)?);
+
+	if phentsize < PHDR_SIZE {
+		return None;
+	}
+
+	Some((0..phnum).map_while(move |i| {
+		let phdr = phoff.checked_add(i.checked_mul(phentsize)?)?;
+		Some(ProgramHeader {
+			ty:     read_u32(elf, phdr)?,
+			flags:  read_u32(elf, phdr.checked_add(0x04)?)?,
+			offset: read_u64(elf, phdr.checked_add(0x08)?)?,
+			vaddr:  read_u64(elf, phdr.checked_add(0x10)?)?,
+			filesz: read_u64(elf, phdr.checked_add(0x20)?)?,
+		})
+	}))
+}
+
+/// Walks the notes in a `PT_NOTE` segment, looking for the
+/// kernel metadata note.
+fn find_note_in(mut notes: &[u8]) -> Option<KernelNote> {
+	let name_start = ::core::mem::size_of::<NoteHeader>();
+
+	while notes.len() >= name_start {
+		let name_length = usize::try_from(read_u32(notes, 0)?).ok()?;
+		let payload_length = usize::try_from(read_u32(notes, 4)?).ok()?;
+		let ty = read_u32(notes, 8)?;
+
+		let payload_start = name_start.checked_add(align4(name_length)?)?;
+		let next = payload_start.checked_add(align4(payload_length)?)?;
+
+		let name = notes.get(name_start..name_start + name_length)?;
+		let payload = notes.get(payload_start..payload_start + payload_length)?;
+
+		if ty == KERNEL_NOTE_TYPE
+			&& *name == NOTE_NAME
+			&& payload.len() >= ::core::mem::size_of::<KernelNote>()
+		{
+			// SAFETY(qix-): The payload is large enough, and `KernelNote`
+			// SAFETY(qix-): is plain data, valid for any bit pattern.
+			return Some(unsafe { payload.as_ptr().cast::<KernelNote>().read_unaligned() });
+		}
+
+		notes = notes.get(next..)?;
+	}
+
+	None
+}
+
+/// Aligns a note name or payload length up to 4 bytes.
+fn align4(len: usize) -> Option<usize> {
+	Some(len.checked_add(3)? & !3)
+}
+
+/// Reads an architecture-endian `u16` at the given offset.
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+	Some(u16::from_ne_bytes(
+		bytes.get(offset..offset.checked_add(2)?)?.try_into().ok()?,
+	))
+}
+
+/// Reads an architecture-endian `u32` at the given offset.
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+	Some(u32::from_ne_bytes(
+		bytes.get(offset..offset.checked_add(4)?)?.try_into().ok()?,
+	))
+}
+
+/// Reads an architecture-endian `u64` at the given offset.
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+	Some(u64::from_ne_bytes(
+		bytes.get(offset..offset.checked_add(8)?)?.try_into().ok()?,
+	))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{MemoryMapRequest, ModulesRequest};
+
+	/// The offset at which [`elf()`] places the segment data.
+	const DATA: usize = 0x200;
+
+	/// The flags of a well-formed requests segment.
+	const REQUESTS_FLAGS: u32 = PF_R | ORO_KERNEL_FLAG | ORO_BOOT_PROTOCOL_FLAG;
+
+	/// A program header, as `(type, flags, offset, vaddr, filesz)`.
+	type Phdr = (u32, u32, u64, u64, u64);
+
+	/// Builds a 64-bit, architecture-endian ELF image with the given
+	/// program headers, followed by `data` at offset [`DATA`].
+	fn elf(phdrs: &[Phdr], data: &[u8]) -> Vec<u8> {
+		#[cfg(target_endian = "little")]
+		const ELFDATA: u8 = 1;
+		#[cfg(target_endian = "big")]
+		const ELFDATA: u8 = 2;
+
+		let mut elf = vec![0; DATA + data.len()];
+		elf[..4].copy_from_slice(b"\x7FELF");
+		elf[4] = 2;
+		elf[5] = ELFDATA;
+		elf[0x20..0x28].copy_from_slice(&64_u64.to_ne_bytes());
+		elf[0x36..0x38].copy_from_slice(&56_u16.to_ne_bytes());
+		elf[0x38..0x3A].copy_from_slice(&(phdrs.len() as u16).to_ne_bytes());
+
+		for (i, &(ty, flags, offset, vaddr, filesz)) in phdrs.iter().enumerate() {
+			let phdr = 64 + i * 56;
+			elf[phdr..phdr + 4].copy_from_slice(&ty.to_ne_bytes());
+			elf[phdr + 0x04..phdr + 0x08].copy_from_slice(&flags.to_ne_bytes());
+			elf[phdr + 0x08..phdr + 0x10].copy_from_slice(&offset.to_ne_bytes());
+			elf[phdr + 0x10..phdr + 0x18].copy_from_slice(&vaddr.to_ne_bytes());
+			elf[phdr + 0x20..phdr + 0x28].copy_from_slice(&filesz.to_ne_bytes());
+		}
+
+		elf[DATA..].copy_from_slice(data);
+		elf
+	}
+
+	/// Builds `len` bytes of segment data with the given tags
+	/// placed at the given offsets.
+	fn segment(len: usize, tags: &[(usize, Tag)]) -> Vec<u8> {
+		let mut data = vec![0; len];
+		for &(offset, tag) in tags {
+			data[offset..offset + 8].copy_from_slice(&tag.to_ne_bytes());
+		}
+		data
+	}
+
+	#[test]
+	fn test_find_requests_match() {
+		let data = segment(64, &[(16, MemoryMapRequest::TAG), (32, 0x1234)]);
+		let elf = elf(&[(PT_LOAD, REQUESTS_FLAGS, DATA as u64, 0x1000, 64)], &data);
+
+		assert_eq!(
+			find_requests(&elf).collect::<Vec<_>>(),
+			[RequestLocation {
+				offset: DATA + 16,
+				tag:    MemoryMapRequest::TAG,
+			}]
+		);
+	}
+
+	#[test]
+	fn test_find_requests_skips_other_segments() {
+		let data = segment(64, &[(0, MemoryMapRequest::TAG)]);
+
+		for flags in [
+			PF_R | ORO_BOOT_PROTOCOL_FLAG,
+			PF_R | ORO_KERNEL_FLAG,
+			REQUESTS_FLAGS | PF_W,
+			REQUESTS_FLAGS | PF_X,
+		] {
+			let elf = elf(&[(PT_LOAD, flags, DATA as u64, 0x1000, 64)], &data);
+			assert_eq!(find_requests(&elf).count(), 0, "flags={flags:#X}");
+		}
+
+		let elf = elf(&[(PT_NOTE, REQUESTS_FLAGS, DATA as u64, 0x1000, 64)], &data);
+		assert_eq!(find_requests(&elf).count(), 0);
+	}
+
+	#[test]
+	fn test_find_requests_misaligned() {
+		// Tags are only looked for on 16-byte boundaries.
+		let data = segment(64, &[(8, MemoryMapRequest::TAG)]);
+		let aligned = elf(&[(PT_LOAD, REQUESTS_FLAGS, DATA as u64, 0x1000, 64)], &data);
+		assert_eq!(find_requests(&aligned).count(), 0);
+
+		// Boundaries are relative to the segment's virtual address,
+		// not its offset within the image.
+		let unaligned = elf(&[(PT_LOAD, REQUESTS_FLAGS, DATA as u64, 0x1008, 64)], &data);
+		assert_eq!(
+			find_requests(&unaligned).collect::<Vec<_>>(),
+			[RequestLocation {
+				offset: DATA + 8,
+				tag:    MemoryMapRequest::TAG,
+			}]
+		);
+	}
+
+	#[test]
+	fn test_find_requests_truncated() {
+		// The segment claims to extend past the end of the image.
+		let data = segment(32, &[(16, MemoryMapRequest::TAG)]);
+		let elf_image = elf(
+			&[(PT_LOAD, REQUESTS_FLAGS, DATA as u64, 0x1000, 4096)],
+			&data,
+		);
+		assert_eq!(
+			find_requests(&elf_image).collect::<Vec<_>>(),
+			[RequestLocation {
+				offset: DATA + 16,
+				tag:    MemoryMapRequest::TAG,
+			}]
+		);
+
+		// A tag cut off by the end of the image isn't read.
+		assert_eq!(find_requests(&elf_image[..DATA + 20]).count(), 0);
+
+		// Program headers cut off by the end of the image are ignored.
+		assert_eq!(find_requests(&elf_image[..64 + 40]).count(), 0);
+		assert_eq!(find_requests(&elf_image[..63]).count(), 0);
+	}
+
+	#[test]
+	fn test_find_requests_duplicates() {
+		let data = segment(
+			64,
+			&[
+				(0, MemoryMapRequest::TAG),
+				(16, ModulesRequest::TAG),
+				(48, MemoryMapRequest::TAG),
+			],
+		);
+		let elf = elf(&[(PT_LOAD, REQUESTS_FLAGS, DATA as u64, 0x1000, 64)], &data);
+
+		assert_eq!(
+			find_requests(&elf)
+				.map(|r| (r.offset, r.tag))
+				.collect::<Vec<_>>(),
+			[
+				(DATA, MemoryMapRequest::TAG),
+				(DATA + 16, ModulesRequest::TAG),
+				(DATA + 48, MemoryMapRequest::TAG),
+			]
+		);
+	}
+
+	#[test]
+	fn test_program_headers() {
+		let phdrs = [
+			(PT_NOTE, PF_R, 0x300, 0, 0x10),
+			(PT_LOAD, REQUESTS_FLAGS, 0x310, 0x2000, 0x20),
+		];
+		let elf = elf(&phdrs, &[]);
+
+		let parsed = program_headers(&elf)
+			.unwrap()
+			.map(|phdr| (phdr.ty, phdr.flags, phdr.offset, phdr.vaddr, phdr.filesz))
+			.collect::<Vec<_>>();
+		assert_eq!(parsed, phdrs);
+
+		// Truncated program headers end the iteration.
+		assert_eq!(program_headers(&elf[..64 + 56 + 0x24]).unwrap().count(), 1);
+	}
+
+	#[test]
+	fn test_program_headers_rejects() {
+		let valid = elf(&[(PT_LOAD, REQUESTS_FLAGS, 0, 0, 0)], &[]);
+		assert!(program_headers(&valid).is_some());
+
+		let mut bad_magic = valid.clone();
+		bad_magic[1] = b'X';
+		assert!(program_headers(&bad_magic).is_none());
+
+		let mut elf32 = valid.clone();
+		elf32[4] = 1;
+		assert!(program_headers(&elf32).is_none());
+
+		let mut wrong_endian = valid.clone();
+		wrong_endian[5] = 3 - wrong_endian[5];
+		assert!(program_headers(&wrong_endian).is_none());
+
+		let mut small_phdrs = valid.clone();
+		small_phdrs[0x36..0x38].copy_from_slice(&32_u16.to_ne_bytes());
+		assert!(program_headers(&small_phdrs).is_none());
+
+		assert!(program_headers(&valid[..63]).is_none());
+	}
+
+	/// Builds an ELF note with the given name, type and payload.
+	fn note(name: &[u8], ty: u32, payload: &[u8]) -> Vec<u8> {
+		let mut note = Vec::new();
+		note.extend_from_slice(&(name.len() as u32).to_ne_bytes());
+		note.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+		note.extend_from_slice(&ty.to_ne_bytes());
+		note.extend_from_slice(name);
+		note.resize(note.len().next_multiple_of(4), 0);
+		note.extend_from_slice(payload);
+		note.resize(note.len().next_multiple_of(4), 0);
+		note
+	}
+
+	/// Returns the architecture-endian bytes of the given kernel note payload.
+	fn payload(kernel_note: &KernelNote) -> Vec<u8> {
+		let mut payload = Vec::new();
+		payload.extend_from_slice(&kernel_note.version_major.to_ne_bytes());
+		payload.extend_from_slice(&kernel_note.version_minor.to_ne_bytes());
+		payload.extend_from_slice(&kernel_note.version_patch.to_ne_bytes());
+		payload.extend_from_slice(&kernel_note.reserved.to_ne_bytes());
+		payload.extend_from_slice(&kernel_note.protocol_revision.to_ne_bytes());
+		payload.extend_from_slice(&kernel_note.required_features.to_ne_bytes());
+		payload
+	}
+
+	/// Builds an ELF image with a single `PT_NOTE` segment holding `notes`.
+	fn note_elf(notes: &[u8]) -> Vec<u8> {
+		elf(
+			&[(PT_NOTE, PF_R, DATA as u64, 0, notes.len() as u64)],
+			notes,
+		)
+	}
+
+	#[test]
+	fn test_find_kernel_note() {
+		let kernel_note = KernelNote::new(crate::note::FEATURE_ACPI);
+
+		let mut notes = note(b"GNU\0", 3, &[0xAA; 20]);
+		notes.extend(note(&NOTE_NAME, KERNEL_NOTE_TYPE, &payload(&kernel_note)));
+
+		assert_eq!(find_kernel_note(&note_elf(&notes)), Some(kernel_note));
+
+		// Payloads may grow in the future; trailing fields are ignored.
+		let mut longer = payload(&kernel_note);
+		longer.extend_from_slice(&[0xBB; 8]);
+		let notes = note(&NOTE_NAME, KERNEL_NOTE_TYPE, &longer);
+		assert_eq!(find_kernel_note(&note_elf(&notes)), Some(kernel_note));
+	}
+
+	#[test]
+	fn test_find_kernel_note_wrong_owner() {
+		let payload = payload(&KernelNote::new(0));
+
+		let notes = note(b"GNU\0", KERNEL_NOTE_TYPE, &payload);
+		assert_eq!(find_kernel_note(&note_elf(&notes)), None);
+
+		let notes = note(&NOTE_NAME, KERNEL_NOTE_TYPE + 1, &payload);
+		assert_eq!(find_kernel_note(&note_elf(&notes)), None);
+
+		// A note in a non-`PT_NOTE` segment isn't looked at.
+		let notes = note(&NOTE_NAME, KERNEL_NOTE_TYPE, &payload);
+		let elf = elf(
+			&[(PT_LOAD, PF_R, DATA as u64, 0, notes.len() as u64)],
+			&notes,
+		);
+		assert_eq!(find_kernel_note(&elf), None);
+	}
+
+	#[test]
+	fn test_find_kernel_note_truncated() {
+		let payload = payload(&KernelNote::new(0));
+
+		// The payload is too short to hold a kernel note.
+		let notes = note(&NOTE_NAME, KERNEL_NOTE_TYPE, &payload[..16]);
+		assert_eq!(find_kernel_note(&note_elf(&notes)), None);
+
+		// The note claims a payload longer than the segment.
+		let mut notes = note(&NOTE_NAME, KERNEL_NOTE_TYPE, &payload);
+		notes[4..8].copy_from_slice(&64_u32.to_ne_bytes());
+		assert_eq!(find_kernel_note(&note_elf(&notes)), None);
+
+		// The segment extends past the end of the image.
+		let notes = note(&NOTE_NAME, KERNEL_NOTE_TYPE, &payload);
+		let elf = note_elf(&notes);
+		assert_eq!(find_kernel_note(&elf[..elf.len() - 1]), None);
+	}
+}
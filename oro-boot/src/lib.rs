@@ -48,6 +48,9 @@ pub enum Error {
 	MapError(MapError),
 	/// An error occurred when parsing the kernel ELF file.
 	ElfError(oro_elf::ElfError),
+	/// The provided kernel ELF file is position-independent (`ET_DYN`);
+	/// the kernel must be linked at a fixed address.
+	RelocatableKernel,
 	/// The provided kernel ELF file has no kernel segments.
 	NoKernelSegments,
 	/// The provided kernel ELF file has an invalid segment.
@@ -56,6 +59,12 @@ pub enum Error {
 	MultipleKernelRequestSegments,
 	/// The kernel module has no kernel request segment.
 	NoKernelRequestSegment,
+	/// The kernel module has no kernel metadata note.
+	NoKernelNote,
+	/// The kernel implements a different boot protocol revision.
+	IncompatibleProtocolRevision(u64),
+	/// The kernel requires features unknown to the bootstrapper.
+	UnknownKernelFeatures(u64),
 }
 
 /// The bootstrapper result type.
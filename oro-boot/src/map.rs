@@ -4,7 +4,7 @@
 use oro_debug::dbg;
 use oro_elf::{Elf, ElfSegment, ElfSegmentType};
 use oro_mem::{
-	mapper::{AddressSegment, AddressSpace, MapError, UnmapError},
+	mapper::{AddressSegment, AddressSpace, MapError},
 	phys::{Phys, PhysAddr},
 };
 
@@ -37,6 +37,41 @@ pub fn map_kernel_to_supervisor_space<
 		.map_err(crate::Error::ElfError)?
 	};
 
+	if kernel_elf.is_relocatable() {
+		return Err(crate::Error::RelocatableKernel);
+	}
+
+	// Reject incompatible kernels before mapping anything.
+	// SAFETY(qix-): We can assume the kernel module is valid given that it's
+	// SAFETY(qix-): been loaded by the bootloader.
+	let kernel_note = oro_boot_protocol::util::find_kernel_note(unsafe {
+		core::slice::from_raw_parts(
+			Phys::from_address_unchecked(kernel_module.base).as_ptr_unchecked::<u8>(),
+			usize::try_from(kernel_module.length).unwrap(),
+		)
+	})
+	.ok_or(crate::Error::NoKernelNote)?;
+
+	if kernel_note.protocol_revision != oro_boot_protocol::note::PROTOCOL_REVISION {
+		return Err(crate::Error::IncompatibleProtocolRevision(
+			kernel_note.protocol_revision,
+		));
+	}
+
+	let unknown_features = kernel_note.required_features & !oro_boot_protocol::note::KNOWN_FEATURES;
+	if unknown_features != 0 {
+		return Err(crate::Error::UnknownKernelFeatures(unknown_features));
+	}
+
+	dbg!(
+		"kernel v{}.{}.{} (boot protocol r{}, features {:#X})",
+		kernel_note.version_major,
+		kernel_note.version_minor,
+		kernel_note.version_patch,
+		kernel_note.protocol_revision,
+		kernel_note.required_features,
+	);
+
 	let num_segments = kernel_elf.segments().count();
 	if num_segments == 0 {
 		return Err(crate::Error::NoKernelSegments);
@@ -113,7 +148,7 @@ pub fn map_kernel_to_supervisor_space<
 				mapper_segment.map_nofree_in(supervisor_space, pfa, target_virt, phys_addr)
 			{
 				panic!(
-					"failed to map kernel segment: {err:?}: ls={load_size} p={page} po={page:X?} \
+					"failed to map kernel segment: {err}: ls={load_size} p={page} po={page:X?} \
 					 lv={load_virt:#016X} tv={target_virt:#016X} s={segment:016X?}"
 				);
 			}
@@ -156,14 +191,12 @@ pub fn map_kernel_stack<
 		>>::range(&kernel_stack_segment)
 		.1 & !0xFFF;
 
-	// make sure top guard page is unmapped
-	match kernel_stack_segment.unmap_in(supervisor_space, pfa, last_stack_page_virt) {
-		// NOTE(qix-): The Ok() case would never hit here since the PFA doesn't support
-		// NOTE(qix-): freeing pages.
-		Ok(_) => unreachable!(),
-		Err(UnmapError::NotMapped) => {}
-		// NOTE(qix-): Should never happen.
-		Err(e) => panic!("failed to test unmap of top kernel stack guard page: {e:?}"),
+	// Make sure the top guard page is unmapped.
+	// NOTE(qix-): Should never fail.
+	if let Err(e) =
+		kernel_stack_segment.assert_guard_unmapped_in(supervisor_space, pfa, last_stack_page_virt)
+	{
+		panic!("kernel stack top guard page check failed: {e}");
 	}
 
 	let mut bottom_stack_page_virt = last_stack_page_virt;
@@ -179,14 +212,14 @@ pub fn map_kernel_stack<
 			.map_err(crate::Error::MapError)?;
 	}
 
-	// Make sure that the bottom guard page is unmapped
-	match kernel_stack_segment.unmap_in(supervisor_space, pfa, bottom_stack_page_virt - 4096) {
-		// NOTE(qix-): The Ok() case would never hit here since the PFA doesn't support
-		// NOTE(qix-): freeing pages.
-		Ok(_) => unreachable!(),
-		Err(UnmapError::NotMapped) => {}
-		// NOTE(qix-): Should never happen.
-		Err(e) => panic!("failed to test unmap of kernel bottom stack guard page: {e:?}"),
+	// Make sure that the bottom guard page is unmapped.
+	// NOTE(qix-): Should never fail.
+	if let Err(e) = kernel_stack_segment.assert_guard_unmapped_in(
+		supervisor_space,
+		pfa,
+		bottom_stack_page_virt - 4096,
+	) {
+		panic!("kernel stack bottom guard page check failed: {e}");
 	}
 
 	Ok(last_stack_page_virt)
@@ -212,6 +212,20 @@ unsafe impl<M: Into<OroMemRe> + Clone, I: Iterator<Item = M> + Clone> oro_mem::p
 	unsafe fn free(&mut self, _frame: u64) {
 		panic!("preboot PFA cannot free frames");
 	}
+
+	fn free_page_count(&self) -> usize {
+		self.total_page_count()
+			.saturating_sub((self.used >> 12) as usize)
+	}
+
+	fn total_page_count(&self) -> usize {
+		self.original_iter
+			.clone()
+			.map(Into::into)
+			.filter(|region: &OroMemRe| region.ty == OroMemTy::Usable)
+			.map(|region| (region.length >> 12) as usize)
+			.sum()
+	}
 }
 
 #[doc(hidden)]
@@ -110,17 +110,34 @@ impl PL011 {
 		stop_bits: StopBits,
 		parity: Parity,
 	) -> Self {
-		let s = Self {
+		let s = Self::attach(base, base_clock, baud_rate, data_bits, stop_bits, parity);
+		s.reset();
+		s
+	}
+
+	/// Creates a driver for a PL011 UART at the given base address
+	/// **without** resetting it, e.g. to write to a UART that was
+	/// already configured by another driver instance.
+	///
+	/// # Safety
+	/// The same requirements as [`Self::new()`] apply.
+	#[must_use]
+	pub unsafe fn attach(
+		base: usize,
+		base_clock: u32,
+		baud_rate: u32,
+		data_bits: DataBits,
+		stop_bits: StopBits,
+		parity: Parity,
+	) -> Self {
+		Self {
 			registers: base as *const RegisterBlock,
 			base_clock,
 			baud_rate,
 			data_bits,
 			stop_bits,
 			parity,
-		};
-
-		s.reset();
-		s
+		}
 	}
 
 	/// Resets the UART
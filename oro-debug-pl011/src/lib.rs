@@ -8,7 +8,10 @@
 
 mod driver;
 
-use core::fmt::{self, Write};
+use core::{
+	fmt::{self, Write},
+	sync::atomic::{AtomicUsize, Ordering::Relaxed},
+};
 
 use oro_sync::{Lock, TicketMutex};
 
@@ -17,6 +20,10 @@ use oro_sync::{Lock, TicketMutex};
 // NOTE(qix-): is implemented.
 static SERIAL: TicketMutex<Option<driver::PL011>> = TicketMutex::new(None);
 
+/// The base address of the initialized PL011, or `0` if
+/// it hasn't been initialized. Used by [`log_unlocked()`].
+static BASE: AtomicUsize = AtomicUsize::new(0);
+
 /// Initializes the PL011.
 pub fn init(offset: usize) {
 	// SAFETY(qix-): This is more or less safe, even if called multiple times.
@@ -34,6 +41,8 @@ pub fn init(offset: usize) {
 			driver::Parity::None,
 		));
 	}
+
+	BASE.store(0x900_0000 + offset, Relaxed);
 }
 
 /// Logs a message to the PL011.
@@ -46,3 +55,42 @@ pub fn log(message: fmt::Arguments) {
 	}
 	.unwrap();
 }
+
+/// Attempts to log a message to the PL011, returning `false` (and
+/// dropping the message) if the serial port is currently in use.
+#[must_use]
+pub fn try_log(message: fmt::Arguments) -> bool {
+	let Some(mut serial) = SERIAL.try_lock() else {
+		return false;
+	};
+
+	if let Some(serial) = serial.as_mut() {
+		let _ = writeln!(serial, "{message}");
+	}
+
+	true
+}
+
+/// Logs a message to the PL011 without taking the serial port's lock.
+///
+/// # Safety
+/// Meant only for paths that cannot risk deadlocking on the lock
+/// (e.g. a panic that may have occurred while it was held). Output
+/// may interleave with that of any other core that is still logging.
+pub unsafe fn log_unlocked(message: fmt::Arguments) {
+	let base = BASE.load(Relaxed);
+	if base == 0 {
+		return;
+	}
+
+	let mut serial = driver::PL011::attach(
+		base,
+		24_000_000,
+		115_200,
+		driver::DataBits::Eight,
+		driver::StopBits::One,
+		driver::Parity::None,
+	);
+
+	let _ = writeln!(serial, "{message}");
+}
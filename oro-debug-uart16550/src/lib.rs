@@ -19,3 +19,25 @@ pub fn init() {
 pub fn log(message: fmt::Arguments) {
 	writeln!(SERIAL.lock(), "{message}").unwrap();
 }
+
+/// Attempts to log a message to the UART, returning `false` (and
+/// dropping the message) if the serial port is currently in use.
+#[must_use]
+pub fn try_log(message: fmt::Arguments) -> bool {
+	let Some(mut serial) = SERIAL.try_lock() else {
+		return false;
+	};
+
+	let _ = writeln!(serial, "{message}");
+	true
+}
+
+/// Logs a message to the UART without taking the serial port's lock.
+///
+/// # Safety
+/// Meant only for paths that cannot risk deadlocking on the lock
+/// (e.g. a panic that may have occurred while it was held). Output
+/// may interleave with that of any other core that is still logging.
+pub unsafe fn log_unlocked(message: fmt::Arguments) {
+	let _ = writeln!(SerialPort::new(0x3F8), "{message}");
+}
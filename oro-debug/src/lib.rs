@@ -9,6 +9,16 @@
 //! if used improperly.
 #![cfg_attr(not(test), no_std)]
 
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering::Relaxed};
+
+use oro_sync::AtomicFnPtr;
+
+/// The installed clock, if any.
+static CLOCK: AtomicFnPtr<Clock> = AtomicFnPtr::new();
+
+/// Reads the current time, in ticks, of the clock used by [`dbg_ratelimited!`].
+pub type Clock = fn() -> u64;
+
 /// Initializes the debug logger with a linear map offset, if one is enabled.
 ///
 /// The linear offset is used for debugging backends that use MMIO
@@ -41,6 +51,90 @@ pub fn log(message: core::fmt::Arguments) {
 	oro_debug_uart16550::log(message);
 }
 
+/// Attempts to log a message to the debug logger, without blocking
+/// if it's currently in use.
+///
+/// Returns `false` if the message was dropped as a result. Always
+/// returns `true` if no debug logger is enabled.
+#[allow(unused_variables)]
+#[must_use]
+pub fn try_log(message: core::fmt::Arguments) -> bool {
+	#[cfg(all(target_arch = "aarch64", feature = "pl011"))]
+	let logged = oro_debug_pl011::try_log(message);
+	#[cfg(all(target_arch = "x86_64", feature = "uart16550"))]
+	let logged = oro_debug_uart16550::try_log(message);
+	#[cfg(not(any(
+		all(target_arch = "aarch64", feature = "pl011"),
+		all(target_arch = "x86_64", feature = "uart16550")
+	)))]
+	let logged = true;
+
+	logged
+}
+
+/// Logs a message to the debug logger without taking any of
+/// its locks.
+///
+/// # Safety
+/// Meant only for the panic path, which cannot risk deadlocking
+/// on a lock that may already be held (e.g. by the very core that
+/// panicked, if it panicked while logging). Output may interleave
+/// with that of any other core that is still logging.
+#[allow(unused_variables)]
+pub unsafe fn log_unlocked(message: core::fmt::Arguments) {
+	#[cfg(all(target_arch = "aarch64", feature = "pl011"))]
+	oro_debug_pl011::log_unlocked(message);
+	#[cfg(all(target_arch = "x86_64", feature = "uart16550"))]
+	oro_debug_uart16550::log_unlocked(message);
+}
+
+/// Installs the clock used by [`dbg_ratelimited!`].
+///
+/// The clock must be monotonic and synchronized across all cores; the
+/// length of a tick is up to the installer. Until a clock is installed,
+/// rate-limited messages are only ever logged once.
+pub fn install_clock(clock: Clock) {
+	CLOCK.store(clock);
+}
+
+/// Returns whether a rate-limited message should be logged, given
+/// the tick at which it was last logged (or `u64::MAX` if never),
+/// updating it if so.
+///
+/// Shouldn't be used directly; use [`dbg_ratelimited!`] instead.
+#[doc(hidden)]
+#[must_use]
+pub fn __ratelimit(last: &AtomicU64, interval: u64) -> bool {
+	let Some(clock) = CLOCK.load() else {
+		return last.compare_exchange(u64::MAX, 0, Relaxed, Relaxed).is_ok();
+	};
+	let now = clock();
+
+	// NOTE(qix-): Only the core that wins the exchange logs the message;
+	// NOTE(qix-): any others re-check against the timestamp it stored.
+	let mut previous = last.load(Relaxed);
+	loop {
+		if previous != u64::MAX && now.wrapping_sub(previous) < interval {
+			return false;
+		}
+
+		match last.compare_exchange_weak(previous, now, Relaxed, Relaxed) {
+			Ok(_) => return true,
+			Err(actual) => previous = actual,
+		}
+	}
+}
+
+/// Returns whether a one-time message should be logged, updating
+/// the flag if so.
+///
+/// Shouldn't be used directly; use [`dbg_once!`] instead.
+#[doc(hidden)]
+#[must_use]
+pub fn __once(logged: &AtomicBool) -> bool {
+	!logged.swap(true, Relaxed)
+}
+
 /// Sends a general debug message to the archiecture-specific debug endpoint.
 #[macro_export]
 #[collapse_debuginfo(yes)]
@@ -67,3 +161,45 @@ macro_rules! dbg_warn {
 		$crate::log(format_args!("{}:{}:W:{}", ::core::file!(), ::core::line!(), format_args!($($arg)*)));
 	}};
 }
+
+/// Sends a warning debug message to the archiecture-specific debug endpoint,
+/// dropping it rather than waiting if the endpoint is currently in use.
+///
+/// Meant for paths that run after the other cores have been halted, any
+/// of which may have been halted while holding the endpoint's lock.
+#[macro_export]
+#[collapse_debuginfo(yes)]
+macro_rules! dbg_warn_try {
+	($($arg:tt)*) => {{
+		let _ = $crate::try_log(format_args!("{}:{}:W:{}", ::core::file!(), ::core::line!(), format_args!($($arg)*)));
+	}};
+}
+
+/// Sends a general debug message to the archiecture-specific debug endpoint,
+/// only the first time the call site is reached.
+#[macro_export]
+#[collapse_debuginfo(yes)]
+macro_rules! dbg_once {
+	($($arg:tt)*) => {{
+		static LOGGED: ::core::sync::atomic::AtomicBool = ::core::sync::atomic::AtomicBool::new(false);
+		if $crate::__once(&LOGGED) {
+			$crate::dbg!($($arg)*);
+		}
+	}};
+}
+
+/// Sends a general debug message to the archiecture-specific debug endpoint,
+/// at most once every `interval_ticks` ticks (of the clock installed via
+/// [`install_clock()`]) per call site. Messages in between are dropped.
+///
+/// Behaves like [`dbg_once!`] until a clock is installed.
+#[macro_export]
+#[collapse_debuginfo(yes)]
+macro_rules! dbg_ratelimited {
+	($interval_ticks:expr, $($arg:tt)*) => {{
+		static LAST: ::core::sync::atomic::AtomicU64 = ::core::sync::atomic::AtomicU64::new(u64::MAX);
+		if $crate::__ratelimit(&LAST, $interval_ticks) {
+			$crate::dbg!($($arg)*);
+		}
+	}};
+}
@@ -139,8 +139,9 @@ impl Elf {
 		/// Validate arch-specific fields.
 		macro_rules! validate_arch_header {
 			($hdr:expr, $end_excl:expr) => {{
-				// 2 == `ET_EXEC`, which is the only thing we support.
-				if $hdr.ty != 2 {
+				// 2 == `ET_EXEC` and 3 == `ET_DYN` (position-independent),
+				// which are the only things we support.
+				if !matches!($hdr.ty, 2 | 3) {
 					return Err(ElfError::NotExecutable($hdr.ty));
 				}
 
@@ -201,6 +202,20 @@ impl Elf {
 		}
 	}
 
+	/// Returns whether or not the ELF file is position-independent
+	/// (`ET_DYN`), and must thus be relocated to wherever it's loaded.
+	///
+	/// Addresses in relocatable ELF files (e.g. segments' target addresses
+	/// and the entry point) are relative to the load base.
+	#[inline]
+	#[must_use]
+	pub fn is_relocatable(&self) -> bool {
+		match self.ident.class {
+			ElfClass::Class32 => unsafe { self.endian.elf32.ty == 3 },
+			ElfClass::Class64 => unsafe { self.endian.elf64.ty == 3 },
+		}
+	}
+
 	/// Returns the entry point of the ELF file.
 	#[inline]
 	#[must_use]
@@ -409,6 +424,11 @@ impl ElfSegment for ElfSegmentHeader<'_> {
 			ElfSegmentHeader::Elf64(_, hdr) => (hdr.flags, hdr.ty),
 		};
 
+		// 2 == `PT_DYNAMIC`.
+		if ptype == 2 {
+			return ElfSegmentType::Dynamic;
+		}
+
 		if ptype != 1 {
 			return ElfSegmentType::Ignored;
 		}
@@ -524,6 +544,8 @@ pub enum ElfSegmentType {
 	ModuleData,
 	/// Module read-only data segment
 	ModuleRoData,
+	/// Dynamic linking information (`PT_DYNAMIC`)
+	Dynamic,
 }
 
 impl ElfSegmentType {
@@ -653,6 +675,18 @@ pub enum ElfMachine {
 	Aarch64 = 0xB7,
 }
 
+impl ElfMachine {
+	/// Returns the machine's relative relocation type (e.g.
+	/// `R_X86_64_RELATIVE`), which adjusts a word by the load base.
+	#[must_use]
+	pub fn relative_relocation(self) -> u32 {
+		match self {
+			Self::X86_64 => 8,
+			Self::Aarch64 => 1027,
+		}
+	}
+}
+
 /// A program header for 32-bit ELF files.
 #[derive(Debug, Clone, Copy)]
 #[repr(C, align(4))]
@@ -739,7 +773,8 @@ pub enum ElfError {
 		/// The expected ELF machine
 		expected: ElfMachine,
 	},
-	/// The ELF file is not executable.
+	/// The ELF file is neither executable (`ET_EXEC`) nor
+	/// position-independent (`ET_DYN`).
 	NotExecutable(u16),
 	/// The program header offset is out of bounds for the ELF file.
 	ProgHeaderOffsetOutOfBounds,
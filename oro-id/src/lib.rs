@@ -74,7 +74,7 @@ use core::{fmt, marker::ConstParamTy, str::FromStr};
 /// Those wishing to manage databases, registries, or other systems
 /// that require a null ID are welcome to use the null ID for whatever
 /// purpose they see fit, as long as it is never served to the kernel.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct Id<const TY: IdType>([u8; 16]);
 
 /// Represents an unknown type ID.
@@ -83,7 +83,7 @@ pub struct Id<const TY: IdType>([u8; 16]);
 /// where the type is not known until parsing.
 ///
 /// For more information on the ID format, see [`Id`].
-#[derive(Debug, PartialEq, Eq, Copy, Clone, ConstParamTy)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, ConstParamTy)]
 pub struct AnyId([u8; 16]);
 
 /// An ID type.
@@ -94,7 +94,7 @@ pub struct AnyId([u8; 16]);
 /// All other ID types are reserved for the Oro ecosystem
 /// and **should not** be used for any purpose (and will be
 /// rejected by the kernel for all operations).
-#[derive(Debug, PartialEq, Eq, Copy, Clone, ConstParamTy)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, ConstParamTy)]
 #[non_exhaustive]
 #[repr(u8)]
 pub enum IdType {
@@ -370,11 +370,10 @@ impl AnyId {
 
 	/// Returns a reference to the raw byte array.
 	///
-	/// # Safety
-	/// The caller must ensure that the type identifier
-	/// is valid before using this method.
+	/// The type identifier is **not** checked; the bytes
+	/// are returned as-is.
 	#[must_use]
-	pub unsafe fn as_bytes(&self) -> &[u8; 16] {
+	pub fn as_bytes(&self) -> &[u8; 16] {
 		&self.0
 	}
 
@@ -551,6 +550,22 @@ impl<const TY: IdType> FromStr for Id<TY> {
 	}
 }
 
+impl TryFrom<&str> for AnyId {
+	type Error = ParseIdError;
+
+	fn try_from(s: &str) -> Result<Self, Self::Error> {
+		s.parse()
+	}
+}
+
+impl<const TY: IdType> TryFrom<&str> for Id<TY> {
+	type Error = ParseIdError;
+
+	fn try_from(s: &str) -> Result<Self, Self::Error> {
+		s.parse()
+	}
+}
+
 impl<const TY: IdType> TryFrom<AnyId> for Id<TY> {
 	type Error = ();
 
@@ -570,6 +585,32 @@ impl<const TY: IdType> From<Id<TY>> for AnyId {
 	}
 }
 
+// NOTE(qix-): The type identifier lives in the first byte, so comparing
+// NOTE(qix-): all 16 bytes never equates IDs of different types.
+impl<const TY: IdType> PartialEq<AnyId> for Id<TY> {
+	fn eq(&self, other: &AnyId) -> bool {
+		self.0 == other.0
+	}
+}
+
+impl<const TY: IdType> PartialEq<Id<TY>> for AnyId {
+	fn eq(&self, other: &Id<TY>) -> bool {
+		self.0 == other.0
+	}
+}
+
+impl<const TY: IdType> AsRef<[u8]> for Id<TY> {
+	fn as_ref(&self) -> &[u8] {
+		&self.0
+	}
+}
+
+impl AsRef<[u8]> for AnyId {
+	fn as_ref(&self) -> &[u8] {
+		&self.0
+	}
+}
+
 impl<const TY: IdType> fmt::Display for Id<TY> {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		let mut buf = [0; 27];
@@ -0,0 +1,81 @@
+//! Houses the [`CoreId`] type, which identifies a single core
+//! (CPU) to the kernel.
+
+/// The kernel-facing identifier of a single core.
+///
+/// The exact value is architecture-specific (e.g. the LAPIC ID
+/// on x86_64, or the affinity bits of the MPIDR on aarch64), but
+/// is guaranteed to be unique across all cores partaking in the
+/// same kernel instance.
+///
+/// Notably, a core ID is **not** a linear index into any list of
+/// cores; it must not be used as one. Boot protocol and hardware
+/// types keep their raw integer representations, and are converted
+/// to a [`CoreId`] at the boundary.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[repr(transparent)]
+pub struct CoreId(u64);
+
+impl CoreId {
+	/// A reserved core ID that never refers to a real core.
+	///
+	/// Used to mark resources that must not be scheduled on any core.
+	pub const INVALID: Self = Self(u64::MAX);
+
+	/// Creates a new core ID from its raw value.
+	#[inline]
+	#[must_use]
+	pub const fn new(id: u64) -> Self {
+		Self(id)
+	}
+
+	/// Returns the raw value of the core ID.
+	#[inline]
+	#[must_use]
+	pub const fn get(self) -> u64 {
+		self.0
+	}
+
+	/// Returns the bit representing this core in a `u64` core mask
+	/// (e.g. a thread's affinity mask), or `None` if the ID is too
+	/// large to be represented in one.
+	#[inline]
+	#[must_use]
+	pub const fn mask_bit(self) -> Option<u64> {
+		if self.0 < 64 { Some(1 << self.0) } else { None }
+	}
+}
+
+impl From<u64> for CoreId {
+	#[inline]
+	fn from(id: u64) -> Self {
+		Self(id)
+	}
+}
+
+impl From<u32> for CoreId {
+	#[inline]
+	fn from(id: u32) -> Self {
+		Self(u64::from(id))
+	}
+}
+
+impl From<u8> for CoreId {
+	#[inline]
+	fn from(id: u8) -> Self {
+		Self(u64::from(id))
+	}
+}
+
+impl From<CoreId> for u64 {
+	#[inline]
+	fn from(id: CoreId) -> Self {
+		id.0
+	}
+}
+
+impl core::fmt::Display for CoreId {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		write!(f, "{}", self.0)
+	}
+}
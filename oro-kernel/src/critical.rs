@@ -0,0 +1,96 @@
+//! RAII guards for critical sections and preemption control.
+//!
+//! Both guards save-and-disable upon construction and restore
+//! upon being dropped, such that early returns (or `?`) cannot
+//! leak a disabled state.
+
+use core::{marker::PhantomData, sync::atomic::Ordering::Relaxed};
+
+use crate::{Arch, Kernel};
+
+/// Disables interrupts on the current core for as long as
+/// the guard lives.
+///
+/// Upon being dropped, the interrupt state that was in effect
+/// when the guard was created is restored. Critical sections
+/// may therefore be nested freely.
+#[must_use = "interrupts are restored as soon as the guard is dropped"]
+pub struct CriticalSection<A: Arch> {
+	/// The interrupt state to restore upon drop.
+	state:   A::InterruptState,
+	/// Critical sections are core-local and must not be sent
+	/// to (or dropped on) another core.
+	_marker: PhantomData<*const ()>,
+}
+
+impl<A: Arch> CriticalSection<A> {
+	/// Saves the current interrupt state and disables interrupts.
+	pub fn new() -> Self {
+		let state = A::fetch_interrupts();
+		A::disable_interrupts();
+		Self {
+			state,
+			_marker: PhantomData,
+		}
+	}
+}
+
+impl<A: Arch> Default for CriticalSection<A> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<A: Arch> Drop for CriticalSection<A> {
+	fn drop(&mut self) {
+		A::restore_interrupts(self.state);
+	}
+}
+
+/// Disables preemption of the current thread on the current core
+/// for as long as the guard lives.
+///
+/// Interrupts remain enabled; the scheduler simply refrains from
+/// switching away from the current thread while any guard is held
+/// (see [`Kernel::preemptible()`]). Guards may be nested.
+#[must_use = "preemption is re-enabled as soon as the guard is dropped"]
+pub struct PreemptGuard<A: Arch> {
+	/// The core-local kernel whose counter was incremented.
+	kernel:  &'static Kernel<A>,
+	/// Preemption guards are core-local and must not be sent
+	/// to (or dropped on) another core.
+	_marker: PhantomData<*const ()>,
+}
+
+impl<A: Arch> PreemptGuard<A> {
+	/// Disables preemption on the current core.
+	pub fn new() -> Self {
+		let kernel = Kernel::<A>::get();
+		let count = kernel.preempt_count.fetch_add(1, Relaxed);
+		debug_assert_ne!(count, u32::MAX, "preempt count overflow");
+		Self {
+			kernel,
+			_marker: PhantomData,
+		}
+	}
+}
+
+impl<A: Arch> Default for PreemptGuard<A> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<A: Arch> Drop for PreemptGuard<A> {
+	fn drop(&mut self) {
+		let count = self.kernel.preempt_count.fetch_sub(1, Relaxed);
+		debug_assert_ne!(count, 0, "preempt count underflow");
+	}
+}
+
+/// Runs the given closure with interrupts disabled, restoring
+/// the previous interrupt state afterward.
+pub fn with_critical<A: Arch, R, F: FnOnce() -> R>(f: F) -> R {
+	let _critical = CriticalSection::<A>::new();
+	f()
+}
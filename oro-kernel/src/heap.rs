@@ -0,0 +1,523 @@
+//! The kernel heap, backing the `alloc` crate.
+//!
+//! The heap lives in the [`AddressSpace::kernel_heap()`] segment and is
+//! installed by the architecture crates as the `#[global_allocator]` by way
+//! of [`LockedHeap`]. Page frames are mapped into the segment on demand as
+//! the heap grows, and the frames spanned by large allocations are returned
+//! to the global page frame allocator some time after they're freed (see
+//! [`LockedHeap::reclaim()`]).
+//!
+//! Free memory is tracked by an intrusive, address-ordered free list whose
+//! headers live at the start of each free block. Allocations are first-fit,
+//! and freed blocks are coalesced with their neighbors.
+//!
+//! # Locking and Reclamation
+//! The free list is guarded by the heap lock; the heap segment's page tables
+//! by a separate mapping lock, which is only ever taken after the heap lock
+//! (or on its own). Neither is ever held across a TLB shootdown, since other
+//! cores may be spinning on them (with interrupts disabled) and would thus
+//! never acknowledge it.
+//!
+//! Since the allocator may be called from just about anywhere (with interrupts
+//! disabled, or with arbitrary locks held), freeing memory never unmaps
+//! anything by itself. Large freed blocks are merely flagged as reclaimable,
+//! and their pages are reclaimed later on by [`LockedHeap::reclaim()`], which
+//! the architecture crates call from a context in which no locks are held
+//! (i.e. the idle loop).
+//!
+//! Reclaiming the pages of a block happens in stages: the block is first
+//! pulled from the free list, its pages are unmapped under the mapping lock
+//! alone, and then - with no locks held - the stale translations are shot
+//! down on all cores via [`Arch::flush_tlb_range()`]. Only then are the
+//! unmapped frames (and any page tables emptied along the way) returned to
+//! the page frame allocator, after which the block is put back on the free
+//! list as sparse.
+
+use core::{
+	alloc::{GlobalAlloc, Layout},
+	marker::PhantomData,
+	ptr::null_mut,
+	sync::atomic::{
+		AtomicBool,
+		Ordering::{Acquire, Release},
+	},
+};
+
+use oro_macro::assert;
+use oro_mem::{
+	global_alloc::GlobalPfa,
+	mapper::{AddressSegment, AddressSpace, MapError},
+	pfa::Alloc,
+	phys::{Phys, PhysAddr},
+};
+use oro_sync::{Lock, TicketMutex};
+
+use crate::{AddrSpace, Arch};
+
+/// The size of a page in the heap segment.
+const PAGE_SIZE: usize = 4096;
+
+/// The minimum alignment, and size granularity, of all heap blocks.
+const MIN_ALIGN: usize = 16;
+
+/// The minimum number of pages by which the heap grows at once.
+const MIN_GROW_PAGES: usize = 4;
+
+/// Freed allocations of at least this many bytes have the page
+/// frames they wholly span returned to the page frame allocator
+/// upon the next call to [`LockedHeap::reclaim()`].
+pub const RECLAIM_THRESHOLD: usize = 4 * PAGE_SIZE;
+
+/// Set in a free block's size if some of its pages might not be mapped.
+const SPARSE: usize = 1;
+
+/// Set in a free block's size if its pages should be reclaimed.
+const RECLAIM: usize = 2;
+
+/// All flags stored in the low bits of a free block's size.
+///
+/// Block sizes are multiples of [`MIN_ALIGN`], leaving these bits free.
+const FLAGS: usize = SPARSE | RECLAIM;
+
+/// The header of a free block, stored at its base address.
+///
+/// The page containing the header is always mapped.
+#[repr(C)]
+struct FreeBlock {
+	/// The size of the block in bytes, including the header.
+	/// The low bits hold the block's [`FLAGS`].
+	size: usize,
+	/// The next free block, by address, or null if this is the last.
+	next: *mut FreeBlock,
+}
+
+/// The mutable state of a [`LockedHeap`].
+struct HeapState {
+	/// The free block with the lowest address, or null if there
+	/// are no free blocks.
+	head: *mut FreeBlock,
+	/// The end of the grown portion of the segment, or `0` if the
+	/// heap hasn't been used yet.
+	brk:  usize,
+}
+
+// SAFETY(qix-): The free list is only ever accessed with the heap lock held.
+unsafe impl Send for HeapState {}
+
+impl HeapState {
+	/// Inserts the given range into the free list with the given [`FLAGS`],
+	/// coalescing it with any adjacent free blocks (whose flags carry over).
+	///
+	/// # Safety
+	/// The range must be unused, not already in the free list, and
+	/// its first page must be mapped.
+	unsafe fn insert(&mut self, base: usize, size: usize, flags: usize) {
+		let mut prev: *mut FreeBlock = null_mut();
+		let mut next = self.head;
+		while !next.is_null() && (next as usize) < base {
+			prev = next;
+			next = (*next).next;
+		}
+
+		let mut block = base as *mut FreeBlock;
+		let mut size = size;
+		let mut flags = flags;
+
+		if !next.is_null() && base + size == next as usize {
+			size += (*next).size & !FLAGS;
+			flags |= (*next).size & FLAGS;
+			next = (*next).next;
+		}
+
+		if !prev.is_null() && prev as usize + ((*prev).size & !FLAGS) == base {
+			size += (*prev).size & !FLAGS;
+			flags |= (*prev).size & FLAGS;
+			block = prev;
+		} else if prev.is_null() {
+			self.head = block;
+		} else {
+			(*prev).next = block;
+		}
+
+		(*block).size = size | flags;
+		(*block).next = next;
+	}
+
+	/// Unlinks the given block from the free list.
+	///
+	/// # Safety
+	/// The block must be in the free list.
+	unsafe fn remove(&mut self, block: *mut FreeBlock) {
+		if self.head == block {
+			self.head = (*block).next;
+			return;
+		}
+
+		let mut prev = self.head;
+		while (*prev).next != block {
+			prev = (*prev).next;
+		}
+
+		(*prev).next = (*block).next;
+	}
+
+	/// Finds the first free block that can hold `size` bytes at the given
+	/// alignment, returning the block and the aligned start address within it.
+	///
+	/// # Safety
+	/// The free list must be well-formed.
+	unsafe fn find(&self, size: usize, align: usize) -> Option<(*mut FreeBlock, usize)> {
+		let mut block = self.head;
+		while !block.is_null() {
+			let base = block as usize;
+			let start = align_up(base, align);
+			if start + size <= base + ((*block).size & !FLAGS) {
+				return Some((block, start));
+			}
+
+			block = (*block).next;
+		}
+
+		None
+	}
+
+	/// Finds the first block flagged for reclamation that wholly spans
+	/// at least one page (other than the one holding its header), and
+	/// unlinks it from the free list.
+	///
+	/// Returns the block, its size, and the page-aligned range of pages to
+	/// reclaim. Blocks that are passed over have their flag cleared.
+	///
+	/// # Safety
+	/// The free list must be well-formed.
+	unsafe fn take_reclaimable(&mut self) -> Option<(*mut FreeBlock, usize, usize, usize)> {
+		let mut block = self.head;
+		while !block.is_null() {
+			let flags = (*block).size & FLAGS;
+			if flags & RECLAIM != 0 {
+				let base = block as usize;
+				let size = (*block).size & !FLAGS;
+				(*block).size = size | (flags & !RECLAIM);
+
+				// NOTE(qix-): Never reclaiming the page holding the header also
+				// NOTE(qix-): guarantees that the first page of the segment is never
+				// NOTE(qix-): unmapped, which matters; unmapping frees any intermediate
+				// NOTE(qix-): page tables that become empty, which would otherwise
+				// NOTE(qix-): clear the top-level entries that were shared between all
+				// NOTE(qix-): cores at boot.
+				let lo = align_up(base + MIN_ALIGN, PAGE_SIZE);
+				let hi = (base + size) & !(PAGE_SIZE - 1);
+				if lo < hi {
+					self.remove(block);
+					return Some((block, size, lo, hi));
+				}
+			}
+
+			block = (*block).next;
+		}
+
+		None
+	}
+}
+
+/// The kernel heap allocator.
+///
+/// Meant to be declared once by the architecture crate as the
+/// `#[global_allocator]`. Must not be used before the
+/// [`AddressSpace::kernel_heap()`] segment has been provisioned.
+pub struct LockedHeap<A: Arch> {
+	/// The free list and the end of the grown heap.
+	state:   TicketMutex<HeapState>,
+	/// Set when a block has been flagged for reclamation since
+	/// the last call to [`Self::reclaim()`].
+	pending: AtomicBool,
+	/// Serializes all modifications to the heap segment's page tables.
+	///
+	/// Only ever taken either with the heap lock held, or with no
+	/// lock held at all, and never the other way around. Never held
+	/// across a TLB shootdown.
+	mapping: TicketMutex<()>,
+	/// The heap segment is arch-specific.
+	_arch:   PhantomData<fn() -> A>,
+}
+
+impl<A: Arch> LockedHeap<A> {
+	/// Creates a new, empty kernel heap.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self {
+			state:   TicketMutex::new(HeapState {
+				head: null_mut(),
+				brk:  0,
+			}),
+			pending: AtomicBool::new(false),
+			mapping: TicketMutex::new(()),
+			_arch:   PhantomData,
+		}
+	}
+
+	/// Grows the heap by at least `min_size` bytes, adding the new pages
+	/// to the free list. Returns `false` if no pages could be mapped.
+	///
+	/// If only some of the pages could be mapped, the heap is grown by
+	/// those pages alone.
+	///
+	/// # Safety
+	/// Must be called with the heap lock held.
+	unsafe fn grow(&self, state: &mut HeapState, min_size: usize) -> bool {
+		let segment = AddrSpace::<A>::kernel_heap();
+		let pages = min_size.div_ceil(PAGE_SIZE).max(MIN_GROW_PAGES);
+		let limit = segment.range().1;
+
+		let _mapping = self.mapping.lock();
+		// SAFETY(qix-): We have exclusive ownership of the heap segment's mappings.
+		let space = AddrSpace::<A>::current_supervisor_space();
+
+		let base = state.brk;
+		let mut mapped = 0;
+		while mapped < pages && base + (mapped + 1) * PAGE_SIZE - 1 <= limit {
+			// NOTE(qix-): The OOM handler is deliberately not consulted here; it may
+			// NOTE(qix-): very well try to free heap memory, which would deadlock.
+			let Some(frame) = GlobalPfa.allocate() else {
+				break;
+			};
+
+			if segment
+				.map(&space, base + mapped * PAGE_SIZE, frame)
+				.is_err()
+			{
+				GlobalPfa.free(frame);
+				break;
+			}
+
+			mapped += 1;
+		}
+
+		if mapped == 0 {
+			return false;
+		}
+
+		state.brk += mapped * PAGE_SIZE;
+		state.insert(base, mapped * PAGE_SIZE, 0);
+
+		true
+	}
+
+	/// Ensures every page overlapping `[start, end)` is mapped.
+	///
+	/// # Safety
+	/// Must be called with the heap lock held, and the range must
+	/// lie within the grown portion of the heap.
+	unsafe fn ensure_mapped(&self, start: usize, end: usize) -> bool {
+		let segment = AddrSpace::<A>::kernel_heap();
+
+		let _mapping = self.mapping.lock();
+		// SAFETY(qix-): We have exclusive ownership of the heap segment's mappings.
+		let space = AddrSpace::<A>::current_supervisor_space();
+
+		for page in (start & !(PAGE_SIZE - 1)..end).step_by(PAGE_SIZE) {
+			let Some(frame) = GlobalPfa.allocate() else {
+				return false;
+			};
+
+			match segment.map(&space, page, frame) {
+				Ok(()) => {}
+				Err(MapError::Exists) => GlobalPfa.free(frame),
+				Err(_) => {
+					GlobalPfa.free(frame);
+					return false;
+				}
+			}
+		}
+
+		true
+	}
+
+	/// Returns the page frames wholly spanned by large freed blocks to the
+	/// global page frame allocator, shooting down their stale translations
+	/// on all cores.
+	///
+	/// Does nothing if no such blocks were freed since the last call.
+	///
+	/// # Safety
+	/// Waits on all other cores to acknowledge the shootdown, and thus
+	/// must be called without holding any locks that another core might
+	/// be spinning on - including the heap's own locks (i.e. it must not
+	/// be called from within the allocator). The idle loop is a good
+	/// place for it.
+	pub unsafe fn reclaim(&self) {
+		if !self.pending.swap(false, Acquire) {
+			return;
+		}
+
+		loop {
+			// NOTE(qix-): The block is pulled from the free list so that nobody else
+			// NOTE(qix-): can touch it while the locks are released.
+			let Some((block, size, lo, hi)) = self.state.lock().take_reclaimable() else {
+				return;
+			};
+
+			let mut deferred = DeferredFree::new();
+
+			{
+				let _mapping = self.mapping.lock();
+				// SAFETY(qix-): We have exclusive ownership of the heap segment's mappings.
+				let space = AddrSpace::<A>::current_supervisor_space();
+				// NOTE(qix-): A failure here leaves some pages mapped, which is harmless;
+				// NOTE(qix-): the block is marked sparse either way. Pages that were
+				// NOTE(qix-): already unmapped are skipped.
+				let _ = AddrSpace::<A>::kernel_heap().unmap_range_in(
+					&space,
+					&mut deferred,
+					lo,
+					hi - lo,
+				);
+			}
+
+			A::flush_tlb_range(lo, hi - lo);
+			deferred.release();
+
+			self.state.lock().insert(block as usize, size, SPARSE);
+		}
+	}
+}
+
+unsafe impl<A: Arch> GlobalAlloc for LockedHeap<A> {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		assert::fits::<FreeBlock, MIN_ALIGN>();
+
+		let size = block_size(layout);
+		let align = layout.align().max(MIN_ALIGN);
+
+		let mut state = self.state.lock();
+		if state.brk == 0 {
+			state.brk = AddrSpace::<A>::kernel_heap().range().0;
+		}
+
+		let (block, start) = match state.find(size, align) {
+			Some(found) => found,
+			None => {
+				if !self.grow(&mut state, size + align) {
+					return null_mut();
+				}
+
+				let Some(found) = state.find(size, align) else {
+					return null_mut();
+				};
+
+				found
+			}
+		};
+
+		let base = block as usize;
+		let block_end = base + ((*block).size & !FLAGS);
+		let flags = (*block).size & FLAGS;
+		let end = start + size;
+
+		// The allocation, along with the header of whatever remains
+		// after it, must be backed by pages.
+		if flags & SPARSE != 0 {
+			let header_end = if end < block_end {
+				end + MIN_ALIGN
+			} else {
+				end
+			};
+
+			if !self.ensure_mapped(start, header_end) {
+				return null_mut();
+			}
+		}
+
+		state.remove(block);
+
+		if start > base {
+			state.insert(base, start - base, flags);
+		}
+
+		if end < block_end {
+			state.insert(end, block_end - end, flags);
+		}
+
+		start as *mut u8
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		let size = block_size(layout);
+
+		// NOTE(qix-): Nothing is unmapped here; doing so requires a TLB shootdown,
+		// NOTE(qix-): which waits on the other cores and thus can't be done with
+		// NOTE(qix-): interrupts disabled or locks held, as the caller may well have.
+		if size >= RECLAIM_THRESHOLD {
+			self.state.lock().insert(ptr as usize, size, RECLAIM);
+			self.pending.store(true, Release);
+		} else {
+			self.state.lock().insert(ptr as usize, size, 0);
+		}
+	}
+}
+
+/// A page frame allocator that allocates from the global page frame
+/// allocator, but holds on to any frames freed into it until
+/// [`Self::release()`] is called.
+///
+/// Held frames are chained together through their first word, by way
+/// of the linear map.
+struct DeferredFree {
+	/// The most recently freed frame, or `u64::MAX` if none are held.
+	head: u64,
+}
+
+impl DeferredFree {
+	/// Creates a new allocator holding no frames.
+	const fn new() -> Self {
+		Self { head: u64::MAX }
+	}
+
+	/// Frees all held frames into the global page frame allocator.
+	///
+	/// # Safety
+	/// The held frames must no longer be referenced by any TLB.
+	unsafe fn release(self) {
+		let mut frame = self.head;
+		while frame != u64::MAX {
+			let next = Phys::from_address_unchecked(frame)
+				.as_ptr_unchecked::<u64>()
+				.read();
+			GlobalPfa.free(frame);
+			frame = next;
+		}
+	}
+}
+
+// SAFETY(qix-): Frames are only ever handed out once, by the global allocator.
+unsafe impl Alloc for DeferredFree {
+	fn allocate(&mut self) -> Option<u64> {
+		GlobalPfa.allocate()
+	}
+
+	unsafe fn free(&mut self, frame: u64) {
+		Phys::from_address_unchecked(frame)
+			.as_mut_ptr_unchecked::<u64>()
+			.write(self.head);
+		self.head = frame;
+	}
+
+	fn free_page_count(&self) -> usize {
+		GlobalPfa.free_page_count()
+	}
+
+	fn total_page_count(&self) -> usize {
+		GlobalPfa.total_page_count()
+	}
+}
+
+/// Returns the size of the block that backs an allocation
+/// with the given layout.
+fn block_size(layout: Layout) -> usize {
+	align_up(layout.size(), MIN_ALIGN).max(MIN_ALIGN)
+}
+
+/// Aligns the given value up to the given power-of-two alignment.
+const fn align_up(value: usize, align: usize) -> usize {
+	(value + align - 1) & !(align - 1)
+}
@@ -10,7 +10,11 @@ use oro_mem::{
 use oro_sync::{Lock, Mutex};
 
 use crate::{
-	AddrSpace, Arch, Kernel, UserHandle, module::Module, port::Port, ring::Ring, thread::Thread,
+	AddrSpace, Arch, Kernel, UserHandle,
+	module::{DepError, Module},
+	port::Port,
+	ring::{PageAccount, Ring},
+	thread::Thread,
 };
 
 /// A singular module instance.
@@ -54,10 +58,15 @@ pub struct Instance<A: Arch> {
 	module: Arc<Mutex<Module<A>>>,
 	/// The ring on which this instance resides.
 	ring: Weak<Mutex<Ring<A>>>,
+	/// The page frame account of the ring on which this instance resides.
+	///
+	/// Held strongly such that frames can still be released to it
+	/// after the ring itself has been dropped.
+	account: Arc<PageAccount>,
 	/// The thread list for the instance.
 	pub(super) threads: Vec<Arc<Mutex<Thread<A>>>>,
 	/// The port list for the instance.
-	ports: Vec<Arc<Mutex<Port>>>,
+	ports: Vec<Arc<Mutex<Port<A>>>>,
 	/// The instance's address space mapper handle.
 	///
 	/// This is typically cloned from the module's user
@@ -87,10 +96,16 @@ impl<A: Arch> Instance<A> {
 		// Makes the instance unique, either duplicating RW pages or marking them as COW.
 		A::make_instance_unique(&mapper)?;
 
+		let account = ring.lock().account().clone();
+
+		// Map in a copy of the module's ELF segments, if it was loaded from an image.
+		module.lock().map_segments(&mapper, &account)?;
+
 		let r = Arc::new(Mutex::new(Self {
 			id,
 			module: module.clone(),
 			ring: Arc::downgrade(ring),
+			account,
 			threads: Vec::new(),
 			ports: Vec::new(),
 			mapper,
@@ -98,15 +113,27 @@ impl<A: Arch> Instance<A> {
 
 		ring.lock().instances.push(r.clone());
 		module.lock().instances.push(Arc::downgrade(&r));
-		Kernel::<A>::get()
-			.state()
-			.instances
-			.lock()
-			.push(Arc::downgrade(&r));
+		Kernel::<A>::get().state().instances.register(&r);
 
 		Ok(r)
 	}
 
+	/// Creates a new instance of the given module that resides on no ring
+	/// and isn't registered with the kernel, for unit tests that can't
+	/// access the kernel state.
+	#[cfg(test)]
+	pub(crate) fn new_detached(id: u64, module: &Arc<Mutex<Module<A>>>) -> Arc<Mutex<Self>> {
+		Arc::new(Mutex::new(Self {
+			id,
+			module: module.clone(),
+			ring: Weak::new(),
+			account: Arc::new(PageAccount::new(None)),
+			threads: Vec::new(),
+			ports: Vec::new(),
+			mapper: AddrSpace::<A>::new_user_space_empty().unwrap(),
+		}))
+	}
+
 	/// Returns the instance ID.
 	#[must_use]
 	pub fn id(&self) -> u64 {
@@ -123,19 +150,64 @@ impl<A: Arch> Instance<A> {
 		self.ring.clone()
 	}
 
+	/// The page frame account to which the instance's allocations are charged.
+	#[must_use]
+	pub fn account(&self) -> &Arc<PageAccount> {
+		&self.account
+	}
+
 	/// Gets a handle to the list of threads for this instance.
 	pub fn threads(&self) -> &[Arc<Mutex<Thread<A>>>] {
 		&self.threads
 	}
 
 	/// Gets a handle to the list of ports for this instance.
-	pub fn ports(&self) -> &[Arc<Mutex<Port>>] {
+	pub fn ports(&self) -> &[Arc<Mutex<Port<A>>>] {
 		&self.ports
 	}
 
 	/// Returns the instance's address space handle.
+	///
+	/// See [`Self::address_space()`].
 	#[must_use]
 	pub fn mapper(&self) -> &UserHandle<A> {
 		&self.mapper
 	}
+
+	/// Returns the instance's private address space.
+	///
+	/// The address space is created when the instance is spawned, from
+	/// the kernel's supervisor space (see [`AddressSpace::new_user_space()`]),
+	/// whose top-level entries are copied in shallowly; core-local
+	/// segments are omitted. Kernel (and other global) mappings are thus
+	/// shared with every other address space, whereas user mappings (the
+	/// ring and module overlays, and the module's segments) are private
+	/// to the instance. Each of the instance's threads runs in a shallow
+	/// duplicate of it, which the architecture switches to (e.g. by loading
+	/// `CR3` on x86_64) whenever it runs the thread.
+	///
+	/// # Shared Kernel Mappings
+	/// Since the kernel half's page tables are shared, changes to mappings
+	/// beneath the copied top-level entries are visible to all address
+	/// spaces. However, stale TLB entries may remain on any core that has
+	/// _any_ of them loaded; modifying (or unmapping) a shared kernel
+	/// mapping must go through the architecture's TLB shootdown path,
+	/// which must reach all cores regardless of the address space they're
+	/// currently running in. New top-level kernel entries are _not_
+	/// propagated to existing address spaces; such segments must be
+	/// provisioned as shared during boot.
+	#[must_use]
+	pub fn address_space(&self) -> &UserHandle<A> {
+		&self.mapper
+	}
+}
+
+/// Errors that can occur when spawning an instance
+/// (see [`crate::KernelState::spawn_instance()`]).
+#[derive(Clone, PartialEq, Debug, Eq)]
+pub enum SpawnError {
+	/// The module's dependencies are not satisfied.
+	Dependency(DepError),
+	/// Creating the instance's address space failed.
+	Map(MapError),
 }
@@ -0,0 +1,38 @@
+//! Architecture-neutral interface to a core's interrupt controller.
+
+/// A core's interrupt controller, through which interrupts are
+/// acknowledged and masked.
+///
+/// Obtained via [`crate::Kernel::interrupt_controller()`]. Interrupt
+/// vectors are architecture-specific.
+///
+/// # EOI Ordering
+/// Interrupt handlers must signal an end-of-interrupt (EOI) exactly
+/// once per delivered interrupt, via [`Self::eoi()`]. For edge-triggered
+/// sources, the EOI may be issued before or after the handler body.
+///
+/// For level-triggered sources, the EOI **must** only be issued once
+/// the source device has been serviced (i.e. has deasserted its line);
+/// otherwise, the still-asserted line is immediately re-delivered. A
+/// handler that cannot service the source in time should instead mask
+/// the vector (see [`Self::mask()`]) prior to the EOI, and unmask it once
+/// the source has been serviced.
+pub trait InterruptController {
+	/// Signals the end of the interrupt currently being serviced
+	/// on this core.
+	///
+	/// Must not be called for spurious interrupts.
+	fn eoi(&self);
+
+	/// Masks the given vector, such that interrupts on it are
+	/// no longer delivered until it's unmasked.
+	///
+	/// Whether this affects only the current core or all cores
+	/// depends on the source (e.g. a core-local timer vs. a device
+	/// routed to several cores).
+	fn mask(&self, vector: u8);
+
+	/// Unmasks the given vector, allowing its interrupts to be
+	/// delivered again.
+	fn unmask(&self, vector: u8);
+}
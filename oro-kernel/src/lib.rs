@@ -3,7 +3,7 @@
 //! This crate is a library with the core kernel functionality, datatypes,
 //! etc. and provides a common interface for architectures to implement
 //! the Oro kernel on their respective platforms.
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 // SAFETY(qix-): `adt_const_params` isn't strictly necessary but is on track for acceptance,
 // SAFETY(qix-): and the open questions (e.g. mangling) are not of concern here.
 // SAFETY(qix-): https://github.com/rust-lang/rust/issues/95174
@@ -12,18 +12,35 @@
 // SAFETY(qix-): https://github.com/rust-lang/rust/issues/29661
 #![feature(associated_type_defaults)]
 
+pub mod core_id;
+pub mod critical;
+pub mod heap;
 pub mod instance;
+pub mod interrupt;
 pub mod module;
+pub mod oom;
 pub mod port;
 pub mod ring;
+pub mod run_queue;
 pub mod scheduler;
 pub mod thread;
+pub mod time;
+pub mod timer_wheel;
+pub mod wait_queue;
+
+mod test_arch;
 
 use core::{
+	cell::UnsafeCell,
 	mem::MaybeUninit,
-	sync::atomic::{AtomicU64, Ordering::Relaxed},
+	sync::atomic::{
+		AtomicBool, AtomicU32, AtomicU64, AtomicUsize,
+		Ordering::{AcqRel, Acquire, Relaxed, Release},
+	},
 };
 
+use oro_elf::{ElfClass, ElfEndianness, ElfMachine};
+use oro_id::{Id, IdType};
 use oro_macro::assert;
 // NOTE(qix-): Bug in Rustfmt where it keeps treating `vec![]` and the `mod vec`
 // NOTE(qix-): as the same, rearranging imports and breaking code. Super annoying.
@@ -35,12 +52,12 @@ use oro_mem::{
 		vec::Vec,
 	},
 	global_alloc::GlobalPfa,
-	mapper::{AddressSegment, AddressSpace, MapError},
+	mapper::{AddressSegment, AddressSpace, MapError, UnmapError},
 	pfa::Alloc,
 };
 use oro_sync::{Lock, Mutex, TicketMutex};
 
-use self::scheduler::Scheduler;
+use self::{core_id::CoreId, scheduler::Scheduler};
 
 /// Core-local instance of the Oro kernel.
 ///
@@ -49,20 +66,40 @@ use self::scheduler::Scheduler;
 /// from anywhere in the kernel as a static reference.
 pub struct Kernel<A: Arch> {
 	/// The core's ID.
-	id:         usize,
+	id: CoreId,
 	/// Local core state. The kernel instance owns this
 	/// due to all of the machinery already in place to make
 	/// this kernel instance object core-local and accessible
 	/// from anywhere in the kernel.
-	core_state: A::CoreState,
+	///
+	/// Held in an [`UnsafeCell`] so that it can be mutated
+	/// by the owning core; see [`Self::core_mut()`].
+	core_state: UnsafeCell<A::CoreState>,
 	/// Global reference to the shared kernel state.
-	state:      &'static KernelState<A>,
+	state: &'static KernelState<A>,
 	/// The kernel scheduler.
 	///
 	/// Guaranteed valid after a successful call to `initialize_for_core`.
-	scheduler:  MaybeUninit<TicketMutex<Scheduler<A>>>,
+	scheduler: MaybeUninit<TicketMutex<Scheduler<A>>>,
 	/// Cached mapper handle for the kernel.
-	mapper:     SupervisorHandle<A>,
+	mapper: SupervisorHandle<A>,
+	/// The core's current interrupt nesting depth.
+	///
+	/// Only ever touched by the owning core; see [`Self::enter_interrupt()`].
+	irq_depth: AtomicU32,
+	/// The number of live [`critical::PreemptGuard`]s on this core.
+	preempt_count: AtomicU32,
+	/// Whether or not a thread was placed onto this core's run queue
+	/// by the core itself since its scheduler last selected a thread.
+	///
+	/// See [`Self::request_reschedule()`].
+	reschedule_pending: AtomicBool,
+	/// Core-local mirror of the scheduler's current thread.
+	///
+	/// Only ever written by the owning core's scheduler (with interrupts
+	/// disabled), such that it can be read without taking the scheduler
+	/// lock; see [`Self::current_thread()`].
+	current_thread: UnsafeCell<Option<Arc<Mutex<thread::Thread<A>>>>>,
 }
 
 impl<A: Arch> Kernel<A> {
@@ -86,7 +123,7 @@ impl<A: Arch> Kernel<A> {
 	/// address space mapper handle for the kernel to use. It must
 	/// be the final one that will be used for the lifetime of the core.
 	pub unsafe fn initialize_for_core(
-		id: usize,
+		id: CoreId,
 		global_state: &'static KernelState<A>,
 		core_state: A::CoreState,
 	) -> Result<&'static Self, MapError> {
@@ -99,26 +136,89 @@ impl<A: Arch> Kernel<A> {
 		debug_assert!((kernel_base as *mut Self).is_aligned());
 
 		{
-			let phys = GlobalPfa.allocate().ok_or(MapError::OutOfMemory)?;
+			let phys = oom::allocate().ok_or(MapError::OutOfMemory)?;
 			core_local_segment.map(&mapper, kernel_base, phys)?;
 		}
 
 		let kernel_ptr = kernel_base as *mut Self;
 		kernel_ptr.write(Self {
 			id,
-			core_state,
+			core_state: UnsafeCell::new(core_state),
 			state: global_state,
 			scheduler: MaybeUninit::uninit(),
 			mapper,
+			irq_depth: AtomicU32::new(0),
+			preempt_count: AtomicU32::new(0),
+			reschedule_pending: AtomicBool::new(false),
+			current_thread: UnsafeCell::new(None),
 		});
 
+		let run_queue = Arc::new(TicketMutex::new(run_queue::RunQueue::new(id)));
+		global_state.run_queues.lock().push((id, run_queue.clone()));
+
 		(*kernel_ptr)
 			.scheduler
-			.write(TicketMutex::new(Scheduler::new(&*kernel_ptr)));
+			.write(TicketMutex::new(Scheduler::new(&*kernel_ptr, run_queue)));
+
+		if let Some(bit) = id.mask_bit() {
+			global_state.online_cores.fetch_or(bit, Release);
+		}
 
 		Ok(&*kernel_ptr)
 	}
 
+	/// Tears down the core-local instance of the Oro kernel, such that
+	/// a subsequent call to [`Self::initialize_for_core()`] succeeds
+	/// (e.g. when the core is brought back up after a powerdown).
+	///
+	/// All threads assigned to this core are released back to the
+	/// shared thread list, where other cores will migrate them to
+	/// themselves on their next scheduling pass. The core-local
+	/// [`Kernel`] (including its core state and scheduler) is then
+	/// dropped, and the [`AddressSpace::kernel_core_local()`] page
+	/// is unmapped and its frame freed.
+	///
+	/// # Lock Ordering
+	/// The scheduler lock is taken first, followed by the kernel state's
+	/// run queue list, this core's run queue and then each individual
+	/// thread's lock, in that order (the same order as the scheduler uses
+	/// when picking a thread). The scheduler lock is released **before**
+	/// the scheduler is dropped, since the lock itself lives within the
+	/// core-local page.
+	///
+	/// # Safety
+	/// Must be called with interrupts disabled, as the very last thing
+	/// the core does before halting or powering down. The caller must
+	/// not hold the scheduler lock.
+	///
+	/// No references to the kernel instance (including those returned
+	/// by [`Self::get()`], [`Self::core()`] or [`Self::scheduler()`])
+	/// may be used after this function is called, even if it fails.
+	pub unsafe fn deinitialize_for_core() -> Result<(), UnmapError> {
+		let kernel_ptr = core::ptr::from_ref(Self::get()).cast_mut();
+
+		if let Some(bit) = (*kernel_ptr).id.mask_bit() {
+			(*kernel_ptr).state.online_cores.fetch_and(!bit, Release);
+		}
+
+		{
+			let mut scheduler = (*kernel_ptr).scheduler().lock();
+			scheduler.release_threads();
+		}
+
+		(*kernel_ptr).scheduler.assume_init_drop();
+		core::ptr::drop_in_place(kernel_ptr);
+
+		let mapper = AddrSpace::<A>::current_supervisor_space();
+		let core_local_segment = AddrSpace::<A>::kernel_core_local();
+		let phys = core_local_segment.unmap(&mapper, core_local_segment.range().0)?;
+
+		// SAFETY(qix-): The frame was allocated by `initialize_for_core` and is no longer mapped.
+		GlobalPfa.free(phys);
+
+		Ok(())
+	}
+
 	/// Returns a reference to the core-local kernel instance.
 	///
 	/// # Assumed Safety
@@ -139,7 +239,7 @@ impl<A: Arch> Kernel<A> {
 
 	/// Returns the core's ID.
 	#[must_use]
-	pub fn id(&self) -> usize {
+	pub fn id(&self) -> CoreId {
 		self.id
 	}
 
@@ -152,7 +252,62 @@ impl<A: Arch> Kernel<A> {
 	/// Returns the architecture-specific core local state reference.
 	#[must_use]
 	pub fn core(&self) -> &A::CoreState {
-		&self.core_state
+		// SAFETY(qix-): Mutable references are only handed out by `core_mut()`,
+		// SAFETY(qix-): whose safety contract forbids overlapping them with this one.
+		unsafe { &*self.core_state.get() }
+	}
+
+	/// Returns a handle to the thread currently running on this core,
+	/// or `None` if the core is idle.
+	///
+	/// Unlike [`Scheduler::current_thread()`], this doesn't take the
+	/// scheduler lock; it's a core-local read, and is thus cheap enough
+	/// to call from interrupt handlers and other hot paths.
+	#[must_use]
+	pub fn current_thread(&self) -> Option<Arc<Mutex<thread::Thread<A>>>> {
+		// SAFETY(qix-): The mirror is only written by this core's scheduler with
+		// SAFETY(qix-): interrupts disabled; disabling them here means the write
+		// SAFETY(qix-): can't interleave with this read.
+		critical::with_critical::<A, _, _>(|| unsafe { (*self.current_thread.get()).clone() })
+	}
+
+	/// Sets the core-local mirror of the scheduler's current thread.
+	///
+	/// # Safety
+	/// Must only be called by this core's scheduler, with interrupts disabled.
+	pub(crate) unsafe fn set_current_thread(&self, thread: Option<Arc<Mutex<thread::Thread<A>>>>) {
+		*self.current_thread.get() = thread;
+	}
+
+	/// Returns the core's interrupt controller.
+	#[must_use]
+	pub fn interrupt_controller(&self) -> &A::IntCtrl {
+		A::interrupt_controller(self.core())
+	}
+
+	/// Returns a mutable reference to the architecture-specific core local state.
+	///
+	/// This is meant for core-local bookkeeping that doesn't warrant interior
+	/// mutability in the core state itself, e.g. the scheduler stashing a handle
+	/// to the thread it's about to switch to, or timer bookkeeping, performed
+	/// from the core's own interrupt handlers.
+	///
+	/// # Safety
+	/// The kernel instance lives at the same virtual address on every core (see
+	/// [`AddressSpace::kernel_core_local()`]), but each address maps to a different,
+	/// core-local frame. The core state must therefore **only** ever be accessed by
+	/// the core that owns it; there is no way for another core to synchronize with
+	/// it, and references to it must never be sent to other cores.
+	///
+	/// Further, the caller must ensure that no other references (mutable or not,
+	/// including those returned by [`Self::core()`]) to the core state are live
+	/// for as long as the returned reference is. Typically, this means
+	/// interrupts must be disabled for the reference's lifetime, lest an
+	/// interrupt handler access the core state while it's being mutated.
+	#[expect(clippy::mut_from_ref)]
+	#[must_use]
+	pub unsafe fn core_mut(&self) -> &mut A::CoreState {
+		&mut *self.core_state.get()
 	}
 
 	/// Returns the mapper for the kernel.
@@ -161,6 +316,222 @@ impl<A: Arch> Kernel<A> {
 		&self.mapper
 	}
 
+	/// Records that the core has entered an interrupt service routine.
+	///
+	/// Architectures must call this upon entry to every interrupt
+	/// handler, and call [`Self::exit_interrupt()`] prior to returning
+	/// (or jumping) out of it.
+	pub fn enter_interrupt(&self) {
+		let depth = self.irq_depth.fetch_add(1, Relaxed);
+		debug_assert_ne!(depth, u32::MAX, "interrupt depth overflow");
+	}
+
+	/// Records that the core is leaving an interrupt service routine.
+	///
+	/// # Safety
+	/// Must be paired with a prior call to [`Self::enter_interrupt()`]
+	/// from the same interrupt handler.
+	pub unsafe fn exit_interrupt(&self) {
+		let depth = self.irq_depth.fetch_sub(1, Relaxed);
+		debug_assert_ne!(depth, 0, "interrupt depth underflow");
+	}
+
+	/// Returns the core's current interrupt nesting depth.
+	///
+	/// Zero if the core is not currently servicing an interrupt;
+	/// greater than one if interrupts have nested.
+	#[must_use]
+	pub fn irq_depth(&self) -> u32 {
+		self.irq_depth.load(Relaxed)
+	}
+
+	/// Returns whether or not the core is currently servicing an interrupt.
+	#[must_use]
+	pub fn in_interrupt(&self) -> bool {
+		self.irq_depth() != 0
+	}
+
+	/// Returns the number of [`critical::PreemptGuard`]s currently
+	/// held on this core.
+	#[must_use]
+	pub fn preempt_count(&self) -> u32 {
+		self.preempt_count.load(Relaxed)
+	}
+
+	/// Returns whether or not the scheduler may preempt the current
+	/// thread on this core (i.e. no [`critical::PreemptGuard`]s are held).
+	#[must_use]
+	pub fn preemptible(&self) -> bool {
+		self.preempt_count() == 0
+	}
+
+	/// Asks the given core to re-run its scheduler, e.g. after a thread
+	/// has been placed onto its run queue.
+	///
+	/// Remote cores are interrupted (see [`Arch::send_reschedule()`]),
+	/// such that idle cores pick up the thread without waiting for their
+	/// next timer event. For the current core, a pending flag is set
+	/// instead (see [`Self::take_reschedule_pending()`]).
+	pub fn request_reschedule(&self, core: CoreId) {
+		if core == self.id {
+			self.reschedule_pending.store(true, Relaxed);
+		} else {
+			A::send_reschedule(core);
+		}
+	}
+
+	/// Returns whether or not this core has requested a reschedule of
+	/// itself since its scheduler last selected a thread, clearing the
+	/// request.
+	///
+	/// Architectures should check this before idling the core.
+	#[must_use]
+	pub fn take_reschedule_pending(&self) -> bool {
+		self.reschedule_pending.swap(false, Relaxed)
+	}
+
+	/// Puts the current thread on this core to sleep until the
+	/// given deadline.
+	///
+	/// The thread is re-queued once the core's timer observes that the
+	/// deadline has passed. See [`Scheduler::sleep_current_until()`].
+	///
+	/// # Safety
+	/// Interrupts must be disabled, and the caller must not hold the
+	/// scheduler lock. The caller must not resume the current thread
+	/// afterward; it must instead defer to the scheduler to select a
+	/// new one.
+	pub unsafe fn sleep_until(&self, deadline: time::Instant) {
+		self.scheduler().lock().sleep_current_until(deadline);
+	}
+
+	/// Joins the given thread on behalf of the current thread on this core.
+	///
+	/// If the thread has already terminated (see [`thread::Thread::exit()`]),
+	/// its exit code is returned immediately. Otherwise, the current thread is
+	/// blocked until it terminates and [`thread::JoinError::WouldBlock`] is
+	/// returned; once woken, the current thread calls this function again to
+	/// read the code. The joined thread is kept alive until then.
+	///
+	/// If the current thread has pending signals (see [`thread::Thread::signal()`]),
+	/// it's never blocked; [`thread::JoinError::Interrupted`] is returned instead.
+	///
+	/// If there is no current thread, or the current thread is the given
+	/// thread, nothing is blocked and [`thread::JoinError::WouldBlock`] is
+	/// returned if the thread hasn't terminated.
+	///
+	/// # Safety
+	/// Interrupts must be disabled, and the caller must not hold the
+	/// scheduler lock or any thread's lock. If [`thread::JoinError::WouldBlock`]
+	/// is returned, the caller must not resume the current thread; it must
+	/// instead defer to the scheduler to select a new one.
+	pub unsafe fn join(
+		&self,
+		thread: &Arc<Mutex<thread::Thread<A>>>,
+	) -> Result<i32, thread::JoinError> {
+		let mut scheduler = self.scheduler().lock();
+
+		let current = match scheduler.current_thread() {
+			Some(current) if !Arc::ptr_eq(&current, thread) => current,
+			_ => {
+				return thread
+					.lock()
+					.exit_code()
+					.ok_or(thread::JoinError::WouldBlock);
+			}
+		};
+
+		if let Some(code) = thread::Thread::join_on(thread, &current) {
+			return Ok(code);
+		}
+
+		if scheduler.block_current() {
+			Err(thread::JoinError::WouldBlock)
+		} else {
+			thread::Thread::cancel_join(thread, &current);
+			Err(thread::JoinError::Interrupted)
+		}
+	}
+
+	/// Polls the given ports on behalf of the current thread on this core,
+	/// returning the set of ready ports (see [`port::Port::is_ready()`]) as
+	/// indices into `ports`.
+	///
+	/// If no ports are ready, the current thread is registered with each
+	/// port's wait queue and blocked until one becomes ready or the timeout
+	/// (if any) expires, and [`port::PollError::WouldBlock`] is returned.
+	/// Once resumed, the thread must call this function again with the same
+	/// ports; its registrations are removed, and the ready set is returned.
+	/// An empty set means the poll timed out.
+	///
+	/// If the current thread has pending signals (see [`thread::Thread::signal()`]),
+	/// it's never blocked; its registrations are removed and
+	/// [`port::PollError::Interrupted`] is returned instead. A thread woken by
+	/// a signal thus observes the interruption upon polling again, rather than
+	/// being blocked until the original deadline.
+	///
+	/// A zero timeout never blocks. If there is no current thread, nothing
+	/// is blocked and the (possibly empty) ready set is returned.
+	///
+	/// # Safety
+	/// Interrupts must be disabled, and the caller must not hold the
+	/// scheduler lock, any thread's lock or any of the ports' locks. If
+	/// [`port::PollError::WouldBlock`] is returned, the caller must not
+	/// resume the current thread; it must instead defer to the scheduler
+	/// to select a new one.
+	pub unsafe fn poll(
+		&self,
+		ports: &[Arc<Mutex<port::Port<A>>>],
+		timeout: Option<time::Duration>,
+	) -> Result<port::PollSet, port::PollError> {
+		if ports.len() > port::PollSet::CAPACITY {
+			return Err(port::PollError::TooManyPorts);
+		}
+
+		let mut scheduler = self.scheduler().lock();
+
+		let Some(current) = scheduler.current_thread() else {
+			return Ok(port::ready_set(ports));
+		};
+
+		match port::poll_on(&current, ports, timeout) {
+			Ok(ready) => Ok(ready),
+			Err(deadline) => {
+				let blocked = match deadline {
+					Some(deadline) => scheduler.block_current_until(deadline),
+					None => scheduler.block_current(),
+				};
+
+				if blocked {
+					Err(port::PollError::WouldBlock)
+				} else {
+					port::cancel_poll(&current);
+					Err(port::PollError::Interrupted)
+				}
+			}
+		}
+	}
+
+	/// Halts all other cores and resets the system.
+	///
+	/// Meant for orderly restarts as well as unrecoverable errors; nothing
+	/// is torn down beforehand. See [`Arch::reboot()`].
+	pub fn reboot() -> ! {
+		A::disable_interrupts();
+		A::halt_other_cores();
+		A::reboot();
+	}
+
+	/// Halts all other cores and powers off the system.
+	///
+	/// Nothing is torn down beforehand. If the system can't be powered
+	/// off, the current core is halted instead. See [`Arch::shutdown()`].
+	pub fn shutdown() -> ! {
+		A::disable_interrupts();
+		A::halt_other_cores();
+		A::shutdown();
+	}
+
 	/// Gets a reference to the scheduler.
 	///
 	/// # Safety
@@ -173,23 +544,114 @@ impl<A: Arch> Kernel<A> {
 	}
 }
 
+/// A snapshot of the kernel's registries.
+///
+/// Obtained via [`KernelState::registry_stats()`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RegistryStats {
+	/// The number of live modules.
+	pub modules:        usize,
+	/// The maximum number of modules ever alive at once.
+	pub peak_modules:   usize,
+	/// The number of live rings.
+	pub rings:          usize,
+	/// The maximum number of rings ever alive at once.
+	pub peak_rings:     usize,
+	/// The number of live instances.
+	pub instances:      usize,
+	/// The maximum number of instances ever alive at once.
+	pub peak_instances: usize,
+	/// The number of live threads.
+	pub threads:        usize,
+	/// The maximum number of threads ever alive at once.
+	pub peak_threads:   usize,
+	/// The number of live ports.
+	pub ports:          usize,
+	/// The maximum number of ports ever alive at once.
+	pub peak_ports:     usize,
+	/// The number of allocated physical page frames.
+	pub used_pages:     usize,
+	/// The total number of physical page frames managed by the kernel.
+	pub total_pages:    usize,
+}
+
+/// A list of weak handles to all objects of some kind,
+/// e.g. the kernel's list of all threads.
+///
+/// Objects are never removed from the list by the registry
+/// itself; once dropped, their handles simply stop upgrading.
+struct Registry<T: Send + Sync + 'static> {
+	/// The registered objects.
+	list: TicketMutex<Vec<Weak<T>>>,
+	/// The maximum number of registered objects ever alive at once.
+	peak: AtomicUsize,
+}
+
+impl<T: Send + Sync + 'static> Registry<T> {
+	/// Creates a new registry holding the given (live) objects.
+	fn new(list: Vec<Weak<T>>) -> Self {
+		Self {
+			peak: AtomicUsize::new(list.len()),
+			list: TicketMutex::new(list),
+		}
+	}
+
+	/// Adds an object to the registry.
+	fn register(&self, object: &Arc<T>) {
+		let mut list = self.list.lock();
+		list.push(Arc::downgrade(object));
+		// NOTE(qix-): The count can only ever go up here, so this is
+		// NOTE(qix-): the only place where a new peak can be reached.
+		self.peak.fetch_max(Self::count_live(&list), Relaxed);
+	}
+
+	/// Returns the number of registered objects that are still alive.
+	fn live(&self) -> usize {
+		Self::count_live(&self.list.lock())
+	}
+
+	/// Returns the maximum number of registered objects ever alive at once.
+	fn peak(&self) -> usize {
+		self.peak.load(Relaxed)
+	}
+
+	/// Counts the entries of the given list that are still alive.
+	fn count_live(list: &[Weak<T>]) -> usize {
+		list.iter()
+			.filter(|entry| entry.strong_count() != 0)
+			.count()
+	}
+}
+
+impl<T: Send + Sync + 'static> Default for Registry<T> {
+	fn default() -> Self {
+		Self::new(Vec::new())
+	}
+}
+
 /// Global state shared by all [`Kernel`] instances across
 /// core boot/powerdown/bringup cycles.
 pub struct KernelState<A: Arch> {
 	/// List of all modules.
-	modules:   TicketMutex<Vec<Weak<Mutex<module::Module<A>>>>>,
+	modules:    Registry<Mutex<module::Module<A>>>,
 	/// List of all rings.
-	rings:     TicketMutex<Vec<Weak<Mutex<ring::Ring<A>>>>>,
+	rings:      Registry<Mutex<ring::Ring<A>>>,
 	/// List of all instances.
-	instances: TicketMutex<Vec<Weak<Mutex<instance::Instance<A>>>>>,
+	instances:  Registry<Mutex<instance::Instance<A>>>,
 	/// List of all threads.
-	threads:   TicketMutex<Vec<Weak<Mutex<thread::Thread<A>>>>>,
+	threads:    Registry<Mutex<thread::Thread<A>>>,
+	/// List of all ports.
+	ports:      Registry<Mutex<port::Port<A>>>,
+	/// The run queues of all online cores.
+	run_queues: TicketMutex<Vec<(CoreId, Arc<TicketMutex<run_queue::RunQueue<A>>>)>>,
 
 	/// The root ring.
 	root_ring: Arc<Mutex<ring::Ring<A>>>,
 
 	/// The ID counter for resource allocation.
-	id_counter: AtomicU64,
+	id_counter:   AtomicU64,
+	/// The mask of online cores (see [`CoreId::mask_bit()`]).
+	online_cores: AtomicU64,
 }
 
 impl<A: Arch> KernelState<A> {
@@ -210,12 +672,15 @@ impl<A: Arch> KernelState<A> {
 		let root_ring = ring::Ring::<A>::new_root()?;
 
 		this.write(Self {
-			root_ring:  root_ring.clone(),
-			modules:    TicketMutex::default(),
-			rings:      TicketMutex::new(vec![Arc::downgrade(&root_ring)]),
-			instances:  TicketMutex::default(),
-			threads:    TicketMutex::default(),
-			id_counter: AtomicU64::new(0),
+			root_ring:    root_ring.clone(),
+			modules:      Registry::default(),
+			rings:        Registry::new(vec![Arc::downgrade(&root_ring)]),
+			instances:    Registry::default(),
+			threads:      Registry::default(),
+			ports:        Registry::default(),
+			run_queues:   TicketMutex::default(),
+			id_counter:   AtomicU64::new(0),
+			online_cores: AtomicU64::new(0),
 		});
 
 		let this = this.assume_init_mut();
@@ -232,11 +697,282 @@ impl<A: Arch> KernelState<A> {
 		self.root_ring.clone()
 	}
 
+	/// Returns an iterator over handles to all live rings, including
+	/// the root ring.
+	///
+	/// The iterator is over a snapshot of the ring list taken at the time
+	/// of the call; the list is not locked while iterating. Rings that have
+	/// since been dropped are skipped.
+	pub fn rings_iter(&'static self) -> impl Iterator<Item = Arc<Mutex<ring::Ring<A>>>> {
+		self.rings
+			.list
+			.lock()
+			.iter()
+			.filter_map(Weak::upgrade)
+			.collect::<Vec<_>>()
+			.into_iter()
+	}
+
+	/// Finds a live ring by its ring ID.
+	///
+	/// Lookup is linear over the ring list for now, and returns
+	/// the first match. Rings that have since been dropped are skipped.
+	pub fn find_ring_by_id(&'static self, id: u64) -> Option<Arc<Mutex<ring::Ring<A>>>> {
+		self.rings
+			.list
+			.lock()
+			.iter()
+			.filter_map(Weak::upgrade)
+			.find(|ring| ring.lock().id() == id)
+	}
+
+	/// Returns the mask of currently online cores.
+	///
+	/// Bit `n` is set if the core with [`CoreId`] `n` is online (see
+	/// [`CoreId::mask_bit()`]). Cores whose IDs don't fit in the mask
+	/// are never reflected in it.
+	#[must_use]
+	pub fn online_cores(&'static self) -> u64 {
+		self.online_cores.load(Acquire)
+	}
+
+	/// Finds the run queue of the given core, if it's online.
+	pub fn find_run_queue(
+		&'static self,
+		core: CoreId,
+	) -> Option<Arc<TicketMutex<run_queue::RunQueue<A>>>> {
+		self.run_queues
+			.lock()
+			.iter()
+			.find(|(id, _)| *id == core)
+			.map(|(_, queue)| queue.clone())
+	}
+
+	/// Returns the IDs of all cores that have run queues, in the
+	/// order in which they came online.
+	pub fn run_queue_cores(&'static self) -> Vec<CoreId> {
+		self.run_queues.lock().iter().map(|(id, _)| *id).collect()
+	}
+
+	/// Removes the given core's run queue from the kernel state.
+	fn unregister_run_queue(&'static self, core: CoreId) {
+		self.run_queues.lock().retain(|(id, _)| *id != core);
+	}
+
 	/// Returns a reference to the mutex-guarded list of threads.
 	pub fn threads(
 		&'static self,
 	) -> &'static impl Lock<Target = Vec<Weak<Mutex<thread::Thread<A>>>>> {
-		&self.threads
+		&self.threads.list
+	}
+
+	/// Registers a new, empty module with the given module ID.
+	///
+	/// The module's mapper can then be used to populate its
+	/// executable code and data prior to spawning instances of it.
+	pub fn register_module(
+		&'static self,
+		id: Id<{ IdType::Module }>,
+	) -> Result<Arc<Mutex<module::Module<A>>>, MapError> {
+		module::Module::new(id)
+	}
+
+	/// Registers a new module with the given module ID, loaded from
+	/// the given ELF image.
+	///
+	/// The image is validated against the architecture (see
+	/// [`Arch::ELF_MACHINE`]) and its loadable segments and entry point
+	/// are recorded on the module. The segments are not mapped until an
+	/// instance of the module is spawned (see [`Self::spawn_instance()`]),
+	/// into which they're copied.
+	///
+	/// Both fixed-address (`ET_EXEC`) and position-independent (`ET_DYN`)
+	/// images are accepted; the latter are relocated (see
+	/// [`module::Module::load()`]).
+	///
+	/// The image need not be aligned, nor outlive the call.
+	pub fn load_module(
+		&'static self,
+		id: Id<{ IdType::Module }>,
+		elf_bytes: &[u8],
+	) -> Result<Arc<Mutex<module::Module<A>>>, module::ModuleError> {
+		module::Module::load(id, elf_bytes)
+	}
+
+	/// Finds a live module by its module ID.
+	///
+	/// Lookup is linear over the module list for now, and returns
+	/// the first match. Modules that have since been dropped are skipped.
+	pub fn find_module(
+		&'static self,
+		id: &Id<{ IdType::Module }>,
+	) -> Option<Arc<Mutex<module::Module<A>>>> {
+		self.modules
+			.list
+			.lock()
+			.iter()
+			.filter_map(Weak::upgrade)
+			.find(|module| module.lock().module_id() == id)
+	}
+
+	/// Spawns a new instance of the given module onto the given ring.
+	///
+	/// The instance is allocated a fresh user address space (overlaid
+	/// with the ring's and module's shared mappings), is appended to the
+	/// ring's instance list, and holds a strong handle to the module
+	/// from which it was spawned.
+	///
+	/// The module's dependencies must be satisfied (see
+	/// [`Self::resolve_dependencies()`]); otherwise, nothing is spawned.
+	///
+	/// Notably, this does **not** spawn any threads; the returned handle
+	/// is to be used by the caller to start threads within the instance.
+	pub fn spawn_instance(
+		&'static self,
+		ring: &Arc<Mutex<ring::Ring<A>>>,
+		module: &Arc<Mutex<module::Module<A>>>,
+	) -> Result<Arc<Mutex<instance::Instance<A>>>, instance::SpawnError> {
+		self.resolve_dependencies(module)
+			.map_err(instance::SpawnError::Dependency)?;
+		instance::Instance::new(module, ring).map_err(instance::SpawnError::Map)
+	}
+
+	/// Checks that all of the given module's dependencies, and theirs
+	/// (transitively), are registered, and that none of them depend on
+	/// themselves.
+	///
+	/// The dependency graph is walked depth-first, iteratively (so as not
+	/// to recurse on the kernel stack), visiting each module once.
+	///
+	/// # Lock Ordering
+	/// No module may be locked by the caller. At most one module is
+	/// locked at a time, along with the module list.
+	pub fn resolve_dependencies(
+		&'static self,
+		module: &Arc<Mutex<module::Module<A>>>,
+	) -> Result<(), module::DepError> {
+		let root = {
+			let module = module.lock();
+			(module.module_id().clone(), module.dependencies().to_vec())
+		};
+
+		// NOTE(qix-): Dependency lists are expected to be small;
+		// NOTE(qix-): linear lookups are fine here.
+		let mut resolved: Vec<Id<{ IdType::Module }>> = Vec::new();
+		// The path from the root to the module currently being visited,
+		// along with each module's dependencies and the index of the next
+		// one to visit.
+		let mut path = vec![(root.0, root.1, 0)];
+
+		while let Some((id, dependencies, next)) = path.last_mut() {
+			let Some(dependency) = dependencies.get(*next).cloned() else {
+				resolved.push(id.clone());
+				path.pop();
+				continue;
+			};
+
+			*next += 1;
+
+			if path.iter().any(|(id, ..)| *id == dependency) {
+				return Err(module::DepError::Cycle(dependency));
+			}
+
+			if resolved.contains(&dependency) {
+				continue;
+			}
+
+			let Some(found) = self.find_module(&dependency) else {
+				return Err(module::DepError::Missing(dependency));
+			};
+
+			let dependencies = found.lock().dependencies().to_vec();
+			path.push((dependency, dependencies, 0));
+		}
+
+		Ok(())
+	}
+
+	/// Creates a new, unconnected port of the given port type.
+	pub fn create_port(
+		&'static self,
+		type_id: Id<{ IdType::PortType }>,
+		slot_size: usize,
+	) -> Arc<Mutex<port::Port<A>>> {
+		let r = Arc::new(Mutex::new(port::Port::new(
+			self.allocate_id(),
+			type_id,
+			slot_size,
+		)));
+
+		self.ports.register(&r);
+
+		r
+	}
+
+	/// Connects two ports of matching port types into a producer/consumer
+	/// pair, with `producer` being the producing end.
+	///
+	/// See [`port::Port::connect`] for more information.
+	pub fn connect(
+		&'static self,
+		producer: &Arc<Mutex<port::Port<A>>>,
+		consumer: &Arc<Mutex<port::Port<A>>>,
+	) -> Result<(), port::PortError> {
+		port::Port::connect(producer, consumer)
+	}
+
+	/// Sets the kernel's out-of-memory handler, replacing any previous one.
+	///
+	/// The handler is invoked whenever the global page frame allocator
+	/// cannot satisfy a request made by the kernel (e.g. when mapping
+	/// memory on behalf of an instance), and decides whether the
+	/// allocation is retried or fails. See [`oom::OomHandler`] for the
+	/// restrictions placed upon handlers.
+	pub fn set_oom_handler(&'static self, handler: oom::OomHandler) {
+		oom::set_handler(handler);
+	}
+
+	/// Returns the number of free physical page frames.
+	#[must_use]
+	pub fn free_page_count(&'static self) -> usize {
+		GlobalPfa.free_page_count()
+	}
+
+	/// Returns the number of allocated physical page frames.
+	#[must_use]
+	pub fn used_page_count(&'static self) -> usize {
+		GlobalPfa.used_page_count()
+	}
+
+	/// Returns the total number of physical page frames
+	/// managed by the kernel.
+	#[must_use]
+	pub fn total_page_count(&'static self) -> usize {
+		GlobalPfa.total_page_count()
+	}
+
+	/// Returns a snapshot of the number of live objects in each of the
+	/// kernel's registries, and the most that were ever alive at once,
+	/// along with physical page usage.
+	///
+	/// Each registry's lock is taken (and released) in turn; the counts
+	/// are not a consistent snapshot across registries.
+	#[must_use]
+	pub fn registry_stats(&'static self) -> RegistryStats {
+		RegistryStats {
+			modules:        self.modules.live(),
+			peak_modules:   self.modules.peak(),
+			rings:          self.rings.live(),
+			peak_rings:     self.rings.peak(),
+			instances:      self.instances.live(),
+			peak_instances: self.instances.peak(),
+			threads:        self.threads.live(),
+			peak_threads:   self.threads.peak(),
+			ports:          self.ports.live(),
+			peak_ports:     self.ports.peak(),
+			used_pages:     self.used_page_count(),
+			total_pages:    self.total_page_count(),
+		}
 	}
 
 	/// Allocates a new resource ID.
@@ -258,6 +994,97 @@ pub trait Arch: 'static {
 	type ThreadState: Sized + Send = ();
 	/// The core-local state type.
 	type CoreState: Sized + Send + Sync + 'static = ();
+	/// The saved interrupt state, as returned by [`Self::fetch_interrupts()`].
+	type InterruptState: Sized + Copy;
+	/// The core-local interrupt controller type.
+	type IntCtrl: interrupt::InterruptController;
+
+	/// The ELF class of the architecture's executables
+	/// (see [`KernelState::load_module()`]).
+	const ELF_CLASS: ElfClass;
+	/// The ELF endianness of the architecture's executables.
+	const ELF_ENDIANNESS: ElfEndianness;
+	/// The ELF machine of the architecture's executables.
+	const ELF_MACHINE: ElfMachine;
+
+	/// Returns the interrupt controller of the core owning the given core state.
+	fn interrupt_controller(core: &Self::CoreState) -> &Self::IntCtrl;
+
+	/// Returns the current core's interrupt state, without modifying it.
+	fn fetch_interrupts() -> Self::InterruptState;
+
+	/// Disables interrupts on the current core.
+	fn disable_interrupts();
+
+	/// Restores the current core's interrupt state to one previously
+	/// returned by [`Self::fetch_interrupts()`].
+	fn restore_interrupts(state: Self::InterruptState);
+
+	/// Interrupts the given (remote) core such that it re-runs its
+	/// scheduler (see [`scheduler::Scheduler::event_reschedule()`]).
+	///
+	/// Never called for the current core.
+	fn send_reschedule(core: CoreId);
+
+	/// Interrupts all other cores such that they disable interrupts
+	/// and halt indefinitely.
+	///
+	/// Called by the panic path (see [`panic()`]); must not allocate,
+	/// log, or take any locks. A no-op if no other cores have been
+	/// brought up yet.
+	fn halt_other_cores();
+
+	/// Invalidates all pages overlapping the given (kernel) virtual
+	/// address range from the TLBs of all cores.
+	///
+	/// Blocks until every core has done so. Must not be called with any
+	/// lock held that another core might be spinning on, lest the other
+	/// core never get around to it.
+	fn flush_tlb_range(virt: usize, len: usize);
+
+	/// Logs a backtrace of the caller, if the architecture supports it.
+	///
+	/// Called by the panic path (see [`panic()`]) after the panic message
+	/// has been logged; must log only via [`oro_debug::log_unlocked()`],
+	/// and must not allocate or take any locks. Does nothing by default.
+	fn log_backtrace() {}
+
+	/// Halts the current core until the next interrupt arrives.
+	///
+	/// May return spuriously.
+	fn halt_once_and_wait();
+
+	/// Resets the system.
+	///
+	/// Called by [`Kernel::reboot()`] with interrupts disabled and all
+	/// other cores halted, any of which may have been holding the debug
+	/// logger's lock; must therefore only log via [`oro_debug::dbg_warn_try!`]
+	/// (or not at all). By default, logs a warning and halts the current core.
+	fn reboot() -> ! {
+		oro_debug::dbg_warn_try!("system reset is not supported on this architecture; halting");
+		Self::halt();
+	}
+
+	/// Powers off the system.
+	///
+	/// Called by [`Kernel::shutdown()`] with interrupts disabled and all
+	/// other cores halted; the same logging restrictions as for
+	/// [`Self::reboot()`] apply. By default, logs a warning and halts
+	/// the current core.
+	fn shutdown() -> ! {
+		oro_debug::dbg_warn_try!("power-off is not supported on this architecture; halting");
+		Self::halt();
+	}
+
+	/// Halts the current core forever.
+	///
+	/// Interrupts are left as they are; with them enabled, their
+	/// handlers still run, but control never returns to the caller.
+	fn halt() -> ! {
+		loop {
+			Self::halt_once_and_wait();
+		}
+	}
 
 	/// Makes the given instance mapper unique, either by duplicating
 	/// all RW pages or by implementing COW (copy-on-write) semantics.
@@ -284,9 +1111,96 @@ pub trait Arch: 'static {
 	);
 }
 
+/// Set by the first core to panic.
+static PANICKING: AtomicBool = AtomicBool::new(false);
+
+/// The kernel's panic path. Meant to be called by the
+/// architecture's `#[panic_handler]`.
+///
+/// Disables interrupts on the current core, halts all other cores
+/// (such that they can't corrupt any state, or garble the output),
+/// logs the panic and then halts the current core, freezing the
+/// system for post-mortem inspection.
+///
+/// Only the first panic is reported; any further panic, whether on
+/// another core or a recursive one (e.g. from within the logger),
+/// simply halts the core it occurred on.
+pub fn panic<A: Arch>(info: &core::panic::PanicInfo) -> ! {
+	A::disable_interrupts();
+
+	if PANICKING.swap(true, AcqRel) {
+		A::halt();
+	}
+
+	A::halt_other_cores();
+
+	// SAFETY(qix-): The debug logger's locks can't be trusted here; the
+	// SAFETY(qix-): panic may very well have occurred while this core was
+	// SAFETY(qix-): holding one of them (e.g. while logging), in which case
+	// SAFETY(qix-): the regular logging path would deadlock.
+	unsafe {
+		if let Some(location) = info.location() {
+			oro_debug::log_unlocked(format_args!(
+				"{}:{}:E:panic: {}",
+				location.file(),
+				location.line(),
+				info.message()
+			));
+		} else {
+			oro_debug::log_unlocked(format_args!("?:?:E:panic: {}", info.message()));
+		}
+	}
+
+	A::log_backtrace();
+
+	A::halt();
+}
+
 /// Helper trait association type for `Arch::AddrSpace`.
 pub(crate) type AddrSpace<A> = <A as Arch>::AddrSpace;
 /// Helper trait association type for `Arch::AddrSpace::SupervisorHandle`.
 pub(crate) type SupervisorHandle<A> = <AddrSpace<A> as AddressSpace>::SupervisorHandle;
 /// Helper trait association type for `Arch::AddrSpace::UserHandle`.
 pub(crate) type UserHandle<A> = <AddrSpace<A> as AddressSpace>::UserHandle;
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn registry_tracks_live_and_peak() {
+		let registry = Registry::<u32>::default();
+		let a = Arc::new(0);
+		let b = Arc::new(1);
+		registry.register(&a);
+		registry.register(&b);
+		assert_eq!(registry.live(), 2);
+		assert_eq!(registry.peak(), 2);
+
+		drop(a);
+		assert_eq!(registry.live(), 1);
+		assert_eq!(registry.peak(), 2);
+
+		let c = Arc::new(2);
+		registry.register(&c);
+		assert_eq!(registry.live(), 2);
+		assert_eq!(registry.peak(), 2);
+
+		let d = Arc::new(3);
+		registry.register(&d);
+		assert_eq!(registry.live(), 3);
+		assert_eq!(registry.peak(), 3);
+
+		drop((b, c, d));
+		assert_eq!(registry.live(), 0);
+		assert_eq!(registry.peak(), 3);
+	}
+
+	#[test]
+	fn registry_counts_initial_objects() {
+		let root = Arc::new(0_u32);
+		let registry = Registry::new(vec![Arc::downgrade(&root)]);
+		assert_eq!(registry.live(), 1);
+		assert_eq!(registry.peak(), 1);
+	}
+}
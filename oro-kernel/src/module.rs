@@ -1,17 +1,25 @@
 //! Implements Oro module instances in the kernel.
 
+use oro_elf::{Elf, ElfClass, ElfEndianness, ElfError, ElfMachine, ElfSegment, ElfSegmentType};
 use oro_id::{Id, IdType};
 use oro_macro::assert;
 use oro_mem::{
 	alloc::{
 		sync::{Arc, Weak},
+		vec,
 		vec::Vec,
 	},
-	mapper::{AddressSpace, MapError},
+	mapper::{AddressSegment, AddressSpace, MapError},
+	pfa::Alloc,
+	phys::{Phys, PhysAddr},
 };
 use oro_sync::{Lock, Mutex};
 
-use crate::{AddrSpace, Arch, Kernel, UserHandle, instance::Instance};
+use crate::{
+	AddrSpace, Arch, Kernel, UserHandle,
+	instance::Instance,
+	ring::{AccountedAlloc, PageAccount},
+};
 
 /// A singular executable module.
 ///
@@ -23,6 +31,10 @@ use crate::{AddrSpace, Arch, Kernel, UserHandle, instance::Instance};
 /// to populate the module with the executable code and data, which is then
 /// used to create instances of the module (followed by a call to
 /// [`crate::Arch::make_instance_unique()`]).
+///
+/// Alternatively, modules may be loaded from an ELF image (see
+/// [`Module::load()`]), in which case their segments are recorded
+/// on the module and copied into each instance as it's spawned.
 pub struct Module<A: Arch> {
 	/// The resource ID.
 	id: u64,
@@ -40,6 +52,13 @@ pub struct Module<A: Arch> {
 	/// When modules are spawned as instances on a ring, each of the
 	/// given entry points are spawned as threads.
 	pub(super) entry_points: Vec<usize>,
+	/// The loadable segments of the module's ELF image, if it was
+	/// loaded from one (see [`Module::load()`]).
+	segments: Vec<ModuleSegment>,
+	/// The IDs of the modules on which this module depends.
+	///
+	/// See [`crate::KernelState::resolve_dependencies()`].
+	dependencies: Vec<Id<{ IdType::Module }>>,
 }
 
 impl<A: Arch> Module<A> {
@@ -55,17 +74,153 @@ impl<A: Arch> Module<A> {
 			instances: Vec::new(),
 			mapper,
 			entry_points: Vec::new(),
+			segments: Vec::new(),
+			dependencies: Vec::new(),
 		}));
 
-		Kernel::<A>::get()
-			.state()
-			.modules
-			.lock()
-			.push(Arc::downgrade(&r));
+		Kernel::<A>::get().state().modules.register(&r);
 
 		Ok(r)
 	}
 
+	/// Creates a new module without registering it with the kernel,
+	/// for unit tests that can't access the kernel state.
+	#[cfg(test)]
+	pub(crate) fn new_detached(id: u64, module_id: Id<{ IdType::Module }>) -> Arc<Mutex<Self>> {
+		Arc::new(Mutex::new(Self {
+			id,
+			module_id,
+			instances: Vec::new(),
+			mapper: AddrSpace::<A>::new_user_space_empty().unwrap(),
+			entry_points: Vec::new(),
+			segments: Vec::new(),
+			dependencies: Vec::new(),
+		}))
+	}
+
+	/// Creates a new module from the given ELF image.
+	///
+	/// Position-independent (`ET_DYN`) images are loaded at the base of the
+	/// user code segment, and their relative relocations applied.
+	///
+	/// See [`crate::KernelState::load_module()`].
+	pub fn load(
+		module_id: Id<{ IdType::Module }>,
+		elf_bytes: &[u8],
+	) -> Result<Arc<Mutex<Self>>, ModuleError> {
+		// NOTE(qix-): The parser requires a 4-byte aligned image;
+		// NOTE(qix-): copy it into a buffer that's guaranteed to be.
+		let mut image = vec![0_u64; elf_bytes.len().div_ceil(8)];
+		// SAFETY(qix-): The buffer is at least as large as the image.
+		unsafe {
+			image
+				.as_mut_ptr()
+				.cast::<u8>()
+				.copy_from_nonoverlapping(elf_bytes.as_ptr(), elf_bytes.len());
+		}
+
+		let image_base = image.as_ptr().cast::<u8>();
+
+		// SAFETY(qix-): The returned reference doesn't outlive `image`.
+		let elf = unsafe {
+			Elf::parse(
+				image_base,
+				elf_bytes.len(),
+				A::ELF_ENDIANNESS,
+				A::ELF_CLASS,
+				A::ELF_MACHINE,
+			)
+		}
+		.map_err(|err| {
+			match err {
+				ElfError::MachineMismatch { elf, expected } => {
+					ModuleError::WrongMachine { elf, expected }
+				}
+				ElfError::NotExecutable(ty) => ModuleError::UnsupportedType(ty),
+				err => ModuleError::Elf(err),
+			}
+		})?;
+
+		// NOTE(qix-): The addresses of relocatable images are relative to their base.
+		let base = if elf.is_relocatable() {
+			AddrSpace::<A>::user_code().range().0
+		} else {
+			0
+		};
+
+		let mut segments = Vec::new();
+		let mut dynamic = None;
+
+		for segment in elf.segments() {
+			let kind = match segment.ty() {
+				ElfSegmentType::ModuleCode => ModuleSegmentKind::Code,
+				ElfSegmentType::ModuleData => ModuleSegmentKind::Data,
+				ElfSegmentType::ModuleRoData => ModuleSegmentKind::RoData,
+				ElfSegmentType::Dynamic => {
+					dynamic = Some(segment_data(&segment, elf_bytes, image_base)?);
+					continue;
+				}
+				ElfSegmentType::Ignored => continue,
+				ty => return Err(ModuleError::InvalidSegment(ty)),
+			};
+
+			if segment.target_size() == 0 {
+				continue;
+			}
+
+			let data = segment_data(&segment, elf_bytes, image_base)?;
+			if data.len() > segment.target_size() {
+				return Err(ModuleError::SegmentOutOfBounds);
+			}
+
+			let target_address = segment
+				.target_address()
+				.checked_add(base)
+				.ok_or(ModuleError::SegmentOutOfRange)?;
+
+			if target_address & 0xFFF != 0 {
+				return Err(ModuleError::UnalignedSegment);
+			}
+
+			let (start, end) = kind.address_segment::<A>().range();
+
+			target_address
+				.checked_add(segment.target_size() - 1)
+				.filter(|&last| target_address >= start && last <= end)
+				.ok_or(ModuleError::SegmentOutOfRange)?;
+
+			segments.push(ModuleSegment {
+				kind,
+				target_address,
+				target_size: segment.target_size(),
+				data: data.to_vec(),
+			});
+		}
+
+		// NOTE(qix-): Fixed-address images are never relocated, even if
+		// NOTE(qix-): they carry a dynamic section.
+		if elf.is_relocatable() {
+			if let Some(dynamic) = dynamic {
+				relocate::<A>(&mut segments, dynamic, base)?;
+			}
+		}
+
+		let entry_point = elf
+			.entry_point()
+			.checked_add(base)
+			.ok_or(ModuleError::SegmentOutOfRange)?;
+
+		let module = Self::new(module_id).map_err(ModuleError::Map)?;
+
+		{
+			let mut module_lock = module.lock();
+			module_lock.segments = segments;
+			module_lock.add_entry_point(entry_point);
+		}
+
+		Ok(module)
+	}
+
 	/// Returns the instance ID.
 	#[must_use]
 	pub fn id(&self) -> u64 {
@@ -93,6 +248,81 @@ impl<A: Arch> Module<A> {
 		&self.mapper
 	}
 
+	/// Returns the module's entry points, each of which is
+	/// to be spawned as a thread in every instance.
+	#[must_use]
+	pub fn entry_points(&self) -> &[usize] {
+		&self.entry_points
+	}
+
+	/// Returns the loadable segments of the module's ELF image.
+	///
+	/// Empty if the module wasn't loaded from an ELF image.
+	#[must_use]
+	pub fn segments(&self) -> &[ModuleSegment] {
+		&self.segments
+	}
+
+	/// Maps a copy of each of the module's loadable segments into the
+	/// given (instance) address space, charging the frames (and any page
+	/// tables) to the given account.
+	///
+	/// Upon failure, the segments mapped thus far are left mapped.
+	pub(crate) fn map_segments(
+		&self,
+		mapper: &UserHandle<A>,
+		account: &PageAccount,
+	) -> Result<(), MapError> {
+		let mut alloc = AccountedAlloc::new(account);
+
+		for segment in &self.segments {
+			let mapper_segment = segment.kind.address_segment::<A>();
+
+			for offset in (0..segment.target_size).step_by(4096) {
+				let phys = alloc.allocate().ok_or(MapError::OutOfMemory)?;
+
+				let data = segment.data.get(offset..).unwrap_or_default();
+				let len = data.len().min(4096);
+
+				// SAFETY(qix-): The frame was just allocated, and is accessed via the linear map.
+				unsafe {
+					let page = Phys::from_address_unchecked(phys).as_mut_ptr_unchecked::<u8>();
+					page.copy_from_nonoverlapping(data.as_ptr(), len);
+					page.add(len).write_bytes(0, 4096 - len);
+				}
+
+				if let Err(err) =
+					mapper_segment.map_in(mapper, &mut alloc, segment.target_address + offset, phys)
+				{
+					// SAFETY(qix-): The frame was never mapped.
+					unsafe {
+						alloc.free(phys);
+					}
+					return Err(err);
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Returns the IDs of the modules on which this module depends.
+	#[must_use]
+	pub fn dependencies(&self) -> &[Id<{ IdType::Module }>] {
+		&self.dependencies
+	}
+
+	/// Adds a dependency on the module with the given ID.
+	///
+	/// Instances of this module may not be spawned until the dependency
+	/// (and, transitively, all of its dependencies) have been registered.
+	/// Adding the same dependency more than once has no effect.
+	pub fn add_dependency(&mut self, id: Id<{ IdType::Module }>) {
+		if !self.dependencies.contains(&id) {
+			self.dependencies.push(id);
+		}
+	}
+
 	/// Adds an entry point to the module.
 	///
 	/// **IMPORTANT:** Calling this method with the same entry point multiple times will result in
@@ -131,3 +361,253 @@ impl<A: Arch> Drop for Module<A> {
 		AddrSpace::<A>::free_user_space_deep(mapper);
 	}
 }
+
+/// The kind of a [`ModuleSegment`], determining its permissions.
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub enum ModuleSegmentKind {
+	/// Executable code (read-only).
+	Code,
+	/// Read-write data.
+	Data,
+	/// Read-only data.
+	RoData,
+}
+
+impl ModuleSegmentKind {
+	/// Returns the user address segment into which segments
+	/// of this kind are mapped.
+	fn address_segment<A: Arch>(self) -> <AddrSpace<A> as AddressSpace>::UserSegment {
+		match self {
+			Self::Code => AddrSpace::<A>::user_code(),
+			Self::Data => AddrSpace::<A>::user_data(),
+			Self::RoData => AddrSpace::<A>::user_rodata(),
+		}
+	}
+}
+
+/// A loadable segment of a module's ELF image (see [`Module::load()`]).
+pub struct ModuleSegment {
+	/// The kind of segment.
+	kind:           ModuleSegmentKind,
+	/// The (page-aligned) virtual address at which the segment is mapped.
+	target_address: usize,
+	/// The size of the segment in memory, in bytes.
+	target_size:    usize,
+	/// The segment's data. Any bytes beyond it, up to the
+	/// target size, are zeroed.
+	data:           Vec<u8>,
+}
+
+impl ModuleSegment {
+	/// Returns the kind of segment.
+	#[must_use]
+	pub fn kind(&self) -> ModuleSegmentKind {
+		self.kind
+	}
+
+	/// Returns the virtual address at which the segment is mapped.
+	#[must_use]
+	pub fn target_address(&self) -> usize {
+		self.target_address
+	}
+
+	/// Returns the size of the segment in memory, in bytes.
+	#[must_use]
+	pub fn target_size(&self) -> usize {
+		self.target_size
+	}
+}
+
+/// The `DT_NULL` dynamic tag, terminating the dynamic section.
+const DT_NULL: u64 = 0;
+/// The `DT_RELA` dynamic tag (the address of the relocation table).
+const DT_RELA: u64 = 7;
+/// The `DT_RELASZ` dynamic tag (the size of the relocation table, in bytes).
+const DT_RELASZ: u64 = 8;
+/// The `DT_RELAENT` dynamic tag (the size of a relocation, in bytes).
+const DT_RELAENT: u64 = 9;
+/// The dynamic tags of unsupported relocation tables
+/// (`DT_REL`, `DT_JMPREL` and `DT_RELR`).
+const DT_UNSUPPORTED: [u64; 3] = [17, 23, 36];
+/// The size of a relocation (`Elf64_Rela`), in bytes.
+const RELA_SIZE: usize = 24;
+
+/// Returns the given segment's data within `image`, a copy of
+/// which was parsed at `image_base`.
+fn segment_data<'a>(
+	segment: &impl ElfSegment,
+	image: &'a [u8],
+	image_base: *const u8,
+) -> Result<&'a [u8], ModuleError> {
+	let offset = segment.load_address() - image_base as usize;
+	offset
+		.checked_add(segment.load_size())
+		.filter(|&end| end <= image.len())
+		.map(|end| &image[offset..end])
+		.ok_or(ModuleError::SegmentOutOfBounds)
+}
+
+/// Reads the (architecture-endian) 64-bit word at `offset` in `bytes`.
+fn read_word<A: Arch>(bytes: &[u8], offset: usize) -> Option<u64> {
+	let word = *bytes.get(offset..)?.first_chunk::<8>()?;
+	if A::ELF_ENDIANNESS == ElfEndianness::Big {
+		Some(u64::from_be_bytes(word))
+	} else {
+		Some(u64::from_le_bytes(word))
+	}
+}
+
+/// Applies a relocatable image's relocations, as described by its dynamic
+/// section (`PT_DYNAMIC`), to its segments, which have been moved to `base`.
+///
+/// Only 64-bit images with relative relocations (see
+/// [`ElfMachine::relative_relocation()`]) are supported.
+fn relocate<A: Arch>(
+	segments: &mut [ModuleSegment],
+	dynamic: &[u8],
+	base: usize,
+) -> Result<(), ModuleError> {
+	if A::ELF_CLASS != ElfClass::Class64 {
+		return Err(ModuleError::InvalidDynamic);
+	}
+
+	let mut rela = None;
+	let mut rela_size = 0;
+	let mut rela_entry = RELA_SIZE as u64;
+
+	let mut offset = 0;
+	while let (Some(tag), Some(value)) = (
+		read_word::<A>(dynamic, offset),
+		read_word::<A>(dynamic, offset + 8),
+	) {
+		offset += 16;
+
+		match tag {
+			DT_NULL => break,
+			DT_RELA => rela = Some(value),
+			DT_RELASZ => rela_size = value,
+			DT_RELAENT => rela_entry = value,
+			tag if DT_UNSUPPORTED.contains(&tag) => return Err(ModuleError::InvalidDynamic),
+			_ => {}
+		}
+	}
+
+	let Some(rela) = rela else {
+		return Ok(());
+	};
+
+	if rela_entry != RELA_SIZE as u64 {
+		return Err(ModuleError::InvalidDynamic);
+	}
+
+	let rela = usize::try_from(rela)
+		.ok()
+		.and_then(|rela| rela.checked_add(base))
+		.ok_or(ModuleError::InvalidDynamic)?;
+	let rela_size = usize::try_from(rela_size).map_err(|_| ModuleError::InvalidDynamic)?;
+
+	// NOTE(qix-): The table is copied out, as it may reside
+	// NOTE(qix-): in a segment that's being relocated.
+	let table = segments
+		.iter()
+		.find_map(|segment| {
+			let offset = rela.checked_sub(segment.target_address)?;
+			segment.data.get(offset..offset.checked_add(rela_size)?)
+		})
+		.ok_or(ModuleError::InvalidDynamic)?
+		.to_vec();
+
+	let relative = A::ELF_MACHINE.relative_relocation();
+
+	for entry in table.chunks_exact(RELA_SIZE) {
+		let (Some(target), Some(info), Some(addend)) = (
+			read_word::<A>(entry, 0),
+			read_word::<A>(entry, 8),
+			read_word::<A>(entry, 16),
+		) else {
+			return Err(ModuleError::InvalidDynamic);
+		};
+
+		// NOTE(qix-): The type is the low 32 bits of the info word (`ELF64_R_TYPE`).
+		let ty = (info & 0xFFFF_FFFF) as u32;
+		if ty != relative {
+			return Err(ModuleError::UnsupportedRelocation(ty));
+		}
+
+		let target = usize::try_from(target)
+			.ok()
+			.and_then(|target| target.checked_add(base))
+			.ok_or(ModuleError::InvalidDynamic)?;
+
+		let (segment, offset) = segments
+			.iter_mut()
+			.find_map(|segment| {
+				let offset = target.checked_sub(segment.target_address)?;
+				(offset.checked_add(8)? <= segment.target_size).then_some((segment, offset))
+			})
+			.ok_or(ModuleError::InvalidDynamic)?;
+
+		// The target may lie in the segment's zeroed tail.
+		if segment.data.len() < offset + 8 {
+			segment.data.resize(offset + 8, 0);
+		}
+
+		let value = (base as u64).wrapping_add(addend);
+		segment.data[offset..offset + 8].copy_from_slice(
+			&if A::ELF_ENDIANNESS == ElfEndianness::Big {
+				value.to_be_bytes()
+			} else {
+				value.to_le_bytes()
+			},
+		);
+	}
+
+	Ok(())
+}
+
+/// Errors that can occur when loading a module from an ELF image.
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub enum ModuleError {
+	/// The image is not a valid ELF file for this architecture.
+	Elf(ElfError),
+	/// The image was built for a different machine.
+	WrongMachine {
+		/// The image's machine.
+		elf:      ElfMachine,
+		/// The architecture's machine.
+		expected: ElfMachine,
+	},
+	/// The image's ELF type (`e_type`) is not supported; only
+	/// executable (`ET_EXEC`) and position-independent (`ET_DYN`)
+	/// images may be loaded.
+	UnsupportedType(u16),
+	/// The image has a segment that isn't a valid module segment.
+	InvalidSegment(ElfSegmentType),
+	/// A segment's data extends beyond the end of the image,
+	/// or beyond the segment's size in memory.
+	SegmentOutOfBounds,
+	/// A segment's virtual address is not page-aligned.
+	UnalignedSegment,
+	/// A segment lies outside of the user address segment for its
+	/// kind (see [`ModuleSegmentKind`]).
+	SegmentOutOfRange,
+	/// A relocatable image's dynamic section (`PT_DYNAMIC`) is malformed,
+	/// refers to data outside of the image's segments, or requires
+	/// unsupported relocation tables.
+	InvalidDynamic,
+	/// A relocatable image has a relocation of the given, unsupported
+	/// type; only relative relocations are supported.
+	UnsupportedRelocation(u32),
+	/// Creating the module's address space failed.
+	Map(MapError),
+}
+
+/// Errors that can occur when resolving a module's dependencies
+/// (see [`crate::KernelState::resolve_dependencies()`]).
+#[derive(Clone, PartialEq, Debug, Eq)]
+pub enum DepError {
+	/// The module with the given ID is depended upon, but isn't registered.
+	Missing(Id<{ IdType::Module }>),
+	/// The module with the given ID (transitively) depends on itself.
+	Cycle(Id<{ IdType::Module }>),
+}
@@ -0,0 +1,126 @@
+//! Out-of-memory (OOM) handling.
+//!
+//! When a page frame allocator cannot satisfy a request made on behalf
+//! of the kernel (including the architecture's page table mappers), the
+//! registered OOM handler (see
+//! [`crate::KernelState::set_oom_handler()`]) is consulted. The handler
+//! may attempt to free memory (e.g. by triggering reclamation, or by
+//! terminating a large instance) and ask for the allocation to be
+//! retried, or let it fail.
+
+use oro_mem::{global_alloc::GlobalPfa, pfa::Alloc};
+use oro_sync::AtomicFnPtr;
+
+/// The maximum number of times a single allocation is retried
+/// at the OOM handler's request before it fails regardless.
+pub const MAX_OOM_RETRIES: usize = 8;
+
+/// The registered OOM handler, if any.
+static OOM_HANDLER: AtomicFnPtr<OomHandler> = AtomicFnPtr::new();
+
+/// Information about an allocation that could not be satisfied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OomContext {
+	/// The number of page frames requested.
+	pub pages:      usize,
+	/// Whether or not the frames must be physically contiguous.
+	pub contiguous: bool,
+	/// The number of free page frames at the time of the failure.
+	pub free_pages: usize,
+	/// The number of times this allocation has already been retried.
+	pub attempt:    usize,
+}
+
+/// The action to take after the OOM handler has run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OomAction {
+	/// The handler freed memory; retry the allocation.
+	Retry,
+	/// Fail the allocation.
+	Fail,
+}
+
+/// An OOM handler.
+///
+/// Handlers **must not** allocate from the global page frame
+/// allocator (which is, by definition, exhausted), and may be
+/// called from any core, with or without interrupts enabled.
+pub type OomHandler = fn(&OomContext) -> OomAction;
+
+/// Sets the OOM handler, replacing any previous one.
+pub(crate) fn set_handler(handler: OomHandler) {
+	OOM_HANDLER.store(handler);
+}
+
+/// Consults the OOM handler about a failed allocation.
+///
+/// `free_pages` is the number of free page frames left in the
+/// allocator that failed.
+///
+/// Returns [`OomAction::Fail`] if no handler has been registered,
+/// or if the allocation has already been retried
+/// [`MAX_OOM_RETRIES`] times.
+fn consult(pages: usize, contiguous: bool, free_pages: usize, attempt: usize) -> OomAction {
+	if attempt >= MAX_OOM_RETRIES {
+		return OomAction::Fail;
+	}
+
+	match OOM_HANDLER.load() {
+		None => OomAction::Fail,
+		Some(handler) => {
+			handler(&OomContext {
+				pages,
+				contiguous,
+				free_pages,
+				attempt,
+			})
+		}
+	}
+}
+
+/// Allocates a single page frame from the global page frame allocator,
+/// consulting the OOM handler upon failure.
+pub fn allocate() -> Option<u64> {
+	allocate_in(&mut GlobalPfa)
+}
+
+/// Allocates a single page frame from the given allocator, consulting
+/// the OOM handler upon failure.
+///
+/// Meant for code that is generic over its allocator, such as the
+/// architectures' address space mappers.
+pub fn allocate_in<A: Alloc>(alloc: &mut A) -> Option<u64> {
+	let mut attempt = 0;
+	loop {
+		if let Some(frame) = alloc.allocate() {
+			return Some(frame);
+		}
+
+		if consult(1, false, alloc.free_page_count(), attempt) == OomAction::Fail {
+			return None;
+		}
+
+		attempt += 1;
+	}
+}
+
+/// Allocates `count` physically contiguous page frames from the global
+/// page frame allocator, consulting the OOM handler upon failure.
+///
+/// See [`Alloc::allocate_contiguous()`].
+pub fn allocate_contiguous(count: usize, align_log2: u32) -> Option<u64> {
+	let mut attempt = 0;
+	loop {
+		if let Some(base) = GlobalPfa.allocate_contiguous(count, align_log2) {
+			return Some(base);
+		}
+
+		if count == 0
+			|| consult(count, true, GlobalPfa.free_page_count(), attempt) == OomAction::Fail
+		{
+			return None;
+		}
+
+		attempt += 1;
+	}
+}
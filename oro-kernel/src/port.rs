@@ -1,6 +1,19 @@
 //! Implements Oro ports in the kernel.
 
 use oro_id::{Id, IdType};
+use oro_mem::alloc::{
+	boxed::Box,
+	sync::{Arc, Weak},
+	vec::Vec,
+};
+use oro_sync::{Lock, Mutex};
+
+use crate::{
+	Arch,
+	thread::{RunState, Thread},
+	time::{self, Duration, Instant},
+	wait_queue::WaitQueue,
+};
 
 /// A singular port.
 ///
@@ -81,16 +94,163 @@ use oro_id::{Id, IdType};
 ///
 /// Ownership of a port may be transferred to another thread, however
 /// this is a somewhat expensive operation and should be done sparingly.
-pub struct Port {
+///
+/// # Message Queue
+/// Each connection carries a single fixed-capacity ([`QUEUE_CAPACITY`] bytes)
+/// ring buffer, shared by both of its ports and allocated upon connection.
+/// The producer writes to it via [`Port::try_send`], and the consumer reads
+/// from it via [`Port::try_recv`]. The queue is a **byte stream**; message
+/// framing is _not_ preserved, and it's up to the higher layers to send and
+/// receive in multiples of [`Port::slot_size`] if framing is required.
+///
+/// # Readiness
+/// A producer port is ready when the queue has room, and a consumer port
+/// when the queue has unread bytes (see [`Port::is_ready`]). Threads may
+/// wait for any of several ports to become ready via [`crate::Kernel::poll`];
+/// [`Port::send`] wakes those waiting on the consumer, and [`Port::recv`]
+/// those waiting on the producer.
+pub struct Port<A: Arch> {
 	/// The resource ID.
 	id:        u64,
 	/// The type ID of the port.
 	type_id:   Id<{ IdType::PortType }>,
 	/// Gets the length of the port's message.
 	slot_size: usize,
+	/// The port's side of the connection, if connected.
+	role:      Option<PortRole>,
+	/// The connected peer port, if any.
+	///
+	/// Weak so as to avoid a reference cycle between the
+	/// two ends of a connection.
+	peer:      Option<Weak<Mutex<Port<A>>>>,
+	/// The message queue shared with the peer, if connected.
+	queue:     Option<Arc<Mutex<Queue>>>,
+	/// Threads polling the port (see [`crate::Kernel::poll`]).
+	waiters:   WaitQueue<A>,
+}
+
+/// The capacity, in bytes, of each connection's message queue.
+pub const QUEUE_CAPACITY: usize = 4096;
+
+/// The ring buffer shared by the two ports of a connection.
+struct Queue {
+	/// The backing storage.
+	buf:  Box<[u8]>,
+	/// The offset of the first unread byte.
+	head: usize,
+	/// The number of unread bytes.
+	len:  usize,
+}
+
+impl Queue {
+	/// Creates a new, empty queue of [`QUEUE_CAPACITY`] bytes.
+	fn new() -> Self {
+		Self {
+			buf:  oro_mem::alloc::vec![0; QUEUE_CAPACITY].into_boxed_slice(),
+			head: 0,
+			len:  0,
+		}
+	}
+
+	/// Returns the number of bytes that can be written without blocking.
+	fn room(&self) -> usize {
+		self.buf.len() - self.len
+	}
+
+	/// Writes as many of the given bytes as will fit, returning
+	/// the number of bytes written.
+	fn write(&mut self, data: &[u8]) -> usize {
+		let count = data.len().min(self.room());
+
+		let tail = (self.head + self.len) % self.buf.len();
+		let first = count.min(self.buf.len() - tail);
+		self.buf[tail..tail + first].copy_from_slice(&data[..first]);
+		// Handle wrap-around.
+		self.buf[..count - first].copy_from_slice(&data[first..count]);
+
+		self.len += count;
+		count
+	}
+
+	/// Reads as many bytes as are available (up to `buf.len()`),
+	/// returning the number of bytes read.
+	fn read(&mut self, buf: &mut [u8]) -> usize {
+		let count = buf.len().min(self.len);
+
+		let first = count.min(self.buf.len() - self.head);
+		buf[..first].copy_from_slice(&self.buf[self.head..self.head + first]);
+		// Handle wrap-around.
+		buf[first..count].copy_from_slice(&self.buf[..count - first]);
+
+		self.head = (self.head + count) % self.buf.len();
+		self.len -= count;
+		count
+	}
 }
 
-impl Port {
+impl<A: Arch> Port<A> {
+	/// Creates a new, unconnected port.
+	///
+	/// Callers should typically use [`crate::KernelState::create_port`]
+	/// instead, which allocates the resource ID and registers the port.
+	pub(crate) fn new(id: u64, type_id: Id<{ IdType::PortType }>, slot_size: usize) -> Self {
+		Self {
+			id,
+			type_id,
+			slot_size,
+			role: None,
+			peer: None,
+			queue: None,
+			waiters: WaitQueue::new(),
+		}
+	}
+
+	/// Connects a producer port to a consumer port.
+	///
+	/// Both ports must be of the same port type, and neither
+	/// may already be connected.
+	pub fn connect(
+		producer: &Arc<Mutex<Self>>,
+		consumer: &Arc<Mutex<Self>>,
+	) -> Result<(), PortError> {
+		if Arc::ptr_eq(producer, consumer) {
+			return Err(PortError::SelfConnection);
+		}
+
+		// NOTE(qix-): Always lock in ID order to prevent two concurrent
+		// NOTE(qix-): connections of the same ports from deadlocking.
+		let (mut producer_lock, mut consumer_lock) = {
+			let producer_id = producer.lock().id;
+			let consumer_id = consumer.lock().id;
+			if producer_id < consumer_id {
+				let p = producer.lock();
+				(p, consumer.lock())
+			} else {
+				let c = consumer.lock();
+				(producer.lock(), c)
+			}
+		};
+
+		if producer_lock.type_id != consumer_lock.type_id {
+			return Err(PortError::TypeMismatch);
+		}
+
+		if producer_lock.is_connected() || consumer_lock.is_connected() {
+			return Err(PortError::AlreadyConnected);
+		}
+
+		let queue = Arc::new(Mutex::new(Queue::new()));
+
+		producer_lock.role = Some(PortRole::Producer);
+		producer_lock.peer = Some(Arc::downgrade(consumer));
+		producer_lock.queue = Some(queue.clone());
+		consumer_lock.role = Some(PortRole::Consumer);
+		consumer_lock.peer = Some(Arc::downgrade(producer));
+		consumer_lock.queue = Some(queue);
+
+		Ok(())
+	}
+
 	/// Returns the port's ID.
 	#[must_use]
 	pub fn id(&self) -> u64 {
@@ -108,4 +268,484 @@ impl Port {
 	pub fn slot_size(&self) -> usize {
 		self.slot_size
 	}
+
+	/// Returns the port's side of the connection, or `None`
+	/// if the port has not been connected.
+	#[must_use]
+	pub fn role(&self) -> Option<PortRole> {
+		self.role
+	}
+
+	/// Returns a handle to the connected peer port, if the port is
+	/// connected and the peer is still alive.
+	#[must_use]
+	pub fn peer(&self) -> Option<Arc<Mutex<Port<A>>>> {
+		self.peer.as_ref().and_then(Weak::upgrade)
+	}
+
+	/// Returns whether or not the port has been connected to a peer.
+	///
+	/// Note that this returns `true` even if the peer has since been
+	/// dropped; ports are never re-wired once connected.
+	#[must_use]
+	pub fn is_connected(&self) -> bool {
+		self.peer.is_some()
+	}
+
+	/// Attempts to write the given bytes to the connection's message queue.
+	///
+	/// Writes as many bytes as will fit, returning the number of bytes
+	/// written (which may be fewer than `data.len()`). If the queue is
+	/// full, returns [`SendError::WouldBlock`] so that the caller may park
+	/// the thread until the consumer has drained the queue.
+	///
+	/// Only producer ports may send.
+	pub fn try_send(&self, data: &[u8]) -> Result<usize, SendError> {
+		let queue = match (self.role, &self.queue) {
+			(Some(PortRole::Producer), Some(queue)) => queue,
+			(Some(PortRole::Consumer), _) => return Err(SendError::NotProducer),
+			_ => return Err(SendError::NotConnected),
+		};
+
+		if data.is_empty() {
+			return Ok(0);
+		}
+
+		match queue.lock().write(data) {
+			0 => Err(SendError::WouldBlock),
+			count => Ok(count),
+		}
+	}
+
+	/// Attempts to read bytes from the connection's message queue into `buf`.
+	///
+	/// Reads as many bytes as are available (up to `buf.len()`), returning
+	/// the number of bytes read. If the queue is empty, returns
+	/// [`RecvError::WouldBlock`] so that the caller may park the thread
+	/// until the producer has written to the queue.
+	///
+	/// Only consumer ports may receive.
+	pub fn try_recv(&self, buf: &mut [u8]) -> Result<usize, RecvError> {
+		let queue = match (self.role, &self.queue) {
+			(Some(PortRole::Consumer), Some(queue)) => queue,
+			(Some(PortRole::Producer), _) => return Err(RecvError::NotConsumer),
+			_ => return Err(RecvError::NotConnected),
+		};
+
+		if buf.is_empty() {
+			return Ok(0);
+		}
+
+		match queue.lock().read(buf) {
+			0 => Err(RecvError::WouldBlock),
+			count => Ok(count),
+		}
+	}
+
+	/// Returns the number of unread bytes in the connection's message
+	/// queue, or `0` if the port isn't connected.
+	#[must_use]
+	pub fn queued(&self) -> usize {
+		self.queue.as_ref().map_or(0, |queue| queue.lock().len)
+	}
+
+	/// Returns whether or not the port is ready; a producer port is
+	/// ready when the queue has room, and a consumer port when the
+	/// queue has unread bytes. Unconnected ports are never ready.
+	#[must_use]
+	pub fn is_ready(&self) -> bool {
+		let Some(queue) = &self.queue else {
+			return false;
+		};
+
+		match self.role {
+			Some(PortRole::Producer) => queue.lock().room() > 0,
+			Some(PortRole::Consumer) => queue.lock().len > 0,
+			None => false,
+		}
+	}
+
+	/// Writes the given bytes to the connection's message queue (see
+	/// [`Self::try_send`]), waking any threads polling the consumer.
+	///
+	/// # Lock Ordering
+	/// Neither the port nor its peer may be locked by the caller.
+	/// The peer is only locked once the port's lock has been released.
+	pub fn send(this: &Arc<Mutex<Self>>, data: &[u8]) -> Result<usize, SendError> {
+		let (result, peer) = {
+			let port = this.lock();
+			(port.try_send(data), port.peer())
+		};
+
+		if result.is_ok() {
+			Self::wake_peer(peer);
+		}
+
+		result
+	}
+
+	/// Reads bytes from the connection's message queue into `buf` (see
+	/// [`Self::try_recv`]), waking any threads polling the producer.
+	///
+	/// # Lock Ordering
+	/// Neither the port nor its peer may be locked by the caller.
+	/// The peer is only locked once the port's lock has been released.
+	pub fn recv(this: &Arc<Mutex<Self>>, buf: &mut [u8]) -> Result<usize, RecvError> {
+		let (result, peer) = {
+			let port = this.lock();
+			(port.try_recv(buf), port.peer())
+		};
+
+		if result.is_ok() {
+			Self::wake_peer(peer);
+		}
+
+		result
+	}
+
+	/// Wakes any threads polling the given peer port, if any.
+	fn wake_peer(peer: Option<Arc<Mutex<Self>>>) {
+		if let Some(peer) = peer {
+			let waiters = peer.lock().waiters.take();
+			waiters.wake_all();
+		}
+	}
+}
+
+/// Errors that can occur when sending to a port's message queue.
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub enum SendError {
+	/// The queue is full; the operation should be retried
+	/// once the consumer has read from the queue.
+	WouldBlock,
+	/// The port is a consumer; only producers may send.
+	NotProducer,
+	/// The port hasn't been connected.
+	NotConnected,
+}
+
+/// Errors that can occur when receiving from a port's message queue.
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub enum RecvError {
+	/// The queue is empty; the operation should be retried
+	/// once the producer has written to the queue.
+	WouldBlock,
+	/// The port is a producer; only consumers may receive.
+	NotConsumer,
+	/// The port hasn't been connected.
+	NotConnected,
+}
+
+/// The side of a port connection.
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub enum PortRole {
+	/// The port produces messages.
+	Producer,
+	/// The port consumes messages.
+	Consumer,
+}
+
+/// Errors that can occur when connecting ports.
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub enum PortError {
+	/// The port types of the two ports do not match.
+	TypeMismatch,
+	/// One or both of the ports are already connected.
+	AlreadyConnected,
+	/// A port cannot be connected to itself.
+	SelfConnection,
+}
+
+/// Errors that can occur when polling ports.
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub enum PollError {
+	/// More than [`PollSet::CAPACITY`] ports were given.
+	TooManyPorts,
+	/// None of the ports are ready; the current thread has been blocked
+	/// until one is, or until the timeout expires. Once resumed, the
+	/// thread must poll again to collect the result.
+	WouldBlock,
+	/// None of the ports are ready, but the current thread has pending
+	/// signals (see [`crate::thread::Thread::signal()`]) and thus hasn't
+	/// been blocked.
+	Interrupted,
+}
+
+/// The set of ready ports returned by [`crate::Kernel::poll`], as a bitset
+/// of indices into the polled ports.
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Default)]
+pub struct PollSet(u64);
+
+impl PollSet {
+	/// The maximum number of ports that may be polled at once.
+	pub const CAPACITY: usize = 64;
+
+	/// Returns the raw bitset.
+	#[must_use]
+	pub fn bits(self) -> u64 {
+		self.0
+	}
+
+	/// Returns whether or not the port at the given index is ready.
+	#[must_use]
+	pub fn contains(self, index: usize) -> bool {
+		index < Self::CAPACITY && self.0 & (1 << index) != 0
+	}
+
+	/// Returns whether or not no ports are ready (i.e. the poll timed out).
+	#[must_use]
+	pub fn is_empty(self) -> bool {
+		self.0 == 0
+	}
+
+	/// Returns the number of ready ports.
+	#[must_use]
+	pub fn len(self) -> usize {
+		self.0.count_ones() as usize
+	}
+}
+
+/// A thread's outstanding registration with a set of ports' wait
+/// queues, kept across the thread blocking in [`crate::Kernel::poll`].
+pub(crate) struct PollRegistration<A: Arch> {
+	/// The ports with which the thread is registered.
+	ports:    Vec<Weak<Mutex<Port<A>>>>,
+	/// The deadline after which the poll times out, if any.
+	deadline: Option<Instant>,
+}
+
+/// Polls the given ports on behalf of `thread` (see [`crate::Kernel::poll`]).
+///
+/// There must be no more than [`PollSet::CAPACITY`] ports.
+///
+/// Any registration left over from a previous, blocking poll by the thread
+/// is removed first. If no ports are ready and the poll hasn't timed out,
+/// the thread is marked as [`RunState::Blocked`] and registered with each
+/// port's wait queue, and the poll's deadline (if any) is returned as the
+/// error; the caller must then block the thread until that deadline, or
+/// call [`cancel_poll`] if the scheduler refuses to block it.
+///
+/// # Lock Ordering
+/// Neither the thread nor any of the ports may be locked by the caller.
+/// The thread and a port are never locked at the same time.
+pub(crate) fn poll_on<A: Arch>(
+	thread: &Arc<Mutex<Thread<A>>>,
+	ports: &[Arc<Mutex<Port<A>>>],
+	timeout: Option<Duration>,
+) -> Result<PollSet, Option<Instant>> {
+	let previous = thread.lock().polling.take();
+	if let Some(previous) = &previous {
+		deregister(thread, previous.ports.iter().filter_map(Weak::upgrade));
+	}
+
+	let ready = ready_set(ports);
+	if !ready.is_empty() {
+		return Ok(ready);
+	}
+
+	let deadline = match previous {
+		Some(previous) => previous.deadline,
+		None => timeout.map(|timeout| time::now().saturating_add(timeout)),
+	};
+
+	if deadline.is_some_and(|deadline| deadline <= time::now()) {
+		return Ok(ready);
+	}
+
+	// NOTE(qix-): The thread is marked as blocked _before_ registering,
+	// NOTE(qix-): and the ports re-checked afterward, such that a port
+	// NOTE(qix-): becoming ready in between can't be missed.
+	thread.lock().run_state = RunState::Blocked;
+
+	for port in ports {
+		port.lock().waiters.push(thread);
+	}
+
+	let ready = ready_set(ports);
+	if !ready.is_empty() {
+		deregister(thread, ports.iter().cloned());
+
+		let mut t = thread.lock();
+		if t.run_state == RunState::Blocked {
+			t.run_state = RunState::Runnable;
+		}
+
+		return Ok(ready);
+	}
+
+	thread.lock().polling = Some(PollRegistration {
+		ports: ports.iter().map(Arc::downgrade).collect(),
+		deadline,
+	});
+
+	Err(deadline)
+}
+
+/// Removes the thread's outstanding registration from a poll (see
+/// [`poll_on`]) that was interrupted before the thread could block.
+///
+/// # Lock Ordering
+/// Neither the thread nor any of the ports may be locked by the caller.
+/// The thread and a port are never locked at the same time.
+pub(crate) fn cancel_poll<A: Arch>(thread: &Arc<Mutex<Thread<A>>>) {
+	let registration = thread.lock().polling.take();
+	if let Some(registration) = registration {
+		deregister(thread, registration.ports.iter().filter_map(Weak::upgrade));
+	}
+}
+
+/// Returns the set of ready ports.
+///
+/// There must be no more than [`PollSet::CAPACITY`] ports.
+pub(crate) fn ready_set<A: Arch>(ports: &[Arc<Mutex<Port<A>>>]) -> PollSet {
+	PollSet(
+		ports
+			.iter()
+			.enumerate()
+			.filter(|(_, port)| port.lock().is_ready())
+			.fold(0, |bits, (index, _)| bits | (1 << index)),
+	)
+}
+
+/// Removes the thread from each of the given ports' wait queues.
+fn deregister<A: Arch, I: Iterator<Item = Arc<Mutex<Port<A>>>>>(
+	thread: &Arc<Mutex<Thread<A>>>,
+	ports: I,
+) {
+	for port in ports {
+		port.lock().waiters.remove(thread);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{instance::Instance, module::Module, test_arch::TestArch};
+
+	/// Creates a connected producer/consumer port pair.
+	fn pair() -> (Arc<Mutex<Port<TestArch>>>, Arc<Mutex<Port<TestArch>>>) {
+		let type_id = Id::from_high_low(0, 1);
+		let producer = Arc::new(Mutex::new(Port::new(1, type_id.clone(), 8)));
+		let consumer = Arc::new(Mutex::new(Port::new(2, type_id, 8)));
+		Port::connect(&producer, &consumer).unwrap();
+		(producer, consumer)
+	}
+
+	#[test]
+	fn send_reaches_peer() {
+		let (producer, consumer) = pair();
+
+		assert_eq!(Port::send(&producer, b"hello"), Ok(5));
+		assert_eq!(consumer.lock().queued(), 5);
+		assert!(consumer.lock().is_ready());
+
+		let mut buf = [0; 16];
+		assert_eq!(Port::recv(&consumer, &mut buf), Ok(5));
+		assert_eq!(&buf[..5], b"hello");
+		assert_eq!(producer.lock().queued(), 0);
+		assert_eq!(Port::recv(&consumer, &mut buf), Err(RecvError::WouldBlock));
+	}
+
+	/// Creates a thread that isn't registered with the kernel.
+	fn thread() -> Arc<Mutex<Thread<TestArch>>> {
+		let module = Module::new_detached(1, Id::from_high_low(0, 1));
+		let instance = Instance::new_detached(2, &module);
+		Thread::new_detached(3, &instance)
+	}
+
+	#[test]
+	fn send_wakes_consumer_poller() {
+		let (producer, consumer) = pair();
+		let thread = thread();
+
+		assert_eq!(poll_on(&thread, &[consumer.clone()], None), Err(None));
+		assert_eq!(thread.lock().run_state, RunState::Blocked);
+
+		assert_eq!(Port::send(&producer, b"x"), Ok(1));
+		assert_eq!(thread.lock().run_state, RunState::Runnable);
+		assert_eq!(poll_on(&thread, &[consumer], None), Ok(PollSet(1)));
+	}
+
+	#[test]
+	fn recv_wakes_producer_poller() {
+		let (producer, consumer) = pair();
+		let thread = thread();
+
+		let data = [0; QUEUE_CAPACITY];
+		assert_eq!(Port::send(&producer, &data), Ok(QUEUE_CAPACITY));
+
+		assert_eq!(poll_on(&thread, &[producer.clone()], None), Err(None));
+		assert_eq!(thread.lock().run_state, RunState::Blocked);
+
+		assert_eq!(Port::recv(&consumer, &mut [0; 1]), Ok(1));
+		assert_eq!(thread.lock().run_state, RunState::Runnable);
+		assert_eq!(poll_on(&thread, &[producer], None), Ok(PollSet(1)));
+	}
+
+	#[test]
+	fn signal_wakes_poller() {
+		let (_producer, consumer) = pair();
+		let thread = thread();
+
+		assert_eq!(poll_on(&thread, &[consumer], None), Err(None));
+		assert_eq!(thread.lock().run_state, RunState::Blocked);
+
+		assert_eq!(Thread::signal(&thread, 3), Ok(()));
+		assert_eq!(thread.lock().run_state, RunState::Runnable);
+		assert!(thread.lock().has_pending_signals());
+
+		cancel_poll(&thread);
+		assert!(thread.lock().polling.is_none());
+		assert_eq!(thread.lock().take_pending(), 1 << 3);
+		assert!(!thread.lock().has_pending_signals());
+	}
+
+	#[test]
+	fn wrong_role() {
+		let (producer, consumer) = pair();
+
+		assert_eq!(Port::send(&consumer, b"x"), Err(SendError::NotProducer));
+		assert_eq!(
+			Port::recv(&producer, &mut [0; 1]),
+			Err(RecvError::NotConsumer)
+		);
+	}
+
+	#[test]
+	fn not_connected() {
+		let port = Arc::new(Mutex::new(Port::<TestArch>::new(
+			1,
+			Id::from_high_low(0, 1),
+			8,
+		)));
+
+		assert_eq!(Port::send(&port, b"x"), Err(SendError::NotConnected));
+		assert_eq!(Port::recv(&port, &mut [0; 1]), Err(RecvError::NotConnected));
+		assert!(!port.lock().is_ready());
+	}
+
+	#[test]
+	fn full_queue_wraps() {
+		let (producer, consumer) = pair();
+
+		let data = (0..QUEUE_CAPACITY + 16)
+			.map(|i| i.to_le_bytes()[0])
+			.collect::<Vec<_>>();
+		assert_eq!(Port::send(&producer, &data), Ok(QUEUE_CAPACITY));
+		assert!(!producer.lock().is_ready());
+		assert_eq!(Port::send(&producer, &data), Err(SendError::WouldBlock));
+
+		let mut buf = [0; 32];
+		assert_eq!(Port::recv(&consumer, &mut buf), Ok(32));
+		assert_eq!(&buf[..], &data[..32]);
+
+		// Wraps around the end of the ring.
+		assert_eq!(Port::send(&producer, &data[QUEUE_CAPACITY..]), Ok(16));
+
+		let mut out = Vec::new();
+		while let Ok(count) = Port::recv(&consumer, &mut buf) {
+			out.extend_from_slice(&buf[..count]);
+		}
+		assert_eq!(&out[..], &data[32..]);
+	}
 }
@@ -1,11 +1,18 @@
 //! Implements Oro rings in the kernel.
 
+use core::sync::atomic::{
+	AtomicUsize,
+	Ordering::{AcqRel, Acquire, Relaxed},
+};
+
 use oro_mem::{
 	alloc::{
 		sync::{Arc, Weak},
 		vec::Vec,
 	},
+	global_alloc::GlobalPfa,
 	mapper::{AddressSegment, AddressSpace, MapError},
+	pfa::Alloc,
 };
 use oro_sync::{Lock, Mutex};
 
@@ -43,6 +50,8 @@ pub struct Ring<A: Arch> {
 	pub(super) mapper: UserHandle<A>,
 	/// The ring's child rings.
 	pub(super) children: Vec<Arc<Mutex<Ring<A>>>>,
+	/// The ring's page frame accounting.
+	account: Arc<PageAccount>,
 }
 
 impl<A: Arch> Ring<A> {
@@ -56,22 +65,23 @@ impl<A: Arch> Ring<A> {
 
 		AddrSpace::<A>::sysabi().provision_as_shared(&mapper)?;
 
+		let account = Arc::new(PageAccount::new(
+			parent.as_ref().map(|p| p.lock().account.clone()),
+		));
+
 		let r = Arc::new(Mutex::new(Self {
 			id,
 			parent: parent.as_ref().map(|p| Arc::downgrade(p)),
 			instances: Vec::new(),
 			mapper,
 			children: Vec::new(),
+			account,
 		}));
 
 		if let Some(p) = parent.as_ref() {
 			p.lock().children.push(r.clone());
 		}
-		Kernel::<A>::get()
-			.state()
-			.rings
-			.lock()
-			.push(Arc::downgrade(&r));
+		Kernel::<A>::get().state().rings.register(&r);
 
 		Ok(r)
 	}
@@ -112,4 +122,235 @@ impl<A: Arch> Ring<A> {
 	pub fn instances(&self) -> &[Arc<Mutex<Instance<A>>>] {
 		&self.instances
 	}
+
+	/// Returns the number of instances on the ring.
+	#[must_use]
+	pub fn instance_count(&self) -> usize {
+		self.instances.len()
+	}
+
+	/// Returns an iterator over handles to the instances on the ring.
+	///
+	/// Unlike [`Self::instances()`], the ring is not kept locked for the
+	/// lifetime of the iterator; it's locked only briefly upon each step,
+	/// to fetch the next instance handle. Instances spawned onto the ring
+	/// during iteration may or may not be observed.
+	///
+	/// # Lock Ordering
+	/// The ring must not be locked by the caller while stepping the
+	/// iterator. The yielded instances may be locked freely in between.
+	pub fn instances_iter(
+		this: &Arc<Mutex<Self>>,
+	) -> impl Iterator<Item = Arc<Mutex<Instance<A>>>> + '_ {
+		let mut index = 0;
+		core::iter::from_fn(move || {
+			let instance = this.lock().instances.get(index).cloned();
+			index += 1;
+			instance
+		})
+	}
+
+	/// Returns the ring's page frame accounting.
+	///
+	/// Instances on the ring charge the frames they allocate to
+	/// this account (see [`AccountedAlloc`]).
+	#[must_use]
+	pub fn account(&self) -> &Arc<PageAccount> {
+		&self.account
+	}
+
+	/// Returns the number of page frames currently accounted to this
+	/// ring, including those of all of its descendant rings.
+	#[must_use]
+	pub fn mem_pages(&self) -> usize {
+		self.account.mem_pages()
+	}
+
+	/// Returns the ring's page quota, if any.
+	///
+	/// The quota limits the number of page frames that may be accounted
+	/// to the ring's entire subtree (see [`Self::mem_pages()`]).
+	#[must_use]
+	pub fn page_quota(&self) -> Option<usize> {
+		self.account.page_quota()
+	}
+
+	/// Sets (or, with `None`, removes) the ring's page quota.
+	///
+	/// Setting a quota below the current usage does not reclaim any
+	/// frames; it only causes subsequent allocations to fail until
+	/// usage drops below it.
+	///
+	/// The root ring is always unlimited; setting its quota has no
+	/// effect. Its usage still reflects every page frame accounted
+	/// to any ring in the system.
+	pub fn set_page_quota(&self, quota: Option<usize>) {
+		if self.parent.is_some() {
+			self.account.set_page_quota(quota);
+		}
+	}
+}
+
+/// Page frame accounting for a single ring, rolling up into
+/// the accounts of all of its ancestor rings.
+///
+/// Charges are enforced against the quota of every account
+/// in the chain, such that a limit on a ring limits its
+/// entire subtree.
+pub struct PageAccount {
+	/// The number of page frames currently charged.
+	mem_pages:  AtomicUsize,
+	/// The maximum number of page frames that may be charged,
+	/// or `usize::MAX` if unlimited.
+	page_quota: AtomicUsize,
+	/// The parent ring's account. `None` for the root ring.
+	parent:     Option<Arc<PageAccount>>,
+}
+
+impl PageAccount {
+	/// Creates a new, unlimited account with the given parent account.
+	pub(crate) fn new(parent: Option<Arc<PageAccount>>) -> Self {
+		Self {
+			mem_pages: AtomicUsize::new(0),
+			page_quota: AtomicUsize::new(usize::MAX),
+			parent,
+		}
+	}
+
+	/// Returns the number of page frames currently charged.
+	#[must_use]
+	pub fn mem_pages(&self) -> usize {
+		self.mem_pages.load(Relaxed)
+	}
+
+	/// Returns the account's quota, if any.
+	#[must_use]
+	pub fn page_quota(&self) -> Option<usize> {
+		match self.page_quota.load(Relaxed) {
+			usize::MAX => None,
+			quota => Some(quota),
+		}
+	}
+
+	/// Sets (or removes) the account's quota.
+	fn set_page_quota(&self, quota: Option<usize>) {
+		self.page_quota.store(quota.unwrap_or(usize::MAX), Relaxed);
+	}
+
+	/// Charges the given number of page frames to this account
+	/// and all of its ancestors.
+	///
+	/// Fails with [`MapError::OutOfMemory`] if doing so would exceed
+	/// the quota of any account in the chain, in which case nothing
+	/// is charged.
+	pub fn charge(&self, pages: usize) -> Result<(), MapError> {
+		let mut node = Some(self);
+
+		while let Some(account) = node {
+			let quota = account.page_quota.load(Relaxed);
+			let charged = account
+				.mem_pages
+				.fetch_update(AcqRel, Acquire, |current| {
+					current.checked_add(pages).filter(|&total| total <= quota)
+				})
+				.is_ok();
+
+			if !charged {
+				// Roll back the accounts that were already charged.
+				let mut undo = Some(self);
+				while let Some(charged) = undo {
+					if core::ptr::eq(charged, account) {
+						break;
+					}
+					charged.mem_pages.fetch_sub(pages, AcqRel);
+					undo = charged.parent.as_deref();
+				}
+
+				return Err(MapError::OutOfMemory);
+			}
+
+			node = account.parent.as_deref();
+		}
+
+		Ok(())
+	}
+
+	/// Releases the given number of page frames from this account
+	/// and all of its ancestors.
+	pub fn uncharge(&self, pages: usize) {
+		let mut node = Some(self);
+
+		while let Some(account) = node {
+			// NOTE(qix-): Saturates rather than underflowing in the (buggy) case of
+			// NOTE(qix-): releasing more than was charged; accounting is best-effort.
+			let _ = account.mem_pages.fetch_update(AcqRel, Acquire, |current| {
+				Some(current.saturating_sub(pages))
+			});
+			node = account.parent.as_deref();
+		}
+	}
+}
+
+/// A page frame allocator that charges every frame it allocates
+/// (and releases every frame it frees) to a [`PageAccount`],
+/// backed by the global page frame allocator.
+///
+/// Allocations that would exceed the account's (or any ancestor's)
+/// quota fail as if the system were out of memory. Allocations that
+/// fail because the system _is_ out of memory consult the kernel's
+/// OOM handler (see [`crate::oom`]).
+pub struct AccountedAlloc<'a> {
+	/// The account to charge.
+	account: &'a PageAccount,
+}
+
+impl<'a> AccountedAlloc<'a> {
+	/// Creates a new accounted allocator for the given account.
+	#[must_use]
+	pub fn new(account: &'a PageAccount) -> Self {
+		Self { account }
+	}
+}
+
+// SAFETY(qix-): All frames come from, and are returned to, the global allocator.
+unsafe impl Alloc for AccountedAlloc<'_> {
+	fn allocate(&mut self) -> Option<u64> {
+		self.account.charge(1).ok()?;
+
+		let frame = crate::oom::allocate();
+		if frame.is_none() {
+			self.account.uncharge(1);
+		}
+
+		frame
+	}
+
+	unsafe fn free(&mut self, frame: u64) {
+		GlobalPfa.free(frame);
+		self.account.uncharge(1);
+	}
+
+	fn allocate_contiguous(&mut self, count: usize, align_log2: u32) -> Option<u64> {
+		self.account.charge(count).ok()?;
+
+		let base = crate::oom::allocate_contiguous(count, align_log2);
+		if base.is_none() {
+			self.account.uncharge(count);
+		}
+
+		base
+	}
+
+	unsafe fn free_contiguous(&mut self, base: u64, count: usize) {
+		GlobalPfa.free_contiguous(base, count);
+		self.account.uncharge(count);
+	}
+
+	fn free_page_count(&self) -> usize {
+		GlobalPfa.free_page_count()
+	}
+
+	fn total_page_count(&self) -> usize {
+		GlobalPfa.total_page_count()
+	}
 }
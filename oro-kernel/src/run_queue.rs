@@ -0,0 +1,294 @@
+//! Per-core run queues, and the work-stealing machinery between them.
+//!
+//! Each core's [`crate::scheduler::Scheduler`] owns a [`RunQueue`] of
+//! threads assigned to it that are ready to run. The queues themselves
+//! live in the shared [`crate::KernelState`] (rather than in the
+//! core-local kernel instance) such that idle cores can steal work
+//! from busy ones.
+//!
+//! # Lock Ordering
+//! When more than one run queue must be locked at once, they **must**
+//! be locked in ascending [`CoreId`] order (see [`lock_pair`]). Run
+//! queues are always locked _before_ any thread they contain.
+
+use oro_mem::alloc::{
+	collections::VecDeque,
+	sync::{Arc, Weak},
+	vec::Vec,
+};
+use oro_sync::{Lock, Mutex};
+
+use crate::{Arch, core_id::CoreId, thread::Thread};
+
+/// A queue of threads that are ready to run on a single core.
+pub struct RunQueue<A: Arch> {
+	/// The core that owns this queue.
+	core:    CoreId,
+	/// The queued threads, in the order they are to be run.
+	threads: VecDeque<Weak<Mutex<Thread<A>>>>,
+}
+
+impl<A: Arch> RunQueue<A> {
+	/// Creates a new, empty run queue for the given core.
+	#[must_use]
+	pub fn new(core: CoreId) -> Self {
+		Self {
+			core,
+			threads: VecDeque::new(),
+		}
+	}
+
+	/// Returns the core that owns this queue.
+	#[must_use]
+	pub fn core(&self) -> CoreId {
+		self.core
+	}
+
+	/// Returns the number of queued threads.
+	///
+	/// May include threads that have since been dropped.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.threads.len()
+	}
+
+	/// Returns whether or not the queue is empty.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.threads.is_empty()
+	}
+
+	/// Appends a thread to the back of the queue.
+	pub fn push(&mut self, thread: &Arc<Mutex<Thread<A>>>) {
+		self.threads.push_back(Arc::downgrade(thread));
+	}
+
+	/// Removes the thread at the front of the queue, skipping
+	/// any that have since been dropped.
+	pub fn pop(&mut self) -> Option<Arc<Mutex<Thread<A>>>> {
+		while let Some(thread) = self.threads.pop_front() {
+			if let Some(thread) = thread.upgrade() {
+				return Some(thread);
+			}
+		}
+
+		None
+	}
+
+	/// Removes and returns up to half (rounded up) of the queued
+	/// threads that are allowed to run on the `thief` core, taken
+	/// from the back of the queue.
+	///
+	/// `online_cores` is the mask of online cores, used to evaluate
+	/// each thread's affinity (see [`Thread::set_affinity()`]).
+	/// Threads that have since been dropped are discarded.
+	pub(crate) fn take_stealable(
+		&mut self,
+		thief: CoreId,
+		online_cores: u64,
+	) -> Vec<Arc<Mutex<Thread<A>>>> {
+		self.threads.retain(|thread| thread.strong_count() > 0);
+
+		let want = self.threads.len().div_ceil(2);
+		let mut stolen = Vec::new();
+
+		let mut i = self.threads.len();
+		while i > 0 && stolen.len() < want {
+			i -= 1;
+
+			let Some(thread) = self.threads[i].upgrade() else {
+				continue;
+			};
+
+			if thread.lock().allows_core(thief, online_cores) {
+				self.threads.remove(i);
+				stolen.push(thread);
+			}
+		}
+
+		stolen
+	}
+
+	/// Removes all threads from the queue, passing each live one to `f`.
+	pub(crate) fn drain<F: FnMut(Arc<Mutex<Thread<A>>>)>(&mut self, mut f: F) {
+		while let Some(thread) = self.pop() {
+			f(thread);
+		}
+	}
+}
+
+/// Locks two distinct run queues in a consistent (ascending [`CoreId`])
+/// order, so as to avoid deadlocks between cores locking the same pair.
+///
+/// Generic over the lock type, although run queues are always held
+/// in an [`oro_sync::TicketMutex`].
+///
+/// Returns the guards in the same order as the arguments.
+///
+/// # Panics
+/// Panics if both queues belong to the same core.
+pub fn lock_pair<'a, L: Lock>(
+	a: &'a L,
+	a_core: CoreId,
+	b: &'a L,
+	b_core: CoreId,
+) -> (L::Guard<'a>, L::Guard<'a>) {
+	assert_ne!(
+		a_core, b_core,
+		"cannot lock a run queue pair of the same core"
+	);
+
+	if a_core < b_core {
+		let a = a.lock();
+		let b = b.lock();
+		(a, b)
+	} else {
+		let b = b.lock();
+		let a = a.lock();
+		(a, b)
+	}
+}
+
+/// Round-robin victim selection for work stealing.
+///
+/// Each call to [`Self::next_victim()`] selects the next core after
+/// the one selected last, skipping the thief itself. Selection only
+/// depends on the given list of cores and the selector's position,
+/// making it fully deterministic.
+#[derive(Debug, Default, Clone)]
+pub struct RoundRobin {
+	/// The index into the core list at which to start the next search.
+	next: usize,
+}
+
+impl RoundRobin {
+	/// Creates a new round-robin selector, starting at the first core.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self { next: 0 }
+	}
+
+	/// Selects the next victim core to steal from, given the list of
+	/// cores that have run queues.
+	///
+	/// Returns `None` if there are no cores other than the thief.
+	pub fn next_victim(&mut self, thief: CoreId, cores: &[CoreId]) -> Option<CoreId> {
+		for _ in 0..cores.len() {
+			let candidate = cores[self.next % cores.len()];
+			self.next = self.next.wrapping_add(1);
+
+			if candidate != thief {
+				return Some(candidate);
+			}
+		}
+
+		None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use core::cell::RefCell;
+
+	use oro_sync::MutexGuard;
+
+	use super::*;
+	use crate::test_arch::TestArch;
+
+	/// A lock that records the order in which it's acquired.
+	struct Recorded<'log> {
+		/// The core the lock is recorded as.
+		core:  CoreId,
+		/// The acquisition log shared between locks.
+		log:   &'log RefCell<Vec<CoreId>>,
+		/// The underlying lock.
+		inner: Mutex<()>,
+	}
+
+	impl<'log> Recorded<'log> {
+		/// Creates a new recorded lock for the given core.
+		fn new(core: u64, log: &'log RefCell<Vec<CoreId>>) -> Self {
+			Self {
+				core: CoreId::new(core),
+				log,
+				inner: Mutex::new(()),
+			}
+		}
+	}
+
+	impl Lock for Recorded<'_> {
+		type Guard<'a>
+			= MutexGuard<'a, ()>
+		where
+			Self: 'a;
+		type Target = ();
+
+		fn lock(&self) -> Self::Guard<'_> {
+			self.log.borrow_mut().push(self.core);
+			self.inner.lock()
+		}
+
+		fn try_lock(&self) -> Option<Self::Guard<'_>> {
+			self.log.borrow_mut().push(self.core);
+			self.inner.try_lock()
+		}
+	}
+
+	#[test]
+	fn lock_pair_ascending() {
+		let log = RefCell::new(Vec::new());
+		let low = Recorded::new(1, &log);
+		let high = Recorded::new(7, &log);
+
+		drop(lock_pair(&low, low.core, &high, high.core));
+		drop(lock_pair(&high, high.core, &low, low.core));
+
+		assert_eq!(
+			*log.borrow(),
+			[1, 7, 1, 7].map(CoreId::new),
+			"pairs must always be locked low core first"
+		);
+	}
+
+	#[test]
+	#[should_panic = "same core"]
+	fn lock_pair_same_core() {
+		let log = RefCell::new(Vec::new());
+		let a = Recorded::new(3, &log);
+		let b = Recorded::new(3, &log);
+		let _ = lock_pair(&a, a.core, &b, b.core);
+	}
+
+	#[test]
+	fn round_robin_rotates() {
+		let cores = [0, 1, 2, 3].map(CoreId::new);
+		let mut victims = RoundRobin::new();
+
+		let picked = (0..6)
+			.map(|_| victims.next_victim(CoreId::new(2), &cores).unwrap().get())
+			.collect::<Vec<_>>();
+
+		assert_eq!(picked, [0, 1, 3, 0, 1, 3]);
+	}
+
+	#[test]
+	fn round_robin_no_victims() {
+		let mut victims = RoundRobin::new();
+		assert_eq!(victims.next_victim(CoreId::new(0), &[]), None);
+		assert_eq!(victims.next_victim(CoreId::new(0), &[CoreId::new(0)]), None);
+	}
+
+	#[test]
+	fn empty_queue() {
+		let mut queue = RunQueue::<TestArch>::new(CoreId::new(4));
+		assert_eq!(queue.core(), CoreId::new(4));
+		assert!(queue.is_empty());
+		assert_eq!(queue.len(), 0);
+		assert!(queue.pop().is_none());
+		assert!(queue.take_stealable(CoreId::new(5), !0).is_empty());
+
+		let mut drained = 0;
+		queue.drain(|_| drained += 1);
+		assert_eq!(drained, 0);
+	}
+}
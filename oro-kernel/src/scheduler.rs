@@ -1,9 +1,22 @@
 //! Houses types, traits and functionality for the Oro kernel scheduler.
 
-use oro_mem::alloc::sync::Arc;
-use oro_sync::{Lock, Mutex};
+use core::sync::atomic::Ordering::Relaxed;
 
-use crate::{Arch, Kernel, thread::Thread};
+use oro_mem::alloc::sync::{Arc, Weak};
+use oro_sync::{Lock, Mutex, TicketMutex};
+
+use crate::{
+	Arch, Kernel,
+	core_id::CoreId,
+	run_queue::{self, RoundRobin, RunQueue},
+	thread::{RunState, Thread, ThreadName},
+	time::{self, Duration, Instant},
+	timer_wheel::TimerWheel,
+};
+
+/// The length of a user thread's time slice, in timer ticks
+/// (see [`Handler::schedule_timer()`]).
+const TIME_SLICE_TICKS: u32 = 1000;
 
 /// Architecture-specific handler for scheduler related
 /// commands.
@@ -37,6 +50,13 @@ pub trait Handler<A: Arch> {
 	/// not call [`Scheduler::event_timer_expired()`].
 	fn cancel_timer(&self);
 
+	/// Converts the given duration to a number of timer ticks,
+	/// as accepted by [`Self::schedule_timer()`].
+	///
+	/// Should round up, saturating at `u32::MAX`, and never
+	/// return zero.
+	fn duration_to_ticks(&self, duration: Duration) -> u32;
+
 	/// Migrates the given thread to this kernel core.
 	///
 	/// This function is called when a thread is assigned to
@@ -46,6 +66,49 @@ pub trait Handler<A: Arch> {
 	fn migrate_thread(kernel: &Kernel<A>, thread: &mut Thread<A>);
 }
 
+/// A snapshot of a core's scheduler statistics.
+///
+/// Obtained via [`Scheduler::stats()`]. The [`core::fmt::Display`]
+/// implementation yields a single-line summary suitable for `dbg!`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SchedStats {
+	/// The number of times a different thread was selected to run.
+	pub context_switches:  u64,
+	/// The number of times a thread gave up the core on its own
+	/// (e.g. by sleeping).
+	pub voluntary_yields:  u64,
+	/// The number of times a thread was preempted by the timer.
+	pub preemptive_yields: u64,
+	/// The number of scheduler events that found nothing to run.
+	pub idle_ticks:        u64,
+	/// The number of runnable threads assigned to this core but
+	/// not currently running, at the time of the snapshot.
+	pub run_queue_depth:   usize,
+	/// The ID and name of the thread running at the time of the
+	/// snapshot, if any.
+	pub current_thread:    Option<(u64, ThreadName)>,
+}
+
+impl core::fmt::Display for SchedStats {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		write!(
+			f,
+			"switches={} voluntary={} preemptive={} idle={} queued={}",
+			self.context_switches,
+			self.voluntary_yields,
+			self.preemptive_yields,
+			self.idle_ticks,
+			self.run_queue_depth
+		)?;
+
+		match self.current_thread {
+			Some((id, name)) if name.as_str().is_some() => write!(f, " current={id}:{name}"),
+			Some((id, _)) => write!(f, " current={id}"),
+			None => Ok(()),
+		}
+	}
+}
+
 /// Main scheduler state machine.
 ///
 /// This type is separated out from the [`crate::Kernel`]
@@ -55,13 +118,28 @@ pub trait Handler<A: Arch> {
 /// functionality to manage the scheduling of tasks within
 /// the Oro kernel, including that of the kernel thread
 /// itself.
+///
+/// # Lock Ordering
+/// The scheduler lock is always taken first, followed by any
+/// run queues (see [`crate::run_queue`]), then the global thread
+/// list, and finally individual threads.
 pub struct Scheduler<A: Arch> {
 	/// A reference to the kernel instance.
-	kernel:     &'static Kernel<A>,
+	kernel:    &'static Kernel<A>,
 	/// The current thread, if there is one being executed.
-	current:    Option<Arc<Mutex<Thread<A>>>>,
-	/// The index of the next thread to execute.
-	next_index: usize,
+	current:   Option<Arc<Mutex<Thread<A>>>>,
+	/// This core's run queue. Shared with the kernel state such
+	/// that other cores may steal from it.
+	run_queue: Arc<TicketMutex<RunQueue<A>>>,
+	/// The victim selection policy for work stealing.
+	victims:   RoundRobin,
+	/// Threads on this core that are sleeping, keyed by their wake deadline.
+	sleepers:  TimerWheel<Weak<Mutex<Thread<A>>>>,
+	/// Scheduler statistics for this core.
+	///
+	/// Only updated under the scheduler lock. The run queue depth
+	/// is not maintained here; it's computed upon snapshot.
+	stats:     SchedStats,
 }
 
 // XXX(qix-): Temporary workaround to make things compile
@@ -70,21 +148,441 @@ unsafe impl<A: Arch> Send for Scheduler<A> {}
 unsafe impl<A: Arch> Sync for Scheduler<A> {}
 
 impl<A: Arch> Scheduler<A> {
-	/// Creates a new scheduler instance.
-	pub(crate) fn new(kernel: &'static Kernel<A>) -> Self {
+	/// Creates a new scheduler instance, with the given (empty)
+	/// run queue for this core.
+	pub(crate) fn new(
+		kernel: &'static Kernel<A>,
+		run_queue: Arc<TicketMutex<RunQueue<A>>>,
+	) -> Self {
 		Self {
 			kernel,
 			current: None,
-			next_index: 0,
+			run_queue,
+			victims: RoundRobin::new(),
+			sleepers: TimerWheel::new(),
+			stats: SchedStats::default(),
 		}
 	}
 
 	/// Returns a handle to the currently processing thread.
+	///
+	/// Returns `None` if the core is idle. Callers not already holding
+	/// the scheduler lock should prefer [`Kernel::current_thread()`].
 	#[must_use]
-	pub fn current_thread(&self) -> Option<Arc<Mutex<Thread<A>>>> {
+	pub fn current(&self) -> Option<Arc<Mutex<Thread<A>>>> {
 		self.current.clone()
 	}
 
+	/// Returns a handle to the currently processing thread.
+	///
+	/// Equivalent to [`Self::current()`].
+	#[must_use]
+	pub fn current_thread(&self) -> Option<Arc<Mutex<Thread<A>>>> {
+		self.current()
+	}
+
+	/// Takes the current thread, if any, leaving the core idle.
+	///
+	/// Keeps the kernel's core-local mirror (see [`Kernel::current_thread()`])
+	/// in sync. Interrupts must be disabled.
+	fn take_current(&mut self) -> Option<Arc<Mutex<Thread<A>>>> {
+		// SAFETY(qix-): Only this core's scheduler writes the mirror, and the
+		// SAFETY(qix-): caller guarantees interrupts are disabled.
+		unsafe {
+			self.kernel.set_current_thread(None);
+		}
+		self.current.take()
+	}
+
+	/// Sets the current thread.
+	///
+	/// Keeps the kernel's core-local mirror (see [`Kernel::current_thread()`])
+	/// in sync. Interrupts must be disabled.
+	fn set_current(&mut self, thread: Arc<Mutex<Thread<A>>>) {
+		// SAFETY(qix-): Only this core's scheduler writes the mirror, and the
+		// SAFETY(qix-): caller guarantees interrupts are disabled.
+		unsafe {
+			self.kernel.set_current_thread(Some(thread.clone()));
+		}
+		self.current = Some(thread);
+	}
+
+	/// Returns a snapshot of this core's scheduler statistics.
+	#[must_use]
+	pub fn stats(&self) -> SchedStats {
+		SchedStats {
+			run_queue_depth: self.run_queue.lock().len(),
+			current_thread: self.current.as_ref().map(|t| {
+				let t = t.lock();
+				(t.id(), t.thread_name())
+			}),
+			..self.stats
+		}
+	}
+
+	/// Records the outcome of a scheduler event in the statistics.
+	fn record_event(
+		&mut self,
+		previous: Option<&Arc<Mutex<Thread<A>>>>,
+		result: Option<&Arc<Mutex<Thread<A>>>>,
+	) {
+		match (previous, result) {
+			(_, None) => self.stats.idle_ticks += 1,
+			(Some(previous), Some(result)) if Arc::ptr_eq(previous, result) => {}
+			(_, Some(_)) => self.stats.context_switches += 1,
+		}
+	}
+
+	/// Releases all threads belonging to this core, such that other cores
+	/// may pick them up.
+	///
+	/// The current thread (if any) is marked as no longer running, and
+	/// every thread assigned to this core (queued or sleeping) is
+	/// unassigned; other cores will migrate them to themselves the next
+	/// time they select a thread. This core's run queue is then removed
+	/// from the kernel state, such that no other core steals from it.
+	///
+	/// # Safety
+	/// Interrupts MUST be disabled before calling this function, and this
+	/// core must not resume any user thread afterward.
+	pub(crate) unsafe fn release_threads(&mut self) {
+		let id = self.kernel.id();
+
+		self.kernel.state().unregister_run_queue(id);
+
+		if let Some(thread) = self.take_current() {
+			let mut t = thread.lock();
+			t.running_on_id = None;
+			t.run_on_id = None;
+		}
+
+		self.run_queue.lock().drain(|thread| {
+			let mut t = thread.lock();
+			if t.run_on_id == Some(id) {
+				t.run_on_id = None;
+			}
+		});
+
+		// Sleeping threads are released along with everything else;
+		// they're woken early, which is permitted. Threads blocked with
+		// a timeout are likewise woken (see `block_current_until()`).
+		self.sleepers
+			.expire(Instant::from_nanos(u64::MAX), |thread| {
+				if let Some(thread) = thread.upgrade() {
+					let mut t = thread.lock();
+					if t.sleeping_until.take().is_some() && t.run_state == RunState::Blocked {
+						t.run_state = RunState::Runnable;
+					}
+					if t.run_on_id == Some(id) {
+						t.run_on_id = None;
+					}
+				}
+			});
+	}
+
+	/// Puts the current thread to sleep until the given deadline.
+	///
+	/// The thread will not be selected to run again until a timer
+	/// event on this core observes that the deadline has passed. If
+	/// the deadline has already passed, or there is no current thread,
+	/// this is a no-op.
+	///
+	/// # Safety
+	/// Interrupts MUST be disabled before calling this function. The
+	/// caller must not resume the current thread; it must instead defer
+	/// to the scheduler to select a new one (e.g. via [`Self::event_idle()`]).
+	///
+	/// Must not be called from within an interrupt handler.
+	pub unsafe fn sleep_current_until(&mut self, deadline: Instant) {
+		debug_assert!(
+			!self.kernel.in_interrupt(),
+			"attempted to sleep from within an interrupt handler"
+		);
+
+		if deadline <= time::now() {
+			return;
+		}
+
+		if let Some(thread) = self.take_current() {
+			self.stats.voluntary_yields += 1;
+
+			{
+				let mut t = thread.lock();
+				t.running_on_id = None;
+				t.sleeping_until = Some(deadline);
+			}
+
+			self.sleepers.insert(deadline, Arc::downgrade(&thread));
+		}
+	}
+
+	/// Stops running the current thread, which has been marked as
+	/// [`crate::thread::RunState::Blocked`] and registered with a
+	/// [`crate::wait_queue::WaitQueue`].
+	///
+	/// The thread is not requeued; whoever wakes it does so. If it has
+	/// already been woken (e.g. by another core), it's already been
+	/// requeued. If there is no current thread, this is a no-op.
+	///
+	/// If the thread has pending signals (see [`Thread::signal()`]), it's
+	/// instead marked as runnable again and kept running, and `false` is
+	/// returned; the caller must undo whatever registration it made and
+	/// may resume the thread.
+	///
+	/// # Safety
+	/// Interrupts MUST be disabled before calling this function. If `true`
+	/// is returned, the caller must not resume the current thread; it must
+	/// instead defer to the scheduler to select a new one (e.g. via
+	/// [`Self::event_idle()`]).
+	///
+	/// Must not be called from within an interrupt handler.
+	#[must_use]
+	pub(crate) unsafe fn block_current(&mut self) -> bool {
+		debug_assert!(
+			!self.kernel.in_interrupt(),
+			"attempted to block from within an interrupt handler"
+		);
+
+		if self.interrupt_block() {
+			return false;
+		}
+
+		if let Some(thread) = self.take_current() {
+			self.stats.voluntary_yields += 1;
+			thread.lock().running_on_id = None;
+		}
+
+		true
+	}
+
+	/// Like [`Self::block_current()`], but additionally wakes the
+	/// current thread once the given deadline passes, if nothing
+	/// else has woken it by then.
+	///
+	/// If the deadline has already passed, the thread is blocked
+	/// and woken upon the next timer event.
+	///
+	/// # Safety
+	/// Interrupts MUST be disabled before calling this function. If `true`
+	/// is returned, the caller must not resume the current thread; it must
+	/// instead defer to the scheduler to select a new one (e.g. via
+	/// [`Self::event_idle()`]).
+	///
+	/// Must not be called from within an interrupt handler.
+	#[must_use]
+	pub(crate) unsafe fn block_current_until(&mut self, deadline: Instant) -> bool {
+		debug_assert!(
+			!self.kernel.in_interrupt(),
+			"attempted to block from within an interrupt handler"
+		);
+
+		if self.interrupt_block() {
+			return false;
+		}
+
+		if let Some(thread) = self.take_current() {
+			self.stats.voluntary_yields += 1;
+
+			{
+				let mut t = thread.lock();
+				t.running_on_id = None;
+				// NOTE(qix-): If the thread has already been woken, there's
+				// NOTE(qix-): nothing to time out.
+				if t.run_state == RunState::Blocked {
+					t.sleeping_until = Some(deadline);
+				}
+			}
+
+			self.sleepers.insert(deadline, Arc::downgrade(&thread));
+		}
+
+		true
+	}
+
+	/// Marks the current thread, which is about to be blocked, as
+	/// runnable again if it has pending signals.
+	///
+	/// Returns whether or not it did so.
+	fn interrupt_block(&self) -> bool {
+		// NOTE(qix-): The thread has already been marked as blocked; a signal
+		// NOTE(qix-): raised after this check wakes it (see `Thread::signal()`),
+		// NOTE(qix-): whereas one raised before it found nothing to wake.
+		self.current.as_ref().is_some_and(|thread| {
+			let mut t = thread.lock();
+			if t.run_state == RunState::Blocked && t.has_pending_signals() {
+				t.run_state = RunState::Runnable;
+				true
+			} else {
+				false
+			}
+		})
+	}
+
+	/// Wakes any sleeping threads whose deadlines have passed,
+	/// placing them back onto this core's run queue.
+	fn wake_expired(&mut self) {
+		let id = self.kernel.id();
+		let mut run_queue = self.run_queue.lock();
+
+		let now = time::now();
+
+		self.sleepers.expire(now, |thread| {
+			if let Some(thread) = thread.upgrade() {
+				let mut t = thread.lock();
+
+				// Stale entry; the thread has since been woken
+				// early (see `block_current_until()`).
+				if !t.sleeping_until.is_some_and(|deadline| deadline <= now) {
+					return;
+				}
+
+				t.sleeping_until = None;
+				if t.run_state == RunState::Blocked {
+					// Timed out.
+					t.run_state = RunState::Runnable;
+				}
+
+				let runs_here = t.run_on_id == Some(id);
+				drop(t);
+
+				if runs_here {
+					run_queue.push(&thread);
+				}
+			}
+		});
+	}
+
+	/// Arms the timer for the next scheduler event.
+	///
+	/// The timer fires at the end of the time slice, or earlier if a
+	/// sleeping thread's deadline comes sooner. When no threads are
+	/// sleeping, no additional wakeups are scheduled.
+	fn arm_timer<H: Handler<A>>(&self, handler: &H) {
+		let ticks = match self.sleepers.next_deadline() {
+			Some(deadline) => {
+				handler
+					.duration_to_ticks(deadline.saturating_duration_since(time::now()))
+					.clamp(1, TIME_SLICE_TICKS)
+			}
+			None => TIME_SLICE_TICKS,
+		};
+
+		handler.schedule_timer(ticks);
+	}
+
+	/// Steals up to half (rounded up) of the given core's queued threads
+	/// that are allowed to run on this core, moving them onto this core's
+	/// run queue.
+	///
+	/// Both run queues are locked in a consistent order (see
+	/// [`crate::run_queue::lock_pair()`]). Each stolen thread is reassigned
+	/// and migrated to this core.
+	///
+	/// Returns the number of threads stolen.
+	///
+	/// # Safety
+	/// Interrupts MUST be disabled before calling this function.
+	pub unsafe fn steal_from<H: Handler<A>>(&mut self, victim: CoreId) -> usize {
+		let id = self.kernel.id();
+
+		if victim == id {
+			return 0;
+		}
+
+		let Some(victim_queue) = self.kernel.state().find_run_queue(victim) else {
+			return 0;
+		};
+
+		let online_cores = self.kernel.state().online_cores();
+		let (mut local, mut remote) =
+			run_queue::lock_pair(&*self.run_queue, id, &*victim_queue, victim);
+
+		let stolen = remote.take_stealable(id, online_cores);
+		drop(remote);
+
+		for thread in &stolen {
+			let mut t = thread.lock();
+			t.run_on_id = Some(id);
+			H::migrate_thread(self.kernel, &mut t);
+			drop(t);
+			local.push(thread);
+		}
+
+		stolen.len()
+	}
+
+	/// Claims a single unassigned thread from the global thread list
+	/// that is allowed to run on this core, placing it onto this core's
+	/// run queue.
+	///
+	/// Returns whether or not a thread was claimed.
+	///
+	/// # Safety
+	/// Interrupts MUST be disabled before calling this function.
+	unsafe fn claim_unassigned<H: Handler<A>>(&mut self) -> bool {
+		let id = self.kernel.id();
+		let online_cores = self.kernel.state().online_cores();
+
+		let claimed = {
+			let thread_list = self.kernel.state().threads().lock();
+
+			thread_list.iter().filter_map(Weak::upgrade).find(|thread| {
+				let mut t = thread.lock();
+
+				if t.run_on_id.is_some()
+					|| t.running_on_id.is_some()
+					|| t.sleeping_until.is_some()
+					|| !t.is_runnable()
+					|| !t.allows_core(id, online_cores)
+				{
+					return false;
+				}
+
+				// Thread is not assigned to any core.
+				// Migrate it to this core.
+				t.run_on_id = Some(id);
+				H::migrate_thread(self.kernel, &mut t);
+				true
+			})
+		};
+
+		if let Some(thread) = claimed {
+			self.run_queue.lock().push(&thread);
+			true
+		} else {
+			false
+		}
+	}
+
+	/// Refills this core's (empty) run queue, first by claiming an
+	/// unassigned thread, and then by stealing from other cores (in
+	/// the order given by the victim selection policy).
+	///
+	/// Returns whether or not any threads were added.
+	///
+	/// # Safety
+	/// Interrupts MUST be disabled before calling this function.
+	unsafe fn refill<H: Handler<A>>(&mut self) -> bool {
+		if self.claim_unassigned::<H>() {
+			return true;
+		}
+
+		let id = self.kernel.id();
+		let cores = self.kernel.state().run_queue_cores();
+
+		for _ in 0..cores.len() {
+			let Some(victim) = self.victims.next_victim(id, &cores) else {
+				break;
+			};
+
+			if self.steal_from::<H>(victim) > 0 {
+				return true;
+			}
+		}
+
+		false
+	}
+
 	/// Selects a new thread to run.
 	///
 	/// This is one of the more expensive operations in the scheduler
@@ -94,8 +592,10 @@ impl<A: Arch> Scheduler<A> {
 	/// It does NOT schedule kernel threads, only user threads.
 	/// Kernel threads must be scheduled by the caller if needed.
 	///
-	/// Performs thread migration if the selected thread is assigned
-	/// to our core but is not currently running on it.
+	/// The previously running thread (if any, and if it's still runnable)
+	/// is placed at the back of this core's run queue. If the queue is
+	/// empty, it's refilled by claiming unassigned threads or stealing
+	/// from other cores, migrating them to this core.
 	///
 	/// Returns None if no user thread is available to run.
 	///
@@ -103,63 +603,72 @@ impl<A: Arch> Scheduler<A> {
 	/// Interrupts MUST be disabled before calling this function.
 	#[must_use]
 	unsafe fn pick_user_thread<H: Handler<A>>(&mut self) -> Option<Arc<Mutex<Thread<A>>>> {
-		if let Some(thread) = self.current.take() {
-			thread.lock().running_on_id = None;
-		}
+		let id = self.kernel.id();
 
-		// XXX(qix-): This is a terrible design but gets the job done for now.
-		// XXX(qix-): Every single core will be competing for a list of the same threads
-		// XXX(qix-): until a thread migration system is implemented.
-		let thread_list = self.kernel.state().threads().lock();
+		if let Some(thread) = self.take_current() {
+			let mut t = thread.lock();
+			t.running_on_id = None;
+			let requeue = t.run_on_id == Some(id) && t.sleeping_until.is_none() && t.is_runnable();
+			drop(t);
 
-		while self.next_index < thread_list.len() {
-			let thread = &thread_list[self.next_index];
-			self.next_index += 1;
+			if requeue {
+				self.run_queue.lock().push(&thread);
+			}
+		}
 
-			if let Some(thread) = thread.upgrade() {
-				let mut t = thread.lock();
+		let online_cores = self.kernel.state().online_cores();
 
-				match (t.run_on_id, t.running_on_id) {
-					(Some(run_on), _) if run_on != self.kernel.id() => {
-						// Not scheduled to run on this core.
-						continue;
-					}
-					(_, Some(running_on)) => {
-						// Thread is currently running; skip it.
-						if running_on == self.kernel.id() {
-							// Something isn't right; it's not running here.
-							// Clear it and continue so it can be tried again later.
-							t.running_on_id = None;
-						}
-						continue;
-					}
-					(None, _) => {
-						// Thread is not assigned to any core.
-						// Migrate it to this core.
-						t.run_on_id = Some(self.kernel.id());
-						H::migrate_thread(self.kernel, &mut t);
-
-						// Select it for execution.
-						drop(t);
-						return Some(thread);
-					}
-					(Some(run_on), None) if run_on == self.kernel.id() => {
-						// Select it for execution.
-						drop(t);
-						return Some(thread.clone());
-					}
-					_ => {
-						// Not pertinent to this core.
-						continue;
-					}
+		loop {
+			let next = self.run_queue.lock().pop();
+
+			let Some(thread) = next else {
+				if self.refill::<H>() {
+					continue;
 				}
+
+				return None;
+			};
+
+			let mut t = thread.lock();
+
+			if t.run_on_id != Some(id)
+				|| t.running_on_id.is_some()
+				|| t.sleeping_until.is_some()
+				|| !t.is_runnable()
+			{
+				// Stale entry; the thread has since been reassigned,
+				// is running elsewhere, is asleep, blocked or terminated.
+				continue;
 			}
-		}
 
-		drop(thread_list);
+			if !t.allows_core(id, online_cores) {
+				// Thread's affinity excludes this core. Release it so
+				// that an allowed core claims and migrates it.
+				t.run_on_id = None;
+				continue;
+			}
+
+			t.running_on_id = Some(id);
+			drop(t);
+
+			self.set_current(thread.clone());
+			return Some(thread);
+		}
+	}
 
-		self.next_index = 0;
-		None
+	/// Wakes expired sleepers, selects the next thread to run and
+	/// re-arms the timer.
+	///
+	/// # Safety
+	/// Interrupts MUST be disabled before calling this function.
+	unsafe fn repick<H: Handler<A>>(&mut self, handler: &H) -> Option<Arc<Mutex<Thread<A>>>> {
+		self.kernel.reschedule_pending.store(false, Relaxed);
+		self.wake_expired();
+		let previous = self.current.clone();
+		let result = self.pick_user_thread::<H>();
+		self.record_event(previous.as_ref(), result.as_ref());
+		self.arm_timer(handler);
+		result
 	}
 
 	/// Called whenever the architecture has reached a codepath
@@ -187,9 +696,7 @@ impl<A: Arch> Scheduler<A> {
 		&mut self,
 		handler: &H,
 	) -> Option<Arc<Mutex<Thread<A>>>> {
-		let result = self.pick_user_thread::<H>();
-		handler.schedule_timer(1000);
-		result
+		self.repick(handler)
 	}
 
 	/// Indicates to the kernel that the system timer has fired,
@@ -199,6 +706,10 @@ impl<A: Arch> Scheduler<A> {
 	/// executing, or `None` if the architecture should enter
 	/// a low-power / wait state until an interrupt or event occurs.
 	///
+	/// If preemption is disabled on this core (see
+	/// [`Kernel::preemptible()`]), the current thread is returned
+	/// and given another time slice.
+	///
 	/// # Interrupt Safety
 	/// This function is safe to call from an interrupt context,
 	/// though it is _not_ explicitly required to be called from
@@ -220,8 +731,55 @@ impl<A: Arch> Scheduler<A> {
 		&mut self,
 		handler: &H,
 	) -> Option<Arc<Mutex<Thread<A>>>> {
-		let result = self.pick_user_thread::<H>();
-		handler.schedule_timer(1000);
-		result
+		if !self.kernel.preemptible() {
+			if let Some(current) = self.current.clone() {
+				// Preemption is disabled; let the current thread
+				// continue for another time slice.
+				self.wake_expired();
+				self.arm_timer(handler);
+				return Some(current);
+			}
+		}
+
+		if self.current.is_some() {
+			self.stats.preemptive_yields += 1;
+		}
+
+		self.repick(handler)
+	}
+
+	/// Indicates to the kernel that another core has asked this core
+	/// to reschedule (see [`Kernel::request_reschedule()`]), typically
+	/// because a thread was placed onto this core's run queue.
+	///
+	/// If a thread is currently running, it's returned and allowed to
+	/// finish its time slice; the new thread is picked up at the next
+	/// timer event. Otherwise, a new thread is selected as with
+	/// [`Self::event_idle()`].
+	///
+	/// Returns either a userspace handle to switch to and continue
+	/// executing, or `None` if the architecture should enter
+	/// a low-power / wait state until an interrupt or event occurs.
+	///
+	/// # Interrupt Safety
+	/// This function is safe to call from an interrupt context,
+	/// though it is _not_ explicitly required to be called from
+	/// such a context.
+	///
+	/// # Safety
+	/// **Interrupts or any other asynchronous events must be
+	/// disabled before calling this function.** At no point
+	/// can other scheduler methods be invoked while this function
+	/// is running.
+	#[must_use]
+	pub unsafe fn event_reschedule<H: Handler<A>>(
+		&mut self,
+		handler: &H,
+	) -> Option<Arc<Mutex<Thread<A>>>> {
+		if let Some(current) = self.current.clone() {
+			return Some(current);
+		}
+
+		self.repick(handler)
 	}
 }
@@ -0,0 +1,277 @@
+//! A no-op [`Arch`] implementation for the kernel's unit tests.
+//!
+//! Address spaces have no backing page tables; every mapping operation
+//! trivially succeeds (without mapping anything) and translations always
+//! fail. Nothing here touches the hardware or the core-local [`Kernel`],
+//! so only code paths that don't require either may be tested with it.
+//!
+//! [`Kernel`]: crate::Kernel
+#![cfg(test)]
+
+use oro_elf::{ElfClass, ElfEndianness, ElfMachine};
+use oro_mem::{
+	mapper::{AddressSegment, AddressSpace, MapError, PageFlags, UnmapError},
+	pfa::Alloc,
+};
+
+use crate::{Arch, core_id::CoreId, interrupt::InterruptController};
+
+/// The no-op test architecture.
+pub struct TestArch;
+
+/// The no-op test address space.
+pub struct TestAddressSpace;
+
+/// The (only) no-op test address segment.
+pub struct TestSegment;
+
+/// The no-op test interrupt controller.
+pub struct TestIntCtrl;
+
+impl Arch for TestArch {
+	type AddrSpace = TestAddressSpace;
+	type IntCtrl = TestIntCtrl;
+	type InterruptState = ();
+
+	const ELF_CLASS: ElfClass = ElfClass::Class64;
+	const ELF_ENDIANNESS: ElfEndianness = ElfEndianness::Little;
+	const ELF_MACHINE: ElfMachine = ElfMachine::X86_64;
+
+	fn interrupt_controller(_core: &Self::CoreState) -> &Self::IntCtrl {
+		&TestIntCtrl
+	}
+
+	fn fetch_interrupts() -> Self::InterruptState {}
+
+	fn disable_interrupts() {}
+
+	fn restore_interrupts(_state: Self::InterruptState) {}
+
+	fn send_reschedule(_core: CoreId) {}
+
+	fn halt_other_cores() {}
+
+	fn flush_tlb_range(_virt: usize, _len: usize) {}
+
+	fn halt_once_and_wait() {
+		panic!("test architecture halted");
+	}
+
+	fn make_instance_unique(_mapper: &()) -> Result<(), MapError> {
+		Ok(())
+	}
+
+	fn new_thread_state(_stack_ptr: usize, _entry_point: usize) -> Self::ThreadState {}
+
+	fn initialize_thread_mappings(
+		_thread: &(),
+		_thread_state: &mut Self::ThreadState,
+	) -> Result<(), MapError> {
+		Ok(())
+	}
+
+	fn reclaim_thread_mappings(_thread: &(), _thread_state: &mut Self::ThreadState) {}
+}
+
+impl InterruptController for TestIntCtrl {
+	fn eoi(&self) {}
+
+	fn mask(&self, _vector: u8) {}
+
+	fn unmask(&self, _vector: u8) {}
+}
+
+// SAFETY(qix-): Nothing is ever mapped.
+unsafe impl AddressSpace for TestAddressSpace {
+	type SupervisorHandle = ();
+	type SupervisorSegment = TestSegment;
+	type UserHandle = ();
+	type UserSegment = TestSegment;
+
+	unsafe fn current_supervisor_space() -> Self::SupervisorHandle {}
+
+	fn new_supervisor_space_in<A>(_alloc: &mut A) -> Option<Self::SupervisorHandle>
+	where
+		A: Alloc,
+	{
+		Some(())
+	}
+
+	fn new_user_space_empty_in<A>(_alloc: &mut A) -> Option<Self::UserHandle>
+	where
+		A: Alloc,
+	{
+		Some(())
+	}
+
+	fn new_user_space_in<A>(_space: &(), _alloc: &mut A) -> Option<Self::UserHandle>
+	where
+		A: Alloc,
+	{
+		Some(())
+	}
+
+	fn duplicate_supervisor_space_shallow_in<A>(
+		_space: &(),
+		_alloc: &mut A,
+	) -> Option<Self::SupervisorHandle>
+	where
+		A: Alloc,
+	{
+		Some(())
+	}
+
+	fn duplicate_user_space_shallow_in<A>(_space: &(), _alloc: &mut A) -> Option<Self::UserHandle>
+	where
+		A: Alloc,
+	{
+		Some(())
+	}
+
+	fn duplicate_user_space_deep_in<A>(_space: &(), _alloc: &mut A) -> Option<Self::UserHandle>
+	where
+		A: Alloc,
+	{
+		Some(())
+	}
+
+	fn free_user_space_handle_in<A>(_space: (), _alloc: &mut A)
+	where
+		A: Alloc,
+	{
+	}
+
+	fn free_user_space_deep_in<A>(_space: (), _alloc: &mut A)
+	where
+		A: Alloc,
+	{
+	}
+
+	fn translate(_space: &(), _virt: usize) -> Option<(u64, PageFlags)> {
+		None
+	}
+
+	unsafe fn resolve_write_fault(_space: &(), _virt: usize) -> bool {
+		false
+	}
+
+	fn kernel_code() -> Self::SupervisorSegment {
+		TestSegment
+	}
+
+	fn kernel_data() -> Self::SupervisorSegment {
+		TestSegment
+	}
+
+	fn kernel_rodata() -> Self::SupervisorSegment {
+		TestSegment
+	}
+
+	fn kernel_stack() -> Self::SupervisorSegment {
+		TestSegment
+	}
+
+	fn kernel_core_local() -> Self::SupervisorSegment {
+		TestSegment
+	}
+
+	fn kernel_heap() -> Self::SupervisorSegment {
+		TestSegment
+	}
+
+	fn sysabi() -> Self::UserSegment {
+		TestSegment
+	}
+
+	fn user_code() -> Self::UserSegment {
+		TestSegment
+	}
+
+	fn user_data() -> Self::UserSegment {
+		TestSegment
+	}
+
+	fn user_rodata() -> Self::UserSegment {
+		TestSegment
+	}
+
+	fn user_heap() -> Self::UserSegment {
+		TestSegment
+	}
+
+	fn user_thread_stack() -> Self::UserSegment {
+		TestSegment
+	}
+}
+
+// SAFETY(qix-): Nothing is ever mapped.
+unsafe impl AddressSegment<()> for TestSegment {
+	fn range(&self) -> (usize, usize) {
+		(0x1000, 0xFFFF_FFFF)
+	}
+
+	fn provision_as_shared_in<A>(&self, _space: &(), _alloc: &mut A) -> Result<(), MapError>
+	where
+		A: Alloc,
+	{
+		Ok(())
+	}
+
+	fn map_in<A>(
+		&self,
+		_space: &(),
+		_alloc: &mut A,
+		_virt: usize,
+		_phys: u64,
+	) -> Result<(), MapError>
+	where
+		A: Alloc,
+	{
+		Ok(())
+	}
+
+	unsafe fn unmap_all_without_reclaim(&self, _space: &()) {}
+
+	fn apply_user_space_shallow(&self, _destination: &(), _overlay: &()) -> Result<(), MapError> {
+		Ok(())
+	}
+
+	unsafe fn unmap_all_and_reclaim_in<A>(&self, _space: &(), _alloc: &mut A)
+	where
+		A: Alloc,
+	{
+	}
+
+	fn map_nofree_in<A>(
+		&self,
+		_space: &(),
+		_alloc: &mut A,
+		_virt: usize,
+		_phys: u64,
+	) -> Result<(), MapError>
+	where
+		A: Alloc,
+	{
+		Ok(())
+	}
+
+	fn unmap_in<A>(&self, _space: &(), _alloc: &mut A, _virt: usize) -> Result<u64, UnmapError>
+	where
+		A: Alloc,
+	{
+		Err(UnmapError::NotMapped)
+	}
+
+	fn remap_in<A>(
+		&self,
+		_space: &(),
+		_alloc: &mut A,
+		_virt: usize,
+		_phys: u64,
+	) -> Result<Option<u64>, MapError>
+	where
+		A: Alloc,
+	{
+		Ok(None)
+	}
+}
@@ -1,15 +1,115 @@
 //! Thread management types and functions.
 
+use core::sync::atomic::{
+	AtomicU64,
+	Ordering::{AcqRel, Acquire},
+};
+
 use oro_macro::assert;
 use oro_mem::{
 	alloc::sync::Arc,
-	global_alloc::GlobalPfa,
-	mapper::{AddressSegment, AddressSpace, MapError, UnmapError},
+	mapper::{AddressSegment, AddressSpace, GuardError, MapError},
 	pfa::Alloc,
 };
 use oro_sync::{Lock, Mutex};
 
-use crate::{AddrSpace, Arch, Kernel, UserHandle, instance::Instance};
+use crate::{
+	AddrSpace, Arch, Kernel, UserHandle,
+	core_id::CoreId,
+	instance::Instance,
+	port::PollRegistration,
+	ring::{AccountedAlloc, PageAccount},
+	time::Instant,
+	wait_queue::WaitQueue,
+};
+
+/// The maximum length, in bytes, of a thread's name.
+pub const THREAD_NAME_LEN: usize = 32;
+
+/// A thread's (optional) name, for debugging purposes.
+///
+/// Stored inline as up to [`THREAD_NAME_LEN`] bytes of UTF-8,
+/// padded with NULs; an all-NUL name means the thread is unnamed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ThreadName([u8; THREAD_NAME_LEN]);
+
+impl ThreadName {
+	/// Creates a new name from the given string.
+	///
+	/// Names longer than [`THREAD_NAME_LEN`] bytes are truncated
+	/// (at a character boundary). Since NULs are used for padding,
+	/// the name is also cut short at the first NUL, if any.
+	#[must_use]
+	pub fn new(name: &str) -> Self {
+		let name = name.split('\0').next().unwrap_or_default();
+
+		let len = (0..=name.len().min(THREAD_NAME_LEN))
+			.rev()
+			.find(|&i| name.is_char_boundary(i))
+			.unwrap_or(0);
+
+		let mut buf = [0; THREAD_NAME_LEN];
+		buf[..len].copy_from_slice(&name.as_bytes()[..len]);
+		Self(buf)
+	}
+
+	/// Returns the name, or `None` if the thread is unnamed.
+	#[must_use]
+	pub fn as_str(&self) -> Option<&str> {
+		let len = self
+			.0
+			.iter()
+			.position(|&b| b == 0)
+			.unwrap_or(THREAD_NAME_LEN);
+
+		// SAFETY(qix-): Only whole `str` prefixes (split at character
+		// SAFETY(qix-): boundaries, without NULs) are ever stored.
+		let name = unsafe { core::str::from_utf8_unchecked(&self.0[..len]) };
+		(!name.is_empty()).then_some(name)
+	}
+}
+
+impl core::fmt::Display for ThreadName {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.write_str(self.as_str().unwrap_or("<unnamed>"))
+	}
+}
+
+/// The number of distinct signals that may be pending on a thread
+/// (see [`Thread::signal()`]).
+pub const SIGNAL_COUNT: u8 = 64;
+
+/// Errors that can occur when signaling a thread.
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub enum SignalError {
+	/// The signal number is not below [`SIGNAL_COUNT`].
+	OutOfRange,
+}
+
+/// Errors that can occur when joining a thread (see [`crate::Kernel::join()`]).
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub enum JoinError {
+	/// The thread hasn't terminated; the current thread has been blocked
+	/// until it does. Once resumed, the current thread must join again to
+	/// read the exit code.
+	WouldBlock,
+	/// The thread hasn't terminated, but the current thread has pending
+	/// signals (see [`Thread::signal()`]) and thus hasn't been blocked.
+	Interrupted,
+}
+
+/// The lifecycle state of a [`Thread`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunState {
+	/// The thread may be selected to run (or is running).
+	Runnable,
+	/// The thread is waiting on a [`WaitQueue`], and is never
+	/// selected to run until it's woken.
+	Blocked,
+	/// The thread has exited with the given code, and will never
+	/// run again (see [`Thread::exit()`]).
+	Terminated(i32),
+}
 
 /// A singular system thread.
 ///
@@ -35,21 +135,51 @@ pub struct Thread<A: Arch> {
 	/// None if this thread hasn't been claimed by any core
 	/// (or the core has powered off and the thread should
 	/// be migrated).
-	pub run_on_id: Option<usize>,
+	pub run_on_id: Option<CoreId>,
 	/// The kernel core ID this thread is currently running on.
 	///
 	/// None if this thread is not currently running.
-	pub running_on_id: Option<usize>,
+	pub running_on_id: Option<CoreId>,
+	/// The deadline until which this thread is sleeping.
+	///
+	/// None if this thread is not sleeping. Sleeping threads
+	/// are never selected to run.
+	pub sleeping_until: Option<Instant>,
+	/// The thread's lifecycle state.
+	pub run_state: RunState,
+	/// The set of cores this thread may run on, as a bitset of
+	/// [`CoreId`]s (see [`Self::set_affinity()`]).
+	affinity: u64,
+	/// The page frame account to which the thread's stack is charged.
+	account: Arc<PageAccount>,
+	/// The thread's name (see [`Self::set_name()`]).
+	name: ThreadName,
+	/// Threads waiting for this thread to terminate.
+	joiners: WaitQueue<A>,
+	/// The thread this thread is joining, if any.
+	///
+	/// Keeps the joined thread (and thus its exit code) alive
+	/// until this thread has read the code.
+	joining: Option<Arc<Mutex<Thread<A>>>>,
+	/// The thread's pending signals, as a bitset of signal numbers
+	/// (see [`Self::signal()`]).
+	pending_signals: AtomicU64,
+	/// The thread's outstanding port registrations, if it's blocked
+	/// in (or was woken from) [`Kernel::poll()`].
+	pub(crate) polling: Option<PollRegistration<A>>,
 }
 
 impl<A: Arch> Thread<A> {
-	/// Creates a new thread in the given module instance.
+	/// Creates a new thread in the given module instance, optionally
+	/// giving it a name (see [`Self::set_name()`]).
 	#[expect(clippy::missing_panics_doc)]
 	pub fn new(
 		instance: &Arc<Mutex<Instance<A>>>,
 		entry_point: usize,
+		name: Option<&str>,
 	) -> Result<Arc<Mutex<Thread<A>>>, MapError> {
 		let id = Kernel::<A>::get().state().allocate_id();
+		let account = instance.lock().account().clone();
 
 		// Allocate a thread stack.
 		// XXX(qix-): This isn't very memory efficient, I just want it to be safe and correct
@@ -64,61 +194,32 @@ impl<A: Arch> Thread<A> {
 			let stack_segment = AddrSpace::<A>::user_thread_stack();
 
 			// TODO(qix-): If/when we support larger page sizes, this will need to be adjusted.
-			let mut stack_ptr = stack_segment.range().1 & !0xFFF;
-
-			// Make sure the top guard page is unmapped.
-			// This is more of a sanity check.
-			match AddrSpace::<A>::user_thread_stack().unmap(&thread_mapper, stack_ptr) {
-				Ok(phys) => {
-					panic!(
-						"empty user address space stack guard page was mapped to physical address \
-						 {phys:#016X}"
-					)
-				}
-				Err(UnmapError::NotMapped) => (),
-				Err(e) => {
-					panic!(
-						"failed to assert unmap of empty user address space stack guard page: \
-						 {e:?}"
-					)
-				}
-			}
+			let stack_ptr = stack_segment.range().1 & !0xFFF;
 
-			let final_stack_ptr = stack_ptr;
-
-			// Map in the stack pages.
+			// Map in the stack pages, between two guard pages.
 			// TODO(qix-): Allow this to be configurable
-			for _ in 0..4 {
-				stack_ptr -= 0x1000;
-				let phys = GlobalPfa.allocate().ok_or(MapError::OutOfMemory)?;
-				stack_segment.map(&thread_mapper, stack_ptr, phys)?;
-			}
-
-			// Make sure the bottom guard page is unmapped.
-			// This is more of a sanity check.
-			stack_ptr -= 0x1000;
-			match AddrSpace::<A>::user_thread_stack().unmap(&thread_mapper, stack_ptr) {
-				Ok(phys) => {
-					panic!(
-						"empty user address space stack guard page was mapped to physical address \
-						 {phys:#016X}"
-					)
-				}
-				Err(UnmapError::NotMapped) => (),
-				Err(e) => {
-					panic!(
-						"failed to assert unmap of empty user address space stack guard page: \
-						 {e:?}"
-					)
-				}
+			// NOTE(qix-): Stack frames (and the page tables backing them) are
+			// NOTE(qix-): charged to the instance's ring.
+			let mut alloc = AccountedAlloc::new(&account);
+			match stack_segment.map_with_guards_in(&thread_mapper, &mut alloc, stack_ptr, 4) {
+				Ok(_) => Ok(stack_ptr),
+				Err(GuardError::Map(err)) => Err(err),
+				// NOTE(qix-): The address space is brand new; this is more of a sanity check.
+				Err(err) => panic!("failed to map user thread stack: {err}"),
 			}
-
-			Ok(final_stack_ptr)
 		};
 
 		let stack_ptr = match stack_ptr {
 			Ok(p) => p,
 			Err(err) => {
+				// Release the (accounted) stack frames before freeing the rest.
+				// SAFETY(qix-): The address space was just created and isn't used elsewhere.
+				unsafe {
+					AddrSpace::<A>::user_thread_stack().unmap_all_and_reclaim_in(
+						&thread_mapper,
+						&mut AccountedAlloc::new(&account),
+					);
+				}
 				AddrSpace::<A>::free_user_space_deep(thread_mapper);
 				return Err(err);
 			}
@@ -147,7 +248,8 @@ impl<A: Arch> Thread<A> {
 			// TODO(qix-): Double check this is correct...
 			// SAFETY: We just allocated this address space, so it should be safe to unmap its thread data.
 			unsafe {
-				AddrSpace::<A>::user_thread_stack().unmap_all_and_reclaim(&mapper);
+				AddrSpace::<A>::user_thread_stack()
+					.unmap_all_and_reclaim_in(&mapper, &mut AccountedAlloc::new(&account));
 			}
 			AddrSpace::<A>::free_user_space_handle(mapper);
 			return Err(err);
@@ -160,24 +262,235 @@ impl<A: Arch> Thread<A> {
 			thread_state,
 			run_on_id: None,
 			running_on_id: None,
+			sleeping_until: None,
+			run_state: RunState::Runnable,
+			affinity: 0,
+			account,
+			name: name.map(ThreadName::new).unwrap_or_default(),
+			joiners: WaitQueue::new(),
+			joining: None,
+			pending_signals: AtomicU64::new(0),
+			polling: None,
 		}));
 
 		instance.lock().threads.push(r.clone());
-		Kernel::<A>::get()
-			.state()
-			.threads()
-			.lock()
-			.push(Arc::downgrade(&r));
+		Kernel::<A>::get().state().threads.register(&r);
 
 		Ok(r)
 	}
 
+	/// Creates a new thread in the given module instance without mapping
+	/// a stack or registering it with the kernel, for unit tests that
+	/// can't access the kernel state.
+	#[cfg(test)]
+	pub(crate) fn new_detached(id: u64, instance: &Arc<Mutex<Instance<A>>>) -> Arc<Mutex<Self>> {
+		Arc::new(Mutex::new(Self {
+			id,
+			instance: instance.clone(),
+			mapper: AddrSpace::<A>::new_user_space_empty().unwrap(),
+			thread_state: A::new_thread_state(0, 0),
+			run_on_id: None,
+			running_on_id: None,
+			sleeping_until: None,
+			run_state: RunState::Runnable,
+			affinity: 0,
+			account: instance.lock().account().clone(),
+			name: ThreadName::default(),
+			joiners: WaitQueue::new(),
+			joining: None,
+			pending_signals: AtomicU64::new(0),
+			polling: None,
+		}))
+	}
+
 	/// Returns the thread's ID.
 	#[must_use]
 	pub fn id(&self) -> u64 {
 		self.id
 	}
 
+	/// Terminates the thread with the given exit code.
+	///
+	/// The thread is marked as [`RunState::Terminated`] and removed from
+	/// its instance, and any threads joining it (see [`Kernel::join()`])
+	/// are woken. The thread itself is freed once the last joiner has
+	/// read the code and all other references to it have been dropped.
+	///
+	/// If the thread is currently running, it continues to do so until
+	/// its core next selects a thread; it's never selected again. Exiting
+	/// an already-terminated thread is a no-op.
+	///
+	/// # Lock Ordering
+	/// The thread must not be locked by the caller.
+	pub fn exit(this: &Arc<Mutex<Self>>, code: i32) {
+		let (instance, joiners) = {
+			let mut t = this.lock();
+			if matches!(t.run_state, RunState::Terminated(_)) {
+				return;
+			}
+
+			t.run_state = RunState::Terminated(code);
+			(t.instance.clone(), t.joiners.take())
+		};
+
+		instance
+			.lock()
+			.threads
+			.retain(|thread| !Arc::ptr_eq(thread, this));
+
+		joiners.wake_all();
+	}
+
+	/// Blocks `joiner` until `this` terminates, registering it with
+	/// `this`'s joiners.
+	///
+	/// If `this` has already terminated, `joiner` is left runnable (and
+	/// released from the join); the exit code is returned instead.
+	///
+	/// The caller must then block `joiner`, which the scheduler refuses
+	/// to do if it has pending signals (see [`Self::signal()`]); the join
+	/// must then be undone via [`Self::cancel_join()`].
+	///
+	/// # Lock Ordering
+	/// Neither thread may be locked by the caller. The two are never
+	/// locked at the same time.
+	pub(crate) fn join_on(this: &Arc<Mutex<Self>>, joiner: &Arc<Mutex<Self>>) -> Option<i32> {
+		// NOTE(qix-): The joiner is marked as blocked _before_ checking for
+		// NOTE(qix-): termination such that a concurrent exit can't miss it.
+		{
+			let mut j = joiner.lock();
+			j.run_state = RunState::Blocked;
+			j.joining = Some(this.clone());
+		}
+
+		let code = {
+			let mut t = this.lock();
+			let code = t.exit_code();
+			if code.is_none() {
+				t.joiners.push(joiner);
+			}
+			code
+		};
+
+		if code.is_some() {
+			let mut j = joiner.lock();
+			j.run_state = RunState::Runnable;
+			j.joining = None;
+		}
+
+		code
+	}
+
+	/// Releases `joiner` from a join on `this` (see [`Self::join_on()`])
+	/// that was interrupted before `joiner` could block.
+	///
+	/// # Lock Ordering
+	/// Neither thread may be locked by the caller. The two are never
+	/// locked at the same time.
+	pub(crate) fn cancel_join(this: &Arc<Mutex<Self>>, joiner: &Arc<Mutex<Self>>) {
+		this.lock().joiners.remove(joiner);
+		joiner.lock().joining = None;
+	}
+
+	/// Raises the given signal on the thread, notifying it of some
+	/// asynchronous event (e.g. a port becoming readable).
+	///
+	/// Signals are edge-triggered and coalesce; each signal number is a
+	/// single pending bit, and raising an already-pending signal is a
+	/// no-op. The thread's pending signals are taken (see
+	/// [`Self::take_pending()`]) as it returns from a system call.
+	///
+	/// If the thread is blocked, it's woken (see [`crate::wait_queue`]);
+	/// note that whatever it was waiting on may not have occurred. While
+	/// signals are pending, the thread is never blocked; [`Kernel::poll()`]
+	/// and [`Kernel::join()`] are interrupted instead. If it's
+	/// instead running on another core, that core is asked to reschedule
+	/// such that the signal is observed promptly.
+	///
+	/// # Lock Ordering
+	/// The thread must not be locked by the caller.
+	pub fn signal(this: &Arc<Mutex<Self>>, sig: u8) -> Result<(), SignalError> {
+		if sig >= SIGNAL_COUNT {
+			return Err(SignalError::OutOfRange);
+		}
+
+		let bit = 1 << sig;
+
+		let running_on = {
+			let t = this.lock();
+			if t.pending_signals.fetch_or(bit, AcqRel) & bit != 0 {
+				return Ok(());
+			}
+			t.running_on_id
+		};
+
+		// NOTE(qix-): The bit is set _before_ attempting the wake; a thread
+		// NOTE(qix-): that blocks afterward must check its pending signals
+		// NOTE(qix-): once it has been marked as blocked, lest it miss this one.
+		if !crate::wait_queue::wake(this) {
+			if let Some(core) = running_on {
+				let kernel = Kernel::<A>::get();
+				if core != kernel.id() {
+					kernel.request_reschedule(core);
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Takes the thread's pending signals (as a bitset of signal
+	/// numbers), clearing them.
+	///
+	/// See [`Self::signal()`].
+	#[must_use]
+	pub fn take_pending(&self) -> u64 {
+		self.pending_signals.swap(0, AcqRel)
+	}
+
+	/// Returns whether or not the thread has any pending signals.
+	///
+	/// See [`Self::signal()`].
+	#[must_use]
+	pub fn has_pending_signals(&self) -> bool {
+		self.pending_signals.load(Acquire) != 0
+	}
+
+	/// Returns the thread's exit code, or `None` if it hasn't terminated.
+	#[must_use]
+	pub fn exit_code(&self) -> Option<i32> {
+		match self.run_state {
+			RunState::Terminated(code) => Some(code),
+			_ => None,
+		}
+	}
+
+	/// Returns whether or not the thread may be selected to run.
+	#[must_use]
+	pub fn is_runnable(&self) -> bool {
+		self.run_state == RunState::Runnable
+	}
+
+	/// Returns the thread's name, or `None` if it's unnamed.
+	#[must_use]
+	pub fn name(&self) -> Option<&str> {
+		self.name.as_str()
+	}
+
+	/// Returns the thread's name as a (copyable) [`ThreadName`].
+	#[must_use]
+	pub fn thread_name(&self) -> ThreadName {
+		self.name
+	}
+
+	/// Sets the thread's name, used only for debugging purposes.
+	///
+	/// See [`ThreadName::new()`] for how long names are handled.
+	/// An empty name makes the thread unnamed.
+	pub fn set_name(&mut self, name: &str) {
+		self.name = ThreadName::new(name);
+	}
+
 	/// Returns module instance [`Handle`] to which this thread belongs.
 	pub fn instance(&self) -> Arc<Mutex<Instance<A>>> {
 		self.instance.clone()
@@ -200,6 +513,40 @@ impl<A: Arch> Thread<A> {
 	pub fn thread_state_mut(&mut self) -> &mut A::ThreadState {
 		&mut self.thread_state
 	}
+
+	/// Returns the thread's CPU affinity mask.
+	///
+	/// See [`Self::set_affinity()`].
+	#[must_use]
+	pub fn affinity(&self) -> u64 {
+		self.affinity
+	}
+
+	/// Sets the thread's CPU affinity mask.
+	///
+	/// Bit `n` of the mask allows the thread to run on the core with
+	/// [`CoreId`] `n` (see [`CoreId::mask_bit()`]). An empty mask allows
+	/// the thread to run on any core; the same goes for a mask that
+	/// excludes all online cores, which is treated as empty. Cores whose
+	/// IDs don't fit in the mask can only run threads with an empty mask.
+	///
+	/// If the thread is currently assigned to a core that the new mask
+	/// excludes, it's migrated to an allowed core the next time the
+	/// scheduler of its current core ticks.
+	pub fn set_affinity(&mut self, mask: u64) {
+		self.affinity = mask;
+	}
+
+	/// Returns whether or not the thread's affinity mask allows it
+	/// to run on the given core, given the mask of online cores.
+	pub(crate) fn allows_core(&self, core: CoreId, online_mask: u64) -> bool {
+		let effective = self.affinity & online_mask;
+		if effective == 0 {
+			return true;
+		}
+
+		core.mask_bit().is_some_and(|bit| effective & bit != 0)
+	}
 }
 
 impl<A: Arch> Drop for Thread<A> {
@@ -212,7 +559,7 @@ impl<A: Arch> Drop for Thread<A> {
 		// making things every so slightly more bulletproof.
 		//
 		// XXX(qix-): Create a better mechanism for preventing dead-thread scheduling.
-		self.run_on_id = Some(usize::MAX);
+		self.run_on_id = Some(CoreId::INVALID);
 
 		// Sanity check; make sure the thread is not running on any scheduler,
 		// as that indicates a bug in the kernel.
@@ -223,7 +570,8 @@ impl<A: Arch> Drop for Thread<A> {
 		// SAFETY: Thread stack regions are specific to the thread and are not shared,
 		// SAFETY: and thus safe to reclaim.
 		unsafe {
-			AddrSpace::<A>::user_thread_stack().unmap_all_and_reclaim(&self.mapper);
+			AddrSpace::<A>::user_thread_stack()
+				.unmap_all_and_reclaim_in(&self.mapper, &mut AccountedAlloc::new(&self.account));
 		}
 
 		// Statically ensure that handles have no drop semantics. Otherwise, the following
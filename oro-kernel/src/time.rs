@@ -0,0 +1,305 @@
+//! Monotonic timekeeping for the Oro kernel.
+//!
+//! Time is measured in nanoseconds since boot, as an [`Instant`],
+//! and spans of time as a [`Duration`]. Neither uses floating point,
+//! and all arithmetic saturates rather than overflowing.
+//!
+//! The architecture registers a clock source (a free-running counter
+//! and its frequency) during boot via [`register_clock_source`]. Until
+//! it does, [`now()`] returns a best-effort monotonic counter that is
+//! guaranteed to increase but has no relation to real time.
+
+use core::sync::atomic::{
+	AtomicU64,
+	Ordering::{AcqRel, Relaxed},
+};
+
+use oro_sync::AtomicFnPtr;
+
+/// The number of nanoseconds in a microsecond.
+const NANOS_PER_MICRO: u64 = 1_000;
+/// The number of nanoseconds in a millisecond.
+const NANOS_PER_MILLI: u64 = 1_000_000;
+/// The number of nanoseconds in a second.
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+/// The registered clock source's counter read function, if any.
+static CLOCK_READ: AtomicFnPtr<ClockRead> = AtomicFnPtr::new();
+/// The registered clock source's counter frequency, in Hz.
+static CLOCK_FREQUENCY: AtomicU64 = AtomicU64::new(0);
+/// The clock source's counter value at the time it was registered.
+static CLOCK_BASE_TICKS: AtomicU64 = AtomicU64::new(0);
+/// The time, in nanoseconds, at which the clock source was registered.
+static CLOCK_BASE_NANOS: AtomicU64 = AtomicU64::new(0);
+/// The most recent time returned by [`now()`], in nanoseconds.
+///
+/// Used to keep time monotonic across cores (whose counters may be
+/// very slightly skewed) and across clock source registration.
+static LAST_NANOS: AtomicU64 = AtomicU64::new(0);
+
+/// Reads the raw counter of a clock source.
+pub type ClockRead = fn() -> u64;
+
+/// Registers the architecture's clock source.
+///
+/// `read` must return the current value of a free-running, monotonically
+/// increasing counter that is synchronized across all cores, and that
+/// ticks at `frequency_hz` Hz. Time continues from the point at which the
+/// clock source is registered; it never goes backwards.
+///
+/// # Safety
+/// Must be called at most once, during boot, by the primary core and
+/// prior to booting any secondary cores.
+///
+/// # Panics
+/// Panics if `frequency_hz` is zero.
+pub unsafe fn register_clock_source(read: ClockRead, frequency_hz: u64) {
+	assert_ne!(frequency_hz, 0, "clock source frequency must be non-zero");
+
+	CLOCK_BASE_NANOS.store(now().as_nanos(), Relaxed);
+	CLOCK_BASE_TICKS.store(read(), Relaxed);
+	CLOCK_FREQUENCY.store(frequency_hz, Relaxed);
+	CLOCK_READ.store(read);
+
+	oro_debug::install_clock(debug_clock);
+}
+
+/// The clock used for rate-limited debug messages, in milliseconds
+/// since boot.
+fn debug_clock() -> u64 {
+	now().saturating_duration_since(Instant::BOOT).as_millis()
+}
+
+/// Returns the current time since boot.
+///
+/// Guaranteed to never go backwards, across all cores.
+#[must_use]
+pub fn now() -> Instant {
+	let nanos = match CLOCK_READ.load() {
+		// Best-effort monotonic counter; tick once per call.
+		None => LAST_NANOS.load(Relaxed).saturating_add(1),
+		Some(read) => {
+			let ticks = read().saturating_sub(CLOCK_BASE_TICKS.load(Relaxed));
+			let frequency = CLOCK_FREQUENCY.load(Relaxed);
+
+			// NOTE(qix-): Widened to avoid overflowing the intermediate product,
+			// NOTE(qix-): which would otherwise occur after mere seconds at GHz rates.
+			#[expect(clippy::integer_division)]
+			let elapsed = (u128::from(ticks) * u128::from(NANOS_PER_SEC)) / u128::from(frequency);
+			let elapsed = u64::try_from(elapsed).unwrap_or(u64::MAX);
+
+			CLOCK_BASE_NANOS.load(Relaxed).saturating_add(elapsed)
+		}
+	};
+
+	let previous = LAST_NANOS.fetch_max(nanos, AcqRel);
+	Instant(nanos.max(previous))
+}
+
+/// A point in time, measured in nanoseconds since boot.
+///
+/// Obtained via [`now()`].
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+#[repr(transparent)]
+pub struct Instant(u64);
+
+impl Instant {
+	/// The instant at which the system booted.
+	pub const BOOT: Self = Self(0);
+
+	/// Creates an instant from the given number of nanoseconds since boot.
+	#[inline]
+	#[must_use]
+	pub const fn from_nanos(nanos: u64) -> Self {
+		Self(nanos)
+	}
+
+	/// Returns the number of nanoseconds since boot.
+	#[inline]
+	#[must_use]
+	pub const fn as_nanos(self) -> u64 {
+		self.0
+	}
+
+	/// Returns the amount of time elapsed from `earlier` to `self`,
+	/// or zero if `earlier` is later than `self`.
+	#[inline]
+	#[must_use]
+	pub const fn saturating_duration_since(self, earlier: Self) -> Duration {
+		Duration(self.0.saturating_sub(earlier.0))
+	}
+
+	/// Returns the amount of time elapsed since this instant.
+	#[inline]
+	#[must_use]
+	pub fn elapsed(self) -> Duration {
+		now().saturating_duration_since(self)
+	}
+
+	/// Adds the given duration to this instant, saturating at the maximum instant.
+	#[inline]
+	#[must_use]
+	pub const fn saturating_add(self, duration: Duration) -> Self {
+		Self(self.0.saturating_add(duration.0))
+	}
+
+	/// Subtracts the given duration from this instant, saturating at boot.
+	#[inline]
+	#[must_use]
+	pub const fn saturating_sub(self, duration: Duration) -> Self {
+		Self(self.0.saturating_sub(duration.0))
+	}
+}
+
+impl core::ops::Add<Duration> for Instant {
+	type Output = Self;
+
+	#[inline]
+	fn add(self, rhs: Duration) -> Self {
+		self.saturating_add(rhs)
+	}
+}
+
+impl core::ops::Sub<Duration> for Instant {
+	type Output = Self;
+
+	#[inline]
+	fn sub(self, rhs: Duration) -> Self {
+		self.saturating_sub(rhs)
+	}
+}
+
+impl core::ops::Sub for Instant {
+	type Output = Duration;
+
+	#[inline]
+	fn sub(self, rhs: Self) -> Duration {
+		self.saturating_duration_since(rhs)
+	}
+}
+
+/// A span of time, with nanosecond precision.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+#[repr(transparent)]
+pub struct Duration(u64);
+
+impl Duration {
+	/// The maximum representable duration.
+	pub const MAX: Self = Self(u64::MAX);
+	/// A duration of zero.
+	pub const ZERO: Self = Self(0);
+
+	/// Creates a duration from the given number of nanoseconds.
+	#[inline]
+	#[must_use]
+	pub const fn from_nanos(nanos: u64) -> Self {
+		Self(nanos)
+	}
+
+	/// Creates a duration from the given number of microseconds,
+	/// saturating at [`Self::MAX`].
+	#[inline]
+	#[must_use]
+	pub const fn from_micros(micros: u64) -> Self {
+		Self(micros.saturating_mul(NANOS_PER_MICRO))
+	}
+
+	/// Creates a duration from the given number of milliseconds,
+	/// saturating at [`Self::MAX`].
+	#[inline]
+	#[must_use]
+	pub const fn from_millis(millis: u64) -> Self {
+		Self(millis.saturating_mul(NANOS_PER_MILLI))
+	}
+
+	/// Creates a duration from the given number of seconds,
+	/// saturating at [`Self::MAX`].
+	#[inline]
+	#[must_use]
+	pub const fn from_secs(secs: u64) -> Self {
+		Self(secs.saturating_mul(NANOS_PER_SEC))
+	}
+
+	/// Returns the total number of whole nanoseconds.
+	#[inline]
+	#[must_use]
+	pub const fn as_nanos(self) -> u64 {
+		self.0
+	}
+
+	/// Returns the total number of whole microseconds.
+	#[inline]
+	#[must_use]
+	#[expect(clippy::integer_division)]
+	pub const fn as_micros(self) -> u64 {
+		self.0 / NANOS_PER_MICRO
+	}
+
+	/// Returns the total number of whole milliseconds.
+	#[inline]
+	#[must_use]
+	#[expect(clippy::integer_division)]
+	pub const fn as_millis(self) -> u64 {
+		self.0 / NANOS_PER_MILLI
+	}
+
+	/// Returns the total number of whole seconds.
+	#[inline]
+	#[must_use]
+	#[expect(clippy::integer_division)]
+	pub const fn as_secs(self) -> u64 {
+		self.0 / NANOS_PER_SEC
+	}
+
+	/// Returns the fractional part of the duration, in nanoseconds.
+	#[inline]
+	#[must_use]
+	pub const fn subsec_nanos(self) -> u32 {
+		(self.0 % NANOS_PER_SEC) as u32
+	}
+
+	/// Adds two durations, saturating at [`Self::MAX`].
+	#[inline]
+	#[must_use]
+	pub const fn saturating_add(self, rhs: Self) -> Self {
+		Self(self.0.saturating_add(rhs.0))
+	}
+
+	/// Subtracts two durations, saturating at [`Self::ZERO`].
+	#[inline]
+	#[must_use]
+	pub const fn saturating_sub(self, rhs: Self) -> Self {
+		Self(self.0.saturating_sub(rhs.0))
+	}
+
+	/// Multiplies the duration by a scalar, saturating at [`Self::MAX`].
+	#[inline]
+	#[must_use]
+	pub const fn saturating_mul(self, rhs: u64) -> Self {
+		Self(self.0.saturating_mul(rhs))
+	}
+}
+
+impl core::ops::Add for Duration {
+	type Output = Self;
+
+	#[inline]
+	fn add(self, rhs: Self) -> Self {
+		self.saturating_add(rhs)
+	}
+}
+
+impl core::ops::Sub for Duration {
+	type Output = Self;
+
+	#[inline]
+	fn sub(self, rhs: Self) -> Self {
+		self.saturating_sub(rhs)
+	}
+}
+
+impl core::fmt::Display for Duration {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		write!(f, "{}.{:09}s", self.as_secs(), self.subsec_nanos())
+	}
+}
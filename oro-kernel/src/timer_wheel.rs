@@ -0,0 +1,131 @@
+//! A hashed timer wheel, used by the scheduler to track
+//! sleeping threads.
+//!
+//! Entries are hashed into one of [`WHEEL_BUCKETS`] buckets by their
+//! expiry tick (of length [`TICK`]). Expiring entries only requires
+//! visiting the buckets for the ticks that have elapsed since the last
+//! expiry, rather than every entry in the wheel.
+
+use oro_mem::alloc::vec::Vec;
+
+use crate::time::{Duration, Instant};
+
+/// The number of buckets in the wheel.
+pub const WHEEL_BUCKETS: usize = 64;
+
+/// The granularity of the wheel; deadlines within the same tick
+/// hash to the same bucket.
+pub const TICK: Duration = Duration::from_millis(1);
+
+/// A hashed timer wheel of items, each with an expiry deadline.
+pub struct TimerWheel<T> {
+	/// The wheel's buckets, indexed by expiry tick modulo [`WHEEL_BUCKETS`].
+	///
+	/// Allocated upon first insertion.
+	buckets:   Vec<Vec<(Instant, T)>>,
+	/// The total number of items in the wheel.
+	len:       usize,
+	/// The tick at which the wheel was last expired.
+	last_tick: u64,
+}
+
+impl<T> TimerWheel<T> {
+	/// Creates a new, empty timer wheel.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self {
+			buckets:   Vec::new(),
+			len:       0,
+			last_tick: 0,
+		}
+	}
+
+	/// Returns the number of items in the wheel.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Returns whether or not the wheel is empty.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Inserts an item that expires at the given deadline.
+	pub fn insert(&mut self, deadline: Instant, item: T) {
+		if self.buckets.is_empty() {
+			self.buckets.resize_with(WHEEL_BUCKETS, Vec::new);
+		}
+
+		// NOTE(qix-): Deadlines that have already passed are placed into the
+		// NOTE(qix-): bucket that will be visited by the next expiry.
+		let tick = tick_of(deadline).max(self.last_tick);
+		self.buckets[bucket_of(tick)].push((deadline, item));
+		self.len += 1;
+	}
+
+	/// Removes all items whose deadlines are at or before `now`,
+	/// passing each to `expired`.
+	pub fn expire<F: FnMut(T)>(&mut self, now: Instant, mut expired: F) {
+		let now_tick = tick_of(now);
+
+		if self.len == 0 {
+			self.last_tick = now_tick;
+			return;
+		}
+
+		// If more ticks have elapsed than there are buckets, every bucket
+		// must be visited (but only once).
+		let span = now_tick
+			.saturating_sub(self.last_tick)
+			.min(WHEEL_BUCKETS as u64 - 1);
+
+		for tick in (now_tick - span)..=now_tick {
+			let bucket = &mut self.buckets[bucket_of(tick)];
+
+			let mut i = 0;
+			while i < bucket.len() {
+				if bucket[i].0 <= now {
+					let (_, item) = bucket.swap_remove(i);
+					self.len -= 1;
+					expired(item);
+				} else {
+					i += 1;
+				}
+			}
+		}
+
+		self.last_tick = now_tick;
+	}
+
+	/// Returns the earliest deadline in the wheel, if any.
+	#[must_use]
+	pub fn next_deadline(&self) -> Option<Instant> {
+		if self.len == 0 {
+			return None;
+		}
+
+		self.buckets
+			.iter()
+			.flat_map(|bucket| bucket.iter().map(|(deadline, _)| *deadline))
+			.min()
+	}
+}
+
+impl<T> Default for TimerWheel<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Returns the wheel tick in which the given instant falls.
+#[expect(clippy::integer_division)]
+fn tick_of(instant: Instant) -> u64 {
+	instant.as_nanos() / TICK.as_nanos()
+}
+
+/// Returns the bucket index for the given tick.
+fn bucket_of(tick: u64) -> usize {
+	(tick % WHEEL_BUCKETS as u64) as usize
+}
@@ -0,0 +1,128 @@
+//! Queues of threads blocked on some event.
+//!
+//! A thread that waits on an event is marked as [`RunState::Blocked`]
+//! and registered with the event's [`WaitQueue`], after which its
+//! scheduler stops running it. When the event fires, the queue is
+//! woken; each waiter is marked runnable again and placed back onto
+//! the run queue of the core it's assigned to.
+//!
+//! # Lock Ordering
+//! Waiters are never locked while the queue's owner is locked by the
+//! waker; [`WaitQueue::wake_all()`] consumes the queue, and must be
+//! called only once the owner's lock has been released.
+
+use oro_mem::alloc::{
+	sync::{Arc, Weak},
+	vec::Vec,
+};
+use oro_sync::{Lock, Mutex};
+
+use crate::{
+	Arch, Kernel,
+	thread::{RunState, Thread},
+};
+
+/// A queue of threads waiting on an event.
+pub struct WaitQueue<A: Arch> {
+	/// The waiting threads, in the order they started waiting.
+	waiters: Vec<Weak<Mutex<Thread<A>>>>,
+}
+
+impl<A: Arch> WaitQueue<A> {
+	/// Creates a new, empty wait queue.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self {
+			waiters: Vec::new(),
+		}
+	}
+
+	/// Returns the number of waiting threads.
+	///
+	/// May include threads that have since been dropped.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.waiters.len()
+	}
+
+	/// Returns whether or not no threads are waiting.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.waiters.is_empty()
+	}
+
+	/// Registers a thread as waiting on the queue.
+	///
+	/// The thread must already have been marked as
+	/// [`RunState::Blocked`], lest a wakeup be missed.
+	pub fn push(&mut self, thread: &Arc<Mutex<Thread<A>>>) {
+		self.waiters.push(Arc::downgrade(thread));
+	}
+
+	/// Removes the given thread from the queue, if it's registered,
+	/// along with any threads that have since been dropped.
+	pub fn remove(&mut self, thread: &Arc<Mutex<Thread<A>>>) {
+		self.waiters
+			.retain(|waiter| waiter.strong_count() != 0 && waiter.as_ptr() != Arc::as_ptr(thread));
+	}
+
+	/// Takes all of the waiters out of the queue, leaving it empty.
+	#[must_use]
+	pub fn take(&mut self) -> Self {
+		core::mem::take(self)
+	}
+
+	/// Wakes all waiting threads, consuming the queue.
+	///
+	/// Each (still blocked) waiter is marked as runnable and pushed
+	/// onto its assigned core's run queue, and that core is asked to
+	/// reschedule (see [`Kernel::request_reschedule()`]). Waiters that
+	/// aren't assigned to an online core are left for any core to claim.
+	pub fn wake_all(self) {
+		for thread in self.waiters.into_iter().filter_map(|t| t.upgrade()) {
+			wake(&thread);
+		}
+	}
+}
+
+/// Wakes the given thread, if it's blocked.
+///
+/// The thread is marked as runnable and pushed onto its assigned core's
+/// run queue, and that core is asked to reschedule (see
+/// [`Kernel::request_reschedule()`]). If the thread isn't assigned to an
+/// online core, it's left for any core to claim.
+///
+/// Returns whether or not the thread was blocked.
+///
+/// # Lock Ordering
+/// The thread must not be locked by the caller.
+pub(crate) fn wake<A: Arch>(thread: &Arc<Mutex<Thread<A>>>) -> bool {
+	let core = {
+		let mut t = thread.lock();
+		if t.run_state != RunState::Blocked {
+			return false;
+		}
+
+		t.run_state = RunState::Runnable;
+		// NOTE(qix-): Cancels any timeout (see `Scheduler::block_current_until()`);
+		// NOTE(qix-): the stale timer entry is ignored once it expires.
+		t.sleeping_until = None;
+		t.run_on_id
+	};
+
+	if let Some(core) = core {
+		let kernel = Kernel::<A>::get();
+		if let Some(run_queue) = kernel.state().find_run_queue(core) {
+			run_queue.lock().push(thread);
+			kernel.request_reschedule(core);
+		}
+	}
+
+	true
+}
+
+impl<A: Arch> Default for WaitQueue<A> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
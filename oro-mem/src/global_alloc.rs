@@ -27,10 +27,15 @@ type Heap = buddy_system_allocator::Heap<64>;
 // NOTE(qix-): this working, and the surface area for improper access is small.
 // TODO(qix-): Put both the PFA and global allocator behind a mutex in a shared
 // TODO(qix-): structure.
+// TODO(qix-): Switch over to the `BitmapAlloc` once the architectures carve out
+// TODO(qix-): storage for its bitmap at boot.
 static mut PFA: FiloPageFrameAllocator = FiloPageFrameAllocator::new();
 
 /// The global heap allocator for the Oro kernel.
-#[cfg_attr(all(not(feature = "std-alloc"), not(test)), global_allocator)]
+#[cfg_attr(
+	all(not(feature = "std-alloc"), not(feature = "kernel-heap"), not(test)),
+	global_allocator
+)]
 static ALLOCATOR: GlobalLockedHeap<TicketMutex<Heap>> =
 	GlobalLockedHeap(TicketMutex::new(Heap::empty()));
 
@@ -58,11 +63,21 @@ where
 		if let Ok(ptr) = heap.alloc(layout) {
 			ptr.as_ptr()
 		} else {
-			try_rescue_heap::<L>(&mut heap);
+			let cb = try_rescue_heap::<L>(&mut heap);
 
-			heap.alloc(layout)
+			let r = heap
+				.alloc(layout)
 				.map(core::ptr::NonNull::as_ptr)
-				.unwrap_or(core::ptr::null_mut())
+				.unwrap_or(core::ptr::null_mut());
+
+			// NOTE(qix-): The low watermark callback must not be invoked
+			// NOTE(qix-): with the heap lock held.
+			drop(heap);
+			if let Some(cb) = cb {
+				cb();
+			}
+
+			r
 		}
 	}
 
@@ -74,17 +89,24 @@ where
 }
 
 /// Attempts to rescue the heap by allocating and mapping new pages.
-fn try_rescue_heap<L>(heap: &mut <L as Lock>::Guard<'_>)
+///
+/// Returns the PFA's low watermark callback if the allocation crossed
+/// it; the caller must invoke it once the heap lock has been released.
+fn try_rescue_heap<L>(heap: &mut <L as Lock>::Guard<'_>) -> Option<fn()>
 where
 	L: Lock<Target = Heap>,
 {
-	// If there are no pages available, we can't do anything;
-	// the allocation will fail on return.
 	// SAFETY: We're in a critical section, so we can safely access the global PFA.
 	// SAFETY: By eschewing a second lock, we can avoid deadlocks.
 	#[expect(static_mut_refs)]
-	let Some(page) = unsafe { &mut PFA }.allocate() else {
-		return;
+	let pfa = unsafe { &mut PFA };
+
+	// If there are no pages available, we can't do anything;
+	// the allocation will fail on return.
+	let page = pfa.allocate();
+	let cb = pfa.take_low_watermark_callback();
+	let Some(page) = page else {
+		return cb;
 	};
 
 	// SAFETY: We just allocated this page, so it's safe to use.
@@ -99,6 +121,8 @@ where
 	unsafe {
 		heap.add_to_heap(virt, virt + 4096);
 	}
+
+	cb
 }
 
 /// Global page frame allocator proxy type.
@@ -151,7 +175,7 @@ impl GlobalPfa {
 		}
 
 		for page in (aligned_base..(aligned_base + length)).step_by(4096) {
-			pfa.free(page);
+			pfa.expose(page);
 		}
 
 		// SAFETY: We are in a critical section, which is good enough for the requirements
@@ -178,11 +202,15 @@ unsafe impl Alloc for GlobalPfa {
 
 		// SAFETY: We're in a critical section, so we can safely access the global PFA.
 		#[expect(static_mut_refs)]
-		let r = unsafe { PFA.allocate() };
+		let (r, cb) = unsafe { (PFA.allocate(), PFA.take_low_watermark_callback()) };
 
 		// Forcefully drop the lock (keep the spaceship flying).
 		drop(lock);
 
+		if let Some(cb) = cb {
+			cb();
+		}
+
 		r
 	}
 
@@ -201,4 +229,78 @@ unsafe impl Alloc for GlobalPfa {
 			PFA.free(frame);
 		}
 	}
+
+	fn allocate_contiguous(&mut self, count: usize, align_log2: u32) -> Option<u64> {
+		// Synthesize a lock from the global allocator,
+		// effectively synchronizing access to the PFA.
+		let lock = ALLOCATOR.0.lock();
+
+		// SAFETY: We're in a critical section, so we can safely access the global PFA.
+		#[expect(static_mut_refs)]
+		let (r, cb) = unsafe {
+			(
+				PFA.allocate_contiguous(count, align_log2),
+				PFA.take_low_watermark_callback(),
+			)
+		};
+
+		// Forcefully drop the lock (keep the spaceship flying).
+		drop(lock);
+
+		if let Some(cb) = cb {
+			cb();
+		}
+
+		r
+	}
+
+	fn free_page_count(&self) -> usize {
+		let lock = ALLOCATOR.0.lock();
+		// SAFETY: We're in a critical section, so we can safely access the global PFA.
+		#[expect(static_mut_refs)]
+		let r = unsafe { PFA.free_page_count() };
+		drop(lock);
+		r
+	}
+
+	fn total_page_count(&self) -> usize {
+		let lock = ALLOCATOR.0.lock();
+		// SAFETY: We're in a critical section, so we can safely access the global PFA.
+		#[expect(static_mut_refs)]
+		let r = unsafe { PFA.total_page_count() };
+		drop(lock);
+		r
+	}
+
+	fn used_page_count(&self) -> usize {
+		// NOTE(qix-): Read both under a single lock to get a consistent snapshot.
+		let lock = ALLOCATOR.0.lock();
+		// SAFETY: We're in a critical section, so we can safely access the global PFA.
+		#[expect(static_mut_refs)]
+		let r = unsafe { PFA.used_page_count() };
+		drop(lock);
+		r
+	}
+
+	fn set_low_watermark(&mut self, pages: usize, cb: fn()) {
+		let lock = ALLOCATOR.0.lock();
+		// SAFETY: We're in a critical section, so we can safely access the global PFA.
+		#[expect(static_mut_refs)]
+		unsafe {
+			PFA.set_low_watermark(pages, cb);
+		}
+		drop(lock);
+	}
+
+	unsafe fn free_contiguous(&mut self, base: u64, count: usize) {
+		// Synthesize a lock from the global allocator,
+		// effectively synchronizing access to the PFA.
+		let _lock = ALLOCATOR.0.lock();
+
+		// SAFETY: We're in a critical section, so we can safely access the global PFA.
+		#[expect(static_mut_refs)]
+		unsafe {
+			PFA.free_contiguous(base, count);
+		}
+	}
 }
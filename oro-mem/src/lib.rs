@@ -10,5 +10,6 @@ pub mod mapper;
 pub mod pfa;
 pub mod phys;
 pub mod translate;
+pub mod user;
 
 pub mod global_alloc;
@@ -172,6 +172,34 @@ pub unsafe trait AddressSpace: 'static {
 	where
 		A: Alloc;
 
+	/// Duplicates the given user address space handle. Uses the global allocator.
+	///
+	/// See [`Self::duplicate_user_space_deep_in()`] for details.
+	///
+	/// Returns None if any allocation(s) fail.
+	fn duplicate_user_space_deep(space: &Self::UserHandle) -> Option<Self::UserHandle> {
+		Self::duplicate_user_space_deep_in(space, &mut crate::global_alloc::GlobalPfa)
+	}
+
+	/// Duplicates the given user address space handle. Uses the given allocator.
+	///
+	/// The duplication is performed deeply for the user (lower) half of the
+	/// address space, meaning that the new handle has its own page tables for
+	/// all user mappings, which initially map the same contents as the original
+	/// handle but are otherwise private to it. Whether leaf pages are copied
+	/// eagerly or shared copy-on-write is up to the architecture. Supervisor
+	/// (upper half) mappings are shared with the original handle, as with
+	/// [`Self::duplicate_user_space_shallow_in()`].
+	///
+	/// Returns None if any allocation(s) fail, in which case everything
+	/// allocated for the new handle has been freed.
+	fn duplicate_user_space_deep_in<A>(
+		space: &Self::UserHandle,
+		alloc: &mut A,
+	) -> Option<Self::UserHandle>
+	where
+		A: Alloc;
+
 	/// Frees and _shallowly_ reclaims the user address space handle. Uses the global allocator.
 	///
 	/// Frees the TOP LEVEL page table, without reclaiming any of the pages
@@ -206,6 +234,30 @@ pub unsafe trait AddressSpace: 'static {
 	where
 		A: Alloc;
 
+	/// Translates the given virtual address to the physical address it maps
+	/// to in the given user address space handle, along with the effective
+	/// permissions of the mapping, by walking its page tables.
+	///
+	/// The effective permissions take into account every level of the page
+	/// table hierarchy (e.g. a page is only writable if every table along
+	/// the way permits writes), as well as huge page mappings.
+	///
+	/// Returns `None` if the address is not mapped.
+	fn translate(space: &Self::UserHandle, virt: usize) -> Option<(u64, PageFlags)>;
+
+	/// Attempts to resolve a write fault at the given virtual address in the
+	/// given user address space handle (e.g. by copying a copy-on-write page),
+	/// such that the page becomes writable for that handle only.
+	///
+	/// Returns `true` if the page is now writable. Used both by page fault
+	/// handlers and by kernel code that writes to user memory by way of
+	/// [`Self::translate()`] (see [`crate::user::copy_to_user()`]).
+	///
+	/// # Safety
+	/// Must not be called while the handle's page tables are being modified
+	/// elsewhere.
+	unsafe fn resolve_write_fault(space: &Self::UserHandle, virt: usize) -> bool;
+
 	/// Returns the layout descriptor for the kernel code segment.
 	///
 	/// This must be read-only, user accessible if the architecture
@@ -244,6 +296,17 @@ pub unsafe trait AddressSpace: 'static {
 	/// It **must not** overlap with any other segment.
 	fn kernel_core_local() -> Self::SupervisorSegment;
 
+	/// Returns the layout descriptor for the kernel heap segment.
+	///
+	/// This must be read-write, non-user accessible, and is
+	/// **not** executable.
+	///
+	/// It **must not** overlap with any other segment, and is
+	/// provisioned as shared (see [`AddressSegment::provision_as_shared()`])
+	/// during boot, such that all cores (and all address spaces
+	/// derived from theirs) see the same heap.
+	fn kernel_heap() -> Self::SupervisorSegment;
+
 	/// Returns the layout descriptor for the sysabi segment,
 	/// exposed to instances and managed by the kernel.
 	///
@@ -275,6 +338,14 @@ pub unsafe trait AddressSpace: 'static {
 	/// prepared for that.**
 	fn user_rodata() -> Self::UserSegment;
 
+	/// Returns the layout descriptor for the userspace heap segment.
+	///
+	/// This must be read-write, user accessible, and is
+	/// **not** executable.
+	///
+	/// It **must not** overlap with any other segment.
+	fn user_heap() -> Self::UserSegment;
+
 	/// Returns the layout descriptor for the userspace thread stack segment.
 	///
 	/// This must be read-write, user accessible, and is
@@ -424,6 +495,10 @@ pub unsafe trait AddressSegment<Handle: Sized>: Send + 'static {
 	///
 	/// Fails if the virtual address is not mapped. Returns the physical address
 	/// that was previously mapped.
+	///
+	/// If the frame remains mapped elsewhere (e.g. as a shared copy-on-write
+	/// page), the page is still unmapped, but [`UnmapError::Shared`] is returned
+	/// in lieu of the physical address.
 	fn unmap(&self, space: &Handle, virt: usize) -> Result<u64, UnmapError> {
 		self.unmap_in(space, &mut crate::global_alloc::GlobalPfa, virt)
 	}
@@ -433,15 +508,186 @@ pub unsafe trait AddressSegment<Handle: Sized>: Send + 'static {
 	///
 	/// Fails if the virtual address is not mapped. Returns the physical address
 	/// that was previously mapped.
+	///
+	/// If the frame remains mapped elsewhere (e.g. as a shared copy-on-write
+	/// page), the page is still unmapped, but [`UnmapError::Shared`] is returned
+	/// in lieu of the physical address.
 	fn unmap_in<A>(&self, space: &Handle, alloc: &mut A, virt: usize) -> Result<u64, UnmapError>
 	where
 		A: Alloc;
 
+	/// Unmaps every 4KiB page in the range `[virt, virt + len)`, freeing the
+	/// previously mapped page frames. Uses the global allocator.
+	///
+	/// See [`Self::unmap_range_in()`] for details.
+	///
+	/// # Safety
+	/// The same requirements as [`Self::unmap_range_in()`] apply.
+	unsafe fn unmap_range(
+		&self,
+		space: &Handle,
+		virt: usize,
+		len: usize,
+	) -> Result<usize, UnmapError> {
+		self.unmap_range_in(space, &mut crate::global_alloc::GlobalPfa, virt, len)
+	}
+
+	/// Unmaps every 4KiB page in the range `[virt, virt + len)`, freeing the
+	/// previously mapped page frames (as well as any intermediate page tables
+	/// that become empty) into the given allocator.
+	///
+	/// `len` is rounded up to a multiple of the page size. Pages in the range
+	/// that are not mapped are skipped. Returns the number of page frames freed
+	/// (not including intermediate page tables).
+	///
+	/// Fails if `virt` is not page-aligned, or if unmapping any page fails for
+	/// any reason other than [`UnmapError::NotMapped`] or [`UnmapError::Shared`]
+	/// (whose frames are not freed), in which case the pages before it have
+	/// already been unmapped and freed.
+	///
+	/// # Safety
+	/// The caller must ensure that the frames mapped in the range are owned
+	/// by the mappings (i.e. are not shared, or mapped elsewhere) and are no
+	/// longer in use.
+	unsafe fn unmap_range_in<A>(
+		&self,
+		space: &Handle,
+		alloc: &mut A,
+		virt: usize,
+		len: usize,
+	) -> Result<usize, UnmapError>
+	where
+		A: Alloc,
+	{
+		if virt & 0xFFF != 0 {
+			return Err(UnmapError::VirtNotAligned);
+		}
+
+		let size = len
+			.div_ceil(4096)
+			.checked_mul(4096)
+			.ok_or(UnmapError::VirtOutOfRange)?;
+		let end = virt.checked_add(size).ok_or(UnmapError::VirtOutOfRange)?;
+
+		let mut freed = 0;
+		for page in (virt..end).step_by(4096) {
+			match self.unmap_in(space, alloc, page) {
+				Ok(phys) => {
+					alloc.free(phys);
+					freed += 1;
+				}
+				Err(UnmapError::NotMapped | UnmapError::Shared) => {}
+				Err(err) => return Err(err),
+			}
+		}
+
+		Ok(freed)
+	}
+
+	/// Verifies that the given guard page is not mapped. Uses the global allocator.
+	///
+	/// See [`Self::assert_guard_unmapped_in()`] for details.
+	fn assert_guard_unmapped(&self, space: &Handle, virt: usize) -> Result<(), GuardError> {
+		self.assert_guard_unmapped_in(space, &mut crate::global_alloc::GlobalPfa, virt)
+	}
+
+	/// Verifies that the given guard page is not mapped. Uses the given allocator.
+	///
+	/// The check is performed by attempting to unmap the page. If it turns
+	/// out to have been mapped, it no longer is, and the physical address it
+	/// was mapped to is returned in [`GuardError::Mapped`]; the frame is **not**
+	/// freed.
+	fn assert_guard_unmapped_in<A>(
+		&self,
+		space: &Handle,
+		alloc: &mut A,
+		virt: usize,
+	) -> Result<(), GuardError>
+	where
+		A: Alloc,
+	{
+		match self.unmap_in(space, alloc, virt) {
+			Ok(phys) => Err(GuardError::Mapped(phys)),
+			Err(UnmapError::NotMapped) => Ok(()),
+			Err(err) => Err(GuardError::Unmap(err)),
+		}
+	}
+
+	/// Maps `count` freshly allocated 4KiB pages directly below the given
+	/// guard page, verifying that it and the guard page below the mapped
+	/// pages are both unmapped. Uses the global allocator.
+	///
+	/// See [`Self::map_with_guards_in()`] for details.
+	fn map_with_guards(
+		&self,
+		space: &Handle,
+		guard: usize,
+		count: usize,
+	) -> Result<usize, GuardError> {
+		self.map_with_guards_in(space, &mut crate::global_alloc::GlobalPfa, guard, count)
+	}
+
+	/// Maps `count` freshly allocated 4KiB pages directly below the given
+	/// guard page, verifying that it and the guard page below the mapped
+	/// pages are both unmapped. Page frames are allocated from (and page
+	/// tables charged to) the given allocator.
+	///
+	/// Meant for stacks, which grow downward; `guard` is thus the page
+	/// directly above the stack. Returns the (page-aligned) lowest address
+	/// of the mapped pages.
+	///
+	/// The page frames are **not** zeroed. If mapping fails, any pages that
+	/// were mapped before the failure are left mapped; callers are expected
+	/// to reclaim them (e.g. via [`Self::unmap_all_and_reclaim_in()`]).
+	fn map_with_guards_in<A>(
+		&self,
+		space: &Handle,
+		alloc: &mut A,
+		guard: usize,
+		count: usize,
+	) -> Result<usize, GuardError>
+	where
+		A: Alloc,
+	{
+		if guard & 0xFFF != 0 {
+			return Err(GuardError::Map(MapError::VirtNotAligned));
+		}
+
+		let base = count
+			.checked_mul(4096)
+			.and_then(|size| guard.checked_sub(size))
+			.ok_or(GuardError::Map(MapError::VirtOutOfRange))?;
+		let low_guard = base
+			.checked_sub(4096)
+			.ok_or(GuardError::Map(MapError::VirtOutOfRange))?;
+
+		self.assert_guard_unmapped_in(space, alloc, guard)?;
+
+		for virt in (base..guard).step_by(4096) {
+			let phys = alloc
+				.allocate()
+				.ok_or(GuardError::Map(MapError::OutOfMemory))?;
+
+			if let Err(err) = self.map_in(space, alloc, virt, phys) {
+				// SAFETY(qix-): The frame was just allocated and never mapped.
+				unsafe {
+					alloc.free(phys);
+				}
+				return Err(GuardError::Map(err));
+			}
+		}
+
+		self.assert_guard_unmapped_in(space, alloc, low_guard)?;
+
+		Ok(base)
+	}
+
 	/// Maps the given physical address into the segment at the given virtual address.
 	/// Uses the global allocator.
 	///
 	/// If the virtual address is already mapped, the physical address is remapped and the
-	/// old physical address is returned.
+	/// old physical address is returned (unless it remains mapped elsewhere, e.g. as a
+	/// shared copy-on-write page, in which case `None` is returned).
 	fn remap(&self, space: &Handle, virt: usize, phys: u64) -> Result<Option<u64>, MapError> {
 		self.remap_in(space, &mut crate::global_alloc::GlobalPfa, virt, phys)
 	}
@@ -450,7 +696,8 @@ pub unsafe trait AddressSegment<Handle: Sized>: Send + 'static {
 	/// Uses the given allocator.
 	///
 	/// If the virtual address is already mapped, the physical address is remapped and the
-	/// old physical address is returned.
+	/// old physical address is returned (unless it remains mapped elsewhere, e.g. as a
+	/// shared copy-on-write page, in which case `None` is returned).
 	fn remap_in<A>(
 		&self,
 		space: &Handle,
@@ -462,46 +709,134 @@ pub unsafe trait AddressSegment<Handle: Sized>: Send + 'static {
 		A: Alloc;
 }
 
+/// The effective permissions of a mapping, as returned by
+/// [`AddressSpace::translate()`].
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Default)]
+pub struct PageFlags {
+	/// The page is writable.
+	pub writable:   bool,
+	/// The page is executable.
+	pub executable: bool,
+	/// The page is accessible from userspace.
+	pub user:       bool,
+}
+
 /// Errors returned by mapping functions
+///
+/// The discriminants are stable and may be used as error codes.
 #[derive(Clone, Copy, PartialEq, Debug, Eq)]
+#[repr(u8)]
 pub enum MapError {
 	/// The page table entry is already present.
-	Exists,
+	Exists                     = 1,
 	/// The virtual address passed to the map function
 	/// is out of range for the given mapper.
-	VirtOutOfRange,
+	VirtOutOfRange             = 2,
 	/// On some architectures, the virtual address must be within
 	/// a certain range that is larger than the logical Oro segment
 	/// range (e.g. TTBR0/TTBR1 on AArch64). This error indicates that
 	/// the virtual address is out of the range of the overall address
 	/// space within which the caller is attempting to perform a mapping
 	/// operation.
-	VirtOutOfAddressSpaceRange,
+	VirtOutOfAddressSpaceRange = 3,
 	/// The virtual address passed to the map function
 	/// is not page-aligned.
-	VirtNotAligned,
+	VirtNotAligned             = 4,
+	/// The physical address passed to the map function
+	/// is not aligned to the requested page size.
+	PhysNotAligned             = 5,
 	/// Out of memory.
-	OutOfMemory,
+	OutOfMemory                = 6,
+}
+
+impl core::fmt::Display for MapError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.write_str(match self {
+			Self::Exists => "mapping already present",
+			Self::VirtOutOfRange => "virtual address out of range",
+			Self::VirtOutOfAddressSpaceRange => "virtual address out of address space range",
+			Self::VirtNotAligned => "virtual address not page-aligned",
+			Self::PhysNotAligned => "physical address not page-aligned",
+			Self::OutOfMemory => "out of memory",
+		})
+	}
 }
 
+impl core::error::Error for MapError {}
+
 /// Errors returned by unmapping functions
+///
+/// The discriminants are stable and may be used as error codes.
 #[derive(Clone, Copy, PartialEq, Debug, Eq)]
+#[repr(u8)]
 pub enum UnmapError {
 	/// No mapping exists at the given virtual address.
-	NotMapped,
+	NotMapped                  = 1,
 	/// The virtual address passed to the map function
 	/// is out of range for the given mapper.
-	VirtOutOfRange,
+	VirtOutOfRange             = 2,
 	/// On some architectures, the virtual address must be within
 	/// a certain range that is larger than the logical Oro segment
 	/// range (e.g. TTBR0/TTBR1 on AArch64). This error indicates that
 	/// the virtual address is out of the range of the overall address
 	/// space within which the caller is attempting to perform a mapping
 	/// operation.
-	VirtOutOfAddressSpaceRange,
+	VirtOutOfAddressSpaceRange = 3,
 	/// The virtual address passed to the map function
 	/// is not page-aligned.
-	VirtNotAligned,
+	VirtNotAligned             = 4,
+	/// The mapping at the given virtual address is of a different
+	/// page size than the operation expected (e.g. a normal unmap
+	/// was attempted on an address backed by a huge page, which
+	/// would otherwise split it).
+	PageSizeMismatch           = 5,
 	/// Out of memory.
-	OutOfMemory,
+	OutOfMemory                = 6,
+	/// The mapping was removed, but the physical frame it mapped is still
+	/// mapped elsewhere (e.g. a copy-on-write page shared with another
+	/// address space) and thus must not be freed by the caller.
+	Shared                     = 7,
+}
+
+impl core::fmt::Display for UnmapError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.write_str(match self {
+			Self::NotMapped => "no mapping present",
+			Self::VirtOutOfRange => "virtual address out of range",
+			Self::VirtOutOfAddressSpaceRange => "virtual address out of address space range",
+			Self::VirtNotAligned => "virtual address not page-aligned",
+			Self::PageSizeMismatch => "mapping is of a different page size",
+			Self::OutOfMemory => "out of memory",
+			Self::Shared => "unmapped frame is still shared",
+		})
+	}
+}
+
+impl core::error::Error for UnmapError {}
+
+/// Errors returned by the guard page functions (see e.g.
+/// [`AddressSegment::map_with_guards()`]).
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub enum GuardError {
+	/// A guard page was mapped to the given physical address.
+	///
+	/// The guard page has since been unmapped, but the frame
+	/// has not been freed.
+	Mapped(u64),
+	/// Verifying that a guard page was unmapped failed.
+	Unmap(UnmapError),
+	/// Mapping a page between the guard pages failed.
+	Map(MapError),
+}
+
+impl core::fmt::Display for GuardError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			Self::Mapped(phys) => write!(f, "guard page was mapped to {phys:#016X}"),
+			Self::Unmap(err) => write!(f, "failed to verify guard page: {err}"),
+			Self::Map(err) => write!(f, "failed to map page between guard pages: {err}"),
+		}
+	}
 }
+
+impl core::error::Error for GuardError {}
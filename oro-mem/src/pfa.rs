@@ -1,5 +1,7 @@
 //! Page frame allocator traits and implementations.
 
+use oro_type::Bitmap;
+
 use crate::phys::{Phys, PhysAddr};
 
 /// A page frame allocator allocates physical memory in units of "page frames".
@@ -24,6 +26,31 @@ pub unsafe trait Alloc {
 	/// that was allocated. If `None` is returned, the system is out of memory.
 	fn allocate(&mut self) -> Option<u64>;
 
+	/// Allocates a new page frame and zeroes it via the linear map,
+	/// returning its physical address. If `None` is returned, the system
+	/// is out of memory.
+	///
+	/// Prefer this over [`Self::allocate()`] whenever the frame's prior
+	/// contents must not be observed, e.g. for new page tables (where stale
+	/// entries would be interpreted as mappings) or for frames handed to
+	/// userspace (where stale contents would leak kernel data). Frames that
+	/// are about to be fully overwritten anyway should use
+	/// [`Self::allocate()`] to avoid the redundant write.
+	///
+	/// Requires the global physical address translator's linear map to
+	/// cover the returned frame.
+	fn allocate_zeroed(&mut self) -> Option<u64> {
+		let frame = self.allocate()?;
+		// SAFETY(qix-): The frame was just allocated and is thus not in use,
+		// SAFETY(qix-): and is page-aligned.
+		unsafe {
+			Phys::from_address_unchecked(frame)
+				.as_mut_ptr_unchecked::<u8>()
+				.write_bytes(0, 4096);
+		}
+		Some(frame)
+	}
+
 	/// Frees a page frame.
 	///
 	/// # Safety
@@ -37,6 +64,69 @@ pub unsafe trait Alloc {
 	///
 	/// 3. Callers **must** ensure the frame is page-aligned.
 	unsafe fn free(&mut self, frame: u64);
+
+	/// Allocates `count` physically contiguous page frames, returning the
+	/// physical address of the first (lowest) frame.
+	///
+	/// The base address is aligned to `1 << align_log2` bytes; alignments
+	/// smaller than a page (`align_log2 < 12`) are treated as page alignment.
+	///
+	/// Returns `None` if `count` is zero, if the system is out of memory, or
+	/// if no suitably aligned contiguous run of free frames is available
+	/// (e.g. due to fragmentation), even if enough frames are free in total.
+	///
+	/// The default implementation does not support contiguous allocations
+	/// and always returns `None`.
+	fn allocate_contiguous(&mut self, count: usize, align_log2: u32) -> Option<u64> {
+		let _ = (count, align_log2);
+		None
+	}
+
+	/// Frees `count` physically contiguous page frames starting at `base`,
+	/// such as those returned by [`Self::allocate_contiguous()`].
+	///
+	/// The default implementation frees each frame individually,
+	/// in ascending order.
+	///
+	/// # Safety
+	/// The same requirements as [`Self::free()`] apply to every frame
+	/// in the range.
+	unsafe fn free_contiguous(&mut self, base: u64, count: usize) {
+		for i in 0..count as u64 {
+			self.free(base + i * 4096);
+		}
+	}
+
+	/// Returns the number of page frames that are currently free.
+	fn free_page_count(&self) -> usize;
+
+	/// Returns the total number of page frames managed by the allocator,
+	/// both free and in use.
+	fn total_page_count(&self) -> usize;
+
+	/// Returns the number of page frames that are currently allocated.
+	fn used_page_count(&self) -> usize {
+		self.total_page_count()
+			.saturating_sub(self.free_page_count())
+	}
+
+	/// Registers a callback to be invoked when the number of free page
+	/// frames drops below `pages` as the result of an allocation.
+	///
+	/// The callback fires once per crossing; it is re-armed only once the
+	/// free page count has recovered to at least `pages` (e.g. after frames
+	/// have been freed), after which the next drop below the threshold
+	/// fires it again. Setting a new watermark replaces the previous one
+	/// and re-arms it.
+	///
+	/// The callback is **never** invoked while the allocator's lock (if
+	/// any) is held, and thus may allocate or free page frames itself.
+	///
+	/// The default implementation does not support watermarks and
+	/// ignores the request.
+	fn set_low_watermark(&mut self, pages: usize, cb: fn()) {
+		let _ = (pages, cb);
+	}
 }
 
 /// First in, last out (FILO) page frame allocator.
@@ -64,9 +154,26 @@ pub unsafe trait Alloc {
 /// newly-freed page. This creates a FILO stack of freed pages
 /// with no more bookkeeping necessary other than the last-free
 /// physical frame pointer.
+///
+/// The allocator also keeps track of how many frames are free, and how
+/// many frames it manages in total. Frames that were never allocated by
+/// the allocator must be introduced via [`FiloPageFrameAllocator::expose()`]
+/// (rather than [`Alloc::free()`]) for the total to remain accurate.
+///
+/// The allocator has no lock of its own, and thus does not invoke the
+/// low watermark callback (see [`Alloc::set_low_watermark()`]) itself;
+/// owners must call [`FiloPageFrameAllocator::take_low_watermark_callback()`]
+/// after an allocation, once any lock guarding the allocator is released,
+/// and invoke the returned callback.
 pub struct FiloPageFrameAllocator {
 	/// The last-free page frame address.
-	last_free: u64,
+	last_free:     u64,
+	/// The number of free page frames.
+	free_pages:    usize,
+	/// The total number of page frames known to the allocator.
+	total_pages:   usize,
+	/// The low watermark state.
+	low_watermark: LowWatermark,
 }
 
 impl FiloPageFrameAllocator {
@@ -75,16 +182,37 @@ impl FiloPageFrameAllocator {
 	#[must_use]
 	pub const fn new() -> Self {
 		Self {
-			last_free: u64::MAX,
+			last_free:     u64::MAX,
+			free_pages:    0,
+			total_pages:   0,
+			low_watermark: LowWatermark::new(),
 		}
 	}
 
 	/// Creates a new FILO page frame allocator with the given
 	/// last-free page frame address.
+	///
+	/// The free list is not walked; the allocator's page counts
+	/// start at zero.
 	#[inline]
 	#[must_use]
 	pub fn with_last_free(last_free: u64) -> Self {
-		Self { last_free }
+		Self {
+			last_free,
+			free_pages: 0,
+			total_pages: 0,
+			low_watermark: LowWatermark::new(),
+		}
+	}
+
+	/// Introduces a page frame to the allocator that it has not
+	/// previously managed, counting it towards the total page count.
+	///
+	/// # Safety
+	/// The same requirements as [`Alloc::free()`] apply.
+	pub unsafe fn expose(&mut self, frame: u64) {
+		self.free(frame);
+		self.total_pages += 1;
 	}
 
 	/// Returns the last-free page frame address.
@@ -93,6 +221,17 @@ impl FiloPageFrameAllocator {
 	pub fn last_free(&self) -> u64 {
 		self.last_free
 	}
+
+	/// Takes the low watermark callback if the watermark has been crossed
+	/// since it was last taken, returning `None` otherwise.
+	///
+	/// The caller **must** invoke the returned callback, and **must not**
+	/// hold any lock guarding the allocator while doing so.
+	#[inline]
+	#[must_use]
+	pub fn take_low_watermark_callback(&mut self) -> Option<fn()> {
+		self.low_watermark.take()
+	}
 }
 
 unsafe impl Alloc for FiloPageFrameAllocator {
@@ -108,12 +247,93 @@ unsafe impl Alloc for FiloPageFrameAllocator {
 					.as_ptr_unchecked::<u64>()
 					.read_volatile()
 			};
+			self.free_pages = self.free_pages.saturating_sub(1);
+			self.low_watermark.check(self.free_pages);
 			#[cfg(debug_assertions)]
 			oro_dbgutil::__oro_dbgutil_pfa_alloc(page_frame);
 			Some(page_frame)
 		}
 	}
 
+	/// Scans the free list for `count` consecutive list entries that are
+	/// also physically contiguous (in descending order, as is the case for
+	/// ranges freed in ascending order, e.g. at boot), and unlinks them.
+	///
+	/// This does not sort or otherwise reorganize the free list; frames that
+	/// are contiguous in memory but not adjacent in the list are not found.
+	fn allocate_contiguous(&mut self, count: usize, align_log2: u32) -> Option<u64> {
+		if count == 0 {
+			return None;
+		}
+
+		let align = 1_u64.checked_shl(align_log2.max(12))?;
+		let span = (count as u64 - 1).checked_mul(4096)?;
+
+		/// Reads the next-free link stored in the given frame.
+		#[inline]
+		unsafe fn next_of(frame: u64) -> u64 {
+			Phys::from_address_unchecked(frame)
+				.as_ptr_unchecked::<u64>()
+				.read_volatile()
+		}
+
+		// The link (`None` for the list head, otherwise the frame holding it)
+		// that points to the first frame of the current run.
+		let mut run_link: Option<u64> = None;
+		let mut run_first = u64::MAX;
+		let mut prev = u64::MAX;
+		let mut prev_link: Option<u64> = None;
+		let mut current = self.last_free;
+
+		while current != u64::MAX {
+			if prev == u64::MAX || prev.checked_sub(4096) != Some(current) {
+				run_link = prev_link;
+				run_first = current;
+			}
+
+			// `current` is the lowest frame of a candidate window spanning
+			// `count` frames, the highest of which is `top`.
+			let top = current.saturating_add(span);
+			if current % align == 0 && top <= run_first {
+				let link = if top == run_first {
+					run_link
+				} else {
+					Some(top + 4096)
+				};
+
+				// SAFETY(qix-): All frames in the free list are valid.
+				unsafe {
+					let after = next_of(current);
+					match link {
+						None => self.last_free = after,
+						Some(frame) => {
+							Phys::from_address_unchecked(frame)
+								.as_mut_ptr_unchecked::<u64>()
+								.write_volatile(after);
+						}
+					}
+				}
+
+				self.free_pages = self.free_pages.saturating_sub(count);
+				self.low_watermark.check(self.free_pages);
+
+				#[cfg(debug_assertions)]
+				for i in 0..count as u64 {
+					oro_dbgutil::__oro_dbgutil_pfa_alloc(current + i * 4096);
+				}
+
+				return Some(current);
+			}
+
+			prev_link = Some(current);
+			prev = current;
+			// SAFETY(qix-): All frames in the free list are valid.
+			current = unsafe { next_of(current) };
+		}
+
+		None
+	}
+
 	unsafe fn free(&mut self, frame: u64) {
 		assert_eq!(frame % 4096, 0, "frame is not page-aligned");
 		#[cfg(debug_assertions)]
@@ -124,5 +344,827 @@ unsafe impl Alloc for FiloPageFrameAllocator {
 				.write_volatile(self.last_free);
 		}
 		self.last_free = frame;
+		self.free_pages += 1;
+		self.low_watermark.rearm(self.free_pages);
+	}
+
+	fn free_page_count(&self) -> usize {
+		self.free_pages
+	}
+
+	fn total_page_count(&self) -> usize {
+		self.total_pages
+	}
+
+	fn set_low_watermark(&mut self, pages: usize, cb: fn()) {
+		self.low_watermark.set(pages, cb);
+	}
+}
+
+/// Bitmap-backed page frame allocator.
+///
+/// Tracks every page frame with a single bit in a caller-provided
+/// [`Bitmap`], whereby bit `n` represents the frame at physical address
+/// `n * 4096` (a set bit being a frame that is in use or unavailable).
+/// The bitmap thus must cover physical memory from address `0` up to
+/// the highest usable frame (see [`BitmapAlloc::words_for()`]); at one
+/// bit per 4KiB frame, that's 32KiB of bitmap per GiB of physical memory.
+///
+/// Unlike the [`FiloPageFrameAllocator`], the allocator never reads or
+/// writes the frames it manages, and thus doesn't require them to be
+/// addressable via the linear map. Contiguous allocations are supported
+/// for any count and alignment, at the cost of a linear scan of the bitmap.
+///
+/// All frames start out as in use; frames are made available via
+/// [`BitmapAlloc::expose_range()`], typically for each `Usable` region
+/// of the memory map (see [`BitmapAlloc::from_usable_regions()`]).
+///
+/// As with the [`FiloPageFrameAllocator`], the allocator has no lock of
+/// its own, and thus does not invoke the low watermark callback itself;
+/// owners must call [`BitmapAlloc::take_low_watermark_callback()`] after
+/// an allocation, once any lock guarding the allocator is released, and
+/// invoke the returned callback.
+///
+/// Note that this allocator does **not** back the global page frame
+/// allocator (see [`crate::global_alloc::GlobalPfa`]), which is still a
+/// [`FiloPageFrameAllocator`]; doing so requires each architecture to carve
+/// out the bitmap's storage from physical memory at boot, which it does not
+/// yet do. For now, it's meant to manage physical memory on its own.
+pub struct BitmapAlloc<'a> {
+	/// One bit per page frame, starting at physical address `0`.
+	bitmap:        Bitmap<'a>,
+	/// The number of free page frames.
+	free_pages:    usize,
+	/// The total number of page frames exposed to the allocator.
+	total_pages:   usize,
+	/// The low watermark state.
+	low_watermark: LowWatermark,
+}
+
+impl<'a> BitmapAlloc<'a> {
+	/// Returns the number of `u64` bitmap words required to manage
+	/// all page frames below the physical address `end`.
+	#[inline]
+	#[must_use]
+	pub const fn words_for(end: u64) -> usize {
+		(end.div_ceil(4096).div_ceil(64)) as usize
+	}
+
+	/// Creates a new bitmap allocator over the given storage, with all
+	/// page frames initially marked as in use.
+	#[must_use]
+	pub fn new(storage: &'a mut [u64]) -> Self {
+		storage.fill(u64::MAX);
+
+		Self {
+			bitmap:        Bitmap::new(storage),
+			free_pages:    0,
+			total_pages:   0,
+			low_watermark: LowWatermark::new(),
+		}
+	}
+
+	/// Creates a new bitmap allocator over the given storage, exposing
+	/// each of the given `(base, length)` physical memory regions.
+	///
+	/// The regions should be exactly the `Usable` regions of a normalized
+	/// memory map (i.e. sorted, non-overlapping regions), **excluding** the
+	/// memory backing `storage` itself.
+	///
+	/// # Safety
+	/// The same requirements as [`BitmapAlloc::expose_range()`] apply to
+	/// each region.
+	#[must_use]
+	pub unsafe fn from_usable_regions<I: IntoIterator<Item = (u64, u64)>>(
+		storage: &'a mut [u64],
+		regions: I,
+	) -> Self {
+		let mut alloc = Self::new(storage);
+
+		for (base, length) in regions {
+			alloc.expose_range(base, length);
+		}
+
+		alloc
+	}
+
+	/// Exposes to the allocator all page frames fully contained in the
+	/// given physical address range, counting them towards the total
+	/// page count.
+	///
+	/// Frames beyond the end of the bitmap are ignored.
+	///
+	/// # Safety
+	/// The caller **must** ensure that the range is valid and unused, and
+	/// has not already been exposed to the allocator.
+	pub unsafe fn expose_range(&mut self, base: u64, length: u64) {
+		let first = base.div_ceil(4096);
+		let end = ((base + length) >> 12).min(self.bitmap.len() as u64);
+
+		if end <= first {
+			return;
+		}
+
+		#[cfg(debug_assertions)]
+		oro_dbgutil::__oro_dbgutil_pfa_mass_free(first << 12, end << 12);
+
+		let count = (end - first) as usize;
+		self.bitmap.clear_range(first as usize, count);
+
+		self.free_pages += count;
+		self.total_pages += count;
+		self.low_watermark.rearm(self.free_pages);
+	}
+
+	/// Takes the low watermark callback if the watermark has been crossed
+	/// since it was last taken, returning `None` otherwise.
+	///
+	/// The caller **must** invoke the returned callback, and **must not**
+	/// hold any lock guarding the allocator while doing so.
+	#[inline]
+	#[must_use]
+	pub fn take_low_watermark_callback(&mut self) -> Option<fn()> {
+		self.low_watermark.take()
+	}
+}
+
+unsafe impl Alloc for BitmapAlloc<'_> {
+	fn allocate(&mut self) -> Option<u64> {
+		let bit = self.bitmap.find_first_zero()?;
+		self.bitmap.set(bit);
+
+		self.free_pages = self.free_pages.saturating_sub(1);
+		self.low_watermark.check(self.free_pages);
+
+		let frame = (bit as u64) << 12;
+		#[cfg(debug_assertions)]
+		oro_dbgutil::__oro_dbgutil_pfa_alloc(frame);
+		Some(frame)
+	}
+
+	fn allocate_contiguous(&mut self, count: usize, align_log2: u32) -> Option<u64> {
+		let align = 1_usize.checked_shl(align_log2.max(12) - 12)?;
+		let bit = self.bitmap.find_run_aligned(count, align)?;
+		self.bitmap.set_range(bit, count);
+
+		self.free_pages = self.free_pages.saturating_sub(count);
+		self.low_watermark.check(self.free_pages);
+
+		let base = (bit as u64) << 12;
+
+		#[cfg(debug_assertions)]
+		for i in 0..count as u64 {
+			oro_dbgutil::__oro_dbgutil_pfa_alloc(base + i * 4096);
+		}
+
+		Some(base)
+	}
+
+	unsafe fn free(&mut self, frame: u64) {
+		assert_eq!(frame % 4096, 0, "frame is not page-aligned");
+		#[cfg(debug_assertions)]
+		oro_dbgutil::__oro_dbgutil_pfa_free(frame);
+
+		let bit = (frame >> 12) as usize;
+		debug_assert!(self.bitmap.test(bit), "double free of frame {frame:#016x}");
+		self.bitmap.clear(bit);
+
+		self.free_pages += 1;
+		self.low_watermark.rearm(self.free_pages);
+	}
+
+	unsafe fn free_contiguous(&mut self, base: u64, count: usize) {
+		assert_eq!(base % 4096, 0, "frame is not page-aligned");
+
+		#[cfg(debug_assertions)]
+		for i in 0..count as u64 {
+			oro_dbgutil::__oro_dbgutil_pfa_free(base + i * 4096);
+		}
+
+		self.bitmap.clear_range((base >> 12) as usize, count);
+
+		self.free_pages += count;
+		self.low_watermark.rearm(self.free_pages);
+	}
+
+	fn free_page_count(&self) -> usize {
+		self.free_pages
+	}
+
+	fn total_page_count(&self) -> usize {
+		self.total_pages
+	}
+
+	fn set_low_watermark(&mut self, pages: usize, cb: fn()) {
+		self.low_watermark.set(pages, cb);
+	}
+}
+
+/// The highest block order managed by the [`BuddyAlloc`]; blocks of
+/// order `n` span `1 << n` page frames (i.e. 4MiB at the maximum order).
+pub const BUDDY_MAX_ORDER: usize = 10;
+
+/// The number of block orders managed by the [`BuddyAlloc`].
+pub const BUDDY_ORDERS: usize = BUDDY_MAX_ORDER + 1;
+
+/// Buddy page frame allocator.
+///
+/// Manages free memory as power-of-two sized, naturally aligned blocks
+/// of up to `1 << BUDDY_MAX_ORDER` page frames, with one free list per
+/// block order. Allocations split larger blocks as needed, and frees
+/// coalesce a block with its "buddy" (the adjacent block of the same
+/// order) whenever both are free, making contiguous allocations of up
+/// to [`BUDDY_MAX_ORDER`] near-constant time, at the cost of rounding
+/// them up to the next power of two internally (the excess is freed
+/// back immediately).
+///
+/// Single-frame allocations are taken straight from the order-0 list
+/// when it's non-empty, and are thus as cheap as with the
+/// [`FiloPageFrameAllocator`].
+///
+/// As with the [`FiloPageFrameAllocator`], the free lists are stored
+/// in the free frames themselves (each free block's first frame holds
+/// its list links), and thus all managed memory must be addressable
+/// via the linear map. Additionally, a caller-provided [`Bitmap`] marks
+/// which frames begin a free block, whereby bit `n` represents the frame
+/// at physical address `n * 4096`; it thus must cover physical memory
+/// from address `0` up to the highest usable frame (see
+/// [`BitmapAlloc::words_for()`]).
+///
+/// The allocator has no lock of its own, and thus does not invoke the
+/// low watermark callback itself; owners must call
+/// [`BuddyAlloc::take_low_watermark_callback()`] after an allocation,
+/// once any lock guarding the allocator is released, and invoke the
+/// returned callback.
+pub struct BuddyAlloc<'a> {
+	/// One bit per page frame, set if the frame begins a free block.
+	heads:         Bitmap<'a>,
+	/// The first free block of each order, or `u64::MAX` if the
+	/// order's list is empty.
+	free_lists:    [u64; BUDDY_ORDERS],
+	/// The number of free blocks of each order.
+	free_blocks:   [usize; BUDDY_ORDERS],
+	/// The number of free page frames.
+	free_pages:    usize,
+	/// The total number of page frames exposed to the allocator.
+	total_pages:   usize,
+	/// The low watermark state.
+	low_watermark: LowWatermark,
+}
+
+/// The list links stored in the first frame of each free buddy block.
+#[repr(C)]
+struct BuddyLink {
+	/// The next free block of the same order, or `u64::MAX`.
+	next:  u64,
+	/// The previous free block of the same order, or `u64::MAX`.
+	prev:  u64,
+	/// The order of this block.
+	order: u64,
+}
+
+impl<'a> BuddyAlloc<'a> {
+	/// Creates a new, empty buddy allocator using the given storage
+	/// to track free blocks.
+	#[must_use]
+	pub fn new(storage: &'a mut [u64]) -> Self {
+		storage.fill(0);
+
+		Self {
+			heads:         Bitmap::new(storage),
+			free_lists:    [u64::MAX; BUDDY_ORDERS],
+			free_blocks:   [0; BUDDY_ORDERS],
+			free_pages:    0,
+			total_pages:   0,
+			low_watermark: LowWatermark::new(),
+		}
+	}
+
+	/// Creates a new buddy allocator using the given storage, exposing
+	/// each of the given `(base, length)` physical memory regions.
+	///
+	/// The regions should be exactly the `Usable` regions of a normalized
+	/// memory map (i.e. sorted, non-overlapping regions), **excluding** the
+	/// memory backing `storage` itself.
+	///
+	/// # Safety
+	/// The same requirements as [`BuddyAlloc::expose_range()`] apply to
+	/// each region.
+	#[must_use]
+	pub unsafe fn from_usable_regions<I: IntoIterator<Item = (u64, u64)>>(
+		storage: &'a mut [u64],
+		regions: I,
+	) -> Self {
+		let mut alloc = Self::new(storage);
+
+		for (base, length) in regions {
+			alloc.expose_range(base, length);
+		}
+
+		alloc
+	}
+
+	/// Exposes to the allocator all page frames fully contained in the
+	/// given physical address range, counting them towards the total
+	/// page count.
+	///
+	/// Frames beyond the end of the bitmap are ignored.
+	///
+	/// # Safety
+	/// The caller **must** ensure that the range is valid and unused, has
+	/// not already been exposed to the allocator, and is addressable via
+	/// the linear map.
+	pub unsafe fn expose_range(&mut self, base: u64, length: u64) {
+		let first = base.div_ceil(4096);
+		let end = ((base + length) >> 12).min(self.heads.len() as u64);
+
+		if end <= first {
+			return;
+		}
+
+		#[cfg(debug_assertions)]
+		oro_dbgutil::__oro_dbgutil_pfa_mass_free(first << 12, end << 12);
+
+		let count = (end - first) as usize;
+		self.free_range(first << 12, count);
+		self.total_pages += count;
+	}
+
+	/// Returns the number of free blocks of each order.
+	///
+	/// Free memory that is split across many low-order blocks (rather
+	/// than a few high-order ones) indicates fragmentation.
+	#[inline]
+	#[must_use]
+	pub fn free_blocks_per_order(&self) -> [usize; BUDDY_ORDERS] {
+		self.free_blocks
+	}
+
+	/// Returns the number of free page frames held in blocks of each order.
+	#[must_use]
+	pub fn free_pages_per_order(&self) -> [usize; BUDDY_ORDERS] {
+		core::array::from_fn(|order| self.free_blocks[order] << order)
+	}
+
+	/// Takes the low watermark callback if the watermark has been crossed
+	/// since it was last taken, returning `None` otherwise.
+	///
+	/// The caller **must** invoke the returned callback, and **must not**
+	/// hold any lock guarding the allocator while doing so.
+	#[inline]
+	#[must_use]
+	pub fn take_low_watermark_callback(&mut self) -> Option<fn()> {
+		self.low_watermark.take()
+	}
+
+	/// Returns a pointer to the list links of the given free block.
+	#[inline]
+	fn link(block: u64) -> *mut BuddyLink {
+		// SAFETY(qix-): Free blocks are always addressable via the linear map.
+		unsafe { Phys::from_address_unchecked(block).as_mut_ptr_unchecked::<BuddyLink>() }
+	}
+
+	/// Pushes a block onto the free list of the given order.
+	fn push(&mut self, order: usize, block: u64) {
+		let head = self.free_lists[order];
+
+		// SAFETY(qix-): The block is free, and thus ours to write to,
+		// SAFETY(qix-): as is the current list head (if any).
+		unsafe {
+			Self::link(block).write_volatile(BuddyLink {
+				next:  head,
+				prev:  u64::MAX,
+				order: order as u64,
+			});
+
+			if head != u64::MAX {
+				(*Self::link(head)).prev = block;
+			}
+		}
+
+		self.free_lists[order] = block;
+		self.free_blocks[order] += 1;
+		self.heads.set((block >> 12) as usize);
+	}
+
+	/// Unlinks a free block from the free list of the given order.
+	fn unlink(&mut self, order: usize, block: u64) {
+		// SAFETY(qix-): The block and its neighbors are all free.
+		unsafe {
+			let BuddyLink { next, prev, .. } = Self::link(block).read_volatile();
+
+			if prev == u64::MAX {
+				self.free_lists[order] = next;
+			} else {
+				(*Self::link(prev)).next = next;
+			}
+
+			if next != u64::MAX {
+				(*Self::link(next)).prev = prev;
+			}
+		}
+
+		self.free_blocks[order] -= 1;
+		self.heads.clear((block >> 12) as usize);
+	}
+
+	/// Returns whether or not the given block is free and of the given order.
+	fn is_free_block(&self, order: usize, block: u64) -> bool {
+		let bit = (block >> 12) as usize;
+		bit < self.heads.len()
+			&& self.heads.test(bit)
+			// SAFETY(qix-): The block is free, and thus holds valid links.
+			&& unsafe { (*Self::link(block)).order } == order as u64
+	}
+
+	/// Allocates a single block of the given order, splitting a
+	/// larger block if necessary.
+	fn allocate_order(&mut self, order: usize) -> Option<u64> {
+		let from = (order..BUDDY_ORDERS).find(|&o| self.free_lists[o] != u64::MAX)?;
+
+		let block = self.free_lists[from];
+		self.unlink(from, block);
+
+		// Return the upper halves of the split block to the free lists.
+		for split in (order..from).rev() {
+			self.push(split, block + (4096 << split));
+		}
+
+		self.free_pages = self.free_pages.saturating_sub(1 << order);
+		self.low_watermark.check(self.free_pages);
+
+		Some(block)
+	}
+
+	/// Frees a single block of the given order, coalescing it
+	/// with its buddies where possible.
+	fn free_order(&mut self, mut block: u64, mut order: usize) {
+		self.free_pages += 1 << order;
+
+		while order < BUDDY_MAX_ORDER {
+			let buddy = block ^ (4096 << order);
+			if !self.is_free_block(order, buddy) {
+				break;
+			}
+
+			self.unlink(order, buddy);
+			block = block.min(buddy);
+			order += 1;
+		}
+
+		self.push(order, block);
+		self.low_watermark.rearm(self.free_pages);
+	}
+
+	/// Frees `count` page frames starting at `base` as the largest
+	/// naturally aligned blocks that fit.
+	fn free_range(&mut self, mut base: u64, mut count: usize) {
+		while count > 0 {
+			let align_order = ((base >> 12).trailing_zeros() as usize).min(BUDDY_MAX_ORDER);
+			let size_order = count.ilog2() as usize;
+			let order = align_order.min(size_order);
+
+			self.free_order(base, order);
+			base += 4096 << order;
+			count -= 1 << order;
+		}
+	}
+}
+
+unsafe impl Alloc for BuddyAlloc<'_> {
+	fn allocate(&mut self) -> Option<u64> {
+		// Fast path; take a frame straight from the order-0 list.
+		let frame = match self.free_lists[0] {
+			u64::MAX => self.allocate_order(0)?,
+			frame => {
+				self.unlink(0, frame);
+				self.free_pages -= 1;
+				self.low_watermark.check(self.free_pages);
+				frame
+			}
+		};
+
+		#[cfg(debug_assertions)]
+		oro_dbgutil::__oro_dbgutil_pfa_alloc(frame);
+		Some(frame)
+	}
+
+	/// Allocates a block of the smallest order that satisfies both the
+	/// count and the alignment, and frees back any excess frames.
+	///
+	/// Fails if the required order exceeds [`BUDDY_MAX_ORDER`].
+	fn allocate_contiguous(&mut self, count: usize, align_log2: u32) -> Option<u64> {
+		if count == 0 {
+			return None;
+		}
+
+		let size_order = count.next_power_of_two().ilog2() as usize;
+		let align_order = align_log2.saturating_sub(12) as usize;
+		let order = size_order.max(align_order);
+
+		if order > BUDDY_MAX_ORDER {
+			return None;
+		}
+
+		let base = self.allocate_order(order)?;
+
+		let excess = (1 << order) - count;
+		if excess > 0 {
+			// NOTE(qix-): Counted as freed here; the excess never left the allocator.
+			self.free_range(base + count as u64 * 4096, excess);
+		}
+
+		#[cfg(debug_assertions)]
+		for i in 0..count as u64 {
+			oro_dbgutil::__oro_dbgutil_pfa_alloc(base + i * 4096);
+		}
+
+		Some(base)
+	}
+
+	unsafe fn free(&mut self, frame: u64) {
+		assert_eq!(frame % 4096, 0, "frame is not page-aligned");
+		#[cfg(debug_assertions)]
+		oro_dbgutil::__oro_dbgutil_pfa_free(frame);
+
+		self.free_order(frame, 0);
+	}
+
+	unsafe fn free_contiguous(&mut self, base: u64, count: usize) {
+		assert_eq!(base % 4096, 0, "frame is not page-aligned");
+
+		#[cfg(debug_assertions)]
+		for i in 0..count as u64 {
+			oro_dbgutil::__oro_dbgutil_pfa_free(base + i * 4096);
+		}
+
+		self.free_range(base, count);
+	}
+
+	fn free_page_count(&self) -> usize {
+		self.free_pages
+	}
+
+	fn total_page_count(&self) -> usize {
+		self.total_pages
+	}
+
+	fn set_low_watermark(&mut self, pages: usize, cb: fn()) {
+		self.low_watermark.set(pages, cb);
+	}
+}
+
+/// Low watermark bookkeeping shared by the page frame allocators
+/// (see [`Alloc::set_low_watermark()`]).
+struct LowWatermark {
+	/// The threshold, in pages, and its callback.
+	threshold: Option<(usize, fn())>,
+	/// Whether the callback fires upon the next crossing.
+	armed:     bool,
+	/// Whether the threshold was crossed and the callback has yet
+	/// to be taken by the owner.
+	pending:   bool,
+}
+
+impl LowWatermark {
+	/// Creates a new, unset low watermark.
+	const fn new() -> Self {
+		Self {
+			threshold: None,
+			armed:     false,
+			pending:   false,
+		}
+	}
+
+	/// Sets (and arms) the low watermark, replacing any previous one.
+	fn set(&mut self, pages: usize, cb: fn()) {
+		self.threshold = Some((pages, cb));
+		self.armed = true;
+		self.pending = false;
+	}
+
+	/// Marks the callback as pending if the free page count has
+	/// dropped below the threshold while armed.
+	#[inline]
+	fn check(&mut self, free_pages: usize) {
+		if let Some((pages, _)) = self.threshold {
+			if self.armed && free_pages < pages {
+				self.armed = false;
+				self.pending = true;
+			}
+		}
+	}
+
+	/// Re-arms the low watermark once the free page count has
+	/// recovered to at least the threshold.
+	#[inline]
+	fn rearm(&mut self, free_pages: usize) {
+		if let Some((pages, _)) = self.threshold {
+			if !self.armed && free_pages >= pages {
+				self.armed = true;
+			}
+		}
+	}
+
+	/// Takes the callback if the threshold has been crossed since
+	/// it was last taken.
+	#[inline]
+	fn take(&mut self) -> Option<fn()> {
+		if core::mem::take(&mut self.pending) {
+			self.threshold.map(|(_, cb)| cb)
+		} else {
+			None
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Once;
+
+	use super::*;
+
+	/// The number of page frames backing the buddy allocator tests.
+	const ARENA_PAGES: usize = 64;
+
+	/// "Physical" memory for the buddy allocator tests, which store
+	/// their free lists in the frames themselves.
+	#[repr(C, align(4096))]
+	struct Arena([u8; ARENA_PAGES * 4096]);
+
+	/// The backing memory of the arena. Physical address `0` is its first byte.
+	static mut ARENA: Arena = Arena([0; ARENA_PAGES * 4096]);
+
+	/// Returns a buddy allocator over the `pages` arena frames
+	/// starting at frame `first`.
+	///
+	/// Tests run in parallel, so each must use its own frames.
+	fn buddy(storage: &mut [u64], first: u64, pages: u64) -> BuddyAlloc<'_> {
+		/// Sets the linear map offset to the arena, once.
+		static INIT: Once = Once::new();
+
+		INIT.call_once(|| {
+			// SAFETY: Only ever set here, once.
+			unsafe {
+				crate::translate::set_global_map_offset(core::ptr::addr_of_mut!(ARENA) as u64);
+			}
+		});
+
+		assert!(first + pages <= ARENA_PAGES as u64);
+
+		let mut pfa = BuddyAlloc::new(storage);
+		// SAFETY: The frames are within the arena and used by this test alone.
+		unsafe {
+			pfa.expose_range(first << 12, pages << 12);
+		}
+		pfa
+	}
+
+	#[test]
+	fn test_bitmap_alloc_free() {
+		let mut storage = [0_u64; 1];
+		let mut pfa = BitmapAlloc::new(&mut storage);
+
+		// SAFETY: The bitmap allocator never touches the frames it manages.
+		unsafe {
+			pfa.expose_range(0x1000, 0x3000);
+		}
+
+		assert_eq!(pfa.total_page_count(), 3);
+		assert_eq!(pfa.free_page_count(), 3);
+
+		assert_eq!(pfa.allocate(), Some(0x1000));
+		assert_eq!(pfa.allocate(), Some(0x2000));
+		assert_eq!(pfa.allocate(), Some(0x3000));
+		assert_eq!(pfa.allocate(), None);
+		assert_eq!(pfa.free_page_count(), 0);
+
+		unsafe {
+			pfa.free(0x2000);
+		}
+
+		assert_eq!(pfa.free_page_count(), 1);
+		assert_eq!(pfa.allocate(), Some(0x2000));
+		assert_eq!(pfa.allocate(), None);
+	}
+
+	#[test]
+	fn test_bitmap_alloc_partial_pages() {
+		let mut storage = [0_u64; 1];
+		let mut pfa = BitmapAlloc::new(&mut storage);
+
+		// Only frames fully within the range (or the bitmap) are exposed.
+		unsafe {
+			pfa.expose_range(0x1800, 0x2000);
+			pfa.expose_range(0x3F000, 0x10000);
+		}
+
+		assert_eq!(pfa.total_page_count(), 2);
+		assert_eq!(pfa.allocate(), Some(0x2000));
+		assert_eq!(pfa.allocate(), Some(0x3F000));
+		assert_eq!(pfa.allocate(), None);
+	}
+
+	#[test]
+	fn test_bitmap_alloc_contiguous() {
+		let mut storage = [0_u64; 1];
+		let mut pfa = BitmapAlloc::new(&mut storage);
+
+		unsafe {
+			pfa.expose_range(0x1000, 63 * 0x1000);
+		}
+
+		assert_eq!(pfa.allocate_contiguous(4, 14), Some(0x4000));
+		assert_eq!(pfa.allocate_contiguous(3, 12), Some(0x1000));
+		assert_eq!(pfa.free_page_count(), 56);
+		assert_eq!(pfa.allocate_contiguous(57, 12), None);
+
+		unsafe {
+			pfa.free_contiguous(0x4000, 4);
+			pfa.free_contiguous(0x1000, 3);
+		}
+
+		assert_eq!(pfa.free_page_count(), 63);
+		assert_eq!(pfa.allocate_contiguous(63, 12), Some(0x1000));
+		assert_eq!(pfa.allocate(), None);
+	}
+
+	#[test]
+	fn test_buddy_alloc_coalesce() {
+		let mut storage = [0_u64; 1];
+		let mut pfa = buddy(&mut storage, 0, 16);
+
+		assert_eq!(pfa.total_page_count(), 16);
+		assert_eq!(pfa.free_blocks_per_order()[..5], [0, 0, 0, 0, 1]);
+
+		// Splits the order-4 block into one free block each of orders 0..=3.
+		assert_eq!(pfa.allocate(), Some(0x0000));
+		assert_eq!(pfa.free_blocks_per_order()[..5], [1, 1, 1, 1, 0]);
+		assert_eq!(pfa.allocate(), Some(0x1000));
+		assert_eq!(pfa.free_page_count(), 14);
+
+		unsafe {
+			pfa.free(0x1000);
+		}
+		assert_eq!(pfa.free_blocks_per_order()[..5], [1, 1, 1, 1, 0]);
+
+		unsafe {
+			pfa.free(0x0000);
+		}
+		assert_eq!(pfa.free_blocks_per_order()[..5], [0, 0, 0, 0, 1]);
+		assert_eq!(pfa.free_page_count(), 16);
+	}
+
+	#[test]
+	fn test_buddy_alloc_exhaustion() {
+		let mut storage = [0_u64; 1];
+		let mut pfa = buddy(&mut storage, 16, 8);
+
+		let mut frames = Vec::new();
+		while let Some(frame) = pfa.allocate() {
+			frames.push(frame);
+		}
+
+		frames.sort_unstable();
+		assert_eq!(frames, (16..24).map(|f| f << 12).collect::<Vec<_>>());
+		assert_eq!(pfa.free_page_count(), 0);
+		assert_eq!(pfa.allocate_contiguous(1, 12), None);
+
+		for frame in frames {
+			unsafe {
+				pfa.free(frame);
+			}
+		}
+
+		assert_eq!(pfa.free_blocks_per_order()[..4], [0, 0, 0, 1]);
+		assert_eq!(pfa.free_page_count(), 8);
+	}
+
+	#[test]
+	fn test_buddy_alloc_contiguous() {
+		let mut storage = [0_u64; 1];
+		let mut pfa = buddy(&mut storage, 32, 32);
+
+		// Rounded up to an order-2 block; the excess frame is freed back.
+		assert_eq!(pfa.allocate_contiguous(3, 12), Some(0x20000));
+		assert_eq!(pfa.free_page_count(), 29);
+		assert_eq!(pfa.allocate(), Some(0x23000));
+
+		assert_eq!(pfa.allocate_contiguous(8, 15), Some(0x28000));
+		assert_eq!(pfa.allocate_contiguous(32, 12), None);
+		assert_eq!(pfa.allocate_contiguous(0, 12), None);
+		assert_eq!(
+			pfa.allocate_contiguous(1, BUDDY_MAX_ORDER as u32 + 13),
+			None
+		);
+
+		unsafe {
+			pfa.free(0x23000);
+			pfa.free_contiguous(0x20000, 3);
+			pfa.free_contiguous(0x28000, 8);
+		}
+
+		assert_eq!(pfa.free_blocks_per_order()[..6], [0, 0, 0, 0, 0, 1]);
+		assert_eq!(pfa.allocate_contiguous(32, 12), Some(0x20000));
 	}
 }
@@ -72,10 +72,12 @@ pub trait PhysAddr: Sized {
 
 	/// Returns a virtual pointer to the physical address as the given type.
 	///
-	/// Returns `None` if the pointer is null or would not be properly aligned.
+	/// Returns `None` if the physical address is beyond the physical
+	/// address limit (see [`crate::translate::to_virtual_checked()`]),
+	/// or if the pointer is null or would not be properly aligned.
 	#[inline(always)]
 	fn as_ptr<T>(&self) -> Option<*const T> {
-		let ptr = unsafe { self.as_ptr_unchecked::<T>() };
+		let ptr = crate::translate::to_virtual_checked(self.address_u64())? as *const T;
 		if !ptr.is_null() && ptr.is_aligned() {
 			Some(ptr)
 		} else {
@@ -85,10 +87,12 @@ pub trait PhysAddr: Sized {
 
 	/// Returns a mutable virtual pointer to the physical address as the given type.
 	///
-	/// Returns `None` if the pointer is null or would not be properly aligned.
+	/// Returns `None` if the physical address is beyond the physical
+	/// address limit (see [`crate::translate::to_virtual_checked()`]),
+	/// or if the pointer is null or would not be properly aligned.
 	#[inline(always)]
 	fn as_mut_ptr<T>(&self) -> Option<*mut T> {
-		let ptr = unsafe { self.as_mut_ptr_unchecked::<T>() };
+		let ptr = crate::translate::to_virtual_checked(self.address_u64())? as *mut T;
 		if !ptr.is_null() && ptr.is_aligned() {
 			Some(ptr)
 		} else {
@@ -53,6 +53,11 @@
 // NOTE(qix-): So, like I said, this is actually the refined and evolved, conscious
 // NOTE(qix-): evolution of this subsystem, despite it being simplistic.
 
+/// The (exclusive) upper bound of the physical addresses that
+/// [`to_virtual_checked`] considers valid. Unbounded by default.
+static PHYSICAL_ADDRESS_LIMIT: ::core::sync::atomic::AtomicU64 =
+	::core::sync::atomic::AtomicU64::new(u64::MAX);
+
 /// Holds the linear map offset for the entire system. The resulting
 /// value (when added to the physical address in question) must result
 /// in a valid virtual address (which also means fitting within a `usize`).
@@ -117,3 +122,39 @@ pub fn global_map_offset() -> u64 {
 pub fn to_virtual(phys: u64) -> usize {
 	usize::try_from(phys + global_map_offset()).unwrap()
 }
+
+/// Sets the (exclusive) upper bound of the physical addresses
+/// that [`to_virtual_checked`] considers valid, typically the end
+/// of the highest region of the memory map.
+///
+/// Should be called by the boot core once the linear map has been
+/// established.
+pub fn set_physical_address_limit(limit: u64) {
+	PHYSICAL_ADDRESS_LIMIT.store(limit, ::core::sync::atomic::Ordering::Relaxed);
+}
+
+/// Gets the (exclusive) upper bound of the physical addresses
+/// that [`to_virtual_checked`] considers valid.
+///
+/// Returns `u64::MAX` if [`set_physical_address_limit`] has not
+/// been called.
+pub fn physical_address_limit() -> u64 {
+	PHYSICAL_ADDRESS_LIMIT.load(::core::sync::atomic::Ordering::Relaxed)
+}
+
+/// Translates a physical address to a virtual address, validating
+/// it against the physical address limit (see [`set_physical_address_limit`]).
+///
+/// Returns `None` if the physical address is at or beyond the limit,
+/// or if the resulting virtual address does not fit within a `usize`.
+/// Meant for addresses from untrusted or potentially unpopulated
+/// sources (e.g. boot protocol or firmware table pointers), which
+/// would otherwise silently translate to bogus virtual addresses.
+#[must_use]
+pub fn to_virtual_checked(phys: u64) -> Option<usize> {
+	if phys >= physical_address_limit() {
+		return None;
+	}
+
+	usize::try_from(phys.checked_add(global_map_offset())?).ok()
+}
@@ -0,0 +1,147 @@
+//! Helpers for safely copying data to and from userspace memory.
+//!
+//! User pointers are never dereferenced directly. Instead, each page is
+//! translated via the user address space's page tables (see
+//! [`AddressSpace::translate()`]), validated to be user accessible (and
+//! writable, where applicable), and accessed via the linear map. Thus,
+//! a bad user pointer results in a [`FaultError`] rather than a kernel
+//! page fault.
+
+use crate::{
+	mapper::{AddressSegment, AddressSpace, PageFlags},
+	phys::{Phys, PhysAddr},
+};
+
+/// Errors returned when copying data to or from userspace.
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub enum FaultError {
+	/// The user pointer plus the length overflows the address space.
+	Overflow,
+	/// The range does not lie entirely within a single user segment.
+	OutOfRange,
+	/// A page within the range is not mapped.
+	NotMapped,
+	/// A page within the range is not accessible from userspace.
+	NotUserAccessible,
+	/// A page within the range is not writable.
+	NotWritable,
+}
+
+/// Copies `buf.len()` bytes from userspace, starting at `user_ptr`, into `buf`.
+///
+/// Fails if the range overflows, does not lie entirely within a single user
+/// segment, or if any page within it is not mapped or not user accessible.
+/// Nothing is guaranteed about the contents of `buf` upon failure.
+pub fn copy_from_user<AS: AddressSpace>(
+	space: &AS::UserHandle,
+	user_ptr: usize,
+	buf: &mut [u8],
+) -> Result<(), FaultError> {
+	for_each_user_chunk::<AS>(space, user_ptr, buf.len(), false, |phys, offset, len| {
+		// SAFETY(qix-): The frame was validated as user accessible, and is
+		// SAFETY(qix-): accessed via the linear map.
+		unsafe {
+			buf[offset..offset + len].copy_from_slice(core::slice::from_raw_parts(
+				Phys::from_address_unchecked(phys).as_ptr_unchecked::<u8>(),
+				len,
+			));
+		}
+	})
+}
+
+/// Copies `buf` into userspace, starting at `user_ptr`.
+///
+/// Pages that are not writable are first given the chance to become
+/// writable (e.g. copy-on-write pages) via [`AddressSpace::resolve_write_fault()`].
+///
+/// Fails if the range overflows, does not lie entirely within a single user
+/// segment, or if any page within it is not mapped, not user accessible, or
+/// not writable. Upon failure, the pages before the offending page have
+/// already been written to.
+pub fn copy_to_user<AS: AddressSpace>(
+	space: &AS::UserHandle,
+	user_ptr: usize,
+	buf: &[u8],
+) -> Result<(), FaultError> {
+	for_each_user_chunk::<AS>(space, user_ptr, buf.len(), true, |phys, offset, len| {
+		// SAFETY(qix-): The frame was validated as user accessible and writable,
+		// SAFETY(qix-): and is accessed via the linear map.
+		unsafe {
+			Phys::from_address_unchecked(phys)
+				.as_mut_ptr_unchecked::<u8>()
+				.copy_from_nonoverlapping(buf[offset..].as_ptr(), len);
+		}
+	})
+}
+
+/// Validates the user range `[user_ptr, user_ptr + len)` and calls `f` with
+/// the physical address, the offset into the range, and the length of each
+/// contiguous chunk of it that does not cross a page boundary.
+fn for_each_user_chunk<AS: AddressSpace>(
+	space: &AS::UserHandle,
+	user_ptr: usize,
+	len: usize,
+	write: bool,
+	mut f: impl FnMut(u64, usize, usize),
+) -> Result<(), FaultError> {
+	if len == 0 {
+		return Ok(());
+	}
+
+	let last = user_ptr.checked_add(len - 1).ok_or(FaultError::Overflow)?;
+
+	let in_segment = [
+		AS::sysabi().range(),
+		AS::user_code().range(),
+		AS::user_data().range(),
+		AS::user_rodata().range(),
+		AS::user_heap().range(),
+		AS::user_thread_stack().range(),
+	]
+	.into_iter()
+	.any(|(start, end)| user_ptr >= start && last <= end);
+
+	if !in_segment {
+		return Err(FaultError::OutOfRange);
+	}
+
+	let mut offset = 0;
+	while offset < len {
+		let virt = user_ptr + offset;
+		let chunk = (4096 - (virt & 0xFFF)).min(len - offset);
+
+		let (mut phys, mut flags) = resolve::<AS>(space, virt)?;
+
+		if write && !flags.writable {
+			// SAFETY(qix-): We do not hold any references into the page tables.
+			if !unsafe { AS::resolve_write_fault(space, virt) } {
+				return Err(FaultError::NotWritable);
+			}
+
+			(phys, flags) = resolve::<AS>(space, virt)?;
+			if !flags.writable {
+				return Err(FaultError::NotWritable);
+			}
+		}
+
+		f(phys, offset, chunk);
+		offset += chunk;
+	}
+
+	Ok(())
+}
+
+/// Translates the given user virtual address, validating that it is
+/// mapped and user accessible.
+fn resolve<AS: AddressSpace>(
+	space: &AS::UserHandle,
+	virt: usize,
+) -> Result<(u64, PageFlags), FaultError> {
+	let (phys, flags) = AS::translate(space, virt).ok_or(FaultError::NotMapped)?;
+
+	if !flags.user {
+		return Err(FaultError::NotUserAccessible);
+	}
+
+	Ok((phys, flags))
+}
@@ -3,10 +3,14 @@
 
 use core::{
 	cell::UnsafeCell,
+	marker::PhantomData,
+	mem::{size_of, transmute_copy},
 	ops::{Deref, DerefMut},
+	ptr::null_mut,
 	sync::atomic::{
-		AtomicBool, AtomicUsize,
+		AtomicBool, AtomicPtr, AtomicUsize,
 		Ordering::{AcqRel, Acquire, Relaxed, Release},
+		fence,
 	},
 };
 
@@ -25,6 +29,10 @@ pub trait Lock {
 
 	/// Acquires a lock, blocking until it's available.
 	fn lock(&self) -> Self::Guard<'_>;
+
+	/// Attempts to acquire the lock without blocking, returning
+	/// `None` if it's currently held (or contended).
+	fn try_lock(&self) -> Option<Self::Guard<'_>>;
 }
 
 /// A simple unfair, greedy spinlock. The most efficient spinlock
@@ -64,6 +72,16 @@ impl<T: Send + 'static> Lock for Mutex<T> {
 			::core::hint::spin_loop();
 		}
 	}
+
+	fn try_lock(&self) -> Option<Self::Guard<'_>> {
+		if self.locked.swap(true, Acquire) {
+			return None;
+		}
+
+		#[cfg(debug_assertions)]
+		::oro_dbgutil::__oro_dbgutil_lock_acquire(self.value.get() as usize);
+		Some(MutexGuard { lock: self })
+	}
 }
 
 impl<T: Default + Send + 'static> Default for Mutex<T> {
@@ -198,6 +216,33 @@ impl<T: Send + 'static> Lock for TicketMutex<T> {
 			}
 		}
 	}
+
+	fn try_lock(&self) -> Option<Self::Guard<'_>> {
+		// Only take a ticket if it'd be served immediately.
+		let ticket = self.now_serving.load(Acquire);
+		if self
+			.next_ticket
+			.compare_exchange(ticket, ticket.wrapping_add(1), AcqRel, Relaxed)
+			.is_err()
+		{
+			return None;
+		}
+
+		// NOTE(qix-): The previous holder may have advanced the ticket
+		// NOTE(qix-): but not yet cleared the lock flag. Rather than wait
+		// NOTE(qix-): on it (which might never happen, if it was interrupted
+		// NOTE(qix-): by the caller), give up the ticket.
+		if self.locked.swap(true, AcqRel) {
+			let _ =
+				self.now_serving
+					.compare_exchange(ticket, ticket.wrapping_add(1), Release, Relaxed);
+			return None;
+		}
+
+		#[cfg(debug_assertions)]
+		::oro_dbgutil::__oro_dbgutil_lock_acquire(self.value.get() as usize);
+		Some(TicketMutexGuard { lock: self, ticket })
+	}
 }
 
 impl<T: Default + Send + 'static> Default for TicketMutex<T> {
@@ -246,3 +291,148 @@ impl<T: Send + 'static> DerefMut for TicketMutexGuard<'_, T> {
 		unsafe { &mut *self.lock.value.get() }
 	}
 }
+
+/// A sequence lock, for small values that are written rarely but read
+/// constantly (e.g. timekeeping calibration, configuration values).
+///
+/// Readers never block writers, nor each other; they take a snapshot
+/// of the value and retry if a write was in progress or took place in
+/// the meantime. Writers are serialized among themselves.
+///
+/// `T` should be small and trivially copyable (plain data with no
+/// pointers or invariants spanning its fields); readers may copy out
+/// a torn value, which is discarded, and larger values make retries
+/// (and thus writer starvation of readers) more likely.
+pub struct SeqLock<T: Copy + Send + 'static> {
+	/// The guarded value.
+	value:    UnsafeCell<T>,
+	/// The sequence number; odd while a write is in progress.
+	sequence: AtomicUsize,
+}
+
+// SAFETY: Readers only ever copy the value out, and writers are serialized.
+unsafe impl<T: Copy + Send + 'static> Sync for SeqLock<T> {}
+
+impl<T: Copy + Send + 'static> SeqLock<T> {
+	/// Creates a new sequence lock for the given value.
+	pub const fn new(value: T) -> Self {
+		Self {
+			value:    UnsafeCell::new(value),
+			sequence: AtomicUsize::new(0),
+		}
+	}
+
+	/// Returns a snapshot of the value, retrying for as long
+	/// as a write is in progress or has raced with the read.
+	pub fn read(&self) -> T {
+		loop {
+			let before = self.sequence.load(Acquire);
+			if before & 1 != 0 {
+				::core::hint::spin_loop();
+				continue;
+			}
+
+			// NOTE(qix-): The read may race with a writer, producing a torn
+			// NOTE(qix-): value; it's volatile so that it isn't elided or
+			// NOTE(qix-): reordered, and the value is discarded in that case.
+			// SAFETY: `T` is `Copy`, and torn values are never returned.
+			let value = unsafe { self.value.get().read_volatile() };
+
+			fence(Acquire);
+
+			if self.sequence.load(Relaxed) == before {
+				return value;
+			}
+
+			::core::hint::spin_loop();
+		}
+	}
+
+	/// Updates the value, blocking until any other writers are finished.
+	///
+	/// Readers retry until the update completes. The closure should
+	/// therefore be short.
+	pub fn write<F: FnOnce(&mut T)>(&self, f: F) {
+		let sequence = loop {
+			let sequence = self.sequence.load(Relaxed);
+			if sequence & 1 == 0
+				&& self
+					.sequence
+					.compare_exchange_weak(sequence, sequence.wrapping_add(1), Acquire, Relaxed)
+					.is_ok()
+			{
+				break sequence;
+			}
+
+			::core::hint::spin_loop();
+		};
+
+		// Keep the update from being observed before the sequence is odd.
+		fence(Release);
+
+		// SAFETY: Writers are serialized by the odd sequence number, and
+		// SAFETY: readers discard anything they read during the update.
+		f(unsafe { &mut *self.value.get() });
+
+		self.sequence.store(sequence.wrapping_add(2), Release);
+	}
+}
+
+impl<T: Copy + Default + Send + 'static> Default for SeqLock<T> {
+	fn default() -> Self {
+		Self::new(T::default())
+	}
+}
+
+/// An atomically replaceable `fn` pointer, for registering hooks and
+/// callbacks in `static`s.
+///
+/// Starts out empty. Loads synchronize with the store that set the
+/// pointer they return.
+///
+/// `F` is meant to be a `fn` pointer type; it must be pointer-sized,
+/// which is checked at compile time, and must never be null.
+pub struct AtomicFnPtr<F: Copy + 'static> {
+	/// The pointer, or null if none has been stored.
+	ptr: AtomicPtr<()>,
+	/// The type of the stored pointer.
+	_fn: PhantomData<F>,
+}
+
+impl<F: Copy + 'static> AtomicFnPtr<F> {
+	/// Asserts that `F` is pointer-sized.
+	const POINTER_SIZED: () = assert!(
+		size_of::<F>() == size_of::<*mut ()>(),
+		"AtomicFnPtr may only hold pointer-sized types"
+	);
+
+	/// Creates a new, empty `fn` pointer.
+	#[must_use]
+	pub const fn new() -> Self {
+		let () = Self::POINTER_SIZED;
+
+		Self {
+			ptr: AtomicPtr::new(null_mut()),
+			_fn: PhantomData,
+		}
+	}
+
+	/// Stores the given `fn` pointer, replacing any previous one.
+	pub fn store(&self, f: F) {
+		// SAFETY: `F` is pointer-sized, as asserted by `Self::new()`.
+		let ptr = unsafe { transmute_copy::<F, *mut ()>(&f) };
+		self.ptr.store(ptr, Release);
+	}
+
+	/// Returns the stored `fn` pointer, or `None` if none has been stored.
+	#[must_use]
+	pub fn load(&self) -> Option<F> {
+		let ptr = self.ptr.load(Acquire);
+		if ptr.is_null() {
+			return None;
+		}
+
+		// SAFETY: Only ever set from a valid `F` by `Self::store()`.
+		Some(unsafe { transmute_copy::<*mut (), F>(&ptr) })
+	}
+}
@@ -0,0 +1,203 @@
+//! Houses the [`ArrayVec`] type, a fixed-capacity, heap-less
+//! alternative to `Vec`.
+
+use core::mem::MaybeUninit;
+
+/// A `Vec`-like buffer with a fixed capacity of `N` elements,
+/// stored inline (typically on the stack).
+///
+/// Unlike `Vec`, never allocates; pushing onto a full buffer
+/// returns the element back to the caller rather than panicking.
+pub struct ArrayVec<T, const N: usize> {
+	/// The backing storage. Only the first `len` elements
+	/// are initialized.
+	data: MaybeUninit<[T; N]>,
+	/// The number of initialized elements.
+	len:  usize,
+}
+
+impl<T, const N: usize> ArrayVec<T, N> {
+	/// Creates a new, empty buffer.
+	#[inline]
+	#[must_use]
+	pub const fn new() -> Self {
+		Self {
+			data: MaybeUninit::uninit(),
+			len:  0,
+		}
+	}
+
+	/// Returns the number of elements in the buffer.
+	#[inline]
+	#[must_use]
+	pub const fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Returns whether or not the buffer is empty.
+	#[inline]
+	#[must_use]
+	pub const fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Returns whether or not the buffer is full.
+	#[inline]
+	#[must_use]
+	pub const fn is_full(&self) -> bool {
+		self.len == N
+	}
+
+	/// Returns the maximum number of elements the buffer can hold.
+	#[inline]
+	#[must_use]
+	pub const fn capacity(&self) -> usize {
+		N
+	}
+
+	/// Appends an element to the back of the buffer.
+	///
+	/// # Errors
+	/// Returns the element back if the buffer is full.
+	pub fn push(&mut self, value: T) -> Result<(), T> {
+		if self.is_full() {
+			return Err(value);
+		}
+
+		// SAFETY(qix-): We've checked that `len < N`, so the slot is in bounds
+		// SAFETY(qix-): and uninitialized.
+		unsafe {
+			self.as_mut_ptr().add(self.len).write(value);
+		}
+
+		self.len += 1;
+		Ok(())
+	}
+
+	/// Removes and returns the last element of the buffer, if any.
+	pub fn pop(&mut self) -> Option<T> {
+		if self.len == 0 {
+			return None;
+		}
+
+		self.len -= 1;
+
+		// SAFETY(qix-): The slot at the (old) last index was initialized, and
+		// SAFETY(qix-): is no longer considered part of the buffer, so it won't
+		// SAFETY(qix-): be read or dropped again.
+		Some(unsafe { self.as_mut_ptr().add(self.len).read() })
+	}
+
+	/// Removes all elements from the buffer, dropping them.
+	pub fn clear(&mut self) {
+		let len = self.len;
+
+		// NOTE(qix-): Length is reset first such that a panicking
+		// NOTE(qix-): `Drop` impl can't cause a double drop.
+		self.len = 0;
+
+		// SAFETY(qix-): The first `len` elements are initialized, and are no
+		// SAFETY(qix-): longer considered part of the buffer.
+		unsafe {
+			core::ptr::drop_in_place(core::ptr::slice_from_raw_parts_mut(self.as_mut_ptr(), len));
+		}
+	}
+
+	/// Returns the initialized elements as a slice.
+	#[inline]
+	#[must_use]
+	pub fn as_slice(&self) -> &[T] {
+		// SAFETY(qix-): The first `len` elements are always initialized.
+		unsafe { core::slice::from_raw_parts(self.data.as_ptr().cast::<T>(), self.len) }
+	}
+
+	/// Returns the initialized elements as a mutable slice.
+	#[inline]
+	#[must_use]
+	pub fn as_mut_slice(&mut self) -> &mut [T] {
+		// SAFETY(qix-): The first `len` elements are always initialized.
+		unsafe { core::slice::from_raw_parts_mut(self.as_mut_ptr(), self.len) }
+	}
+
+	/// Returns a pointer to the first slot of the backing storage.
+	#[inline]
+	fn as_mut_ptr(&mut self) -> *mut T {
+		self.data.as_mut_ptr().cast::<T>()
+	}
+}
+
+impl<T, const N: usize> Default for ArrayVec<T, N> {
+	#[inline]
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T, const N: usize> Drop for ArrayVec<T, N> {
+	fn drop(&mut self) {
+		self.clear();
+	}
+}
+
+impl<T, const N: usize> core::ops::Deref for ArrayVec<T, N> {
+	type Target = [T];
+
+	#[inline]
+	fn deref(&self) -> &[T] {
+		self.as_slice()
+	}
+}
+
+impl<T, const N: usize> core::ops::DerefMut for ArrayVec<T, N> {
+	#[inline]
+	fn deref_mut(&mut self) -> &mut [T] {
+		self.as_mut_slice()
+	}
+}
+
+impl<T: core::fmt::Debug, const N: usize> core::fmt::Debug for ArrayVec<T, N> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_list().entries(self.as_slice()).finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::rc::Rc;
+
+	use super::*;
+
+	#[test]
+	fn test_push_pop() {
+		let mut v = ArrayVec::<u32, 3>::new();
+		assert!(v.is_empty());
+		assert_eq!(v.push(1), Ok(()));
+		assert_eq!(v.push(2), Ok(()));
+		assert_eq!(v.push(3), Ok(()));
+		assert!(v.is_full());
+		assert_eq!(v.push(4), Err(4));
+		assert_eq!(v.as_slice(), &[1, 2, 3]);
+		assert_eq!(v.pop(), Some(3));
+		assert_eq!(v.len(), 2);
+		assert_eq!(v.pop(), Some(2));
+		assert_eq!(v.pop(), Some(1));
+		assert_eq!(v.pop(), None);
+	}
+
+	#[test]
+	fn test_drops_initialized_prefix() {
+		let rc = Rc::new(());
+
+		{
+			let mut v = ArrayVec::<Rc<()>, 4>::new();
+			v.push(rc.clone()).unwrap();
+			v.push(rc.clone()).unwrap();
+			assert_eq!(Rc::strong_count(&rc), 3);
+
+			drop(v.pop());
+			assert_eq!(Rc::strong_count(&rc), 2);
+		}
+
+		assert_eq!(Rc::strong_count(&rc), 1);
+	}
+}
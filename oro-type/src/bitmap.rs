@@ -0,0 +1,403 @@
+//! Bitmaps over borrowed word slices.
+//!
+//! # Layout
+//! Both [`Bitmap`] and [`AtomicBitmap`] address their bits the same
+//! way, such that their in-memory layout is stable and the two can be
+//! used interchangeably over the same memory:
+//!
+//! - Bit `i` lives in word `i / 64` of the backing slice, with words
+//!   in ascending (slice) order.
+//! - Within a word, bit `i` is bit `i % 64`, counting from the least
+//!   significant bit (i.e. it is `word & (1 << (i % 64))`).
+//!
+//! Words are native-endian `u64`s. A bitmap always spans exactly
+//! `words.len() * 64` bits; callers that need fewer should set the
+//! trailing bits so they are never found to be free.
+//!
+//! A set bit (`1`) is considered "used", and a clear bit (`0`) "free";
+//! the `find_*` methods search for clear bits.
+
+use core::sync::atomic::{
+	AtomicU64,
+	Ordering::{AcqRel, Acquire, Relaxed},
+};
+
+/// A bitmap over a mutably borrowed slice of words.
+///
+/// See the [module documentation](self) for the bit layout.
+pub struct Bitmap<'a> {
+	/// The backing words.
+	words: &'a mut [u64],
+}
+
+impl<'a> Bitmap<'a> {
+	/// Creates a bitmap over the given words, leaving their
+	/// contents as-is.
+	#[inline]
+	#[must_use]
+	pub fn new(words: &'a mut [u64]) -> Self {
+		Self { words }
+	}
+
+	/// Returns the number of bits in the bitmap.
+	#[inline]
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.words.len() << 6
+	}
+
+	/// Returns whether or not the bitmap has no bits at all.
+	#[inline]
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.words.is_empty()
+	}
+
+	/// Returns the backing words.
+	#[inline]
+	#[must_use]
+	pub fn words(&self) -> &[u64] {
+		self.words
+	}
+
+	/// Sets the given bit.
+	///
+	/// # Panics
+	/// Panics if `bit` is out of bounds.
+	#[inline]
+	pub fn set(&mut self, bit: usize) {
+		self.words[bit >> 6] |= mask_of(bit);
+	}
+
+	/// Clears the given bit.
+	///
+	/// # Panics
+	/// Panics if `bit` is out of bounds.
+	#[inline]
+	pub fn clear(&mut self, bit: usize) {
+		self.words[bit >> 6] &= !mask_of(bit);
+	}
+
+	/// Returns whether or not the given bit is set.
+	///
+	/// # Panics
+	/// Panics if `bit` is out of bounds.
+	#[inline]
+	#[must_use]
+	pub fn test(&self, bit: usize) -> bool {
+		self.words[bit >> 6] & mask_of(bit) != 0
+	}
+
+	/// Sets `count` bits, starting at `start`.
+	///
+	/// # Panics
+	/// Panics if any bit in the range is out of bounds.
+	pub fn set_range(&mut self, start: usize, count: usize) {
+		for_each_word_in(start, count, |word, mask| self.words[word] |= mask);
+	}
+
+	/// Clears `count` bits, starting at `start`.
+	///
+	/// # Panics
+	/// Panics if any bit in the range is out of bounds.
+	pub fn clear_range(&mut self, start: usize, count: usize) {
+		for_each_word_in(start, count, |word, mask| self.words[word] &= !mask);
+	}
+
+	/// Returns the number of clear bits.
+	#[must_use]
+	pub fn count_zeros(&self) -> usize {
+		self.words.iter().map(|w| w.count_zeros() as usize).sum()
+	}
+
+	/// Returns the index of the lowest clear bit, if any.
+	#[must_use]
+	pub fn find_first_zero(&self) -> Option<usize> {
+		find_first_zero(self.words.len(), |w| self.words[w])
+	}
+
+	/// Returns the index of the lowest bit that begins a run of `len`
+	/// consecutive clear bits, if any.
+	///
+	/// Returns `None` if `len` is zero.
+	#[must_use]
+	pub fn find_run(&self, len: usize) -> Option<usize> {
+		self.find_run_aligned(len, 1)
+	}
+
+	/// Like [`Self::find_run()`], but the run must begin at a
+	/// multiple of `align` (treated as `1` if zero).
+	#[must_use]
+	pub fn find_run_aligned(&self, len: usize, align: usize) -> Option<usize> {
+		find_run(self.words.len(), |w| self.words[w], len, align)
+	}
+}
+
+/// A bitmap over a shared slice of atomic words, safe to use
+/// concurrently from multiple cores.
+///
+/// Individual bit operations are atomic. The `find_*` methods
+/// operate on a snapshot that may be stale by the time they return;
+/// use [`Self::try_claim()`] or [`Self::claim_first_zero()`] to
+/// reliably take ownership of a free bit.
+///
+/// See the [module documentation](self) for the bit layout.
+pub struct AtomicBitmap<'a> {
+	/// The backing words.
+	words: &'a [AtomicU64],
+}
+
+impl<'a> AtomicBitmap<'a> {
+	/// Creates a bitmap over the given words, leaving their
+	/// contents as-is.
+	#[inline]
+	#[must_use]
+	pub fn new(words: &'a [AtomicU64]) -> Self {
+		Self { words }
+	}
+
+	/// Returns the number of bits in the bitmap.
+	#[inline]
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.words.len() << 6
+	}
+
+	/// Returns whether or not the bitmap has no bits at all.
+	#[inline]
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.words.is_empty()
+	}
+
+	/// Sets the given bit.
+	///
+	/// # Panics
+	/// Panics if `bit` is out of bounds.
+	#[inline]
+	pub fn set(&self, bit: usize) {
+		self.words[bit >> 6].fetch_or(mask_of(bit), AcqRel);
+	}
+
+	/// Clears the given bit.
+	///
+	/// # Panics
+	/// Panics if `bit` is out of bounds.
+	#[inline]
+	pub fn clear(&self, bit: usize) {
+		self.words[bit >> 6].fetch_and(!mask_of(bit), AcqRel);
+	}
+
+	/// Returns whether or not the given bit is set.
+	///
+	/// # Panics
+	/// Panics if `bit` is out of bounds.
+	#[inline]
+	#[must_use]
+	pub fn test(&self, bit: usize) -> bool {
+		self.words[bit >> 6].load(Acquire) & mask_of(bit) != 0
+	}
+
+	/// Attempts to set the given bit, returning `true` only if it
+	/// was clear and this call was the one to set it.
+	///
+	/// # Panics
+	/// Panics if `bit` is out of bounds.
+	#[must_use]
+	pub fn try_claim(&self, bit: usize) -> bool {
+		let word = &self.words[bit >> 6];
+		let mask = mask_of(bit);
+
+		let mut current = word.load(Relaxed);
+		loop {
+			if current & mask != 0 {
+				return false;
+			}
+
+			match word.compare_exchange_weak(current, current | mask, AcqRel, Relaxed) {
+				Ok(_) => return true,
+				Err(actual) => current = actual,
+			}
+		}
+	}
+
+	/// Finds and sets the lowest clear bit, returning its index.
+	///
+	/// Returns `None` if every bit is set.
+	#[must_use]
+	pub fn claim_first_zero(&self) -> Option<usize> {
+		for (w, word) in self.words.iter().enumerate() {
+			let mut current = word.load(Relaxed);
+			while current != u64::MAX {
+				let bit = current.trailing_ones();
+				match word.compare_exchange_weak(current, current | (1 << bit), AcqRel, Relaxed) {
+					Ok(_) => return Some((w << 6) + bit as usize),
+					Err(actual) => current = actual,
+				}
+			}
+		}
+
+		None
+	}
+
+	/// Returns the number of clear bits (at the time of the call).
+	#[must_use]
+	pub fn count_zeros(&self) -> usize {
+		self.words
+			.iter()
+			.map(|w| w.load(Relaxed).count_zeros() as usize)
+			.sum()
+	}
+
+	/// Returns the index of the lowest clear bit, if any.
+	///
+	/// The bit may have been set by the time this returns.
+	#[must_use]
+	pub fn find_first_zero(&self) -> Option<usize> {
+		find_first_zero(self.words.len(), |w| self.words[w].load(Acquire))
+	}
+
+	/// Returns the index of the lowest bit that begins a run of `len`
+	/// consecutive clear bits, if any.
+	///
+	/// Returns `None` if `len` is zero. The bits may have been set by
+	/// the time this returns.
+	#[must_use]
+	pub fn find_run(&self, len: usize) -> Option<usize> {
+		find_run(self.words.len(), |w| self.words[w].load(Acquire), len, 1)
+	}
+}
+
+/// Returns the mask of the given bit within its word.
+#[inline]
+const fn mask_of(bit: usize) -> u64 {
+	1 << (bit & 63)
+}
+
+/// Calls `f` with each word index and mask covering the `count`
+/// bits starting at `start`.
+fn for_each_word_in<F: FnMut(usize, u64)>(start: usize, count: usize, mut f: F) {
+	let end = start + count;
+	let mut bit = start;
+
+	while bit < end {
+		let offset = bit & 63;
+		let n = (64 - offset).min(end - bit);
+		let mask = if n == 64 {
+			u64::MAX
+		} else {
+			((1 << n) - 1) << offset
+		};
+
+		f(bit >> 6, mask);
+		bit += n;
+	}
+}
+
+/// Returns the lowest clear bit among `words` words, each read via `word`.
+fn find_first_zero<W: Fn(usize) -> u64>(words: usize, word: W) -> Option<usize> {
+	(0..words).find_map(|w| {
+		let bits = word(w);
+		(bits != u64::MAX).then(|| (w << 6) + bits.trailing_ones() as usize)
+	})
+}
+
+/// Returns the highest set bit in `[start, end)`, each word read via `word`.
+///
+/// `start` must be less than `end`.
+fn last_set_in<W: Fn(usize) -> u64>(word: &W, start: usize, end: usize) -> Option<usize> {
+	let mut w = (end - 1) >> 6;
+
+	loop {
+		let base = w << 6;
+		let mut bits = word(w);
+
+		let hi = end - base;
+		if hi < 64 {
+			bits &= (1 << hi) - 1;
+		}
+
+		if start > base {
+			bits &= !((1 << (start - base)) - 1);
+		}
+
+		if bits != 0 {
+			return Some(base + 63 - bits.leading_zeros() as usize);
+		}
+
+		if base <= start {
+			return None;
+		}
+
+		w -= 1;
+	}
+}
+
+/// Finds the lowest `align`-aligned run of `len` clear bits among `words`
+/// words, each read via `word`.
+fn find_run<W: Fn(usize) -> u64>(words: usize, word: W, len: usize, align: usize) -> Option<usize> {
+	if len == 0 {
+		return None;
+	}
+
+	let bits = words << 6;
+	let align = align.max(1);
+	let mut start = 0_usize;
+
+	while start.checked_add(len)? <= bits {
+		match last_set_in(&word, start, start + len) {
+			None => return Some(start),
+			// Any run overlapping the set bit can't be free; skip past it.
+			Some(set) => start = (set + 1).checked_next_multiple_of(align)?,
+		}
+	}
+
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_bitmap_layout() {
+		let mut words = [0_u64; 2];
+		let mut bitmap = Bitmap::new(&mut words);
+		bitmap.set(0);
+		bitmap.set(65);
+		assert!(bitmap.test(65));
+		assert!(!bitmap.test(64));
+		assert_eq!(bitmap.find_first_zero(), Some(1));
+		bitmap.clear(0);
+		assert_eq!(bitmap.find_first_zero(), Some(0));
+		assert_eq!(words, [0, 0b10]);
+	}
+
+	#[test]
+	fn test_bitmap_find_run() {
+		let mut words = [0_u64; 3];
+		let mut bitmap = Bitmap::new(&mut words);
+		bitmap.set_range(0, 60);
+		bitmap.set(70);
+		assert_eq!(bitmap.find_run(4), Some(60));
+		assert_eq!(bitmap.find_run(11), Some(71));
+		assert_eq!(bitmap.find_run_aligned(8, 8), Some(72));
+		assert_eq!(bitmap.find_run(200), None);
+		bitmap.clear_range(0, 60);
+		assert_eq!(bitmap.count_zeros(), 191);
+		assert_eq!(bitmap.find_run(70), Some(0));
+	}
+
+	#[test]
+	fn test_atomic_bitmap_claim() {
+		let words = [AtomicU64::new(u64::MAX), AtomicU64::new(0b1)];
+		let bitmap = AtomicBitmap::new(&words);
+		assert_eq!(bitmap.find_first_zero(), Some(65));
+		assert!(!bitmap.try_claim(64));
+		assert!(bitmap.try_claim(66));
+		assert_eq!(bitmap.claim_first_zero(), Some(65));
+		assert_eq!(bitmap.claim_first_zero(), Some(67));
+		bitmap.clear(66);
+		assert!(!bitmap.test(66));
+	}
+}
@@ -0,0 +1,190 @@
+//! CRC-32 checksums.
+//!
+//! Uses the standard (IEEE 802.3) reflected polynomial, `0xEDB88320`,
+//! with an initial value and final XOR of `0xFFFF_FFFF`; the same CRC-32
+//! used by Ethernet, zlib, PNG, etc.
+//!
+//! Data is processed eight bytes at a time ("slice-by-8"), using lookup
+//! tables generated at compile time. Everything here is `const`, so
+//! checksums of constant data cost nothing at runtime.
+
+/// The reflected IEEE 802.3 polynomial.
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+/// The slice-by-8 lookup tables.
+///
+/// `TABLES[0]` is the standard byte-wise table; `TABLES[n][b]` is the
+/// CRC of the byte `b` followed by `n` zero bytes.
+const TABLES: [[u32; 256]; 8] = make_tables();
+
+/// Generates the slice-by-8 lookup tables.
+const fn make_tables() -> [[u32; 256]; 8] {
+	let mut tables = [[0; 256]; 8];
+
+	let mut i = 0;
+	while i < 256 {
+		let mut crc = i as u32;
+		let mut bit = 0;
+		while bit < 8 {
+			crc = if crc & 1 == 0 {
+				crc >> 1
+			} else {
+				(crc >> 1) ^ POLYNOMIAL
+			};
+			bit += 1;
+		}
+		tables[0][i] = crc;
+		i += 1;
+	}
+
+	let mut i = 0;
+	while i < 256 {
+		let mut n = 1;
+		while n < 8 {
+			let prev = tables[n - 1][i];
+			tables[n][i] = (prev >> 8) ^ tables[0][(prev & 0xFF) as usize];
+			n += 1;
+		}
+		i += 1;
+	}
+
+	tables
+}
+
+/// Feeds `data` into the (non-finalized) CRC state `crc`.
+const fn update(mut crc: u32, data: &[u8]) -> u32 {
+	let mut i = 0;
+
+	while i + 8 <= data.len() {
+		let lo = crc ^ u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+		crc = TABLES[7][(lo & 0xFF) as usize]
+			^ TABLES[6][((lo >> 8) & 0xFF) as usize]
+			^ TABLES[5][((lo >> 16) & 0xFF) as usize]
+			^ TABLES[4][(lo >> 24) as usize]
+			^ TABLES[3][data[i + 4] as usize]
+			^ TABLES[2][data[i + 5] as usize]
+			^ TABLES[1][data[i + 6] as usize]
+			^ TABLES[0][data[i + 7] as usize];
+		i += 8;
+	}
+
+	while i < data.len() {
+		crc = (crc >> 8) ^ TABLES[0][((crc ^ data[i] as u32) & 0xFF) as usize];
+		i += 1;
+	}
+
+	crc
+}
+
+/// Computes the CRC-32 of `data` in one go.
+///
+/// Equivalent to feeding `data` to a fresh [`Crc32`] and finalizing it.
+#[must_use]
+pub const fn crc32(data: &[u8]) -> u32 {
+	!update(!0, data)
+}
+
+/// An incremental CRC-32 computation.
+///
+/// Feeding data in pieces via [`Self::update()`] yields the same
+/// checksum as passing it all to [`crc32()`] at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crc32 {
+	/// The running (non-finalized) CRC.
+	state: u32,
+}
+
+impl Crc32 {
+	/// Creates a new CRC-32 computation over no data.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self { state: !0 }
+	}
+
+	/// Feeds more data into the computation.
+	pub const fn update(&mut self, data: &[u8]) {
+		self.state = update(self.state, data);
+	}
+
+	/// Returns the CRC-32 of all data fed in so far.
+	///
+	/// The computation may continue to be updated afterward.
+	#[must_use]
+	pub const fn finalize(&self) -> u32 {
+		!self.state
+	}
+}
+
+impl Default for Crc32 {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A straightforward bit-wise CRC-32, for comparison.
+	fn reference(data: &[u8]) -> u32 {
+		let mut crc = !0_u32;
+		for &byte in data {
+			crc ^= u32::from(byte);
+			for _ in 0..8 {
+				crc = if crc & 1 == 0 {
+					crc >> 1
+				} else {
+					(crc >> 1) ^ POLYNOMIAL
+				};
+			}
+		}
+		!crc
+	}
+
+	#[test]
+	fn test_known_values() {
+		assert_eq!(crc32(b""), 0);
+		assert_eq!(crc32(b"a"), 0xE8B7_BE43);
+		assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+		assert_eq!(
+			crc32(b"The quick brown fox jumps over the lazy dog"),
+			0x414F_A339
+		);
+	}
+
+	#[test]
+	fn test_const() {
+		const CHECK: u32 = crc32(b"123456789");
+		assert_eq!(CHECK, 0xCBF4_3926);
+	}
+
+	#[test]
+	fn test_matches_reference() {
+		let data = (0..1024_u32)
+			.map(|i| (i.wrapping_mul(31) ^ (i >> 3)) as u8)
+			.collect::<Vec<_>>();
+
+		for len in 0..data.len() {
+			assert_eq!(crc32(&data[..len]), reference(&data[..len]), "len={len}");
+		}
+	}
+
+	#[test]
+	fn test_incremental() {
+		let data = b"The quick brown fox jumps over the lazy dog";
+
+		for split in 0..=data.len() {
+			let mut crc = Crc32::new();
+			crc.update(&data[..split]);
+			crc.update(&data[split..]);
+			assert_eq!(crc.finalize(), crc32(data), "split={split}");
+		}
+
+		let mut crc = Crc32::default();
+		assert_eq!(crc.finalize(), 0);
+		for byte in data {
+			crc.update(core::slice::from_ref(byte));
+		}
+		assert_eq!(crc.finalize(), crc32(data));
+	}
+}
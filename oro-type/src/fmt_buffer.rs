@@ -0,0 +1,137 @@
+//! Houses the [`FmtBuffer`] type, an allocation-free target
+//! for `write!()`.
+
+/// A fixed-capacity string buffer of `N` bytes that implements
+/// [`core::fmt::Write`].
+///
+/// Formatted text that doesn't fit is truncated (at a character
+/// boundary, such that the contents always remain valid UTF-8)
+/// rather than causing an error; whether or not truncation
+/// occurred can be checked via [`Self::is_truncated()`].
+pub struct FmtBuffer<const N: usize> {
+	/// The backing storage. Only the first `len` bytes are valid.
+	buf:       [u8; N],
+	/// The number of bytes written.
+	len:       usize,
+	/// Whether or not any written text was dropped.
+	truncated: bool,
+}
+
+impl<const N: usize> FmtBuffer<N> {
+	/// Creates a new, empty buffer.
+	#[inline]
+	#[must_use]
+	pub const fn new() -> Self {
+		Self {
+			buf:       [0; N],
+			len:       0,
+			truncated: false,
+		}
+	}
+
+	/// Returns the written text.
+	#[must_use]
+	pub fn as_str(&self) -> &str {
+		// SAFETY(qix-): Only whole `str` prefixes (split at character
+		// SAFETY(qix-): boundaries) are ever copied into the buffer.
+		unsafe { core::str::from_utf8_unchecked(&self.buf[..self.len]) }
+	}
+
+	/// Returns the number of bytes written.
+	#[inline]
+	#[must_use]
+	pub const fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Returns whether or not the buffer is empty.
+	#[inline]
+	#[must_use]
+	pub const fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Returns whether or not any written text had to be dropped
+	/// due to the buffer being full.
+	#[inline]
+	#[must_use]
+	pub const fn is_truncated(&self) -> bool {
+		self.truncated
+	}
+
+	/// Empties the buffer and resets its truncation flag.
+	#[inline]
+	pub fn clear(&mut self) {
+		self.len = 0;
+		self.truncated = false;
+	}
+}
+
+impl<const N: usize> Default for FmtBuffer<N> {
+	#[inline]
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<const N: usize> core::fmt::Write for FmtBuffer<N> {
+	fn write_str(&mut self, s: &str) -> core::fmt::Result {
+		let remaining = N - self.len;
+
+		let count = if s.len() <= remaining {
+			s.len()
+		} else {
+			self.truncated = true;
+			(0..=remaining)
+				.rev()
+				.find(|&i| s.is_char_boundary(i))
+				.unwrap_or(0)
+		};
+
+		self.buf[self.len..self.len + count].copy_from_slice(&s.as_bytes()[..count]);
+		self.len += count;
+
+		Ok(())
+	}
+}
+
+impl<const N: usize> core::fmt::Display for FmtBuffer<N> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+
+impl<const N: usize> core::fmt::Debug for FmtBuffer<N> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		core::fmt::Debug::fmt(self.as_str(), f)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use core::fmt::Write;
+
+	use super::*;
+
+	#[test]
+	fn test_write() {
+		let mut buf = FmtBuffer::<16>::new();
+		write!(buf, "{}-{:x}", 42, 255).unwrap();
+		assert_eq!(buf.as_str(), "42-ff");
+		assert!(!buf.is_truncated());
+	}
+
+	#[test]
+	fn test_truncate() {
+		let mut buf = FmtBuffer::<4>::new();
+		write!(buf, "abc").unwrap();
+		write!(buf, "\u{e9}").unwrap();
+		assert_eq!(buf.as_str(), "abc");
+		assert!(buf.is_truncated());
+
+		buf.clear();
+		write!(buf, "abcdef").unwrap();
+		assert_eq!(buf.as_str(), "abcd");
+		assert!(buf.is_truncated());
+	}
+}
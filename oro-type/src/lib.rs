@@ -2,12 +2,26 @@
 //!
 //! This crate consists more or less of primitive type
 //! wrappers and associated traits for them (e.g.
-//! forced endianness types).
+//! forced endianness types), as well as a few small,
+//! fixed-capacity containers that don't require a heap
+//! and CRC-32 checksums.
 #![cfg_attr(not(test), no_std)]
 #![expect(clippy::inline_always, clippy::wrong_self_convention)]
 
+pub mod array_vec;
+pub mod bitmap;
+pub mod crc32;
+pub mod fmt_buffer;
+
 use core::marker::PhantomData;
 
+pub use self::{
+	array_vec::ArrayVec,
+	bitmap::{AtomicBitmap, Bitmap},
+	crc32::Crc32,
+	fmt_buffer::FmtBuffer,
+};
+
 /// Wrapper around numeric types that enforces a specific
 /// endianness.
 ///